@@ -0,0 +1,315 @@
+use chrono::Timelike;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a running transfer looks at the clock for schedule changes
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A daily time window with its own rate limit, from `[[schedule]]` in
+/// config.toml.
+///
+/// Windows where `from` is later than `to` wrap around midnight; equal
+/// times cover the whole day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandwidthWindow {
+    /// Minutes after local midnight, inclusive
+    pub from: u16,
+    /// Minutes after local midnight, exclusive
+    pub to: u16,
+    /// Bytes per second per file, `None` for unlimited
+    pub rate_limit: Option<u64>,
+}
+
+impl BandwidthWindow {
+    pub fn contains(&self, minute: u16) -> bool {
+        match self.from.cmp(&self.to) {
+            std::cmp::Ordering::Less => (self.from..self.to).contains(&minute),
+            std::cmp::Ordering::Greater => minute >= self.from || minute < self.to,
+            std::cmp::Ordering::Equal => true,
+        }
+    }
+}
+
+/// Parses a `HH:MM` time of day into minutes after midnight.
+pub fn parse_time(value: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time '{}', expected HH:MM", value);
+    let (hours, minutes) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 24 || minutes > 59 || (hours == 24 && minutes > 0) {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Formats minutes after midnight as `HH:MM`.
+pub fn format_time(minute: u16) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Rate limit in effect at `minute`: the first window containing it
+/// replaces `base`.
+pub fn limit_at(base: Option<u64>, schedule: &[BandwidthWindow], minute: u16) -> Option<u64> {
+    match schedule.iter().find(|window| window.contains(minute)) {
+        Some(window) => window.rate_limit,
+        None => base,
+    }
+}
+
+fn local_minute() -> u16 {
+    let now = chrono::Local::now();
+    (now.hour() * 60 + now.minute()) as u16
+}
+
+/// Token bucket keeping transfers under a rate limit.
+///
+/// One limiter can stand for a single download, for every download from a
+/// host or for everything a [`Downloader`](crate::Downloader) transfers;
+/// a limiter made [`within`](Self::within) others waits for them as well,
+/// so one download can be held to all three. After an idle spell the first
+/// [`burst`](Self::burst) bytes go through at full speed.
+///
+/// The limit is looked up again every second when there is a schedule, so
+/// a long download speeds up or slows down as it crosses the windows.
+///
+/// ```
+/// use dwrs::bandwidth::RateLimiter;
+/// use std::sync::Arc;
+///
+/// # async fn example() {
+/// let total = Arc::new(RateLimiter::new(Some(10 << 20)));
+/// let download = RateLimiter::new(Some(1 << 20)).burst(64 << 10).within(total);
+/// download.acquire(16 << 10).await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RateLimiter {
+    base: Option<u64>,
+    schedule: Vec<BandwidthWindow>,
+    /// Size of the bucket, one second of the limit when unset
+    burst: Option<u64>,
+    parents: Vec<Arc<RateLimiter>>,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: Option<u64>,
+    /// Bytes that may go through right away; negative while callers wait
+    tokens: f64,
+    refilled: Instant,
+    checked: Instant,
+}
+
+impl RateLimiter {
+    /// Limiter for `rate` bytes per second, unlimited for `None`.
+    pub fn new(rate: Option<u64>) -> Self {
+        Self::scheduled(rate, &[])
+    }
+
+    /// Limiter whose rate is replaced by the window of `schedule` that is
+    /// current, if any.
+    pub fn scheduled(base: Option<u64>, schedule: &[BandwidthWindow]) -> Self {
+        let now = Instant::now();
+        let limit = limit_at(base, schedule, local_minute()).filter(|l| *l > 0);
+        Self {
+            base,
+            schedule: schedule.to_vec(),
+            burst: None,
+            parents: Vec::new(),
+            state: Mutex::new(Bucket {
+                limit,
+                tokens: limit.unwrap_or(0) as f64,
+                refilled: now,
+                checked: now,
+            }),
+        }
+    }
+
+    /// Lets up to `bytes` through at once after an idle spell.
+    pub fn burst(mut self, bytes: u64) -> Self {
+        let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        state.tokens = bytes as f64;
+        self.burst = Some(bytes).filter(|b| *b > 0);
+        self
+    }
+
+    /// Also waits for `parent`, e.g. the limiter of a host or of all
+    /// downloads.
+    pub fn within(mut self, parent: Arc<RateLimiter>) -> Self {
+        self.parents.push(parent);
+        self
+    }
+
+    /// Bytes per second currently allowed by this limiter itself.
+    pub fn limit(&self) -> Option<u64> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).limit
+    }
+
+    /// Waits until `bytes` more may be transferred.
+    ///
+    /// The bytes are counted right away, so concurrent callers queue up
+    /// behind each other instead of all going through at once.
+    pub async fn acquire(&self, bytes: u64) {
+        let delay = self.take(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        for parent in &self.parents {
+            Box::pin(parent.acquire(bytes)).await;
+        }
+    }
+
+    /// Takes `bytes` from the bucket and returns how long to wait for them.
+    fn take(&self, bytes: u64) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if !self.schedule.is_empty() && now.duration_since(state.checked) >= RECHECK_INTERVAL {
+            state.checked = now;
+            let limit = limit_at(self.base, &self.schedule, local_minute()).filter(|l| *l > 0);
+            if limit != state.limit {
+                log::info!(
+                    "Rate limit changed by schedule: {}",
+                    limit.map_or("unlimited".to_string(), |l| crate::units::format_speed(
+                        l as f64
+                    ))
+                );
+                state.limit = limit;
+                state.tokens = state.tokens.max(0.0);
+            }
+        }
+        let Some(limit) = state.limit else {
+            return Duration::ZERO;
+        };
+        let capacity = self.burst.unwrap_or(limit) as f64;
+        let refill = now.duration_since(state.refilled).as_secs_f64() * limit as f64;
+        state.tokens = (state.tokens + refill).min(capacity) - bytes as f64;
+        state.refilled = now;
+        if state.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-state.tokens / limit as f64)
+    }
+}
+
+/// The limiters downloads share: one per host and one for all of them.
+#[derive(Debug)]
+pub struct SharedLimits {
+    /// Bytes per second for each host
+    per_host: Option<u64>,
+    burst: Option<u64>,
+    total: Option<Arc<RateLimiter>>,
+    hosts: Mutex<HashMap<String, Arc<RateLimiter>>>,
+}
+
+impl SharedLimits {
+    /// Limits of `per_host` bytes per second for each host and `total`
+    /// for all downloads, each letting `burst` bytes through at once.
+    pub fn new(per_host: Option<u64>, total: Option<u64>, burst: Option<u64>) -> Self {
+        let limiter = |rate| {
+            let limiter = RateLimiter::new(Some(rate));
+            Arc::new(match burst {
+                Some(burst) => limiter.burst(burst),
+                None => limiter,
+            })
+        };
+        Self {
+            per_host: per_host.filter(|r| *r > 0),
+            burst,
+            total: total.filter(|r| *r > 0).map(limiter),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limiter shared by everything fetched from the host of `url` and all
+    /// other downloads; unlimited when neither limit is set.
+    pub fn shared(&self, url: &str) -> Arc<RateLimiter> {
+        self.download(url, None, &[])
+    }
+
+    /// Limiter for one download of `url` at `rate` bytes per second, or as
+    /// `schedule` says, that also keeps to the host and total limits.
+    pub fn download(
+        &self,
+        url: &str,
+        rate: Option<u64>,
+        schedule: &[BandwidthWindow],
+    ) -> Arc<RateLimiter> {
+        let mut limiter = RateLimiter::scheduled(rate, schedule);
+        if let Some(burst) = self.burst {
+            limiter = limiter.burst(burst);
+        }
+        if let Some(rate) = self.per_host
+            && let Some(host) = reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        {
+            let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+            let host = hosts.entry(host).or_insert_with(|| {
+                let host = RateLimiter::new(Some(rate));
+                Arc::new(match self.burst {
+                    Some(burst) => host.burst(burst),
+                    None => host,
+                })
+            });
+            limiter = limiter.within(host.clone());
+        }
+        if let Some(total) = &self.total {
+            limiter = limiter.within(total.clone());
+        }
+        Arc::new(limiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let day = BandwidthWindow {
+            from: parse_time("08:00").unwrap(),
+            to: parse_time("18:00").unwrap(),
+            rate_limit: Some(1 << 20),
+        };
+        let night = BandwidthWindow {
+            from: parse_time("22:30").unwrap(),
+            to: parse_time("06:00").unwrap(),
+            rate_limit: None,
+        };
+        assert!(day.contains(8 * 60) && !day.contains(18 * 60));
+        assert!(night.contains(23 * 60) && night.contains(0) && !night.contains(6 * 60));
+
+        let schedule = [day, night];
+        assert_eq!(limit_at(Some(100), &schedule, 12 * 60), Some(1 << 20));
+        assert_eq!(limit_at(Some(100), &schedule, 23 * 60), None);
+        assert_eq!(limit_at(Some(100), &schedule, 20 * 60), Some(100));
+
+        assert_eq!(format_time(parse_time("7:05").unwrap()), "07:05");
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("noon").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limits = SharedLimits::new(Some(100_000), None, Some(5_000));
+        let a = limits.download("https://example.com/a", None, &[]);
+        let b = limits.download("https://EXAMPLE.com/b", Some(1 << 30), &[]);
+        let other = limits.download("https://example.org/c", None, &[]);
+
+        let start = Instant::now();
+        a.acquire(5_000).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+        // Both downloads from example.com share its 100 KB per second
+        b.acquire(10_000).await;
+        a.acquire(10_000).await;
+        assert!(start.elapsed() >= Duration::from_millis(190));
+        let before = Instant::now();
+        other.acquire(5_000).await;
+        assert!(before.elapsed() < Duration::from_millis(50));
+
+        assert_eq!(RateLimiter::new(Some(0)).limit(), None);
+        assert_eq!(limits.shared("not a url").limit(), None);
+    }
+}
@@ -0,0 +1,87 @@
+//! Audible signal when a batch is done, for `--bell`.
+//!
+//! The terminal bell travels over SSH to whatever terminal the session runs
+//! in, so it works where no notification daemon does. A sound file can be
+//! played on this machine as well, with the first player found of
+//! `paplay`, `aplay` and `ffplay` (`afplay` on macOS, PowerShell on
+//! Windows).
+
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Rings the terminal bell, unless stderr is not a terminal.
+pub fn ring() {
+    let mut stderr = std::io::stderr();
+    if stderr.is_terminal() {
+        stderr.write_all(b"\x07").ok();
+        stderr.flush().ok();
+    }
+}
+
+/// Starts playing `sound` in the background with the first player that
+/// can be run.
+pub fn play(sound: &Path) -> std::io::Result<()> {
+    let mut last = None;
+    for mut player in players(sound) {
+        match player
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| std::io::Error::other("no sound player available")))
+}
+
+/// Commands that play `sound`, in the order they are tried.
+fn players(sound: &Path) -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        let mut afplay = Command::new("afplay");
+        afplay.arg(sound);
+        vec![afplay]
+    } else if cfg!(windows) {
+        let mut powershell = Command::new("powershell");
+        powershell.args(["-NoProfile", "-Command"]).arg(format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            sound.display().to_string().replace('\'', "''")
+        ));
+        vec![powershell]
+    } else {
+        let mut paplay = Command::new("paplay");
+        paplay.arg(sound);
+        let mut aplay = Command::new("aplay");
+        aplay.arg("-q").arg(sound);
+        let mut ffplay = Command::new("ffplay");
+        ffplay
+            .args(["-nodisp", "-autoexit", "-loglevel", "quiet"])
+            .arg(sound);
+        vec![paplay, aplay, ffplay]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players() {
+        let players = players(Path::new("done.wav"));
+        let first = &players[0];
+        let args: Vec<_> = first.get_args().collect();
+        if cfg!(target_os = "macos") {
+            assert_eq!(first.get_program(), "afplay");
+        } else if cfg!(windows) {
+            assert_eq!(first.get_program(), "powershell");
+        } else {
+            assert_eq!(first.get_program(), "paplay");
+            assert_eq!(players.len(), 3);
+        }
+        if !cfg!(windows) {
+            assert_eq!(args.last().unwrap(), &"done.wav");
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Throughput of a URL with different worker counts and buffer sizes, for
+//! `dwrs bench`.
+//!
+//! Each run is a regular download into a temporary file, split into ranged
+//! requests between the workers when the server takes them, that stops
+//! after a time limit. The file is removed afterwards and rate limits do
+//! not apply.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::bandwidth::RateLimiter;
+use crate::download::{Connections, DownloadOptions, download_file};
+use crate::filter::TypeFilter;
+use crate::progress::ProgressBar;
+use crate::transport::Transport;
+use crate::units::{format_bytes, format_speed};
+
+/// One combination of settings to measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchCase {
+    pub workers: usize,
+    pub buffer_size: usize,
+}
+
+impl BenchCase {
+    /// Every combination of `workers` and `buffer_sizes`.
+    pub fn grid(workers: &[usize], buffer_sizes: &[usize]) -> Vec<Self> {
+        workers
+            .iter()
+            .flat_map(|&workers| {
+                buffer_sizes.iter().map(move |&buffer_size| Self {
+                    workers,
+                    buffer_size,
+                })
+            })
+            .collect()
+    }
+}
+
+/// What one run achieved
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub case: BenchCase,
+    /// Connections actually opened; 1 if the server does not take ranges
+    pub connections: usize,
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Bytes per second
+    pub throughput: f64,
+}
+
+/// Downloads `url` with the settings of `case` into a temporary file for
+/// at most `limit`, then removes it.
+///
+/// Fails like the download would, e.g. when a ranged request is not
+/// answered with `206 Partial Content`.
+pub async fn measure(
+    transport: &Arc<dyn Transport>,
+    url: &str,
+    headers: &[(String, String)],
+    case: BenchCase,
+    limit: Duration,
+) -> Result<BenchResult, Box<dyn std::error::Error + Send + Sync>> {
+    let dir = std::env::temp_dir().join(format!("dwrs-bench-{}", std::process::id()));
+    let output = dir.join("bench.bin");
+    let pb = ProgressBar::hidden();
+    let connections = Connections::new();
+    let opts = DownloadOptions {
+        transport,
+        url,
+        output: &output,
+        pb: &pb,
+        resume: false,
+        workers: case.workers,
+        buffer_size: case.buffer_size,
+        min_parallel_size: 0,
+        headers,
+        payload: None,
+        checksum: None,
+        limiter: Arc::new(RateLimiter::new(None)),
+        type_filter: &TypeFilter::default(),
+        provenance: false,
+        decompress: false,
+        pipe: None,
+        tee: false,
+        connections: Some(&connections),
+    };
+
+    let started = Instant::now();
+    let result = tokio::time::timeout(limit, download_file(opts)).await;
+    let duration = started.elapsed().min(limit);
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+        log::warn!("Failed to remove {}: {}", dir.display(), e);
+    }
+    if let Ok(Err(e)) = result {
+        return Err(e);
+    }
+
+    let bytes = pb.position();
+    let secs = duration.as_secs_f64();
+    Ok(BenchResult {
+        case,
+        connections: connections.snapshot().len().max(1),
+        bytes,
+        duration,
+        throughput: if secs > 0.0 { bytes as f64 / secs } else { 0.0 },
+    })
+}
+
+/// Column headings matching [`render_row`].
+pub fn render_header() -> String {
+    format!(
+        "{:>7}  {:>10}  {:>10}  {:>12}",
+        "Workers", "Buffer", "Received", "Throughput"
+    )
+}
+
+/// One line of the results table; runs that fell back to fewer
+/// connections than asked for say so.
+pub fn render_row(result: &BenchResult) -> String {
+    let mut row = format!(
+        "{:>7}  {:>10}  {:>10}  {:>12}",
+        result.case.workers,
+        format_bytes(result.case.buffer_size as u64),
+        format_bytes(result.bytes),
+        format_speed(result.throughput)
+    );
+    if result.connections < result.case.workers {
+        row.push_str(&format!("  ({} connection)", result.connections));
+    }
+    row
+}
+
+/// The case with the highest throughput, preferring fewer workers and
+/// smaller buffers on ties.
+pub fn best(results: &[BenchResult]) -> Option<&BenchResult> {
+    results
+        .iter()
+        .rev()
+        .max_by(|a, b| a.throughput.total_cmp(&b.throughput))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[tokio::test]
+    async fn test_measure() {
+        // Two chunks of the smallest size worth a connection of its own
+        let chunk = 2 * 1024 * 1024;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method("HEAD").path("/bench.bin");
+            then.status(200)
+                .header("Content-Length", (2 * chunk).to_string())
+                .header("Accept-Ranges", "bytes");
+        });
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/bench.bin")
+                .header("Range", format!("bytes=0-{}", chunk - 1));
+            then.status(206).body(vec![b'x'; chunk]);
+        });
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/bench.bin")
+                .header("Range", format!("bytes={}-{}", chunk, 2 * chunk - 1));
+            then.status(206).body(vec![b'y'; chunk]);
+        });
+        let transport: Arc<dyn Transport> = Arc::new(reqwest::Client::new());
+        let cases = BenchCase::grid(&[2], &[1024, 4096]);
+        let mut results = Vec::new();
+        for case in cases {
+            let result = measure(
+                &transport,
+                &server.url("/bench.bin"),
+                &[],
+                case,
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+            assert_eq!((result.connections, result.bytes), (2, 2 * chunk as u64));
+            results.push(result);
+        }
+        assert_eq!(results.len(), 2);
+        assert!(best(&results).is_some());
+        assert!(render_row(&results[0]).starts_with("      2    1.00 KiB"));
+
+        server.mock(|when, then| {
+            when.method("HEAD").path("/full.bin");
+            then.status(200)
+                .header("Content-Length", (2 * chunk).to_string())
+                .header("Accept-Ranges", "bytes");
+        });
+        server.mock(|when, then| {
+            when.method("GET").path("/full.bin");
+            then.status(200).body(vec![b'x'; 2 * chunk]);
+        });
+        let err = measure(
+            &transport,
+            &server.url("/full.bin"),
+            &[],
+            BenchCase {
+                workers: 2,
+                buffer_size: 1024,
+            },
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("instead of 206"));
+    }
+}
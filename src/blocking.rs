@@ -0,0 +1,214 @@
+//! Blocking version of [`Downloader`](crate::Downloader) for code that
+//! is not async, such as build scripts and simple command line tools.
+//!
+//! Every call blocks the current thread until it is done, on a runtime
+//! owned by the downloader. Like `reqwest::blocking`, it must not be used
+//! from within an async runtime, where it panics.
+//!
+//! ```rust,no_run
+//! use dwrs::blocking::Downloader;
+//! use std::path::PathBuf;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let downloader = Downloader::new_default();
+//! downloader.download_file("https://example.com/file.zip", PathBuf::from("file.zip"))?;
+//! let notes = downloader.download_to_vec("https://example.com/notes.txt", 64 * 1024)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
+use tokio::runtime::Runtime;
+
+use crate::{
+    DownloadConfig, DownloadEntry, DownloadReport, middleware, notifier, resolver, spider,
+    transport,
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Blocking wrapper around an async [`Downloader`](crate::Downloader)
+pub struct Downloader {
+    inner: crate::Downloader,
+    runtime: Runtime,
+}
+
+impl Downloader {
+    /// Creates a downloader with its own runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime or the HTTP client cannot be created.
+    pub fn new(config: DownloadConfig) -> Self {
+        crate::Downloader::new(config).into()
+    }
+
+    /// Creates a downloader with the default configuration.
+    pub fn new_default() -> Self {
+        Self::new(DownloadConfig::default())
+    }
+
+    /// The async downloader the calls go to.
+    pub fn inner(&self) -> &crate::Downloader {
+        &self.inner
+    }
+
+    /// See [`Downloader::register_resolver`](crate::Downloader::register_resolver).
+    pub fn register_resolver(&mut self, resolver: impl resolver::Resolver + 'static) {
+        self.inner.register_resolver(resolver);
+    }
+
+    /// See [`Downloader::set_transport`](crate::Downloader::set_transport).
+    pub fn set_transport(&mut self, transport: impl transport::Transport + 'static) {
+        self.inner.set_transport(transport);
+    }
+
+    /// See [`Downloader::add_middleware`](crate::Downloader::add_middleware).
+    pub fn add_middleware(&mut self, middleware: impl middleware::Middleware + 'static) {
+        self.inner.add_middleware(middleware);
+    }
+
+    /// See [`Downloader::add_notifier`](crate::Downloader::add_notifier).
+    pub fn add_notifier(&mut self, notifier: impl notifier::Notifier + 'static) {
+        self.inner.add_notifier(notifier);
+    }
+
+    /// See [`Downloader::with_multi_progress`](crate::Downloader::with_multi_progress).
+    pub fn with_multi_progress(self, mp: crate::progress::MultiProgress) -> Self {
+        Self {
+            inner: self.inner.with_multi_progress(mp),
+            ..self
+        }
+    }
+
+    /// See [`Downloader::download_file`](crate::Downloader::download_file).
+    pub fn download_file(&self, url: &str, output_path: PathBuf) -> Result<DownloadReport, Error> {
+        self.runtime
+            .block_on(self.inner.download_file(url, output_path))
+    }
+
+    /// See [`Downloader::download_multiple`](crate::Downloader::download_multiple).
+    pub fn download_multiple(&self, downloads: Vec<(&str, PathBuf)>) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.download_multiple(downloads))
+    }
+
+    /// See [`Downloader::download_entries`](crate::Downloader::download_entries).
+    pub fn download_entries(&self, downloads: Vec<DownloadEntry>) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.download_entries(downloads))
+    }
+
+    /// See [`Downloader::download_from_file`](crate::Downloader::download_from_file).
+    pub fn download_from_file(&self, file_path: PathBuf) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.download_from_file(file_path))
+    }
+
+    /// Streams a download into `writer`, see
+    /// [`Downloader::download_to_writer`](crate::Downloader::download_to_writer).
+    ///
+    /// `writer` is flushed at the end.
+    pub fn download_to_writer<W: Write + Unpin + Send>(
+        &self,
+        url: &str,
+        writer: W,
+    ) -> Result<u64, Error> {
+        self.runtime
+            .block_on(self.inner.download_to_writer(url, SyncWriter(writer)))
+    }
+
+    /// See [`Downloader::download_bytes`](crate::Downloader::download_bytes).
+    pub fn download_bytes(&self, url: &str, limit: usize) -> Result<bytes::Bytes, Error> {
+        self.runtime.block_on(self.inner.download_bytes(url, limit))
+    }
+
+    /// See [`Downloader::download_to_vec`](crate::Downloader::download_to_vec).
+    pub fn download_to_vec(&self, url: &str, limit: usize) -> Result<Vec<u8>, Error> {
+        self.runtime
+            .block_on(self.inner.download_to_vec(url, limit))
+    }
+
+    /// See [`Downloader::check_url`](crate::Downloader::check_url).
+    pub fn check_url(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<spider::UrlStatus, Error> {
+        self.runtime.block_on(self.inner.check_url(url, headers))
+    }
+}
+
+impl From<crate::Downloader> for Downloader {
+    /// Wraps an async downloader, e.g. one made with
+    /// [`Downloader::builder`](crate::Downloader::builder).
+    fn from(inner: crate::Downloader) -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build the runtime");
+        Self { inner, runtime }
+    }
+}
+
+/// Lets a blocking writer take the place of an async one; writes block
+/// the thread of the runtime, which is the caller's anyway.
+struct SyncWriter<W>(W);
+
+impl<W: Write + Unpin> AsyncWrite for SyncWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.get_mut().0.write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(self.get_mut().0.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[test]
+    fn test_blocking_downloader() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/file.txt");
+            then.status(200)
+                .header("Content-Length", "11")
+                .body("hello world");
+        });
+        let downloader: Downloader = crate::Downloader::builder()
+            .progress(false)
+            .build()
+            .unwrap()
+            .into();
+        let url = server.url("/file.txt");
+
+        assert_eq!(
+            downloader.download_to_vec(&url, 100).unwrap(),
+            b"hello world"
+        );
+        let mut out = Vec::new();
+        assert_eq!(downloader.download_to_writer(&url, &mut out).unwrap(), 11);
+        assert_eq!(out, b"hello world");
+
+        let path = std::env::temp_dir().join(format!("dwrs-blocking-{}", std::process::id()));
+        downloader.download_file(&url, path.clone()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    DownloadConfig, Downloader, bandwidth, checksum, config, filter, hooks, middleware, notifier,
+    pipe, progress, resolver, retry, rewrite, transport,
+};
+
+/// Builds a [`DownloadConfig`] field by field, checking it at the end.
+///
+/// Unlike a struct literal, code using the builder keeps compiling when
+/// fields are added. Unset fields keep their [`Default`] value.
+///
+/// ```
+/// use dwrs::DownloadConfig;
+///
+/// let config = DownloadConfig::builder()
+///     .workers(8)
+///     .retries(5)
+///     .rate_limit(2 * 1024 * 1024)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.rate_limit, Some(2 * 1024 * 1024));
+///
+/// assert!(DownloadConfig::builder().workers(0).build().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DownloadConfigBuilder {
+    config: DownloadConfig,
+}
+
+/// Builds a [`Downloader`], taking the same settings as
+/// [`DownloadConfigBuilder`] plus the resolvers to register and the
+/// transport to use.
+#[derive(Default)]
+pub struct DownloaderBuilder {
+    config: DownloadConfigBuilder,
+    resolvers: Vec<Arc<dyn resolver::Resolver>>,
+    transport: Option<Arc<dyn transport::Transport>>,
+    middleware: Vec<Arc<dyn middleware::Middleware>>,
+    multi_progress: Option<progress::MultiProgress>,
+}
+
+/// Generates one setter per [`DownloadConfig`] field on both builders,
+/// taking the given argument type; `=> Some` marks optional fields, which
+/// the setter turns on.
+macro_rules! setters {
+    ($($(#[$attr:meta])* $field:ident: $ty:ty $(=> $some:ident)?;)*) => {
+        impl DownloadConfigBuilder {
+            $(
+                $(#[$attr])*
+                #[doc = concat!("Sets [`DownloadConfig::", stringify!($field), "`].")]
+                pub fn $field(mut self, value: $ty) -> Self {
+                    self.config.$field = $($some)?(value.into());
+                    self
+                }
+            )*
+        }
+
+        impl DownloaderBuilder {
+            $(
+                $(#[$attr])*
+                #[doc = concat!("Sets [`DownloadConfig::", stringify!($field), "`].")]
+                pub fn $field(mut self, value: $ty) -> Self {
+                    self.config = self.config.$field(value);
+                    self
+                }
+            )*
+        }
+    };
+}
+
+setters! {
+    workers: usize;
+    continue_download: bool;
+    #[cfg(feature = "notify")]
+    notify: bool;
+    template: impl Into<String>;
+    msg_template: impl Into<String>;
+    chars: impl Into<String>;
+    buffer_size: usize;
+    pool_size: usize;
+    retries: usize;
+    min_parallel_size: u64;
+    max_concurrent_files: usize => Some;
+    failed_file: impl Into<PathBuf> => Some;
+    user_agent: impl Into<String>;
+    referer: impl Into<String> => Some;
+    payload: transport::Payload => Some;
+    proxy: impl Into<String> => Some;
+    rate_limit: u64 => Some;
+    schedule: Vec<bandwidth::BandwidthWindow>;
+    host_rate_limit: u64 => Some;
+    total_rate_limit: u64 => Some;
+    rate_burst: u64 => Some;
+    quota: u64 => Some;
+    type_filter: filter::TypeFilter;
+    #[cfg(feature = "history")]
+    history_file: impl Into<PathBuf> => Some;
+    progress: bool;
+    progress_interval: Duration => Some;
+    terminal_title: bool;
+    bell: bool;
+    bell_sound: impl Into<PathBuf> => Some;
+    fail_fast: bool;
+    preflight: bool;
+    summary: bool;
+    report_json: impl Into<PathBuf> => Some;
+    write_checksums: checksum::ChecksumOutput => Some;
+    provenance: bool;
+    decompress: bool;
+    pipe: pipe::PipeCommand => Some;
+    tee: bool;
+    #[cfg(feature = "extract")]
+    extract: bool;
+    #[cfg(feature = "extract")]
+    delete_archive: bool;
+    on_complete: hooks::Hook => Some;
+    on_error: hooks::Hook => Some;
+    notify_messages: notifier::Messages;
+    notify_threshold: notifier::Threshold;
+    notify_webhook: hooks::Webhook => Some;
+    #[cfg(feature = "email")]
+    email: crate::email::Mailer => Some;
+    rewrite: rewrite::Rewriter;
+    status_file: impl Into<PathBuf> => Some;
+    hosts: BTreeMap<String, config::HostConfig>;
+}
+
+impl DownloadConfigBuilder {
+    /// Sets [`DownloadConfig::retry_policy`].
+    pub fn retry_policy(mut self, policy: impl retry::RetryPolicy + 'static) -> Self {
+        self.config.retry_policy = Arc::new(policy);
+        self
+    }
+
+    /// Passes `value` to the setter `set` if there is one, for settings
+    /// that are only sometimes given:
+    /// `.maybe(proxy, DownloadConfigBuilder::proxy)`.
+    pub fn maybe<T>(self, value: Option<T>, set: impl FnOnce(Self, T) -> Self) -> Self {
+        match value {
+            Some(value) => set(self, value),
+            None => self,
+        }
+    }
+
+    /// Checks the configuration, see [`DownloadConfig::validate`].
+    pub fn build(self) -> Result<DownloadConfig, String> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+impl DownloaderBuilder {
+    /// Starts from `config` instead of the defaults.
+    pub fn config(mut self, config: DownloadConfig) -> Self {
+        self.config = DownloadConfigBuilder { config };
+        self
+    }
+
+    /// Registers a resolver, see [`Downloader::register_resolver`].
+    pub fn resolver(mut self, resolver: impl resolver::Resolver + 'static) -> Self {
+        self.resolvers.push(Arc::new(resolver));
+        self
+    }
+
+    /// Sends requests through `transport`, see [`Downloader::set_transport`].
+    pub fn transport(mut self, transport: impl transport::Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Sets [`DownloadConfig::retry_policy`].
+    pub fn retry_policy(mut self, policy: impl retry::RetryPolicy + 'static) -> Self {
+        self.config = self.config.retry_policy(policy);
+        self
+    }
+
+    /// Passes `value` to the setter `set` if there is one, see
+    /// [`DownloadConfigBuilder::maybe`].
+    pub fn maybe<T>(self, value: Option<T>, set: impl FnOnce(Self, T) -> Self) -> Self {
+        match value {
+            Some(value) => set(self, value),
+            None => self,
+        }
+    }
+
+    /// Adds middleware, see [`Downloader::add_middleware`].
+    pub fn middleware(mut self, middleware: impl middleware::Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Draws the bars in `mp`, see [`Downloader::with_multi_progress`].
+    pub fn multi_progress(mut self, mp: progress::MultiProgress) -> Self {
+        self.multi_progress = Some(mp);
+        self
+    }
+
+    /// Checks the configuration and creates the [`Downloader`].
+    pub fn build(self) -> Result<Downloader, String> {
+        let mut downloader = Downloader::new(self.config.build()?);
+        for resolver in self.resolvers {
+            downloader.resolvers.register(resolver);
+        }
+        downloader.middleware = self.middleware;
+        downloader.multi_progress = self.multi_progress;
+        let transport = self.transport.unwrap_or_else(|| downloader.backend.clone());
+        downloader.use_transport(transport);
+        Ok(downloader)
+    }
+}
+
+impl DownloadConfig {
+    /// Starts a [`DownloadConfigBuilder`] from the defaults.
+    pub fn builder() -> DownloadConfigBuilder {
+        DownloadConfigBuilder::default()
+    }
+
+    /// Checks the settings a struct literal can get wrong, with the same
+    /// bounds as the config file.
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |key: &str, value: usize, min: usize, max: usize| {
+            if value < min || value > max {
+                return Err(format!("{}: {} is outside {}..={}", key, value, min, max));
+            }
+            Ok(())
+        };
+        in_range("workers", self.workers, 1, 128)?;
+        in_range("buffer_size", self.buffer_size, 1024, 64 * 1024 * 1024)?;
+        in_range("pool_size", self.pool_size, 0, 10_000)?;
+        in_range("retries", self.retries, 1, 100)?;
+        if self.max_concurrent_files == Some(0) {
+            return Err("max_concurrent_files: must be at least 1".to_string());
+        }
+        if let Err(e) = progress::check_template(&self.template) {
+            return Err(format!("template: {}", e));
+        }
+        if self.chars.chars().count() < 2 {
+            return Err("chars: needs at least 2 characters".to_string());
+        }
+        if let Err(e) = crate::user_agent::check(&self.user_agent) {
+            return Err(format!("user_agent: {}", e));
+        }
+        if let Some(proxy) = &self.proxy
+            && reqwest::Proxy::all(proxy).is_err()
+        {
+            return Err(format!("proxy: invalid proxy URL '{}'", proxy));
+        }
+        Ok(())
+    }
+}
+
+impl Downloader {
+    /// Starts a [`DownloaderBuilder`] from the default configuration.
+    ///
+    /// ```
+    /// use dwrs::Downloader;
+    ///
+    /// let downloader = Downloader::builder()
+    ///     .workers(8)
+    ///     .decompress(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> DownloaderBuilder {
+        DownloaderBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builders() {
+        let config = DownloadConfig::builder()
+            .workers(8)
+            .proxy("http://127.0.0.1:3128")
+            .build()
+            .unwrap();
+        assert_eq!(config.workers, 8);
+        assert_eq!(config.proxy.as_deref(), Some("http://127.0.0.1:3128"));
+        assert_eq!(config.retries, DownloadConfig::default().retries);
+
+        let err = DownloadConfig::builder().buffer_size(16).build();
+        assert_eq!(
+            err.unwrap_err(),
+            "buffer_size: 16 is outside 1024..=67108864"
+        );
+        assert!(
+            DownloadConfig::builder()
+                .max_concurrent_files(0)
+                .build()
+                .is_err()
+        );
+
+        let downloader = Downloader::builder()
+            .retries(1)
+            .resolver(resolver::S3)
+            .build()
+            .unwrap();
+        assert_eq!(downloader.config.retries, 1);
+        assert_eq!(downloader.resolvers.names()[0], "s3");
+        assert!(Downloader::builder().retries(0).build().is_err());
+        assert_eq!(
+            Downloader::builder()
+                .user_agent("bad\nagent")
+                .build()
+                .err()
+                .unwrap(),
+            "user_agent: invalid user agent 'bad\nagent'"
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use base64::Engine;
+use md5::Md5;
+use reqwest::header::{CONTENT_ENCODING, HeaderMap};
+use sha2::digest::DynDigest;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+/// File `--write-checksums` collects the digests of a run in
+pub const SUMS_FILE: &str = "SHA256SUMS";
+
+/// Supported checksum algorithms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Md5 => write!(f, "md5"),
+            Algorithm::Sha256 => write!(f, "sha256"),
+            Algorithm::Sha512 => write!(f, "sha512"),
+        }
+    }
+}
+
+/// Expected digest of a file, written as `algo:hex` or bare hex (SHA-256)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub hex: String,
+}
+
+impl Checksum {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (algorithm, hex) = match spec.split_once(':') {
+            Some((algo, hex)) => {
+                let algorithm = match algo.to_ascii_lowercase().as_str() {
+                    "md5" => Algorithm::Md5,
+                    "sha256" | "sha-256" => Algorithm::Sha256,
+                    "sha512" | "sha-512" => Algorithm::Sha512,
+                    other => return Err(format!("Unsupported checksum algorithm: {}", other)),
+                };
+                (algorithm, hex)
+            }
+            None => (Algorithm::Sha256, spec),
+        };
+
+        let expected_len = match algorithm {
+            Algorithm::Md5 => 32,
+            Algorithm::Sha256 => 64,
+            Algorithm::Sha512 => 128,
+        };
+        if hex.len() != expected_len || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid {} checksum: {}", algorithm, hex));
+        }
+
+        Ok(Self {
+            algorithm,
+            hex: hex.to_ascii_lowercase(),
+        })
+    }
+}
+
+/// Checksum of the whole body announced in a `Digest` (RFC 3230) or
+/// `Content-MD5` header, the strongest one if there are several.
+///
+/// Returns `None` for content-encoded responses, whose digests cover the
+/// encoded bytes rather than the saved file.
+pub fn from_headers(headers: &HeaderMap) -> Option<Checksum> {
+    if headers
+        .get(CONTENT_ENCODING)
+        .is_some_and(|v| v.as_bytes() != b"identity")
+    {
+        return None;
+    }
+    let digest = headers
+        .get_all("digest")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|item| item.trim().split_once('='));
+    let content_md5 = headers
+        .get("content-md5")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| ("md5", v));
+    digest
+        .chain(content_md5)
+        .filter_map(|(algo, value)| {
+            let algorithm = match algo.trim().to_ascii_lowercase().as_str() {
+                "md5" => Algorithm::Md5,
+                "sha-256" => Algorithm::Sha256,
+                "sha-512" => Algorithm::Sha512,
+                _ => return None,
+            };
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(value.trim())
+                .ok()?;
+            Checksum::parse(&format!("{}:{}", algorithm, hex::encode(bytes))).ok()
+        })
+        .max_by_key(|checksum| checksum.algorithm)
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+/// Digest of a byte stream fed piece by piece, e.g. while it is written
+pub struct Hasher {
+    digest: Box<dyn DynDigest + Send>,
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Self {
+        let digest: Box<dyn DynDigest + Send> = match algorithm {
+            Algorithm::Md5 => Box::new(Md5::new()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha512 => Box::new(Sha512::new()),
+        };
+        Self { digest }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.digest.update(bytes);
+    }
+
+    /// Hex digest of everything fed so far.
+    pub fn finalize(self) -> String {
+        hex::encode(self.digest.finalize())
+    }
+}
+
+/// Computes the hex digest of a file.
+pub async fn hash_file(path: &Path, algorithm: Algorithm) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Error returned when a file does not have its expected checksum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    pub expected: Checksum,
+    /// Hex digest the file actually has
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checksum mismatch for {}: expected {}, got {}:{}",
+            self.path.display(),
+            self.expected,
+            self.expected.algorithm,
+            self.actual
+        )
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Compares the hex digest `actual` of the file at `path` with `expected`,
+/// failing with [`Mismatch`] if they differ.
+pub fn check(
+    path: &Path,
+    expected: &Checksum,
+    actual: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if actual != expected.hex {
+        log::error!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            path.display(),
+            expected.hex,
+            actual
+        );
+        return Err(Mismatch {
+            path: path.to_path_buf(),
+            expected: expected.clone(),
+            actual: actual.to_string(),
+        }
+        .into());
+    }
+    log::info!("Checksum verified for {}", path.display());
+    Ok(())
+}
+
+/// Verifies a downloaded file against its expected checksum.
+pub async fn verify_file(
+    path: &Path,
+    expected: &Checksum,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let actual = hash_file(path, expected.algorithm).await?;
+    check(path, expected, &actual)
+}
+
+/// State of a local file compared with its expected checksum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileCheck {
+    Ok,
+    Missing,
+    /// The file has this other hex digest
+    Mismatch(String),
+}
+
+/// Compares the file at `path` with `expected` without failing on a mismatch.
+pub async fn check_file(path: &Path, expected: &Checksum) -> std::io::Result<FileCheck> {
+    match hash_file(path, expected.algorithm).await {
+        Ok(actual) if actual == expected.hex => Ok(FileCheck::Ok),
+        Ok(actual) => Ok(FileCheck::Mismatch(actual)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FileCheck::Missing),
+        Err(e) => Err(e),
+    }
+}
+
+/// Where the SHA-256 digests of downloaded files are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChecksumOutput {
+    /// One `SHA256SUMS` file in the directory holding the downloads,
+    /// updated on every run
+    Sums,
+    /// A `<file>.sha256` next to every downloaded file
+    Sidecar,
+}
+
+/// Parses `sha256sum` output into paths and hex digests.
+fn parse_sums(content: &str) -> BTreeMap<PathBuf, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hex, path) = line.split_once(' ')?;
+            let path = path.strip_prefix(['*', ' ']).unwrap_or(path);
+            Some((PathBuf::from(path), hex.to_string()))
+        })
+        .collect()
+}
+
+/// Reads a `SHA256SUMS` or `.sha256` style file.
+///
+/// The algorithm of each line follows from the digest length, so MD5 and
+/// SHA-512 sums work too. Relative paths are taken from the directory of
+/// the sums file; lines that are not a digest and a path are skipped.
+pub async fn read_sums(path: &Path) -> std::io::Result<Vec<(PathBuf, Checksum)>> {
+    let content = tokio::fs::read_to_string(path).await?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(parse_sums(&content)
+        .into_iter()
+        .filter_map(|(file, hex)| {
+            let algorithm = match hex.len() {
+                32 => Algorithm::Md5,
+                64 => Algorithm::Sha256,
+                128 => Algorithm::Sha512,
+                _ => return None,
+            };
+            let checksum = Checksum::parse(&format!("{}:{}", algorithm, hex)).ok()?;
+            Some((dir.join(file), checksum))
+        })
+        .collect())
+}
+
+/// Deepest directory holding all of `files`.
+fn common_dir(files: &[PathBuf]) -> PathBuf {
+    let mut dirs = files
+        .iter()
+        .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone()))
+        .map(|path| path.parent().map(Path::to_path_buf).unwrap_or_default());
+    let Some(mut common) = dirs.next() else {
+        return PathBuf::new();
+    };
+    for dir in dirs {
+        while !dir.starts_with(&common) && common.pop() {}
+    }
+    common
+}
+
+/// Replaces `path` with `content` through a temporary file, so readers
+/// never see it half written.
+async fn write_atomic(path: &Path, content: String) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".tmp-{}", std::process::id()));
+    tokio::fs::write(&temp, content).await?;
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        tokio::fs::remove_file(&temp).await.ok();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Writes the SHA-256 of every file in `files` in `sha256sum` format.
+///
+/// Files that already carry a SHA-256 checksum, such as the digest taken
+/// while they were downloaded, are not hashed again. With
+/// [`ChecksumOutput::Sums`] the sums file goes to the deepest directory
+/// holding all files, listing them relative to it, and existing lines for
+/// other files are kept.
+pub async fn write_checksums(
+    files: &[(PathBuf, Option<Checksum>)],
+    output: ChecksumOutput,
+) -> std::io::Result<()> {
+    let dir = common_dir(
+        &files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>(),
+    );
+    let sums_file = dir.join(SUMS_FILE);
+    let mut sums = match output {
+        ChecksumOutput::Sums => match tokio::fs::read_to_string(&sums_file).await {
+            Ok(content) => parse_sums(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        },
+        ChecksumOutput::Sidecar => BTreeMap::new(),
+    };
+
+    for (path, checksum) in files {
+        let hex = match checksum {
+            Some(checksum) if checksum.algorithm == Algorithm::Sha256 => checksum.hex.clone(),
+            _ => hash_file(path, Algorithm::Sha256).await?,
+        };
+        match output {
+            ChecksumOutput::Sums => {
+                let absolute = std::path::absolute(path)?;
+                let relative = absolute.strip_prefix(&dir).unwrap_or(&absolute);
+                sums.insert(relative.to_path_buf(), hex);
+            }
+            ChecksumOutput::Sidecar => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                let mut sidecar = path.clone().into_os_string();
+                sidecar.push(".sha256");
+                let line = format!("{}  {}\n", hex, Path::new(name).display());
+                write_atomic(Path::new(&sidecar), line).await?;
+            }
+        }
+    }
+
+    if output == ChecksumOutput::Sums && !files.is_empty() {
+        let content: String = sums
+            .iter()
+            .map(|(path, hex)| format!("{}  {}\n", hex, path.display()))
+            .collect();
+        write_atomic(&sums_file, content).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers), None);
+
+        // MD5 and SHA-256 of "hello"
+        headers.insert("content-md5", "XUFAKrxLKna5cZ2REBfFkg==".parse().unwrap());
+        assert_eq!(
+            from_headers(&headers).unwrap().to_string(),
+            "md5:5d41402abc4b2a76b9719d911017c592"
+        );
+        headers.insert(
+            "digest",
+            "UNIXsum=30637, SHA-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ="
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            from_headers(&headers).unwrap().to_string(),
+            "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+
+        headers.insert("content-encoding", "gzip".parse().unwrap());
+        assert_eq!(from_headers(&headers), None);
+
+        let mut hasher = Hasher::new(Algorithm::Md5);
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+        assert_eq!(hasher.finalize(), "5d41402abc4b2a76b9719d911017c592");
+    }
+
+    #[tokio::test]
+    async fn test_sums_files() {
+        let dir = std::env::temp_dir().join(format!("dwrs-sums-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("hello.txt");
+        std::fs::write(&file, "hello").unwrap();
+
+        write_checksums(&[(file.clone(), None)], ChecksumOutput::Sidecar)
+            .await
+            .unwrap();
+        let sidecar = std::fs::read_to_string(dir.join("hello.txt.sha256")).unwrap();
+        assert_eq!(
+            sidecar,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  hello.txt\n"
+        );
+        assert_eq!(
+            parse_sums(&sidecar).get(Path::new("hello.txt")).unwrap(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert!(parse_sums("abc *bin/a b.iso\n").contains_key(Path::new("bin/a b.iso")));
+
+        let sums = read_sums(&dir.join("hello.txt.sha256")).await.unwrap();
+        assert_eq!(sums[0].0, file);
+        assert_eq!(check_file(&file, &sums[0].1).await.unwrap(), FileCheck::Ok);
+        std::fs::write(&file, "hellO").unwrap();
+        assert!(matches!(
+            check_file(&file, &sums[0].1).await.unwrap(),
+            FileCheck::Mismatch(_)
+        ));
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(
+            check_file(&file, &sums[0].1).await.unwrap(),
+            FileCheck::Missing
+        );
+
+        // The sums file lands next to the downloads, reusing known digests
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.txt"), "a").unwrap();
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+        let known = Checksum::parse(&"0".repeat(64)).unwrap();
+        let files = [
+            (dir.join("sub/a.txt"), Some(known)),
+            (dir.join("b.txt"), None),
+        ];
+        write_checksums(&files, ChecksumOutput::Sums).await.unwrap();
+        let sums = read_sums(&dir.join(SUMS_FILE)).await.unwrap();
+        assert_eq!(sums.len(), 2);
+        assert_eq!(sums[0].0, dir.join("b.txt"));
+        assert_eq!(
+            sums[1],
+            (
+                dir.join("sub/a.txt"),
+                Checksum::parse(&"0".repeat(64)).unwrap()
+            )
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,6 +1,10 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use lazy_static::lazy_static;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::color::ColorChoice;
+use crate::file_parser::{CollisionPolicy, ManifestFormat};
 
 lazy_static! {
     static ref ABOUT_TEXT: String =
@@ -8,19 +12,75 @@ lazy_static! {
             .to_string();
 }
 
+fn parse_rate(value: &str) -> Result<u64, String> {
+    crate::utils::parse_size(value).ok_or_else(|| format!("invalid rate: {}", value))
+}
+
+fn parse_method(value: &str) -> Result<reqwest::Method, String> {
+    reqwest::Method::from_bytes(value.to_ascii_uppercase().as_bytes())
+        .map_err(|_| format!("invalid method: {}", value))
+}
+
+fn parse_user_agent(value: &str) -> Result<String, String> {
+    crate::user_agent::check(value).map(|()| value.to_string())
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    crate::utils::parse_size(value).ok_or_else(|| format!("invalid size: {}", value))
+}
+
 #[derive(Parser)]
 #[command(name = "dwrs", author, version, about = ABOUT_TEXT.as_str())]
-#[command(group(clap::ArgGroup::new("input").required(true).args(&["url","file"])))]
+#[command(after_help = crate::exit::HELP)]
+#[command(group(clap::ArgGroup::new("input").args(&["url","file","sitemap","feed","retry_failed"])))]
 pub struct Args {
-    #[cfg(feature = "notify")]
-    #[arg(short, long)]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Announce finished downloads through the backends of notify_backend,
+    /// the desktop by default
+    #[arg(short, long, global = true)]
     pub notify: bool,
+    /// Only print errors: no progress bars, summary or log messages
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Log more details (-v for info, -vv for debug, -vvv for trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Cancel the remaining downloads as soon as one fails
+    #[arg(long, global = true)]
+    pub fail_fast: bool,
+    /// Check all URLs before a batch starts, to show the total size, check
+    /// the free disk space and download the largest files first
+    #[arg(long, global = true)]
+    pub preflight: bool,
+    /// When to use colors in output and progress bars
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, global = true)]
+    pub color: ColorChoice,
+    /// Show downloads in an interactive full-screen table instead of progress bars
+    #[cfg(feature = "tui")]
+    #[arg(long, global = true, conflicts_with_all = ["quiet", "spider"])]
+    pub tui: bool,
+    /// Write a JSON report of every download to PATH ("-" for stdout)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub report_json: Option<PathBuf>,
+    /// Record the SHA-256 of every downloaded file in a SHA256SUMS next to the downloads or in a .sha256 file next to each
+    #[arg(long, value_enum, value_name = "WHERE", num_args = 0..=1, require_equals = true, default_missing_value = "sums", global = true)]
+    pub write_checksums: Option<crate::checksum::ChecksumOutput>,
+    /// Do not record the source URL and ETag in extended attributes of downloaded files
+    #[arg(long, global = true)]
+    pub no_xattr: bool,
     // enable in background mode
     #[arg(long)]
     pub background: bool,
     // continue downloading from last position
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, default_value_t = false, global = true)]
     pub continue_: bool,
+    /// Overwrite existing output files without asking
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+    /// Skip downloads whose output file already exists
+    #[arg(long, global = true, conflicts_with = "yes")]
+    pub no: bool,
     // url of file to download
     #[arg(required = false)]
     pub url: Vec<String>,
@@ -28,32 +88,532 @@ pub struct Args {
     #[arg(short, long)]
     pub output: Vec<String>,
     // count of workers
-    #[arg(short, long, default_value = "4")]
+    #[arg(short, long, default_value = "4", global = true)]
     pub workers: usize,
-    // file for parsing
+    /// File with URLs to download ("-" reads from stdin)
     #[arg(short, long)]
     pub file: Option<PathBuf>,
+    /// Re-run only the entries recorded in a failed-downloads file [default: --failed-file]
+    #[arg(long, value_name = "PATH")]
+    pub retry_failed: Option<Option<PathBuf>>,
+
+    /// Where failed batch entries are written for --retry-failed [default: <data dir>/dwrs/failed.json]
+    #[arg(long, value_name = "PATH", global = true)]
+    pub failed_file: Option<PathBuf>,
+
+    /// Manifest format for --file (detected from the extension by default)
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    pub format: Option<ManifestFormat>,
+
+    /// What to do when several downloads of a batch have the same output file
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = CollisionPolicy::Rename, global = true)]
+    pub on_collision: CollisionPolicy,
+
+    /// Base URL that relative entries in --file are joined against
+    #[arg(long, value_name = "URL", global = true)]
+    pub base: Option<String>,
+
+    /// CSV column mapping, e.g. "url=Link,output=Name,sha256=Hash"
+    #[arg(long, value_name = "MAPPING", global = true)]
+    pub csv_columns: Option<String>,
     // config file
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub config: Option<String>,
 
+    /// Use the [profile.NAME] section of the config file
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
+    /// User-Agent of every request, or a preset: browser, chrome, curl, wget, dwrs
+    #[arg(long, visible_alias = "ua", value_name = "AGENT", value_parser = parse_user_agent, global = true)]
+    pub user_agent: Option<String>,
+
+    /// Send this Referer with every request; links found by --recursive get their page
+    #[arg(long, value_name = "URL", global = true)]
+    pub referer: Option<String>,
+
+    /// HTTP method of the downloads, e.g. POST for export endpoints
+    #[arg(long, value_name = "METHOD", value_parser = parse_method, global = true)]
+    pub method: Option<reqwest::Method>,
+
+    /// Request body of the downloads, or @FILE to read it (@- for stdin); implies POST.
+    /// Retries send the body again, use -r 0 if the request must not repeat
+    #[arg(long, value_name = "DATA", global = true)]
+    pub data: Option<String>,
+
+    /// Send all requests through this proxy (http, https or socks5 URL)
+    #[arg(long, value_name = "URL", global = true)]
+    pub proxy: Option<String>,
+
+    /// Limit the download rate per file, e.g. 500K or 2M (bytes per second); overrides [[schedule]] windows
+    #[arg(long, value_name = "RATE", value_parser = parse_rate, global = true)]
+    pub limit_rate: Option<u64>,
+
+    /// Limit the download rate shared by all files from one host, e.g. 5M
+    #[arg(long, value_name = "RATE", value_parser = parse_rate, global = true)]
+    pub limit_rate_host: Option<u64>,
+
+    /// Limit the download rate shared by all files, e.g. 10M
+    #[arg(long, value_name = "RATE", value_parser = parse_rate, global = true)]
+    pub limit_rate_total: Option<u64>,
+
+    /// Bytes the rate limits let through at full speed after an idle spell, e.g. 256K
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, global = true)]
+    pub rate_burst: Option<u64>,
+
+    /// Stop starting new downloads once this many bytes were downloaded, e.g. 2G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, global = true)]
+    pub quota: Option<u64>,
+
+    /// Only save responses of these content types, e.g. application/pdf,image/*
+    #[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = crate::filter::parse_media_range, global = true)]
+    pub accept_type: Vec<String>,
+
+    /// Skip responses of these content types, e.g. text/html
+    #[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = crate::filter::parse_media_range, global = true)]
+    pub reject_type: Vec<String>,
+
+    /// Only download URLs whose file name matches one of these globs, e.g. '*.iso,*.torrent'
+    #[arg(
+        short = 'A',
+        long,
+        value_name = "PATTERNS",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub accept: Vec<String>,
+
+    /// Skip URLs whose file name matches one of these globs, e.g. '*.tmp'
+    #[arg(
+        short = 'R',
+        long,
+        value_name = "PATTERNS",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub reject: Vec<String>,
+
+    /// How often to print progress lines when stderr is not a terminal, e.g. 30s
+    #[arg(long, value_name = "DURATION", value_parser = crate::deferred::parse_duration, default_value = "10s", global = true)]
+    pub progress_interval: Duration,
+
+    /// Language of messages instead of the system locale, e.g. ru
+    #[arg(long, value_name = "LANG", global = true)]
+    pub lang: Option<String>,
+
+    /// Show the overall progress in the terminal window title
+    #[arg(long, global = true)]
+    pub title: bool,
+
+    /// Ring the terminal bell when the last download finishes
+    #[arg(long, global = true)]
+    pub bell: bool,
+
+    /// Also play this sound file on this machine when the last download finishes
+    #[arg(long, value_name = "FILE", global = true)]
+    pub bell_sound: Option<PathBuf>,
+
+    /// Wait until this local time before starting, e.g. 02:30 or "2024-05-01 02:30"
+    #[arg(long, value_name = "TIME", value_parser = crate::deferred::parse_start_at_now, global = true)]
+    pub start_at: Option<SystemTime>,
+
+    /// Wait this long before starting, e.g. 90s, 15m or 1h30m
+    #[arg(long, value_name = "DURATION", value_parser = crate::deferred::parse_duration, conflicts_with = "start_at", global = true)]
+    pub start_in: Option<Duration>,
+
+    /// Save downloads without an output name to a path built from their URL,
+    /// e.g. "{host}/{dirname}/{filename}" or "{date}-{filename}"
+    #[arg(long, value_name = "TEMPLATE", global = true)]
+    pub output_template: Option<String>,
+
+    /// Run a command after every successful download, e.g. 'clamscan {path}'
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub on_complete: Option<String>,
+
+    /// Run a command after every failed download, e.g. 'notify-send failed {url}'
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub on_error: Option<String>,
+
+    /// POST a JSON report of every finished download to this URL, e.g. a
+    /// Slack, Discord or Home Assistant webhook
+    #[arg(long, value_name = "URL", global = true)]
+    pub notify_webhook: Option<String>,
+
+    /// Save .gz and .zst downloads decompressed, e.g. data.json.gz as data.json
+    #[arg(long, global = true)]
+    pub decompress: bool,
+
+    /// Stream every download through a command and save what it prints,
+    /// e.g. 'gpg --decrypt' ({url} and {path} are filled in)
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub pipe: Option<String>,
+
+    /// Save the download and also stream it to stdout, like curl | tee
+    #[arg(long, global = true)]
+    pub tee: bool,
+
+    /// Unpack downloaded .zip, .tar, .tar.gz and .tar.zst archives into the
+    /// directory they were saved to
+    #[cfg(feature = "extract")]
+    #[arg(long, global = true)]
+    pub extract: bool,
+
+    /// Remove archives once --extract has unpacked them
+    #[cfg(feature = "extract")]
+    #[arg(long, requires = "extract", global = true)]
+    pub delete_archive: bool,
+
+    /// Replace the first match of a regular expression in every URL before
+    /// downloading it, e.g. --rewrite '^https://old\.example/' https://new.example/
+    /// (repeatable; runs after the [[rewrite]] rules of the config)
+    #[arg(long, num_args = 2, value_names = ["PATTERN", "REPLACEMENT"], global = true)]
+    pub rewrite: Vec<String>,
+
+    /// Map every URL to the one to download with a command that prints it,
+    /// e.g. 'mirror-map {url}'
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub rewrite_command: Option<String>,
+
+    /// Also write detailed logs to this file (rotated by size)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub log_file: Option<PathBuf>,
+
     /// Buffer size in KB (default: 256)
-    #[arg(long, value_name = "KB")]
+    #[arg(long, value_name = "KB", global = true)]
     pub buffer_size: Option<usize>,
 
     /// Connection pool size per host
-    #[arg(long, default_value = "100")]
+    #[arg(long, default_value = "100", global = true)]
     pub pool_size: usize,
 
     /// Retry failed downloads N times
-    #[arg(short = 'r', long, default_value = "3")]
+    #[arg(short = 'r', long, default_value = "3", global = true)]
     pub retries: usize,
 
     /// Concurrent file limit (auto if not set)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub max_files: Option<usize>,
 
     /// Minimum file size in MB to use parallel chunk downloading
-    #[arg(long, default_value = "5")]
+    #[arg(long, default_value = "5", global = true)]
     pub min_parallel_size: u64,
+
+    /// Recursively mirror the site subtree below each URL
+    #[arg(long)]
+    pub recursive: bool,
+
+    /// Pick which crawled links to download from an interactive, filterable list
+    #[cfg(feature = "tui")]
+    #[arg(long, requires = "recursive")]
+    pub pick: bool,
+
+    /// Maximum recursion depth for --recursive (0 = unlimited)
+    #[arg(short = 'l', long, default_value = "5", value_name = "N")]
+    pub level: usize,
+
+    /// Never ascend to the parent directory when recursing
+    #[arg(long)]
+    pub no_parent: bool,
+
+    /// Ignore robots.txt rules and crawl-delay when recursing
+    #[arg(long)]
+    pub no_robots: bool,
+
+    /// Recreate the host and directories of each URL, as --recursive does
+    #[arg(short = 'x', long)]
+    pub force_directories: bool,
+
+    /// Leave out the host directory when recreating URL paths
+    #[arg(long)]
+    pub no_host_directories: bool,
+
+    /// Leave out the first N directories of URL paths when recreating them
+    #[arg(long, value_name = "N", default_value = "0")]
+    pub cut_dirs: usize,
+
+    /// Save downloads with a relative output path under DIR
+    #[arg(short = 'P', long, value_name = "DIR")]
+    pub directory_prefix: Option<PathBuf>,
+
+    /// Download every URL listed in a sitemap.xml (indexes are followed)
+    #[arg(long, value_name = "URL")]
+    pub sitemap: Option<String>,
+
+    /// Only download sitemap URLs matching this regex
+    #[arg(long, value_name = "REGEX")]
+    pub include: Option<String>,
+
+    /// Skip sitemap URLs matching this regex
+    #[arg(long, value_name = "REGEX")]
+    pub exclude: Option<String>,
+
+    /// Download new enclosures from an RSS/Atom feed
+    #[arg(long, value_name = "URL")]
+    pub feed: Option<String>,
+
+    /// State file tracking already downloaded feed items
+    #[arg(long, value_name = "PATH")]
+    pub feed_state: Option<PathBuf>,
+
+    /// Only check that the URLs are reachable; exits non-zero if any is dead
+    #[arg(long, global = true)]
+    pub spider: bool,
+
+    /// Do not record downloads in the history database
+    #[cfg(feature = "history")]
+    #[arg(long, global = true)]
+    pub no_history: bool,
+}
+
+impl Args {
+    /// Log level selected by -q / -v; `RUST_LOG` overrides it.
+    pub fn log_level(&self) -> log::LevelFilter {
+        if self.quiet {
+            return log::LevelFilter::Error;
+        }
+        match self.verbose {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+
+    /// When downloads should start according to --start-at or --start-in.
+    pub fn start_time(&self) -> Option<SystemTime> {
+        self.start_at
+            .or_else(|| self.start_in.map(|delay| SystemTime::now() + delay))
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Download one or more URLs (same as `dwrs URL...`)
+    Get(GetArgs),
+    /// Download every entry of a manifest file
+    Batch(BatchArgs),
+    /// Continue interrupted downloads from their partial files
+    Resume(GetArgs),
+    /// Check local files against checksums from the command line, a manifest or a sums file
+    Verify(VerifyArgs),
+    /// Show response headers, final URL, size and resumability of URLs
+    Head(HeadArgs),
+    /// Measure download throughput with different worker counts and buffer sizes
+    Bench(BenchArgs),
+    /// Show previously finished downloads
+    #[cfg(feature = "history")]
+    History(HistoryArgs),
+    /// Keep running and process downloads added at runtime
+    Daemon(DaemonArgs),
+    /// Download the manifests dropped into a directory
+    WatchDir(WatchDirArgs),
+    /// Download URLs as they are copied to the clipboard
+    #[cfg(feature = "clipboard")]
+    WatchClipboard(WatchClipboardArgs),
+    /// Show the progress of background and daemon downloads
+    Status(StatusArgs),
+    /// Create or inspect the configuration file
+    Config(ConfigArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GetArgs {
+    /// URLs to download
+    #[arg(required = true)]
+    pub url: Vec<String>,
+
+    /// Output file names, one per URL
+    #[arg(short, long)]
+    pub output: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct BatchArgs {
+    /// Manifest with the downloads ("-" reads from stdin)
+    pub file: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct VerifyArgs {
+    /// File to check
+    #[arg(required_unless_present_any = ["manifest", "sums"], requires = "checksum")]
+    pub file: Option<PathBuf>,
+
+    /// Expected digest as "sha256:<hex>", "sha512:<hex>", "md5:<hex>" or bare SHA-256 hex
+    pub checksum: Option<String>,
+
+    /// Check the output of every manifest entry that has a checksum
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["file", "sums"])]
+    pub manifest: Option<PathBuf>,
+
+    /// Check the files listed in a SHA256SUMS or .sha256 file from an earlier run
+    #[arg(long, value_name = "PATH", conflicts_with = "file")]
+    pub sums: Option<PathBuf>,
+
+    /// Download missing and corrupt manifest entries again
+    #[arg(long, requires = "manifest")]
+    pub redownload: bool,
+}
+
+#[derive(clap::Args)]
+pub struct HeadArgs {
+    /// URLs to inspect
+    #[arg(required = true)]
+    pub url: Vec<String>,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// URL of a large file that supports ranges; it is downloaded into a
+    /// temporary file and removed
+    pub url: String,
+
+    /// Worker counts to try
+    #[arg(long, value_delimiter = ',', default_value = "1,2,4,8,16")]
+    pub workers: Vec<usize>,
+
+    /// Buffer sizes to try, e.g. 64K,256K,1M
+    #[arg(long, value_name = "SIZES", value_delimiter = ',', value_parser = parse_size, default_value = "64K,256K,1M")]
+    pub buffer_sizes: Vec<u64>,
+
+    /// How long each combination runs at most
+    #[arg(long, value_name = "DURATION", value_parser = crate::deferred::parse_duration, default_value = "5s")]
+    pub duration: Duration,
+}
+
+#[derive(clap::Args)]
+pub struct DaemonArgs {
+    /// URLs queued right away
+    pub url: Vec<String>,
+
+    /// Serve the aria2-compatible JSON-RPC API
+    #[cfg_attr(feature = "webui", doc = "and the web dashboard at /")]
+    #[cfg_attr(feature = "metrics", doc = "and Prometheus metrics at /metrics")]
+    #[arg(long)]
+    pub enable_rpc: bool,
+
+    /// Port of the JSON-RPC server
+    #[arg(long, default_value_t = crate::rpc::DEFAULT_PORT, value_name = "PORT")]
+    pub rpc_listen_port: u16,
+
+    /// Accept JSON-RPC connections on all interfaces instead of localhost only (needs --rpc-secret)
+    #[arg(long)]
+    pub rpc_listen_all: bool,
+
+    /// Secret token JSON-RPC clients must send as "token:<secret>"
+    #[arg(long, value_name = "TOKEN")]
+    pub rpc_secret: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct WatchDirArgs {
+    /// Directory to watch for .txt, .json and .csv manifests
+    pub path: PathBuf,
+
+    /// Directory the downloads are saved in
+    #[arg(long, default_value = ".", value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// How often to scan the directory, in seconds
+    #[arg(long, default_value = "2", value_name = "SECS")]
+    pub interval: u64,
+}
+
+#[cfg(feature = "clipboard")]
+#[derive(clap::Args)]
+pub struct WatchClipboardArgs {
+    /// Only download copied URLs matching this regex
+    #[arg(long, value_name = "REGEX")]
+    pub pattern: Option<String>,
+
+    /// Directory the downloads are saved in
+    #[arg(long, default_value = ".", value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// How often to read the clipboard, in milliseconds
+    #[arg(long, default_value = "500", value_name = "MS")]
+    pub interval: u64,
+}
+
+#[derive(clap::Args)]
+pub struct StatusArgs {
+    /// Print a JSON snapshot instead of progress bars
+    #[arg(long, conflicts_with = "watch")]
+    pub json: bool,
+
+    /// Keep refreshing until no downloads are left
+    #[arg(long)]
+    pub watch: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Write a commented default config.toml
+    Init {
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the effective configuration and where each value comes from
+    Show,
+    /// Check the config file for syntax errors, unknown keys and bad values
+    Validate,
+    /// Print the path of the config file
+    Path,
+}
+
+#[cfg(feature = "history")]
+#[derive(clap::Args)]
+pub struct HistoryArgs {
+    /// Only show failed downloads
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only show downloads whose URL or path contains TEXT
+    #[arg(long, value_name = "TEXT")]
+    pub search: Option<String>,
+
+    /// Maximum number of entries to show (0 = all)
+    #[arg(long, default_value = "50", value_name = "N")]
+    pub limit: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Args::command().debug_assert();
+
+        let args =
+            Args::try_parse_from(["dwrs", "-w", "8", "get", "https://example.com/a"]).unwrap();
+        assert_eq!(args.workers, 8);
+        assert!(matches!(args.command, Some(Command::Get(ref g)) if g.url.len() == 1));
+
+        let args = Args::try_parse_from(["dwrs", "https://example.com/a"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.url, vec!["https://example.com/a"]);
+        assert_eq!(args.log_level(), log::LevelFilter::Warn);
+
+        let args = Args::try_parse_from(["dwrs", "get", "-vv", "https://example.com/a"]).unwrap();
+        assert_eq!(args.log_level(), log::LevelFilter::Debug);
+        assert!(Args::try_parse_from(["dwrs", "-q", "-v", "https://example.com/a"]).is_err());
+
+        let args =
+            Args::try_parse_from(["dwrs", "--color", "never", "https://example.com/a"]).unwrap();
+        assert_eq!(args.color, ColorChoice::Never);
+        assert!(!args.color.enabled(true));
+    }
 }
@@ -0,0 +1,119 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::file_parser::{DownloadEntry, filename_from_url};
+use crate::queue::DownloadQueue;
+
+static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bhttps?://[^\s<>"'`]+"#).expect("valid URL regex"));
+
+/// Options for [`watch`]
+#[derive(Debug, Clone)]
+pub struct ClipboardOptions {
+    /// Only URLs matching this pattern are queued
+    pub pattern: Option<Regex>,
+    /// Directory the downloads are saved in
+    pub dir: PathBuf,
+    /// How often the clipboard is read
+    pub interval: Duration,
+}
+
+impl Default for ClipboardOptions {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            dir: PathBuf::from("."),
+            interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Finds the `http(s)` URLs in copied text, without trailing punctuation.
+pub fn extract_urls(text: &str) -> Vec<&str> {
+    URL_RE
+        .find_iter(text)
+        .map(|m| {
+            m.as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']'])
+        })
+        .collect()
+}
+
+/// Queues the URLs found in `text` that match the pattern and were not seen
+/// before. Returns how many were queued.
+pub fn queue_urls(
+    text: &str,
+    queue: &DownloadQueue,
+    opts: &ClipboardOptions,
+    seen: &mut HashSet<String>,
+) -> usize {
+    let mut queued = 0;
+    for url in extract_urls(text) {
+        if opts.pattern.as_ref().is_some_and(|re| !re.is_match(url)) {
+            log::debug!("Ignoring copied URL {}: does not match the pattern", url);
+            continue;
+        }
+        if !seen.insert(url.to_string()) {
+            continue;
+        }
+        log::info!("Queued {} from the clipboard", url);
+        let output = opts.dir.join(filename_from_url(url));
+        queue.push(DownloadEntry::new(url, output), 0);
+        queued += 1;
+    }
+    queued
+}
+
+/// Polls the clipboard on a background thread and pushes every newly copied
+/// URL to `queue`.
+///
+/// Text already in the clipboard at start is ignored. The thread ends once
+/// downloads are [stopped](crate::control::stop).
+pub fn watch(
+    queue: DownloadQueue,
+    opts: ClipboardOptions,
+) -> Result<std::thread::JoinHandle<()>, arboard::Error> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    Ok(std::thread::spawn(move || {
+        let mut last = clipboard.get_text().ok();
+        let mut seen = HashSet::new();
+        while !crate::control::is_stopping() {
+            std::thread::sleep(opts.interval);
+            let Ok(text) = clipboard.get_text() else {
+                continue;
+            };
+            if last.as_ref() != Some(&text) {
+                queue_urls(&text, &queue, &opts, &mut seen);
+                last = Some(text);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_copied_urls() {
+        assert_eq!(
+            extract_urls("see https://example.com/a.iso, and (http://example.org/b.zip)."),
+            vec!["https://example.com/a.iso", "http://example.org/b.zip"]
+        );
+
+        let queue = DownloadQueue::new(1);
+        let opts = ClipboardOptions {
+            pattern: Some(Regex::new(r"\.iso$").unwrap()),
+            dir: PathBuf::from("/tmp/dl"),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let text = "https://example.com/a.iso https://example.com/b.zip";
+        assert_eq!(queue_urls(text, &queue, &opts, &mut seen), 1);
+        assert_eq!(queue_urls(text, &queue, &opts, &mut seen), 0);
+        assert_eq!(queue.snapshot()[0].output, PathBuf::from("/tmp/dl/a.iso"));
+    }
+}
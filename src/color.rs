@@ -0,0 +1,40 @@
+use std::io::IsTerminal;
+
+/// When to use colors, as given with `--color`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color terminals unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether a stream should be colored; `auto` follows the
+    /// [NO_COLOR](https://no-color.org) convention.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        }
+    }
+
+    /// Applies the choice to `colored` output on stdout and to the
+    /// progress bars on stderr.
+    pub fn apply(self) {
+        let stdout = self.enabled(std::io::stdout().is_terminal());
+        let stderr = self.enabled(std::io::stderr().is_terminal());
+        #[cfg(feature = "terminal")]
+        {
+            colored::control::set_override(stdout);
+            console::set_colors_enabled(stdout);
+            console::set_colors_enabled_stderr(stderr);
+        }
+        #[cfg(not(feature = "terminal"))]
+        let _ = (stdout, stderr);
+    }
+}
@@ -1,8 +1,21 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ConfigFile {
+use crate::bandwidth::{self, BandwidthWindow};
+use crate::cli::Args;
+use crate::hooks::{Hook, Webhook};
+use crate::output_template::OutputTemplate;
+use crate::rewrite::{RewriteCommand, RewriteRule};
+use crate::sanitize::FilenameMode;
+use crate::units::Units;
+use crate::utils::{Token, parse_size, parse_template};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Settings {
     pub msg_template: Option<String>,
     pub template: Option<String>,
     pub bar_chars: Option<String>,
@@ -11,6 +24,720 @@ struct ConfigFile {
     pub pool_size: Option<usize>,
     pub retries: Option<usize>,
     pub min_parallel_size: Option<u64>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    /// Bytes per second, as a number or a size such as "2M"
+    pub rate_limit: Option<toml::Value>,
+    pub log_file: Option<PathBuf>,
+    pub filename_mode: Option<FilenameMode>,
+    pub units: Option<Units>,
+    pub lang: Option<String>,
+    pub terminal_title: Option<bool>,
+    pub bell: Option<bool>,
+    pub bell_sound: Option<PathBuf>,
+    pub notify_backend: Option<Vec<NotifyBackend>>,
+    /// Seconds
+    pub notify_min_duration: Option<u64>,
+    /// Bytes, as a number or a size such as "100M"
+    pub notify_min_size: Option<toml::Value>,
+    pub notify_template: Option<String>,
+    pub notify_error_template: Option<String>,
+    pub output_template: Option<String>,
+    pub on_complete: Option<String>,
+    pub on_error: Option<String>,
+    pub notify_webhook: Option<String>,
+    pub rewrite_command: Option<String>,
+    /// `[[schedule]]` windows; a profile's list replaces the top-level one
+    pub schedule: Option<Vec<ScheduleSettings>>,
+    /// `[[rewrite]]` rules; a profile's list replaces the top-level one
+    pub rewrite: Option<Vec<RewriteSettings>>,
+    #[serde(default)]
+    pub host: BTreeMap<String, HostSettings>,
+    /// `[email]` section; a profile's section replaces the top-level one
+    pub email: Option<EmailSettings>,
+    pub telegram: Option<TelegramSettings>,
+    pub matrix: Option<MatrixSettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScheduleSettings {
+    pub from: String,
+    pub to: String,
+    /// Limit inside the window; `0` means unlimited
+    pub rate_limit: toml::Value,
+}
+
+impl ScheduleSettings {
+    fn parse(&self) -> Result<BandwidthWindow, String> {
+        Ok(BandwidthWindow {
+            from: bandwidth::parse_time(&self.from)?,
+            to: bandwidth::parse_time(&self.to)?,
+            rate_limit: Some(parse_rate(&self.rate_limit)?).filter(|r| *r > 0),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RewriteSettings {
+    pub pattern: String,
+    pub replace: String,
+}
+
+impl RewriteSettings {
+    fn parse(&self) -> Result<RewriteRule, String> {
+        RewriteRule::new(&self.pattern, &self.replace)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HostSettings {
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub auth: Option<HostAuth>,
+    pub workers: Option<usize>,
+    pub rate_limit: Option<toml::Value>,
+    pub retries: Option<usize>,
+}
+
+/// Credentials sent to a host as an `Authorization` header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HostAuth {
+    Basic {
+        user: String,
+        #[serde(default)]
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+}
+
+impl HostAuth {
+    pub fn header_value(&self) -> String {
+        use base64::Engine;
+        match self {
+            HostAuth::Basic { user, password } => format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password))
+            ),
+            HostAuth::Bearer { token } => format!("Bearer {}", token),
+        }
+    }
+}
+
+/// How the connection to an SMTP server is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually on port 465
+    #[serde(rename = "tls")]
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually on port 587
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// No encryption, for a relay on the same machine; logging in is only
+    /// allowed to a loopback address
+    #[serde(rename = "none")]
+    Plain,
+}
+
+/// `[email]` section: where batch reports are mailed to, see
+/// [`crate::email`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailSettings {
+    /// SMTP server host name
+    pub server: String,
+    /// Defaults to 465 with `tls`, 587 with `starttls` and 25 without
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl EmailSettings {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.security {
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Plain => 25,
+        })
+    }
+
+    /// Checks what serde cannot: something to send to and from, and no
+    /// password over an unencrypted connection to another machine.
+    pub fn check(&self) -> Result<(), String> {
+        if self.server.trim().is_empty() {
+            return Err("server is empty".to_string());
+        }
+        if !self.from.contains('@') {
+            return Err(format!("from: '{}' is not an email address", self.from));
+        }
+        if self.to.is_empty() {
+            return Err("to: no recipients".to_string());
+        }
+        if let Some(to) = self.to.iter().find(|to| !to.contains('@')) {
+            return Err(format!("to: '{}' is not an email address", to));
+        }
+        let server = self.server.trim_matches(['[', ']']);
+        let loopback = server.eq_ignore_ascii_case("localhost")
+            || server
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        if self.security == SmtpSecurity::Plain && self.username.is_some() && !loopback {
+            return Err(format!(
+                "security: refusing to log in to {} without encryption",
+                self.server
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where notifications go, see [`crate::notifier`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyBackend {
+    Desktop,
+    Telegram,
+    Matrix,
+}
+
+impl NotifyBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyBackend::Desktop => "desktop",
+            NotifyBackend::Telegram => "telegram",
+            NotifyBackend::Matrix => "matrix",
+        }
+    }
+}
+
+impl std::str::FromStr for NotifyBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "desktop" => Ok(NotifyBackend::Desktop),
+            "telegram" => Ok(NotifyBackend::Telegram),
+            "matrix" => Ok(NotifyBackend::Matrix),
+            other => Err(format!(
+                "unknown notify backend '{}' (expected desktop, telegram or matrix)",
+                other
+            )),
+        }
+    }
+}
+
+/// `[telegram]` section: the bot that notifications are sent by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelegramSettings {
+    /// Token given by @BotFather
+    pub token: String,
+    /// Chat, group or channel to write to; a number or `@channelname`
+    #[serde(deserialize_with = "string_or_number")]
+    pub chat_id: String,
+    /// Bot API server, for self-hosted ones
+    #[serde(default = "default_telegram_api")]
+    pub api_url: String,
+}
+
+fn default_telegram_api() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+fn string_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(i64),
+        String(String),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::Number(n) => n.to_string(),
+        Id::String(s) => s,
+    })
+}
+
+/// `[matrix]` section: the room notifications are posted to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatrixSettings {
+    /// e.g. `https://matrix.org`
+    pub homeserver: String,
+    pub access_token: String,
+    /// Internal room id such as `!abc:matrix.org`, not an alias
+    pub room_id: String,
+}
+
+/// Settings from a `[host."name"]` section, applied to URLs on that host
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostConfig {
+    pub headers: Vec<(String, String)>,
+    pub auth: Option<HostAuth>,
+    pub workers: Option<usize>,
+    /// Bytes per second per file
+    pub rate_limit: Option<u64>,
+    pub retries: Option<usize>,
+}
+
+impl HostConfig {
+    fn merge(&mut self, settings: HostSettings) -> Result<(), String> {
+        for (name, value) in settings.headers {
+            self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+            self.headers.push((name, value));
+        }
+        if settings.auth.is_some() {
+            self.auth = settings.auth;
+        }
+        if settings.workers.is_some() {
+            self.workers = settings.workers;
+        }
+        if let Some(rate) = &settings.rate_limit {
+            self.rate_limit = Some(parse_rate(rate)?);
+        }
+        if settings.retries.is_some() {
+            self.retries = settings.retries;
+        }
+        Ok(())
+    }
+
+    /// Configured headers followed by `Authorization`, minus the names `overrides` already sets.
+    pub fn request_headers(&self, overrides: &[(String, String)]) -> Vec<(String, String)> {
+        let auth = self
+            .auth
+            .as_ref()
+            .map(|auth| ("Authorization".to_string(), auth.header_value()));
+        self.headers
+            .iter()
+            .cloned()
+            .chain(auth)
+            .filter(|(name, _)| !overrides.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)))
+            .chain(overrides.iter().cloned())
+            .collect()
+    }
+}
+
+/// Finds the host section for `url`.
+///
+/// Exact host names win over `*.example.com` patterns, which also match
+/// `example.com` itself; among patterns the longest one wins.
+pub fn match_host<'a>(
+    hosts: &'a BTreeMap<String, HostConfig>,
+    url: &str,
+) -> Option<&'a HostConfig> {
+    let host = reqwest::Url::parse(url)
+        .ok()?
+        .host_str()?
+        .to_ascii_lowercase();
+    if let Some(config) = hosts.get(&host) {
+        return Some(config);
+    }
+    hosts
+        .iter()
+        .filter_map(|(pattern, config)| {
+            let suffix = pattern.strip_prefix("*.")?;
+            let matches = host == suffix || host.ends_with(&format!(".{}", suffix));
+            matches.then_some((suffix.len(), config))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, config)| config)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub profile: BTreeMap<String, Settings>,
+}
+
+fn parse_rate(value: &toml::Value) -> Result<u64, String> {
+    parse_bytes("rate_limit", value)
+}
+
+/// Bytes given as a number or a size such as "2M"
+fn parse_bytes(key: &str, value: &toml::Value) -> Result<u64, String> {
+    match value {
+        toml::Value::Integer(n) if *n >= 0 => Ok(*n as u64),
+        toml::Value::String(s) => parse_size(s).ok_or_else(|| format!("Invalid {}: {}", key, s)),
+        other => Err(format!("Invalid {}: {}", key, other)),
+    }
+}
+
+/// Where an effective configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File(PathBuf),
+    /// `[profile.<name>]` section of the config file
+    Profile(String),
+    /// Environment variable
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Profile(name) => write!(f, "profile {}", name),
+            ConfigSource::Env(var) => write!(f, "${}", var),
+            ConfigSource::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// Keys allowed inside a `[host."name"]` section
+const HOST_KEYS: &[&str] = &["headers", "auth", "workers", "rate_limit", "retries"];
+
+/// Keys allowed in the `[email]` section
+const EMAIL_KEYS: &[&str] = &[
+    "server", "port", "security", "username", "password", "from", "to",
+];
+
+/// Keys allowed in the `[telegram]` section
+const TELEGRAM_KEYS: &[&str] = &["token", "chat_id", "api_url"];
+
+/// Keys allowed in the `[matrix]` section
+const MATRIX_KEYS: &[&str] = &["homeserver", "access_token", "room_id"];
+
+/// Keys allowed in a `[[schedule]]` entry
+const SCHEDULE_KEYS: &[&str] = &["from", "to", "rate_limit"];
+
+/// Keys allowed in a `[[rewrite]]` entry
+const REWRITE_KEYS: &[&str] = &["pattern", "replace"];
+
+/// Variables understood by `msg_template`
+const MSG_VARS: &[&str] = &["download", "url", "output"];
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by [`validate`], with its 1-based position when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, content: &str, span: Option<Range<usize>>, message: String) -> Self {
+        let position = span.map(|span| {
+            let before = &content[..span.start.min(content.len())];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            (
+                before.matches('\n').count() + 1,
+                before[line_start..].chars().count() + 1,
+            )
+        });
+        Self {
+            severity,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Checks the contents of a config file without loading it.
+///
+/// Reports TOML syntax and type errors, unknown keys, templates that do not
+/// compile and values outside sane ranges, in every profile and host section.
+/// An empty result means the file is valid.
+pub fn validate(content: &str) -> Vec<Diagnostic> {
+    let table = match toml::de::DeTable::parse(content) {
+        Ok(table) => table,
+        Err(e) => {
+            let message = e.message().to_string();
+            return vec![Diagnostic::new(Severity::Error, content, e.span(), message)];
+        }
+    };
+
+    let mut spans = BTreeMap::new();
+    let mut diagnostics = Vec::new();
+    collect_keys(table.get_ref(), "", true, &mut spans, &mut |path, span| {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            content,
+            Some(span),
+            format!("unknown key '{}'", path),
+        ))
+    });
+
+    let file: ConfigFile = match toml::from_str(content) {
+        Ok(file) => file,
+        Err(e) => {
+            let message = e.message().to_string();
+            diagnostics.push(Diagnostic::new(Severity::Error, content, e.span(), message));
+            return diagnostics;
+        }
+    };
+
+    let mut problems = Vec::new();
+    check_settings(&file.settings, "", &mut problems);
+    for (name, settings) in &file.profile {
+        check_settings(settings, &format!("profile.{}.", name), &mut problems);
+    }
+    for (severity, path, message) in problems {
+        let span = spans.get(&path).cloned();
+        diagnostics.push(Diagnostic::new(
+            severity,
+            content,
+            span,
+            format!("{}: {}", path, message),
+        ));
+    }
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Records the span of every key under its dotted path and reports unknown ones.
+fn collect_keys(
+    table: &toml::de::DeTable,
+    prefix: &str,
+    top_level: bool,
+    spans: &mut BTreeMap<String, Range<usize>>,
+    unknown: &mut dyn FnMut(String, Range<usize>),
+) {
+    for (key, value) in table {
+        let name = key.get_ref().as_ref();
+        let path = format!("{}{}", prefix, name);
+        spans.insert(path.clone(), key.span());
+        let entry_keys = match name {
+            "schedule" => Some(SCHEDULE_KEYS),
+            "rewrite" => Some(REWRITE_KEYS),
+            _ => None,
+        };
+        if let Some(entry_keys) = entry_keys
+            && let toml::de::DeValue::Array(entries) = value.get_ref()
+        {
+            for (i, entry) in entries.iter().enumerate() {
+                let toml::de::DeValue::Table(entry) = entry.get_ref() else {
+                    continue;
+                };
+                for (key, _) in entry {
+                    let path = format!("{}[{}].{}", path, i, key.get_ref());
+                    spans.insert(path.clone(), key.span());
+                    if !entry_keys.contains(&key.get_ref().as_ref()) {
+                        unknown(path, key.span());
+                    }
+                }
+            }
+            continue;
+        }
+        let toml::de::DeValue::Table(inner) = value.get_ref() else {
+            if !KEYS.contains(&name) && entry_keys.is_none() {
+                unknown(path, key.span());
+            }
+            continue;
+        };
+        match name {
+            "host" => {
+                for (host, settings) in inner {
+                    let host_path = format!("{}.\"{}\"", path, host.get_ref());
+                    spans.insert(host_path.clone(), host.span());
+                    let toml::de::DeValue::Table(settings) = settings.get_ref() else {
+                        continue;
+                    };
+                    for (key, _) in settings {
+                        let path = format!("{}.{}", host_path, key.get_ref());
+                        spans.insert(path.clone(), key.span());
+                        if !HOST_KEYS.contains(&key.get_ref().as_ref()) {
+                            unknown(path, key.span());
+                        }
+                    }
+                }
+            }
+            "email" | "telegram" | "matrix" => {
+                let section_keys = match name {
+                    "email" => EMAIL_KEYS,
+                    "telegram" => TELEGRAM_KEYS,
+                    _ => MATRIX_KEYS,
+                };
+                for (key, _) in inner {
+                    let path = format!("{}.{}", path, key.get_ref());
+                    spans.insert(path.clone(), key.span());
+                    if !section_keys.contains(&key.get_ref().as_ref()) {
+                        unknown(path, key.span());
+                    }
+                }
+            }
+            "profile" if top_level => {
+                for (profile, settings) in inner {
+                    if let toml::de::DeValue::Table(settings) = settings.get_ref() {
+                        let prefix = format!("profile.{}.", profile.get_ref());
+                        collect_keys(settings, &prefix, false, spans, unknown);
+                    }
+                }
+            }
+            _ if KEYS.contains(&name) => {}
+            _ => unknown(path, key.span()),
+        }
+    }
+}
+
+/// Semantic checks that the TOML types alone cannot express.
+fn check_settings(settings: &Settings, prefix: &str, out: &mut Vec<(Severity, String, String)>) {
+    let mut error = |key: &str, message: String| {
+        out.push((Severity::Error, format!("{}{}", prefix, key), message))
+    };
+    let in_range = |value: usize, min: usize, max: usize| {
+        (value < min || value > max).then(|| format!("{} is outside {}..={}", value, min, max))
+    };
+
+    if let Some(template) = &settings.template
+        && let Err(e) = crate::progress::check_template(template)
+    {
+        error("template", e);
+    }
+    if let Some(chars) = &settings.bar_chars
+        && chars.chars().count() < 2
+    {
+        error("bar_chars", "needs at least 2 characters".to_string());
+    }
+    if let Some(message) = settings.workers.and_then(|v| in_range(v, 1, 128)) {
+        error("workers", message);
+    }
+    if let Some(message) = settings
+        .buffer_size
+        .and_then(|v| in_range(v, 1024, 64 * 1024 * 1024))
+    {
+        error("buffer_size", message);
+    }
+    if let Some(message) = settings.pool_size.and_then(|v| in_range(v, 0, 10_000)) {
+        error("pool_size", message);
+    }
+    if let Some(message) = settings.retries.and_then(|v| in_range(v, 1, 100)) {
+        error("retries", message);
+    }
+    if let Some(agent) = &settings.user_agent
+        && let Err(e) = crate::user_agent::check(agent)
+    {
+        error("user_agent", e);
+    }
+    if let Some(proxy) = &settings.proxy
+        && reqwest::Proxy::all(proxy).is_err()
+    {
+        error("proxy", format!("invalid proxy URL '{}'", proxy));
+    }
+    if let Some(rate) = &settings.rate_limit
+        && let Err(e) = parse_rate(rate)
+    {
+        error("rate_limit", e);
+    }
+    if let Some(size) = &settings.notify_min_size
+        && let Err(e) = parse_bytes("notify_min_size", size)
+    {
+        error("notify_min_size", e);
+    }
+    if let Some(lang) = &settings.lang
+        && let Err(e) = crate::localization::resolve(lang)
+    {
+        error("lang", e);
+    }
+    if let Some(template) = &settings.output_template
+        && let Err(e) = OutputTemplate::parse(template)
+    {
+        error("output_template", e);
+    }
+    for (key, command) in [
+        ("on_complete", &settings.on_complete),
+        ("on_error", &settings.on_error),
+    ] {
+        if let Some(command) = command
+            && let Err(e) = Hook::parse(command)
+        {
+            error(key, e);
+        }
+    }
+    if let Some(endpoint) = &settings.notify_webhook
+        && let Err(e) = Webhook::parse(endpoint)
+    {
+        error("notify_webhook", e);
+    }
+    if let Some(email) = &settings.email
+        && let Err(e) = email.check()
+    {
+        error("email", e);
+    }
+    if let Some(command) = &settings.rewrite_command
+        && let Err(e) = RewriteCommand::parse(command)
+    {
+        error("rewrite_command", e);
+    }
+    for (i, rule) in settings.rewrite.iter().flatten().enumerate() {
+        if let Err(e) = rule.parse() {
+            error(&format!("rewrite[{}].pattern", i), e);
+        }
+    }
+    for (i, window) in settings.schedule.iter().flatten().enumerate() {
+        let key = |name: &str| format!("schedule[{}].{}", i, name);
+        for (name, time) in [("from", &window.from), ("to", &window.to)] {
+            if let Err(e) = bandwidth::parse_time(time) {
+                error(&key(name), e);
+            }
+        }
+        if let Err(e) = parse_rate(&window.rate_limit) {
+            error(&key("rate_limit"), e);
+        }
+    }
+    for (host, host_settings) in &settings.host {
+        let key = |name: &str| format!("host.\"{}\".{}", host, name);
+        if host.trim_start_matches("*.").contains('*') {
+            error(
+                &format!("host.\"{}\"", host),
+                "only a leading '*.' wildcard is supported".to_string(),
+            );
+        }
+        if let Some(message) = host_settings.workers.and_then(|v| in_range(v, 1, 128)) {
+            error(&key("workers"), message);
+        }
+        if let Some(message) = host_settings.retries.and_then(|v| in_range(v, 1, 100)) {
+            error(&key("retries"), message);
+        }
+        if let Some(rate) = &host_settings.rate_limit
+            && let Err(e) = parse_rate(rate)
+        {
+            error(&key("rate_limit"), e);
+        }
+    }
+
+    if let Some(template) = &settings.msg_template {
+        for token in parse_template(template) {
+            if let Token::Var { name, .. } = token
+                && !MSG_VARS.contains(&name.as_str())
+            {
+                out.push((
+                    Severity::Warning,
+                    format!("{}msg_template", prefix),
+                    format!(
+                        "unknown variable {{{}}} (expected one of {})",
+                        name,
+                        MSG_VARS.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -23,55 +750,719 @@ pub struct Config {
     pub pool_size: usize,
     pub retries: usize,
     pub min_parallel_size: u64,
+    /// Preset name or literal agent, see [`crate::user_agent`]
+    pub user_agent: String,
+    pub proxy: Option<String>,
+    /// Bytes per second per file
+    pub rate_limit: Option<u64>,
+    /// Detailed log written alongside the terminal output, see [`crate::logging`]
+    pub log_file: Option<PathBuf>,
+    /// How names taken from URLs are cleaned up, see [`crate::sanitize`]
+    pub filename_mode: FilenameMode,
+    /// Prefixes sizes are shown with, see [`crate::units`]
+    pub units: Units,
+    /// UI language instead of the system locale, see [`crate::localization`]
+    pub lang: Option<String>,
+    /// Overall progress in the terminal window title, see [`crate::title`]
+    pub terminal_title: bool,
+    /// Ring the terminal bell when a batch is done, see [`crate::bell`]
+    pub bell: bool,
+    /// Sound file played when a batch is done
+    pub bell_sound: Option<PathBuf>,
+    /// Where notifications go when they are on, see [`crate::notifier`]
+    pub notify_backend: Vec<NotifyBackend>,
+    /// Seconds a download must take to be notified about, unless it is
+    /// large enough, see [`crate::notifier::Threshold`]
+    pub notify_min_duration: Option<u64>,
+    /// Bytes a download must have to be notified about, unless it took
+    /// long enough
+    pub notify_min_size: Option<u64>,
+    /// Body of notifications about finished downloads, see
+    /// [`crate::notifier::Messages`]
+    pub notify_template: Option<String>,
+    /// Body of notifications about failed downloads
+    pub notify_error_template: Option<String>,
+    /// Layout of output paths derived from URLs, see [`OutputTemplate`]
+    pub output_template: Option<String>,
+    /// Command run after every successful download, see [`Hook`]
+    pub on_complete: Option<String>,
+    /// Command run after every failed download, see [`Hook`]
+    pub on_error: Option<String>,
+    /// Endpoint finished downloads are POSTed to, see [`Webhook`]
+    pub notify_webhook: Option<String>,
+    /// Command mapping every URL to the one downloaded, see [`RewriteCommand`]
+    pub rewrite_command: Option<String>,
+    /// `[[rewrite]]` rules applied to every URL before it is downloaded
+    pub rewrite: Vec<RewriteRule>,
+    /// `[host."name"]` sections keyed by host name or `*.domain` pattern
+    pub hosts: BTreeMap<String, HostConfig>,
+    /// `[[schedule]]` windows with their own rate limit
+    pub schedule: Vec<BandwidthWindow>,
+    /// `[email]` section, for reports of finished batches
+    pub email: Option<EmailSettings>,
+    /// `[telegram]` section, for the `telegram` notify backend
+    pub telegram: Option<TelegramSettings>,
+    /// `[matrix]` section, for the `matrix` notify backend
+    pub matrix: Option<MatrixSettings>,
+    /// Source of every value that was not left at its default
+    pub sources: BTreeMap<&'static str, ConfigSource>,
 }
 
+/// Keys in the order they appear in config.toml
+pub const KEYS: &[&str] = &[
+    "msg_template",
+    "template",
+    "bar_chars",
+    "workers",
+    "buffer_size",
+    "pool_size",
+    "retries",
+    "min_parallel_size",
+    "user_agent",
+    "proxy",
+    "rate_limit",
+    "log_file",
+    "filename_mode",
+    "units",
+    "lang",
+    "terminal_title",
+    "bell",
+    "bell_sound",
+    "notify_backend",
+    "notify_min_duration",
+    "notify_min_size",
+    "notify_template",
+    "notify_error_template",
+    "output_template",
+    "on_complete",
+    "on_error",
+    "notify_webhook",
+    "rewrite_command",
+];
+
 impl Config {
+    /// Default config file: `<config dir>/dwrs/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("dwrs").join("config.toml"))
+    }
+
+    /// Loads a config file, reporting unreadable or invalid files as errors.
+    ///
+    /// Any error found by [`validate`] fails the load with its position;
+    /// warnings such as unknown keys are logged. Values from
+    /// `[profile.<name>]` override the top-level ones when a profile is
+    /// given; an unknown profile is an error.
+    pub fn try_load(path: &Path, profile: Option<&str>) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        for diagnostic in validate(&content) {
+            let separator = if diagnostic.line.is_some() { ":" } else { ": " };
+            let message = format!("{}{}{}", path.display(), separator, diagnostic);
+            match diagnostic.severity {
+                Severity::Error => return Err(message),
+                Severity::Warning => log::warn!("{}", message),
+            }
+        }
+        let mut file: ConfigFile = toml::from_str(&content)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+
+        let mut config = Self::default();
+        config.apply(file.settings, ConfigSource::File(path.to_path_buf()))?;
+
+        if let Some(name) = profile {
+            let Some(settings) = file.profile.remove(name) else {
+                let available: Vec<&str> = file.profile.keys().map(String::as_str).collect();
+                return Err(format!(
+                    "Unknown profile '{}' in {} (available: {})",
+                    name,
+                    path.display(),
+                    if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    }
+                ));
+            };
+            log::debug!("Using config profile {}", name);
+            config.apply(settings, ConfigSource::Profile(name.to_string()))?;
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, settings: Settings, source: ConfigSource) -> Result<(), String> {
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(if let Some(value) = settings.$field {
+                    self.$field = value;
+                    self.sources.insert(stringify!($field), source.clone());
+                })*
+            };
+        }
+        apply!(
+            msg_template,
+            template,
+            bar_chars,
+            workers,
+            buffer_size,
+            pool_size,
+            retries,
+            min_parallel_size,
+            filename_mode,
+            units,
+            terminal_title,
+            bell,
+            notify_backend
+        );
+        if let Some(agent) = settings.user_agent {
+            self.user_agent = agent;
+            self.sources.insert("user_agent", source.clone());
+        }
+        if let Some(proxy) = settings.proxy {
+            self.proxy = Some(proxy);
+            self.sources.insert("proxy", source.clone());
+        }
+        if let Some(lang) = settings.lang {
+            self.lang = Some(lang);
+            self.sources.insert("lang", source.clone());
+        }
+        if let Some(log_file) = settings.log_file {
+            self.log_file = Some(log_file);
+            self.sources.insert("log_file", source.clone());
+        }
+        if let Some(sound) = settings.bell_sound {
+            self.bell_sound = Some(sound);
+            self.sources.insert("bell_sound", source.clone());
+        }
+        if let Some(template) = settings.notify_template {
+            self.notify_template = Some(template);
+            self.sources.insert("notify_template", source.clone());
+        }
+        if let Some(template) = settings.notify_error_template {
+            self.notify_error_template = Some(template);
+            self.sources.insert("notify_error_template", source.clone());
+        }
+        if let Some(template) = settings.output_template {
+            self.output_template = Some(template);
+            self.sources.insert("output_template", source.clone());
+        }
+        if let Some(command) = settings.on_complete {
+            self.on_complete = Some(command);
+            self.sources.insert("on_complete", source.clone());
+        }
+        if let Some(command) = settings.on_error {
+            self.on_error = Some(command);
+            self.sources.insert("on_error", source.clone());
+        }
+        if let Some(endpoint) = settings.notify_webhook {
+            self.notify_webhook = Some(endpoint);
+            self.sources.insert("notify_webhook", source.clone());
+        }
+        if let Some(command) = settings.rewrite_command {
+            self.rewrite_command = Some(command);
+            self.sources.insert("rewrite_command", source.clone());
+        }
+        if let Some(rate) = &settings.rate_limit {
+            self.rate_limit = Some(parse_rate(rate)?);
+            self.sources.insert("rate_limit", source.clone());
+        }
+        if let Some(seconds) = settings.notify_min_duration {
+            self.notify_min_duration = Some(seconds).filter(|s| *s > 0);
+            self.sources.insert("notify_min_duration", source.clone());
+        }
+        if let Some(size) = &settings.notify_min_size {
+            self.notify_min_size = Some(parse_bytes("notify_min_size", size)?).filter(|s| *s > 0);
+            self.sources.insert("notify_min_size", source.clone());
+        }
+        if let Some(schedule) = &settings.schedule {
+            self.schedule = schedule
+                .iter()
+                .map(ScheduleSettings::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("[[schedule]]: {}", e))?;
+            self.sources.insert("schedule", source.clone());
+        }
+        if let Some(email) = settings.email {
+            self.email = Some(email);
+            self.sources.insert("email", source.clone());
+        }
+        if let Some(telegram) = settings.telegram {
+            self.telegram = Some(telegram);
+            self.sources.insert("telegram", source.clone());
+        }
+        if let Some(matrix) = settings.matrix {
+            self.matrix = Some(matrix);
+            self.sources.insert("matrix", source.clone());
+        }
+        if let Some(rules) = &settings.rewrite {
+            self.rewrite = rules
+                .iter()
+                .map(RewriteSettings::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("[[rewrite]]: {}", e))?;
+            self.sources.insert("rewrite", source);
+        }
+        for (host, host_settings) in settings.host {
+            self.hosts
+                .entry(host.to_ascii_lowercase())
+                .or_default()
+                .merge(host_settings)
+                .map_err(|e| format!("[host.\"{}\"]: {}", host, e))?;
+        }
+        Ok(())
+    }
+
+    /// Lenient variant of [`Config::try_load`]: problems are logged and the
+    /// defaults are used instead.
     pub fn load(path: &str) -> Self {
-        let config_file: ConfigFile = fs::read_to_string(path)
-            .ok()
-            .and_then(|content| toml::from_str(&content).ok())
-            .unwrap_or(ConfigFile {
-                msg_template: None,
-                template: None,
-                bar_chars: None,
-                workers: None,
-                buffer_size: None,
-                pool_size: None,
-                retries: None,
-                min_parallel_size: None,
-            });
-        let default = Self::default();
-        Self {
-            msg_template: config_file.msg_template.unwrap_or(default.msg_template),
-            template: config_file.template.unwrap_or(default.template),
-            bar_chars: config_file.bar_chars.unwrap_or(default.bar_chars),
-            workers: config_file.workers.unwrap_or(default.workers),
-            buffer_size: config_file.buffer_size.unwrap_or(default.buffer_size),
-            pool_size: config_file.pool_size.unwrap_or(default.pool_size),
-            retries: config_file.retries.unwrap_or(default.retries),
-            min_parallel_size: config_file
-                .min_parallel_size
-                .unwrap_or(default.min_parallel_size),
+        let path = Path::new(path);
+        if !path.exists() {
+            log::debug!("No config at {}, using defaults", path.display());
+            return Self::default();
         }
+        Self::try_load(path, None).unwrap_or_else(|e| {
+            log::warn!("{}; using default config", e);
+            Self::default()
+        })
     }
 
     pub fn load_from_config_dir() -> Self {
-        log::debug!(
-            "Loading config from: {}",
-            dirs::config_dir()
-                .unwrap_or_default()
-                .join("dwrs")
-                .join("config.toml")
-                .to_str()
-                .unwrap_or("")
-        );
-        if let Some(mut path) = dirs::config_dir() {
-            path.push("dwrs");
-            path.push("config.toml");
-            return Self::load(path.to_str().unwrap_or_default());
+        match Self::default_path() {
+            Some(path) => {
+                log::debug!("Loading config from: {}", path.display());
+                Self::load(path.to_str().unwrap_or_default())
+            }
+            None => {
+                log::warn!("Config dir not found, using default config");
+                Self::default()
+            }
+        }
+    }
+
+    /// Sets `key` from its string form, as used by environment variables.
+    ///
+    /// Sizes and rates accept K/M/G suffixes.
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let number = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("invalid number '{}': {}", value, e))
+        };
+        let size =
+            |value: &str| parse_size(value).ok_or_else(|| format!("invalid size '{}'", value));
+
+        match key {
+            "msg_template" => self.msg_template = value.to_string(),
+            "template" => self.template = value.to_string(),
+            "bar_chars" => self.bar_chars = value.to_string(),
+            "workers" => self.workers = number(value)?,
+            "buffer_size" => self.buffer_size = size(value)? as usize,
+            "pool_size" => self.pool_size = number(value)?,
+            "retries" => self.retries = number(value)?,
+            "min_parallel_size" => self.min_parallel_size = size(value)?,
+            "user_agent" => {
+                crate::user_agent::check(value)?;
+                self.user_agent = value.to_string()
+            }
+            "proxy" => self.proxy = Some(value.to_string()).filter(|p| !p.is_empty()),
+            "rate_limit" => self.rate_limit = Some(size(value)?).filter(|r| *r > 0),
+            "log_file" => {
+                self.log_file = Some(PathBuf::from(value)).filter(|p| !p.as_os_str().is_empty())
+            }
+            "filename_mode" => self.filename_mode = value.parse()?,
+            "units" => self.units = value.parse()?,
+            "lang" => {
+                if !value.is_empty() {
+                    crate::localization::resolve(value)?;
+                }
+                self.lang = Some(value.to_string()).filter(|l| !l.is_empty())
+            }
+            "notify_backend" => {
+                self.notify_backend = value
+                    .split(',')
+                    .filter(|backend| !backend.trim().is_empty())
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?
+            }
+            "notify_min_duration" => {
+                self.notify_min_duration = Some(number(value)? as u64).filter(|s| *s > 0)
+            }
+            "notify_min_size" => self.notify_min_size = Some(size(value)?).filter(|s| *s > 0),
+            "notify_template" => {
+                self.notify_template = Some(value.to_string()).filter(|t| !t.is_empty())
+            }
+            "notify_error_template" => {
+                self.notify_error_template = Some(value.to_string()).filter(|t| !t.is_empty())
+            }
+            "terminal_title" | "bell" => {
+                let enabled = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid boolean '{}'", value))?;
+                if key == "bell" {
+                    self.bell = enabled;
+                } else {
+                    self.terminal_title = enabled;
+                }
+            }
+            "bell_sound" => {
+                self.bell_sound = Some(PathBuf::from(value)).filter(|p| !p.as_os_str().is_empty())
+            }
+            "output_template" => {
+                if !value.is_empty() {
+                    OutputTemplate::parse(value)?;
+                }
+                self.output_template = Some(value.to_string()).filter(|t| !t.is_empty())
+            }
+            "on_complete" | "on_error" => {
+                if !value.is_empty() {
+                    Hook::parse(value)?;
+                }
+                let command = Some(value.to_string()).filter(|c| !c.is_empty());
+                if key == "on_complete" {
+                    self.on_complete = command;
+                } else {
+                    self.on_error = command;
+                }
+            }
+            "notify_webhook" => {
+                if !value.is_empty() {
+                    Webhook::parse(value)?;
+                }
+                self.notify_webhook = Some(value.to_string()).filter(|e| !e.is_empty())
+            }
+            "rewrite_command" => {
+                if !value.is_empty() {
+                    RewriteCommand::parse(value)?;
+                }
+                self.rewrite_command = Some(value.to_string()).filter(|c| !c.is_empty())
+            }
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+        Ok(())
+    }
+
+    /// Applies `DWRS_<KEY>` environment variables, e.g. `DWRS_WORKERS=8`.
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        self.apply_vars(|name| std::env::var(name).ok())
+    }
+
+    fn apply_vars(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        for key in KEYS {
+            let name = format!("DWRS_{}", key.to_ascii_uppercase());
+            if let Some(value) = var(&name) {
+                self.set_value(key, &value)
+                    .map_err(|e| format!("{}: {}", name, e))?;
+                self.sources.insert(key, ConfigSource::Env(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies command line flags that differ from their defaults.
+    pub fn apply_args(&mut self, args: &Args) {
+        let mut overrides: Vec<&'static str> = Vec::new();
+
+        if args.workers != 4 {
+            self.workers = args.workers;
+            overrides.push("workers");
+        }
+        if let Some(kb) = args.buffer_size {
+            self.buffer_size = kb * 1024;
+            overrides.push("buffer_size");
+        }
+        if args.pool_size != 100 {
+            self.pool_size = args.pool_size;
+            overrides.push("pool_size");
+        }
+        if args.retries != 3 {
+            self.retries = args.retries;
+            overrides.push("retries");
+        }
+        if args.min_parallel_size != 5 {
+            self.min_parallel_size = args.min_parallel_size * 1024 * 1024;
+            overrides.push("min_parallel_size");
+        }
+        if let Some(agent) = &args.user_agent {
+            self.user_agent = agent.clone();
+            overrides.push("user_agent");
+        }
+        if let Some(proxy) = &args.proxy {
+            self.proxy = Some(proxy.clone());
+            overrides.push("proxy");
+        }
+        if let Some(rate) = args.limit_rate {
+            self.rate_limit = Some(rate);
+            overrides.push("rate_limit");
+        }
+        if let Some(log_file) = &args.log_file {
+            self.log_file = Some(log_file.clone());
+            overrides.push("log_file");
+        }
+        if let Some(lang) = &args.lang {
+            self.lang = Some(lang.clone());
+            overrides.push("lang");
+        }
+        if args.title {
+            self.terminal_title = true;
+            overrides.push("terminal_title");
+        }
+        if args.bell {
+            self.bell = true;
+            overrides.push("bell");
+        }
+        if let Some(sound) = &args.bell_sound {
+            self.bell_sound = Some(sound.clone());
+            overrides.push("bell_sound");
+        }
+        if let Some(template) = &args.output_template {
+            self.output_template = Some(template.clone());
+            overrides.push("output_template");
+        }
+        if let Some(command) = &args.on_complete {
+            self.on_complete = Some(command.clone());
+            overrides.push("on_complete");
+        }
+        if let Some(command) = &args.on_error {
+            self.on_error = Some(command.clone());
+            overrides.push("on_error");
+        }
+        if let Some(endpoint) = &args.notify_webhook {
+            self.notify_webhook = Some(endpoint.clone());
+            overrides.push("notify_webhook");
+        }
+        if let Some(command) = &args.rewrite_command {
+            self.rewrite_command = Some(command.clone());
+            overrides.push("rewrite_command");
+        }
+
+        for key in overrides {
+            self.sources.insert(key, ConfigSource::Cli);
         }
-        log::warn!("Config dir not found, using default config");
-        Self::default()
+    }
+
+    pub fn source(&self, key: &str) -> &ConfigSource {
+        self.sources.get(key).unwrap_or(&ConfigSource::Default)
+    }
+
+    /// Value of `key` rendered as TOML.
+    pub fn value(&self, key: &str) -> Option<String> {
+        let quoted = |s: &str| toml::Value::String(s.to_string()).to_string();
+        Some(match key {
+            "msg_template" => quoted(&self.msg_template),
+            "template" => quoted(&self.template),
+            "bar_chars" => quoted(&self.bar_chars),
+            "workers" => self.workers.to_string(),
+            "buffer_size" => self.buffer_size.to_string(),
+            "pool_size" => self.pool_size.to_string(),
+            "retries" => self.retries.to_string(),
+            "min_parallel_size" => self.min_parallel_size.to_string(),
+            "user_agent" => quoted(&self.user_agent),
+            "proxy" => quoted(self.proxy.as_deref()?),
+            "rate_limit" => self.rate_limit?.to_string(),
+            "log_file" => quoted(&self.log_file.as_ref()?.to_string_lossy()),
+            "filename_mode" => quoted(self.filename_mode.as_str()),
+            "units" => quoted(self.units.as_str()),
+            "lang" => quoted(self.lang.as_deref()?),
+            "terminal_title" => self.terminal_title.to_string(),
+            "bell" => self.bell.to_string(),
+            "bell_sound" => quoted(&self.bell_sound.as_ref()?.to_string_lossy()),
+            "notify_backend" => toml::Value::Array(
+                self.notify_backend
+                    .iter()
+                    .map(|backend| toml::Value::String(backend.as_str().to_string()))
+                    .collect(),
+            )
+            .to_string(),
+            "notify_min_duration" => self.notify_min_duration?.to_string(),
+            "notify_min_size" => self.notify_min_size?.to_string(),
+            "notify_template" => quoted(self.notify_template.as_deref()?),
+            "notify_error_template" => quoted(self.notify_error_template.as_deref()?),
+            "output_template" => quoted(self.output_template.as_deref()?),
+            "on_complete" => quoted(self.on_complete.as_deref()?),
+            "on_error" => quoted(self.on_error.as_deref()?),
+            "notify_webhook" => quoted(self.notify_webhook.as_deref()?),
+            "rewrite_command" => quoted(self.rewrite_command.as_deref()?),
+            _ => return None,
+        })
+    }
+
+    /// Commented config.toml listing every key with its default value.
+    pub fn default_toml() -> String {
+        let defaults = Self::default();
+        let value = |key| defaults.value(key).unwrap_or_default();
+        format!(
+            "# dwrs configuration
+#
+# Every key is optional; uncomment a line to override the default.
+# DWRS_<KEY> environment variables (e.g. DWRS_WORKERS=8) override this
+# file, and command line flags override both.
+
+# Progress bar message. Variables: {{download}}, {{url}}, {{output}}
+# msg_template = {}
+
+# indicatif progress bar template; besides the indicatif keys, {{size}},
+# {{total_size}} and {{speed}} are shown in the configured units, and
+# {{speed}} and {{eta}} are smoothed over the last seconds
+# template = {}
+
+# Characters used to draw the progress bar (filled, current, empty)
+# bar_chars = {}
+
+# Parallel connections per file
+# workers = {}
+
+# Read buffer size in bytes
+# buffer_size = {}
+
+# Idle connections kept per host
+# pool_size = {}
+
+# Attempts per download before giving up
+# retries = {}
+
+# Files smaller than this many bytes are downloaded over a single connection
+# min_parallel_size = {}
+
+# User-Agent of every request, like --user-agent: a preset (\"browser\",
+# \"chrome\", \"curl\", \"wget\", \"dwrs\") or the literal header value
+# user_agent = {}
+
+# Proxy for all requests (http, https or socks5)
+# proxy = \"socks5://127.0.0.1:1080\"
+
+# Maximum download rate per file, in bytes per second or with a K/M/G suffix
+# rate_limit = \"2M\"
+
+# Detailed log file, rotated at 10 MiB with three old copies kept
+# log_file = \"/var/log/dwrs/dwrs.log\"
+
+# How file names taken from URLs are cleaned up. \"basic\" only replaces path
+# separators and control characters and defuses \"..\"; \"strict\" also keeps
+# nothing but letters, digits, '.', '-' and '_' and avoids names Windows
+# rejects, such as CON or a trailing dot. On Windows this is always done.
+# filename_mode = {}
+
+# Sizes and speeds in powers of 1024 (\"binary\": KiB, MiB) or of 1000
+# (\"decimal\": kB, MB)
+# units = {}
+
+# Language of messages instead of the system locale, like --lang
+# lang = \"ru\"
+
+# Show the overall progress in the terminal window title, like --title
+# terminal_title = {}
+
+# Ring the terminal bell when the last download finishes, like --bell. It
+# reaches the terminal over SSH too; bell_sound also plays a file here.
+# bell = {}
+# bell_sound = \"/usr/share/sounds/freedesktop/stereo/complete.oga\"
+
+# Where notifications go: \"desktop\", \"telegram\" and \"matrix\", the latter
+# two set up in the sections below. Setting this turns notifications on
+# without --notify.
+# notify_backend = {}
+
+# Only notify about downloads that took at least this many seconds or have
+# at least this size, so quick small ones stay quiet. Failures are always
+# notified.
+# notify_min_duration = 30
+# notify_min_size = \"100M\"
+
+# Notification text for finished and failed downloads, instead of the
+# translated default. Variables: {{file}}, {{size}}, {{duration}}, {{error}}
+# notify_template = \"{{file}} is ready ({{size}} in {{duration}})\"
+# notify_error_template = \"Could not get {{file}}: {{error}}\"
+
+# Where downloads without an explicit output name are saved, relative to the
+# current directory. Variables: {{host}}, {{dirname}}, {{filename}}, {{stem}},
+# {{ext}}, {{date}}
+# output_template = \"{{host}}/{{dirname}}/{{filename}}\"
+
+# Commands run after every successful or failed download. Variables: {{url}},
+# {{path}}, {{name}}, {{size}}, {{error}}, also passed as DWRS_URL etc. Words
+# are split like a shell would, but no shell runs; use sh -c for pipes.
+# on_complete = \"clamscan --no-summary {{path}}\"
+# on_error = \"notify-send 'Download failed' {{url}}\"
+
+# URL every finished download is POSTed to as JSON with url, path, name,
+# status, size, duration, error and a text line, e.g. a Slack or Home
+# Assistant webhook
+# notify_webhook = \"https://hooks.slack.com/services/T000/B000/XXXX\"
+
+# Command that maps every URL to the one actually downloaded, after the
+# [[rewrite]] rules below. It gets {{url}} (also as DWRS_URL) and prints the
+# new URL; no output or a failure keeps the URL.
+# rewrite_command = \"mirror-map {{url}}\"
+
+# Per-host settings, merged into every download from a matching host.
+# \"*.example.com\" matches example.com and all of its subdomains.
+# [host.\"downloads.example.com\"]
+# workers = 8
+# retries = 5
+# rate_limit = \"10M\"
+# headers = {{ \"X-Api-Key\" = \"secret\" }}
+# auth = {{ user = \"me\", password = \"hunter2\" }}   # or {{ token = \"...\" }}
+
+# Rate limit by time of day. The first window containing the local time
+# replaces rate_limit and per-host limits; 0 means unlimited. Windows may
+# wrap around midnight.
+# [[schedule]]
+# from = \"08:00\"
+# to = \"18:00\"
+# rate_limit = \"1M\"
+
+# Rewrite URLs before downloading them, e.g. to swap a dead mirror across a
+# whole manifest. Rules run in order, each replacing the first match of its
+# regular expression; $1 or ${{name}} insert capture groups.
+# [[rewrite]]
+# pattern = \"^https?://dead-mirror\\\\.example\\\\.org/\"
+# replace = \"https://mirror.example.net/\"
+
+# Mail a summary of every finished batch, for servers without a desktop.
+# Needs dwrs built with the \"email\" feature. security is \"starttls\",
+# \"tls\" or \"none\"; port defaults to 587, 465 or 25 to match.
+# [email]
+# server = \"smtp.example.com\"
+# security = \"starttls\"
+# username = \"me@example.com\"
+# password = \"app-password\"
+# from = \"dwrs@example.com\"
+# to = [\"me@example.com\"]
+
+# Telegram bot for the \"telegram\" notify backend; chat_id is a number or
+# @channelname
+# [telegram]
+# token = \"123456:ABC-DEF...\"
+# chat_id = 123456789
+
+# Matrix room for the \"matrix\" notify backend
+# [matrix]
+# homeserver = \"https://matrix.org\"
+# access_token = \"syt_...\"
+# room_id = \"!abcdefg:matrix.org\"
+
+# Named profiles override any key above and are selected with --profile NAME
+# [profile.vpn]
+# workers = 2
+# rate_limit = \"500K\"
+# proxy = \"socks5://10.8.0.1:1080\"
+",
+            value("msg_template"),
+            value("template"),
+            value("bar_chars"),
+            value("workers"),
+            value("buffer_size"),
+            value("pool_size"),
+            value("retries"),
+            value("min_parallel_size"),
+            value("user_agent"),
+            value("filename_mode"),
+            value("units"),
+            value("terminal_title"),
+            value("bell"),
+            value("notify_backend"),
+        )
     }
 }
 
@@ -87,6 +1478,228 @@ impl Default for Config {
             pool_size: 100,
             retries: 3,
             min_parallel_size: 5 * 1024 * 1024,
+            user_agent: "dwrs".to_string(),
+            proxy: None,
+            rate_limit: None,
+            log_file: None,
+            filename_mode: FilenameMode::default(),
+            units: Units::default(),
+            lang: None,
+            terminal_title: false,
+            bell: false,
+            bell_sound: None,
+            notify_backend: vec![NotifyBackend::Desktop],
+            notify_min_duration: None,
+            notify_min_size: None,
+            notify_template: None,
+            notify_error_template: None,
+            output_template: None,
+            on_complete: None,
+            on_error: None,
+            notify_webhook: None,
+            rewrite_command: None,
+            rewrite: Vec::new(),
+            hosts: BTreeMap::new(),
+            schedule: Vec::new(),
+            email: None,
+            telegram: None,
+            matrix: None,
+            sources: BTreeMap::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_toml_round_trip() {
+        let uncommented: String = Config::default_toml()
+            .lines()
+            .take_while(|l| !l.starts_with("# [host"))
+            .filter_map(|l| l.strip_prefix("# "))
+            .filter(|l| l.contains(" = "))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let file: ConfigFile = toml::from_str(&uncommented).unwrap();
+        assert_eq!(file.settings.template, Some(Config::default().template));
+        assert_eq!(file.settings.min_parallel_size, Some(5 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let path = std::env::temp_dir().join(format!("dwrs-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "workers = 8\nretries = 5\n\n[profile.vpn]\nworkers = 2\nrate_limit = \"500K\"\n",
+        )
+        .unwrap();
+
+        let base = Config::try_load(&path, None).unwrap();
+        assert_eq!((base.workers, base.rate_limit), (8, None));
+
+        let vpn = Config::try_load(&path, Some("vpn")).unwrap();
+        assert_eq!(vpn.workers, 2);
+        assert_eq!(vpn.retries, 5);
+        assert_eq!(vpn.rate_limit, Some(500 * 1024));
+        assert_eq!(
+            vpn.source("workers"),
+            &ConfigSource::Profile("vpn".to_string())
+        );
+
+        let mut env = vpn.clone();
+        env.apply_vars(|name| match name {
+            "DWRS_WORKERS" => Some("6".to_string()),
+            "DWRS_RATE_LIMIT" => Some("1M".to_string()),
+            "DWRS_FILENAME_MODE" => Some("strict".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!((env.workers, env.rate_limit), (6, Some(1 << 20)));
+        assert_eq!(env.filename_mode, FilenameMode::Strict);
+        assert_eq!(
+            env.source("workers"),
+            &ConfigSource::Env("DWRS_WORKERS".to_string())
+        );
+        assert!(
+            env.apply_vars(|name| (name == "DWRS_RETRIES").then(|| "many".to_string()))
+                .is_err()
+        );
+
+        let err = Config::try_load(&path, Some("home")).unwrap_err();
+        assert!(err.contains("available: vpn"), "{}", err);
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_host_sections() {
+        let mut file: ConfigFile = toml::from_str(
+            r#"
+            [host."dl.example.com"]
+            workers = 8
+            headers = { "X-Key" = "a", "Accept" = "*/*" }
+            auth = { user = "me", password = "pw" }
+
+            [host."*.example.com"]
+            retries = 9
+
+            [profile.ci.host."dl.example.com"]
+            rate_limit = "1M"
+            auth = { token = "t" }
+            "#,
+        )
+        .unwrap();
+        let profile = file.profile.remove("ci").unwrap();
+        let mut config = Config::default();
+        config.apply(file.settings, ConfigSource::Cli).unwrap();
+        let host = match_host(&config.hosts, "https://dl.example.com/a.iso").unwrap();
+        assert_eq!(host.workers, Some(8));
+        assert_eq!(
+            host.request_headers(&[("accept".to_string(), "text/plain".to_string())]),
+            vec![
+                ("X-Key".to_string(), "a".to_string()),
+                ("Authorization".to_string(), "Basic bWU6cHc=".to_string()),
+                ("accept".to_string(), "text/plain".to_string()),
+            ]
+        );
+        assert_eq!(
+            match_host(&config.hosts, "http://cdn.example.com/x")
+                .unwrap()
+                .retries,
+            Some(9)
+        );
+        assert!(match_host(&config.hosts, "http://example.org/x").is_none());
+
+        config
+            .apply(profile, ConfigSource::Profile("ci".to_string()))
+            .unwrap();
+        let host = &config.hosts["dl.example.com"];
+        assert_eq!((host.workers, host.rate_limit), (Some(8), Some(1 << 20)));
+        assert_eq!(
+            host.auth,
+            Some(HostAuth::Bearer {
+                token: "t".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [[schedule]]
+            from = "08:00"
+            to = "18:00"
+            rate_limit = "1M"
+
+            [[schedule]]
+            from = "22:00"
+            to = "06:00"
+            rate_limit = 0
+            "#,
+        )
+        .unwrap();
+        let mut config = Config::default();
+        config.apply(file.settings, ConfigSource::Cli).unwrap();
+        assert_eq!(
+            config.schedule,
+            vec![
+                BandwidthWindow {
+                    from: 8 * 60,
+                    to: 18 * 60,
+                    rate_limit: Some(1 << 20),
+                },
+                BandwidthWindow {
+                    from: 22 * 60,
+                    to: 6 * 60,
+                    rate_limit: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&Config::default_toml()).is_empty());
+
+        let diagnostics = validate(
+            "workers = 0\nwrokers = 3\n\n[host.\"a.com\"]\nretries = 2\nfoo = 1\n\n[profile.vpn]\nrate_limit = \"fast\"\n",
+        );
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.line, d.column))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Error, Some(1), Some(1)),
+                (Severity::Warning, Some(2), Some(1)),
+                (Severity::Warning, Some(6), Some(1)),
+                (Severity::Error, Some(9), Some(1)),
+            ]
+        );
+        assert_eq!(diagnostics[1].message, "unknown key 'wrokers'");
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "6:1: unknown key 'host.\"a.com\".foo'"
+        );
+
+        let schedule = validate(
+            "[[schedule]]\nfrom = \"08:00\"\nto = \"25:00\"\nrate_limit = \"1M\"\nfrom_ = 1\n",
+        );
+        let summary: Vec<_> = schedule.iter().map(|d| (d.severity, d.line)).collect();
+        assert_eq!(
+            summary,
+            vec![(Severity::Error, Some(3)), (Severity::Warning, Some(5))]
+        );
+
+        let syntax = validate("workers = 4\ntemplate = \"{bar\n");
+        assert_eq!(syntax.len(), 1);
+        assert_eq!(
+            (syntax[0].severity, syntax[0].line),
+            (Severity::Error, Some(2))
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// What running transfers should do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    /// Stop reading from the network but keep connections and files open
+    Paused,
+    /// Flush what was received and give up, leaving partial files to resume
+    Stopping,
+}
+
+static STATE: Lazy<watch::Sender<RunState>> = Lazy::new(|| watch::Sender::new(RunState::Running));
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static IDLE: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+
+/// Error returned by transfers cut short by [`stop`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+pub fn state() -> RunState {
+    *STATE.borrow()
+}
+
+/// Holds every running transfer before its next read. No-op while stopping.
+pub fn pause() -> bool {
+    STATE.send_if_modified(|state| {
+        let changed = *state == RunState::Running;
+        if changed {
+            *state = RunState::Paused;
+        }
+        changed
+    })
+}
+
+/// Lets paused transfers continue.
+pub fn resume() -> bool {
+    STATE.send_if_modified(|state| {
+        let changed = *state == RunState::Paused;
+        if changed {
+            *state = RunState::Running;
+        }
+        changed
+    })
+}
+
+/// Makes every transfer flush its file and fail with [`Interrupted`].
+///
+/// There is no way back: retries are skipped and new transfers fail at once.
+pub fn stop() {
+    STATE.send_replace(RunState::Stopping);
+}
+
+pub fn is_stopping() -> bool {
+    state() == RunState::Stopping
+}
+
+/// Waits while paused and tells whether it had to; fails once [`stop`] was called.
+///
+/// Called by the download loops before reading the next piece of the body.
+pub(crate) async fn checkpoint() -> Result<bool, Interrupted> {
+    let mut rx = STATE.subscribe();
+    let waited = *rx.borrow() == RunState::Paused;
+    let state = *rx
+        .wait_for(|state| *state != RunState::Paused)
+        .await
+        .map_err(|_| Interrupted)?;
+    match state {
+        RunState::Stopping => Err(Interrupted),
+        _ => Ok(waited),
+    }
+}
+
+/// Counts a transfer as running until the guard is dropped.
+pub(crate) struct ActiveGuard(());
+
+impl ActiveGuard {
+    pub(crate) fn new() -> Self {
+        ACTIVE.fetch_add(1, Ordering::SeqCst);
+        ActiveGuard(())
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        if ACTIVE.fetch_sub(1, Ordering::SeqCst) == 1 {
+            IDLE.notify_waiters();
+        }
+    }
+}
+
+/// Waits until no transfer is running, or at most `timeout`.
+///
+/// Returns `false` on timeout.
+pub async fn wait_idle(timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, async {
+        loop {
+            let idle = IDLE.notified();
+            if ACTIVE.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    })
+    .await
+    .is_ok()
+}
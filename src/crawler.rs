@@ -0,0 +1,463 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{Client, Url};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::DownloadEntry;
+use crate::bandwidth::RateLimiter;
+use crate::filter::{NameFilter, TypeFilter};
+use crate::robots::Robots;
+use crate::sanitize::sanitize;
+
+static LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b(?:href|src)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'<>]+))"#)
+        .expect("valid link regex")
+});
+
+/// Options for recursive mirroring
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Maximum recursion depth, `0` means unlimited
+    pub max_depth: usize,
+    /// Never ascend above the directory of the start URL
+    pub no_parent: bool,
+    /// Directory under which the `host/path` tree is rebuilt
+    pub output_dir: PathBuf,
+    /// How URL paths are laid out under `output_dir`
+    pub layout: DirLayout,
+    /// Honour robots.txt rules and crawl-delay
+    pub respect_robots: bool,
+    /// Content types of pages to save; links are followed either way
+    pub type_filter: TypeFilter,
+    /// File names of resources and pages to save; links are followed either way
+    pub name_filter: NameFilter,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 5,
+            no_parent: false,
+            output_dir: PathBuf::from("."),
+            layout: DirLayout::default(),
+            respect_robots: true,
+            type_filter: TypeFilter::default(),
+            name_filter: NameFilter::default(),
+        }
+    }
+}
+
+/// Extracts `href`/`src` targets from an HTML document and resolves them against `base`.
+pub fn extract_links(html: &str, base: &Url) -> Vec<Url> {
+    LINK_RE
+        .captures_iter(html)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)).or_else(|| c.get(3)))
+        .map(|m| m.as_str().trim())
+        .filter(|s| !s.is_empty() && !s.starts_with('#'))
+        .filter_map(|s| base.join(s).ok())
+        .filter(|u| u.scheme() == "http" || u.scheme() == "https")
+        .map(|mut u| {
+            u.set_fragment(None);
+            u
+        })
+        .collect()
+}
+
+/// How URLs are laid out as local paths, like wget's
+/// `--no-host-directories` and `--cut-dirs`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirLayout {
+    /// Leave out the host directory
+    pub no_host: bool,
+    /// Number of leading directories of the URL path left out
+    pub cut_dirs: usize,
+}
+
+impl DirLayout {
+    /// Maps a URL to its local path (`output_dir/host/path`), using
+    /// `index.html` for directories.
+    pub fn path(&self, url: &Url, output_dir: &Path) -> PathBuf {
+        let mut path = output_dir.to_path_buf();
+        if !self.no_host {
+            path.push(url.host_str().unwrap_or("unknown"));
+        }
+        let mut segments: Vec<&str> = url
+            .path_segments()
+            .map(|s| s.filter(|seg| !seg.is_empty() && *seg != "..").collect())
+            .unwrap_or_default();
+        let file = if url.path().ends_with('/') {
+            None
+        } else {
+            segments.pop()
+        };
+
+        for seg in segments.iter().skip(self.cut_dirs) {
+            path.push(sanitize(seg));
+        }
+        path.push(file.map_or_else(|| "index.html".to_string(), sanitize));
+        if let Some(query) = url.query() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            path.set_file_name(sanitize(&format!("{}?{}", name, query.replace('/', "%2F"))));
+        }
+        path
+    }
+}
+
+/// Maps a URL to its local path (`output_dir/host/path`), using `index.html` for directories.
+pub fn local_path(url: &Url, output_dir: &Path) -> PathBuf {
+    DirLayout::default().path(url, output_dir)
+}
+
+fn parent_prefix(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(i) => path[..=i].to_string(),
+        None => "/".to_string(),
+    }
+}
+
+/// Path and query of `url`, as robots.txt rules match them.
+fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    }
+}
+
+fn looks_like_page(url: &Url) -> bool {
+    let last = url.path().rsplit('/').next().unwrap_or("");
+    match last.rsplit_once('.') {
+        None => true,
+        Some((_, ext)) => matches!(
+            ext.to_ascii_lowercase().as_str(),
+            "html" | "htm" | "xhtml" | "php" | "asp" | "aspx" | "jsp" | "cgi"
+        ),
+    }
+}
+
+/// Breadth-first crawler that discovers a site subtree.
+///
+/// HTML pages are fetched, saved and scanned for links; every other resource
+/// is returned as a [`DownloadEntry`] for the regular batch pipeline. Pages
+/// and resources are requested with the page they were linked from as
+/// `Referer`, which many file hosts insist on.
+pub struct Crawler<'a> {
+    client: &'a Client,
+    opts: CrawlOptions,
+    /// URL, depth and the page linking to it
+    queue: VecDeque<(Url, usize, Option<String>)>,
+    /// `Referer` of the start page
+    referer: Option<String>,
+    visited: HashSet<String>,
+    limiter: Arc<RateLimiter>,
+    /// Token robots.txt groups are matched against
+    agent: String,
+    crawl_delay: Option<Duration>,
+}
+
+impl<'a> Crawler<'a> {
+    pub fn new(client: &'a Client, opts: CrawlOptions) -> Self {
+        Self {
+            client,
+            opts,
+            queue: VecDeque::new(),
+            referer: None,
+            visited: HashSet::new(),
+            limiter: Arc::new(RateLimiter::new(None)),
+            agent: crate::robots::AGENT.to_string(),
+            crawl_delay: None,
+        }
+    }
+
+    /// Counts the pages read against `limiter`, e.g. the one of their host.
+    pub fn limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Follows the robots.txt rules for `user_agent`, the one the requests
+    /// are sent with.
+    pub fn agent(mut self, user_agent: &str) -> Self {
+        self.agent = crate::robots::agent_token(user_agent).to_string();
+        self
+    }
+
+    /// Requests the start page with `referer`; the pages found below it
+    /// get the page linking to them instead.
+    pub fn referer(mut self, referer: Option<String>) -> Self {
+        self.referer = referer;
+        self
+    }
+
+    /// `Crawl-delay` of the last crawled site, which the downloads of its
+    /// resources should keep to as well.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    fn in_scope(&self, url: &Url, root: &Url, prefix: &str) -> bool {
+        if url.host_str() != root.host_str()
+            || url.port_or_known_default() != root.port_or_known_default()
+        {
+            return false;
+        }
+        !self.opts.no_parent || url.path().starts_with(prefix)
+    }
+
+    /// Crawls from `start` and returns the non-HTML resources to download.
+    pub async fn crawl(
+        &mut self,
+        start: &str,
+    ) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        let root = Url::parse(start).map_err(|e| format!("Invalid URL {}: {}", start, e))?;
+        let prefix = parent_prefix(&root);
+        let robots = if self.opts.respect_robots {
+            Robots::fetch(self.client, &root, &self.agent).await
+        } else {
+            Robots::default()
+        };
+        let crawl_delay = robots.crawl_delay();
+        self.crawl_delay = crawl_delay;
+        if !robots.is_allowed(&robots_path(&root)) {
+            return Err(format!("{} is disallowed by robots.txt", root).into());
+        }
+        let mut resources = Vec::new();
+        let mut pages = 0usize;
+
+        self.visited.insert(root.to_string());
+        self.queue
+            .push_back((root.clone(), 0, self.referer.clone()));
+
+        while let Some((url, depth, referer)) = self.queue.pop_front() {
+            let output = self.opts.layout.path(&url, &self.opts.output_dir);
+            let wanted = self.opts.name_filter.allows_url(url.as_str());
+            let resource = |output| {
+                let mut entry = DownloadEntry::new(url.as_str(), output);
+                if let Some(referer) = &referer {
+                    entry.headers.push(("Referer".to_string(), referer.clone()));
+                }
+                entry
+            };
+
+            if !looks_like_page(&url) {
+                if wanted {
+                    resources.push(resource(output));
+                } else {
+                    log::debug!("Rejected by name: {}", url);
+                }
+                continue;
+            }
+
+            if let Some(delay) = crawl_delay
+                && pages > 0
+            {
+                tokio::time::sleep(delay).await;
+            }
+
+            let mut request = self.client.get(url.clone());
+            if let Some(referer) = &referer {
+                request = request.header(reqwest::header::REFERER, referer.as_str());
+            }
+            let resp = match request.send().await {
+                Ok(resp) if resp.status().is_success() => resp,
+                Ok(resp) => {
+                    log::warn!("Skipping {}: HTTP {}", url, resp.status());
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            let content_type = resp
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let is_html = content_type
+                .as_deref()
+                .is_some_and(|ct| ct.contains("html"));
+
+            if !is_html {
+                if wanted {
+                    resources.push(resource(output));
+                } else {
+                    log::debug!("Rejected by name: {}", url);
+                }
+                continue;
+            }
+
+            let base = resp.url().clone();
+            let body = match resp.text().await {
+                Ok(body) => body,
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", url, e);
+                    continue;
+                }
+            };
+            self.limiter.acquire(body.len() as u64).await;
+            pages += 1;
+            if wanted && self.opts.type_filter.allows(content_type.as_deref()) {
+                if let Some(parent) = output.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&output, &body).await?;
+                log::info!("Saved page {} -> {}", url, output.display());
+            } else {
+                log::info!("Not saving rejected page {}", url);
+            }
+
+            if self.opts.max_depth != 0 && depth >= self.opts.max_depth {
+                continue;
+            }
+
+            for link in extract_links(&body, &base) {
+                if !self.in_scope(&link, &root, &prefix) {
+                    continue;
+                }
+                if !robots.is_allowed(&robots_path(&link)) {
+                    log::debug!("Disallowed by robots.txt: {}", link);
+                    continue;
+                }
+                if self.visited.insert(link.to_string()) {
+                    self.queue
+                        .push_back((link, depth + 1, Some(url.to_string())));
+                }
+            }
+        }
+
+        log::info!(
+            "Crawl of {} finished: {} pages visited, {} resources queued",
+            start,
+            pages,
+            resources.len()
+        );
+        Ok(resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let base = Url::parse("https://example.com/pub/index.html").unwrap();
+        let html = r#"<a href="a.zip">a</a> <img src='/img/b.png'> <a href=#top>x</a>
+            <a HREF="https://other.org/c">c</a> <a href="mailto:x@y">m</a>"#;
+        let links: Vec<String> = extract_links(html, &base)
+            .into_iter()
+            .map(|u| u.to_string())
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/pub/a.zip",
+                "https://example.com/img/b.png",
+                "https://other.org/c",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_path() {
+        let out = Path::new("out");
+        let url = Url::parse("https://example.com/pub/").unwrap();
+        assert_eq!(
+            local_path(&url, out),
+            out.join("example.com/pub/index.html")
+        );
+        let url = Url::parse("https://example.com/pub/../a/b.zip").unwrap();
+        assert_eq!(local_path(&url, out), out.join("example.com/a/b.zip"));
+
+        let layout = DirLayout {
+            no_host: true,
+            cut_dirs: 1,
+        };
+        let url = Url::parse("https://example.com/pub/linux/x.iso").unwrap();
+        assert_eq!(layout.path(&url, out), out.join("linux/x.iso"));
+        let url = Url::parse("https://example.com/pub/").unwrap();
+        assert_eq!(layout.path(&url, out), out.join("index.html"));
+    }
+
+    #[tokio::test]
+    async fn test_referer() {
+        use httpmock::MockServer;
+
+        let server = MockServer::start();
+        let index = server.url("/pub/index.html");
+        let root = server.mock(|when, then| {
+            when.method("GET")
+                .path("/pub/index.html")
+                .header("Referer", "https://example.com/");
+            then.status(200)
+                .header("Content-Type", "text/html")
+                .body(r#"<a href="more.html">more</a> <a href="a.zip">a</a>"#);
+        });
+        let more = server.mock(|when, then| {
+            when.method("GET")
+                .path("/pub/more.html")
+                .header("Referer", index.as_str());
+            then.status(200)
+                .header("Content-Type", "text/html")
+                .body("no links");
+        });
+        let client = Client::new();
+        let opts = CrawlOptions {
+            output_dir: std::env::temp_dir().join(format!("dwrs-referer-{}", std::process::id())),
+            respect_robots: false,
+            ..Default::default()
+        };
+        let output_dir = opts.output_dir.clone();
+        let resources = Crawler::new(&client, opts)
+            .referer(Some("https://example.com/".to_string()))
+            .crawl(&index)
+            .await
+            .unwrap();
+        root.assert();
+        more.assert();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].url, server.url("/pub/a.zip"));
+        assert_eq!(
+            resources[0].headers,
+            vec![("Referer".to_string(), index.clone())]
+        );
+        std::fs::remove_dir_all(output_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_robots() {
+        use httpmock::MockServer;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method("GET").path("/robots.txt");
+            then.status(200).body(
+                "User-agent: *\nCrawl-delay: 2\n\nUser-agent: mybot\nDisallow: /private/\nCrawl-delay: 86400\n",
+            );
+        });
+        let page = server.mock(|when, then| {
+            when.method("GET").path("/private/index.html");
+            then.status(200)
+                .header("Content-Type", "text/html")
+                .body("");
+        });
+        let client = Client::new();
+
+        let mut crawler = Crawler::new(&client, CrawlOptions::default()).agent("MyBot/1.0");
+        let err = crawler
+            .crawl(&server.url("/private/index.html"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("disallowed by robots.txt"));
+        assert_eq!(crawler.crawl_delay(), Some(crate::robots::MAX_CRAWL_DELAY));
+        page.assert_calls(0);
+    }
+}
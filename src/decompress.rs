@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::AsyncWrite;
+
+/// Compression layer `--decompress` strips while downloading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// Extensions of compressed files and what is left once they are stripped
+const EXTENSIONS: &[(&str, Compression, &str)] = &[
+    ("gz", Compression::Gzip, ""),
+    ("tgz", Compression::Gzip, "tar"),
+    ("zst", Compression::Zstd, ""),
+    ("tzst", Compression::Zstd, "tar"),
+];
+
+impl Compression {
+    /// Compression of a file named like `path`, e.g. `data.json.gz`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::split(path).map(|(compression, _)| compression)
+    }
+
+    /// Compression announced by a `Content-Type` such as `application/gzip`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match media_type.as_str() {
+            "application/gzip" | "application/x-gzip" => Some(Compression::Gzip),
+            "application/zstd" | "application/x-zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    fn split(path: &Path) -> Option<(Self, PathBuf)> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        let stem = path.file_stem().filter(|stem| !stem.is_empty())?;
+        let (_, compression, replacement) = EXTENSIONS.iter().find(|(e, ..)| *e == ext)?;
+        let mut stripped = path.with_file_name(stem);
+        if !replacement.is_empty() {
+            stripped.set_extension(replacement);
+        }
+        Some((*compression, stripped))
+    }
+
+    /// Wraps `writer` so everything written to it is decompressed first.
+    ///
+    /// The decoder must be shut down to write the last bytes.
+    pub fn decoder<'a, W>(self, writer: W) -> Pin<Box<dyn AsyncWrite + Send + 'a>>
+    where
+        W: AsyncWrite + Send + 'a,
+    {
+        use async_compression::tokio::write::{GzipDecoder, ZstdDecoder};
+        match self {
+            Compression::Gzip => Box::pin(GzipDecoder::new(writer)),
+            Compression::Zstd => Box::pin(ZstdDecoder::new(writer)),
+        }
+    }
+}
+
+/// Where `output` is saved with `--decompress`: `data.json.gz` becomes
+/// `data.json` and `src.tgz` becomes `src.tar`; other names are kept.
+pub fn output_path(output: &Path) -> PathBuf {
+    Compression::split(output).map_or_else(|| output.to_path_buf(), |(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_decompress_layer() {
+        assert_eq!(
+            output_path(Path::new("dl/data.json.GZ")),
+            PathBuf::from("dl/data.json")
+        );
+        assert_eq!(output_path(Path::new("src.tzst")), PathBuf::from("src.tar"));
+        assert_eq!(output_path(Path::new(".gz")), PathBuf::from(".gz"));
+        assert_eq!(output_path(Path::new("a.zip")), PathBuf::from("a.zip"));
+        assert_eq!(
+            Compression::from_content_type("application/x-gzip; charset=binary"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::from_content_type("text/plain"), None);
+
+        let text = b"hello world\n".repeat(100);
+        let mut compressed = Vec::new();
+        async_compression::tokio::bufread::GzipEncoder::new(text.as_slice())
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+
+        let mut out = Vec::new();
+        let mut decoder = Compression::Gzip.decoder(&mut out);
+        // Fed in small pieces like a response body
+        for chunk in compressed.chunks(7) {
+            decoder.write_all(chunk).await.unwrap();
+        }
+        decoder.shutdown().await.unwrap();
+        drop(decoder);
+        assert_eq!(out, text);
+
+        let mut decoder = Compression::Gzip.decoder(Vec::new());
+        decoder
+            .write_all(&compressed[..compressed.len() / 2])
+            .await
+            .unwrap();
+        assert!(decoder.shutdown().await.is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::time::{Duration, SystemTime};
+
+/// Parses a delay such as `90`, `45s`, `15m`, `2h`, `1h30m` or `1d`.
+///
+/// A bare number counts seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected e.g. 90s, 15m or 1h30m",
+            value
+        )
+    };
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = number.parse().map_err(|_| invalid())?;
+        total = n
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || value.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parses a start time relative to `now`.
+///
+/// A bare `HH:MM[:SS]` means its next occurrence, today or tomorrow.
+/// `YYYY-MM-DD HH:MM[:SS]` and RFC 3339 timestamps must not be in the past.
+pub fn parse_start_at(value: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(|| format!("'{}' does not exist in the local time zone", value))
+    };
+
+    let time = NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"));
+    if let Ok(time) = time {
+        // Today's time may not exist when the clocks go forward
+        if let Ok(today) = local(now.date_naive().and_time(time))
+            && today > now
+        {
+            return Ok(today);
+        }
+        let tomorrow = now.date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+        return local(tomorrow.and_time(time));
+    }
+
+    let at = match DateTime::parse_from_rfc3339(value) {
+        Ok(at) => at.with_timezone(&Local),
+        Err(_) => {
+            let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "invalid start time '{}', expected HH:MM or YYYY-MM-DD HH:MM",
+                        value
+                    )
+                })?;
+            local(naive)?
+        }
+    };
+    if at < now {
+        return Err(format!("start time '{}' is in the past", value));
+    }
+    Ok(at)
+}
+
+/// `--start-at` value parser.
+pub fn parse_start_at_now(value: &str) -> Result<SystemTime, String> {
+    parse_start_at(value, Local::now()).map(SystemTime::from)
+}
+
+/// Formats `at` in local time, leaving out the date when it is today.
+pub fn format_local(at: SystemTime) -> String {
+    let at = DateTime::<Local>::from(at);
+    if at.date_naive() == Local::now().date_naive() {
+        at.format("%H:%M:%S").to_string()
+    } else {
+        at.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+/// Formats a countdown as `H:MM:SS`, with days in front when needed.
+pub fn format_countdown(left: Duration) -> String {
+    let secs = left.as_secs();
+    let clock = format!(
+        "{}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    );
+    match secs / 86_400 {
+        0 => clock,
+        days => format!("{}d {}", days, clock),
+    }
+}
+
+/// Sleeps until `at`, showing a countdown on stderr if `progress` is set.
+pub async fn wait_until(at: SystemTime, progress: bool) {
+    log::info!("Waiting until {} before starting", format_local(at));
+    let pb = crate::progress::spinner(progress);
+    let target = format_local(at);
+    while let Ok(left) = at.duration_since(SystemTime::now()) {
+        pb.set_message(format!(
+            "Starting at {} (in {})",
+            target,
+            format_countdown(left + Duration::from_millis(999))
+        ));
+        pb.tick();
+        // Whole seconds keep the countdown in step with the clock
+        let step = Duration::from_nanos(u64::from(left.subsec_nanos()));
+        tokio::time::sleep(if step.is_zero() {
+            left.min(Duration::from_secs(1))
+        } else {
+            step
+        })
+        .await;
+    }
+    pb.finish_and_clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_start() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("2x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("5m3").is_err());
+
+        let now = Local.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+        let at = |s| parse_start_at(s, now).map(|t| t.naive_local().to_string());
+        assert_eq!(at("14:30").unwrap(), "2024-03-10 14:30:00");
+        assert_eq!(at("02:30").unwrap(), "2024-03-11 02:30:00");
+        assert_eq!(at("2024-03-12 08:00").unwrap(), "2024-03-12 08:00:00");
+        assert!(at("2024-03-09 08:00").unwrap_err().contains("in the past"));
+        assert!(at("soon").is_err());
+
+        assert_eq!(format_countdown(Duration::from_secs(3725)), "1:02:05");
+        assert_eq!(format_countdown(Duration::from_secs(90_061)), "1d 1:01:01");
+    }
+}
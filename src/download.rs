@@ -1,18 +1,163 @@
 use futures::StreamExt;
-use indicatif::ProgressBar;
-use reqwest::Client;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::{fs, io::AsyncWriteExt};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+use tokio::fs;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::Instrument;
+
+use crate::bandwidth::RateLimiter;
+use crate::checksum::{self, Algorithm, Checksum, Hasher};
+use crate::control;
+use crate::decompress::Compression;
+use crate::filter::TypeFilter;
+use crate::pipe::{Pipe, PipeCommand};
+use crate::progress::{ProgressBar, RateEstimator};
+use crate::retry::RetryPolicy;
+use crate::transport::{Payload, Transport, request, with_range};
 
 const DEFAULT_BUFFER_SIZE: usize = 256 * 1024;
 const STREAM_CHUNK_SIZE: usize = 64 * 1024;
 const MIN_CHUNK_SIZE: u64 = 2 * 1024 * 1024;
+/// Extensions of binary files, the only ones checked for error pages;
+/// text formats such as `.xml`, `.svg` or `.txt` may well start with markup
+const BINARY_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "appimage", "avi", "bin", "bz2", "deb", "dmg", "exe", "flac", "gif", "gz", "img",
+    "iso", "jar", "jpeg", "jpg", "mkv", "mov", "mp3", "mp4", "msi", "ogg", "pdf", "png", "rar",
+    "rpm", "tar", "tgz", "wav", "webm", "webp", "whl", "xz", "zip", "zst",
+];
+/// HTML downloads up to this size count as error pages even when the
+/// server did not label them as HTML
+const ERROR_PAGE_MAX_SIZE: u64 = 64 * 1024;
+/// Bytes read from the start of a download to recognize HTML
+const SNIFF_LEN: u64 = 1024;
+/// How often the connections of a parallel download are logged
+const CONNECTIONS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Output path that stands for standard output, as in `-o -`
+pub const STDOUT: &str = "-";
+
+/// Whether `output` is [`STDOUT`].
+pub fn is_stdout(output: &Path) -> bool {
+    output == Path::new(STDOUT)
+}
+
+/// In-memory writer that fails once more than `limit` bytes are written
+pub(crate) struct CappedBuffer {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl CappedBuffer {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            limit,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl AsyncWrite for CappedBuffer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if this.buf.len() + buf.len() > this.limit {
+            return Poll::Ready(Err(std::io::Error::other(format!(
+                "Response is larger than {} bytes",
+                this.limit
+            ))));
+        }
+        this.buf.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Writer that also copies everything written to `file` into `copy`,
+/// as `tee` does.
+///
+/// Bytes accepted by `file` are kept until `copy` takes them, so the copy
+/// is never more than one write behind.
+struct Tee<A, B> {
+    file: A,
+    copy: B,
+    backlog: Vec<u8>,
+}
+
+impl<A, B> Tee<A, B> {
+    fn new(file: A, copy: B) -> Self {
+        Self {
+            file,
+            copy,
+            backlog: Vec::new(),
+        }
+    }
+}
+
+impl<A: AsyncWrite + Unpin, B: AsyncWrite + Unpin> Tee<A, B> {
+    fn poll_backlog(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.backlog.is_empty() {
+            let n = ready!(Pin::new(&mut self.copy).poll_write(cx, &self.backlog))?;
+            if n == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.backlog.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<A: AsyncWrite + Unpin, B: AsyncWrite + Unpin> AsyncWrite for Tee<A, B> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_backlog(cx))?;
+        let n = ready!(Pin::new(&mut this.file).poll_write(cx, buf))?;
+        this.backlog.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_backlog(cx))?;
+        ready!(Pin::new(&mut this.file).poll_flush(cx))?;
+        Pin::new(&mut this.copy).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_backlog(cx))?;
+        ready!(Pin::new(&mut this.file).poll_shutdown(cx))?;
+        Pin::new(&mut this.copy).poll_shutdown(cx)
+    }
+}
 
 /// Options for downloading a file
 pub struct DownloadOptions<'a> {
-    pub client: &'a Client,
+    /// Sends the requests; shared with the tasks of parallel chunks
+    pub transport: &'a Arc<dyn Transport>,
     pub url: &'a str,
     pub output: &'a Path,
     pub pb: &'a ProgressBar,
@@ -20,13 +165,108 @@ pub struct DownloadOptions<'a> {
     pub workers: usize,
     pub buffer_size: usize,
     pub min_parallel_size: u64,
+    /// Extra headers sent with the HEAD, GET and range requests
+    pub headers: &'a [(String, String)],
+    /// Method and body sent instead of the HEAD and GET requests
+    pub payload: Option<&'a Payload>,
+    /// Expected checksum verified once the file is complete
+    pub checksum: Option<&'a Checksum>,
+    /// Keeps the transfer under its rate limits, shared by the chunks
+    pub limiter: Arc<RateLimiter>,
+    /// Content types the response must have to be saved
+    pub type_filter: &'a TypeFilter,
+    /// Record where the finished file came from in its extended attributes
+    pub provenance: bool,
+    /// Strip a gzip or zstd layer, recognized by the URL or `Content-Type`,
+    /// while writing the file
+    pub decompress: bool,
+    /// Command the response is streamed through, saving its output instead
+    pub pipe: Option<&'a PipeCommand>,
+    /// Also stream the saved bytes to stdout while writing the file
+    pub tee: bool,
+    /// Receives the progress of each connection if the download runs in
+    /// parallel chunks
+    pub connections: Option<&'a Connections>,
+}
+
+/// What a finished download did
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadReport {
+    /// Bytes received, not counting those an earlier run already saved
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Average bytes per second over `duration`
+    pub avg_speed: f64,
+    /// Attempts made, the successful one included
+    pub attempts: usize,
+    /// Bytes an earlier run already saved, if the download resumed
+    pub resumed_from: Option<u64>,
+    /// Digest of the saved file as `algorithm:hex`, in the algorithm of the
+    /// expected checksum or else SHA-256; `None` for files found complete
+    pub checksum: Option<String>,
 }
 
+impl DownloadReport {
+    pub(crate) fn new(
+        bytes: u64,
+        duration: Duration,
+        resumed_from: u64,
+        checksum: Option<String>,
+    ) -> Self {
+        let secs = duration.as_secs_f64();
+        Self {
+            bytes,
+            duration,
+            avg_speed: if secs > 0.0 { bytes as f64 / secs } else { 0.0 },
+            attempts: 1,
+            resumed_from: Some(resumed_from).filter(|&offset| offset > 0),
+            checksum,
+        }
+    }
+}
+
+/// Error returned when the response has a content type the
+/// [`TypeFilter`] does not allow; nothing is written in that case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejected {
+    pub content_type: Option<String>,
+}
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.content_type {
+            Some(content_type) => write!(f, "Rejected content type {}", content_type),
+            None => f.write_str("Rejected missing content type"),
+        }
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// Fails with [`Rejected`] unless `type_filter` allows the response.
+fn check_type(
+    resp: &reqwest::Response,
+    type_filter: &TypeFilter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if type_filter.allows(content_type) {
+        return Ok(());
+    }
+    Err(Rejected {
+        content_type: content_type.map(str::to_string),
+    }
+    .into())
+}
+
+/// Downloads a file in a single attempt and reports what it took.
 pub async fn download_file(
     opts: DownloadOptions<'_>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<DownloadReport, Box<dyn std::error::Error + Send + Sync>> {
     let DownloadOptions {
-        client,
+        transport,
         url,
         output,
         pb,
@@ -34,34 +274,121 @@ pub async fn download_file(
         workers,
         buffer_size,
         min_parallel_size,
+        headers,
+        payload,
+        checksum,
+        limiter,
+        type_filter,
+        provenance,
+        decompress,
+        pipe,
+        tee,
+        connections,
     } = opts;
+    let output = &crate::sanitize::long_path(output);
+    let started = Instant::now();
 
     log::debug!("Starting download: {} -> {}", url, output.display());
+    let _active = control::ActiveGuard::new();
+    if control::checkpoint().await.is_err() {
+        return Err(interrupted(pb, output));
+    }
 
-    let head_resp = match client.head(url).send().await {
-        Ok(resp) => {
-            log::debug!(
-                "HEAD request successful for {}: status {}",
-                url,
-                resp.status()
+    // What a request with a body returns is only known once it is sent
+    let head_resp = match payload {
+        Some(_) => None,
+        None => match transport
+            .execute(request(Method::HEAD, url, headers)?)
+            .await
+        {
+            Ok(resp) => {
+                log::debug!(
+                    "HEAD request successful for {}: status {}",
+                    url,
+                    resp.status()
+                );
+                Some(resp)
+            }
+            Err(e) => {
+                log::error!("HEAD request failed for {}: {}", url, e);
+                return Err(format!("Failed to connect: {}", e).into());
+            }
+        },
+    };
+
+    let mut content_type = None;
+    let mut etag = None;
+    let mut announced = None;
+    if let Some(head_resp) = head_resp.as_ref().filter(|resp| resp.status().is_success()) {
+        announced = checksum::from_headers(head_resp.headers());
+        check_type(head_resp, type_filter)?;
+        let header = |name| {
+            head_resp
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        content_type = header(reqwest::header::CONTENT_TYPE);
+        etag = header(reqwest::header::ETAG);
+    }
+
+    let expected = match (checksum, announced.as_ref()) {
+        (Some(checksum), _) => Some(checksum),
+        (None, Some(announced)) => {
+            log::info!(
+                "Verifying {} against the {} digest sent by the server",
+                output.display(),
+                announced.algorithm
             );
-            resp
-        }
-        Err(e) => {
-            log::error!("HEAD request failed for {}: {}", url, e);
-            return Err(format!("Failed to connect: {}", e).into());
+            Some(announced)
         }
+        (None, None) => None,
     };
+    // Files nothing is expected of are still hashed for the report
+    let hash = Some(expected.map_or(Algorithm::Sha256, |checksum| checksum.algorithm));
+
+    let compression = if decompress {
+        compression_of(url, content_type.as_deref())
+    } else {
+        None
+    };
+    match compression {
+        Some(compression) => log::info!(
+            "Decompressing {:?} download of {} into {}",
+            compression,
+            url,
+            output.display()
+        ),
+        None if decompress => log::info!("{} is not compressed, saving it as is", url),
+        None => {}
+    }
+    let to_stdout = is_stdout(output);
+    // Readers of stdout must get the file from its first byte, in order
+    let tee = tee && !to_stdout;
+    if pipe.is_some() {
+        log::info!("Piping {} into {}", url, output.display());
+    }
+    // The file holds transformed bytes, so there is no offset to resume from
+    let transformed = compression.is_some() || pipe.is_some();
+    // and ranges do not apply to requests with a body
+    let resume = resume && payload.is_none() && !transformed && !to_stdout && !tee;
+
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).await?;
+    }
 
     let total_size = head_resp
-        .headers()
-        .get(reqwest::header::CONTENT_LENGTH)
+        .as_ref()
+        .and_then(|resp| resp.headers().get(reqwest::header::CONTENT_LENGTH))
         .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
         .unwrap_or(0);
 
     let accept_ranges = head_resp
-        .headers()
-        .get(reqwest::header::ACCEPT_RANGES)
+        .as_ref()
+        .and_then(|resp| resp.headers().get(reqwest::header::ACCEPT_RANGES))
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
@@ -74,9 +401,15 @@ pub async fn download_file(
 
     pb.set_length(total_size);
 
-    let use_parallel = accept_ranges == "bytes" && total_size > min_parallel_size && workers > 1;
+    // Compressed and piped streams can only be consumed in order
+    let use_parallel = accept_ranges == "bytes"
+        && total_size > min_parallel_size
+        && workers > 1
+        && !transformed
+        && !to_stdout
+        && !tee;
 
-    if !use_parallel {
+    let transfer = if !use_parallel {
         log::info!(
             "Using sequential download for {} (workers={}, size={}, threshold={})",
             url,
@@ -84,37 +417,481 @@ pub async fn download_file(
             total_size,
             min_parallel_size
         );
-        return download_optimized(client, url, output, pb, resume, total_size, buffer_size).await;
+        let opts = SequentialOptions {
+            transport: &**transport,
+            url,
+            output,
+            pb,
+            resume,
+            total_size,
+            buffer_size,
+            headers,
+            payload,
+            limiter: &limiter,
+            type_filter,
+            hash,
+            compression,
+            pipe,
+            tee,
+        };
+        download_optimized(opts).await?
+    } else {
+        log::info!(
+            "Using parallel download for {} with {} workers",
+            url,
+            workers
+        );
+        let opts = ParallelOptions {
+            transport,
+            url,
+            output,
+            pb,
+            resume,
+            total_size,
+            workers,
+            buffer_size,
+            headers,
+            limiter,
+            hash,
+            connections,
+        };
+        download_parallel(opts).await?
+    };
+    if !to_stdout {
+        check_error_page(output, content_type.as_deref()).await?;
     }
+    let digest = verify(output, expected, transfer.digest).await?;
 
-    log::info!(
-        "Using parallel download for {} with {} workers",
-        url,
-        workers
-    );
+    if provenance && !to_stdout {
+        crate::provenance::record(output, url, etag.as_deref());
+    }
+    Ok(DownloadReport::new(
+        pb.position().saturating_sub(transfer.resumed_from),
+        started.elapsed(),
+        transfer.resumed_from,
+        digest.as_ref().map(ToString::to_string),
+    ))
+}
+
+/// What [`download_optimized`] and [`download_parallel`] did
+#[derive(Debug)]
+struct Transfer {
+    /// Digest of the file, if one was asked for and could be taken
+    digest: Option<String>,
+    /// Bytes an earlier run already saved
+    resumed_from: u64,
+}
 
-    let opts = ParallelOptions {
-        client,
+/// Options for streaming a download into a writer
+pub struct WriterOptions<'a> {
+    pub transport: &'a dyn Transport,
+    pub url: &'a str,
+    pub pb: &'a ProgressBar,
+    /// Extra headers sent with the request
+    pub headers: &'a [(String, String)],
+    /// Method and body sent instead of a GET request
+    pub payload: Option<&'a Payload>,
+    /// Keeps the transfer under its rate limits
+    pub limiter: &'a RateLimiter,
+    /// Content types the response must have to be written
+    pub type_filter: &'a TypeFilter,
+    /// Strip a gzip or zstd layer, recognized by the URL or `Content-Type`
+    pub decompress: bool,
+}
+
+/// Streams `url` into `writer` with a single request and returns the
+/// number of bytes received.
+///
+/// As the bytes have to arrive in order, the download is never split into
+/// chunks or resumed. A digest announced by the server is checked once
+/// everything is written, and `writer` is shut down at the end so encoders
+/// can finish.
+pub async fn download_to_writer<W>(
+    opts: WriterOptions<'_>,
+    writer: &mut W,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>
+where
+    W: AsyncWrite + Unpin + Send + ?Sized,
+{
+    let WriterOptions {
+        transport,
         url,
-        output,
         pb,
-        resume,
-        total_size,
-        workers,
-        buffer_size,
+        headers,
+        payload,
+        limiter,
+        type_filter,
+        decompress,
+    } = opts;
+    let _active = control::ActiveGuard::new();
+
+    let request = match payload {
+        Some(payload) => payload.request(url, headers)?,
+        None => request(Method::GET, url, headers)?,
+    };
+    let resp = transport.execute(request).await?.error_for_status()?;
+    check_type(&resp, type_filter)?;
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    let compression = compression_of(url, content_type).filter(|_| decompress);
+    let expected = checksum::from_headers(resp.headers());
+    pb.set_length(resp.content_length().unwrap_or(0));
+
+    let mut writer = match compression {
+        Some(compression) => compression.decoder(writer),
+        None => Box::pin(writer),
     };
-    download_parallel(opts).await
+    let mut hasher = expected.as_ref().map(|c| Hasher::new(c.algorithm));
+    let mut stream = resp.bytes_stream();
+    let mut downloaded = 0;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        writer.write_all(&chunk).await?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&chunk);
+        }
+        downloaded += chunk.len() as u64;
+        pb.set_position(downloaded);
+        limiter.acquire(chunk.len() as u64).await;
+        if control::checkpoint().await.is_err() {
+            return Err(interrupted(pb, Path::new(url)));
+        }
+    }
+    if let Err(e) = writer.shutdown().await {
+        return Err(match compression {
+            Some(_) => format!("Failed to decompress {}: {}", url, e).into(),
+            None => e.into(),
+        });
+    }
+    if let (Some(expected), Some(hasher)) = (expected, hasher) {
+        checksum::check(Path::new(url), &expected, &hasher.finalize())?;
+    }
+    log::info!("Download complete: {} ({} bytes)", url, downloaded);
+    pb.finish();
+    Ok(downloaded)
 }
 
-async fn download_optimized(
-    client: &Client,
-    url: &str,
+/// Options for [`byte_stream`], owned so the stream can outlive them
+pub struct StreamOptions {
+    pub transport: Arc<dyn Transport>,
+    pub url: String,
+    /// Extra headers sent with every request
+    pub headers: Vec<(String, String)>,
+    /// Method and body sent instead of a GET request; a stream with one
+    /// cannot reconnect once bytes arrived
+    pub payload: Option<Payload>,
+    /// Keeps the stream under its rate limits as the chunks are taken
+    pub limiter: Arc<RateLimiter>,
+    /// Content types the response must have
+    pub type_filter: TypeFilter,
+    /// Attempts made before an error ends the stream
+    pub retries: usize,
+    /// Delay before reconnecting, see [`RetryPolicy`]
+    pub retry_policy: Arc<dyn RetryPolicy>,
+}
+
+/// Where a [`byte_stream`] is at between two chunks
+struct StreamState {
+    opts: StreamOptions,
+    body: Option<futures::stream::BoxStream<'static, reqwest::Result<bytes::Bytes>>>,
+    received: u64,
+    attempt: usize,
+    done: bool,
+}
+
+impl StreamState {
+    /// Sends the request for the bytes not received yet.
+    async fn connect(&self) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let StreamOptions {
+            transport,
+            url,
+            headers,
+            payload,
+            type_filter,
+            ..
+        } = &self.opts;
+        let mut request = match payload {
+            Some(payload) => payload.request(url, headers)?,
+            None => request(Method::GET, url, headers)?,
+        };
+        if self.received > 0 {
+            if payload.is_some() {
+                let message = format!("Cannot resume {} at byte {}", url, self.received);
+                return Err(Unresumable(message).into());
+            }
+            request = with_range(request, self.received, None);
+        }
+        let resp = transport.execute(request).await?.error_for_status()?;
+        if self.received == 0 {
+            check_type(&resp, type_filter)?;
+        } else if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            let message = format!("Cannot resume {} at byte {}", url, self.received);
+            return Err(Unresumable(message).into());
+        }
+        Ok(resp)
+    }
+
+    /// Whether to try again after `e`, waiting before it if so.
+    async fn retry(&mut self, e: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        self.attempt += 1;
+        if e.is::<Rejected>() || e.is::<Unresumable>() || self.attempt >= self.opts.retries {
+            return false;
+        }
+        let Some(delay) = self.opts.retry_policy.should_retry(e, self.attempt) else {
+            return false;
+        };
+        log::warn!(
+            "Reconnecting to {} at byte {} (attempt {}/{}), waiting {}s: {}",
+            self.opts.url,
+            self.received,
+            self.attempt + 1,
+            self.opts.retries,
+            delay.as_secs_f64(),
+            e
+        );
+        tokio::time::sleep(delay).await;
+        true
+    }
+}
+
+/// Error for a stream the server cannot continue with a range request
+#[derive(Debug)]
+struct Unresumable(String);
+
+impl std::fmt::Display for Unresumable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unresumable {}
+
+/// Streams the body of a download chunk by chunk.
+///
+/// A connection lost midway is reopened with a range request for the rest,
+/// so the stream carries on where it stopped; servers that answer such a
+/// request with the whole file, and streams with a [`Payload`], end the
+/// stream with an error instead. The rate limit applies as the chunks are
+/// taken.
+pub fn byte_stream(
+    opts: StreamOptions,
+) -> impl futures::Stream<Item = Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>>> {
+    let state = StreamState {
+        opts,
+        body: None,
+        received: 0,
+        attempt: 0,
+        done: false,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        while !state.done {
+            let Some(body) = state.body.as_mut() else {
+                match state.connect().await {
+                    Ok(resp) => state.body = Some(resp.bytes_stream().boxed()),
+                    Err(e) if state.retry(&*e).await => {}
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                }
+                continue;
+            };
+            match body.next().await {
+                Some(Ok(chunk)) => {
+                    state.received += chunk.len() as u64;
+                    state.opts.limiter.acquire(chunk.len() as u64).await;
+                    return Some((Ok(chunk), state));
+                }
+                Some(Err(e)) => {
+                    state.body = None;
+                    let e: Box<dyn std::error::Error + Send + Sync> = e.into();
+                    if !state.retry(&*e).await {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                }
+                None => state.done = true,
+            }
+        }
+        None
+    })
+}
+
+/// Compression of the response for `url`, from its `Content-Type` or
+/// else the extension of its last path segment.
+fn compression_of(url: &str, content_type: Option<&str>) -> Option<Compression> {
+    content_type
+        .and_then(Compression::from_content_type)
+        .or_else(|| {
+            let url = reqwest::Url::parse(url).ok()?;
+            let name = url.path_segments()?.next_back()?.to_string();
+            Compression::from_path(Path::new(&name))
+        })
+}
+
+/// Whether `head`, the start of a file, is an HTML document.
+fn sniff_html(head: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    text.starts_with("<!doctype html")
+        || text.starts_with("<html")
+        || (text.starts_with('<') && text.contains("<html"))
+}
+
+/// Whether a finished download looks like an HTML error page sent with
+/// status 200 in place of the file, e.g. a mirror's "not found" page.
+///
+/// Only outputs with one of the [`BINARY_EXTENSIONS`] are suspect; their
+/// content must be HTML and either labelled as such or no larger than
+/// [`ERROR_PAGE_MAX_SIZE`].
+fn is_error_page(output: &Path, content_type: Option<&str>, size: u64, head: &[u8]) -> bool {
+    let Some(ext) = output.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    if !BINARY_EXTENSIONS
+        .iter()
+        .any(|binary| binary.eq_ignore_ascii_case(ext))
+    {
+        return false;
+    }
+    let labelled_html = content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("html"));
+    sniff_html(head) && (labelled_html || size <= ERROR_PAGE_MAX_SIZE)
+}
+
+/// Error for a download that turned out to be an HTML error page; another
+/// attempt would most likely get the same page, so it is not retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorPage {
+    pub output: PathBuf,
+    pub size: u64,
+}
+
+impl std::fmt::Display for ErrorPage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Server sent an HTML page instead of {} ({} bytes), probably an error page",
+            self.output.display(),
+            self.size
+        )
+    }
+}
+
+impl std::error::Error for ErrorPage {}
+
+/// Removes `output` and fails with [`ErrorPage`] if it turned out to be an
+/// [error page](is_error_page).
+async fn check_error_page(
     output: &Path,
-    pb: &ProgressBar,
+    content_type: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use tokio::io::AsyncReadExt;
+
+    let file = fs::File::open(output).await?;
+    let size = file.metadata().await?.len();
+    let mut head = Vec::new();
+    file.take(SNIFF_LEN).read_to_end(&mut head).await?;
+    if !is_error_page(output, content_type, size, &head) {
+        return Ok(());
+    }
+    fs::remove_file(output).await.ok();
+    Err(ErrorPage {
+        output: output.to_path_buf(),
+        size,
+    }
+    .into())
+}
+
+/// Verifies `output` against `expected`, using the digest computed while
+/// downloading if there is one instead of reading the file again. A
+/// corrupt `output` is removed so a retry downloads it from scratch.
+///
+/// Returns the digest of `output`, also when nothing is expected of it.
+async fn verify(
+    output: &Path,
+    expected: Option<&Checksum>,
+    digest: Option<String>,
+) -> Result<Option<Checksum>, Box<dyn std::error::Error + Send + Sync>> {
+    let algorithm = expected.map_or(Algorithm::Sha256, |expected| expected.algorithm);
+    let hex = match digest {
+        Some(hex) => hex,
+        // Resumed files were only partly hashed while they were written
+        None if !is_stdout(output) => checksum::hash_file(output, algorithm).await?,
+        None => return Ok(None),
+    };
+    if let Some(expected) = expected
+        && let Err(e) = checksum::check(output, expected, &hex)
+    {
+        if let Err(err) = tokio::fs::remove_file(output).await {
+            log::warn!("Failed to remove corrupt {}: {}", output.display(), err);
+        }
+        return Err(e);
+    }
+    Ok(Some(Checksum { algorithm, hex }))
+}
+
+/// Leaves the progress bar in place marked as interrupted and returns
+/// the matching error.
+fn interrupted(pb: &ProgressBar, output: &Path) -> Box<dyn std::error::Error + Send + Sync> {
+    pb.abandon_with_message(format!("⏸ {} (interrupted)", output.display()));
+    control::Interrupted.into()
+}
+
+/// Options for sequential download
+struct SequentialOptions<'a> {
+    transport: &'a dyn Transport,
+    url: &'a str,
+    output: &'a Path,
+    pb: &'a ProgressBar,
     resume: bool,
     total_size: u64,
     buffer_size: usize,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    headers: &'a [(String, String)],
+    /// Method and body of the request; never set together with `resume`
+    payload: Option<&'a Payload>,
+    limiter: &'a RateLimiter,
+    type_filter: &'a TypeFilter,
+    /// Algorithm to hash the response with while it is written
+    hash: Option<Algorithm>,
+    /// Layer to strip from the response; never set together with `resume`
+    compression: Option<Compression>,
+    /// Command the (decompressed) response is written to; never set
+    /// together with `resume`
+    pipe: Option<&'a PipeCommand>,
+    /// Copy what is written to the file to stdout; never set together
+    /// with `resume`
+    tee: bool,
+}
+
+/// Downloads `url` in one stream; the digest is only taken if one was
+/// asked for and the download did not resume.
+///
+/// Progress, rate limit and digest all count the bytes of the response,
+/// so with `compression` or `pipe` the bar follows the response while the
+/// size of the written file is only known at the end.
+async fn download_optimized(
+    opts: SequentialOptions<'_>,
+) -> Result<Transfer, Box<dyn std::error::Error + Send + Sync>> {
+    let SequentialOptions {
+        transport,
+        url,
+        output,
+        pb,
+        resume,
+        total_size,
+        buffer_size,
+        headers,
+        payload,
+        limiter,
+        type_filter,
+        hash,
+        compression,
+        pipe,
+        tee,
+    } = opts;
     let mut start_byte = 0u64;
 
     if resume && output.exists() {
@@ -129,7 +906,10 @@ async fn download_optimized(
                 } else if existing == total_size {
                     log::info!("File already complete: {}", output.display());
                     pb.finish_with_message("Already complete");
-                    return Ok(());
+                    return Ok(Transfer {
+                        digest: None,
+                        resumed_from: existing,
+                    });
                 } else {
                     log::warn!(
                         "Existing file larger than expected, removing: {}",
@@ -145,26 +925,105 @@ async fn download_optimized(
         }
     }
 
-    let mut request = client.get(url);
+    let mut request = match payload {
+        Some(payload) => payload.request(url, headers)?,
+        None => request(Method::GET, url, headers)?,
+    };
     if start_byte > 0 {
-        request = request.header("Range", format!("bytes={}-", start_byte));
+        request = with_range(request, start_byte, None);
         log::debug!("Adding Range header: bytes={}-", start_byte);
+        // Asks for the whole file instead if it changed since the first run
+        if let Some(etag) = crate::provenance::partial_etag(output)
+            && let Ok(value) = reqwest::header::HeaderValue::from_str(&etag)
+        {
+            request
+                .headers_mut()
+                .insert(reqwest::header::IF_RANGE, value);
+        }
     }
 
-    let resp = request.send().await?.error_for_status()?;
+    let resp = transport.execute(request).await?.error_for_status()?;
     log::debug!("GET request successful, status: {}", resp.status());
+    check_type(&resp, type_filter)?;
+    if start_byte > 0 {
+        if resp.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            let first = resp
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(range_start);
+            match first {
+                Some(first) if first == start_byte => {}
+                Some(first) => {
+                    return Err(format!(
+                        "Server resumed {} at byte {} instead of {}",
+                        url, first, start_byte
+                    )
+                    .into());
+                }
+                None => {
+                    return Err(
+                        format!("Server resumed {} without a valid Content-Range", url).into(),
+                    );
+                }
+            }
+        } else {
+            log::info!(
+                "Server sent all of {} again, restarting {}",
+                url,
+                output.display()
+            );
+            start_byte = 0;
+            pb.set_position(0);
+        }
+    }
+    // Without a HEAD request the size comes with the response
+    if payload.is_some()
+        && let Some(len) = resp.content_length()
+    {
+        pb.set_length(len);
+    }
 
-    let file = if resume && start_byte > 0 {
-        fs::OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(output)
-            .await?
+    let file: Pin<Box<dyn AsyncWrite + Send>> = if is_stdout(output) {
+        Box::pin(tokio::io::stdout())
+    } else if resume && start_byte > 0 {
+        Box::pin(
+            fs::OpenOptions::new()
+                .write(true)
+                .append(true)
+                .open(output)
+                .await?,
+        )
     } else {
-        fs::File::create(output).await?
+        let file = fs::File::create(output).await?;
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok());
+        crate::provenance::mark_partial(output, etag);
+        Box::pin(file)
+    };
+    let file: Pin<Box<dyn AsyncWrite + Send>> = if tee {
+        Box::pin(Tee::new(file, tokio::io::stdout()))
+    } else {
+        file
     };
 
-    let mut writer = tokio::io::BufWriter::with_capacity(buffer_size, file);
+    let file = tokio::io::BufWriter::with_capacity(buffer_size, file);
+    // Dropping `pipe` before its input is closed kills the command
+    let (sink, pipe): (Pin<Box<dyn AsyncWrite + Send>>, _) = match pipe {
+        Some(command) => {
+            let (stdin, pipe) = command.start(url, output, file)?;
+            (Box::pin(stdin), Some(pipe))
+        }
+        None => (Box::pin(file), None),
+    };
+    let mut writer = match compression {
+        Some(compression) => compression.decoder(sink),
+        None => sink,
+    };
+    // The bytes of a resumed file went by in an earlier run
+    let mut hasher = hash.filter(|_| start_byte == 0).map(Hasher::new);
     let mut stream = resp.bytes_stream();
     let mut downloaded = start_byte;
     let mut last_log = downloaded;
@@ -173,9 +1032,21 @@ async fn download_optimized(
     while let Some(chunk) = stream.next().await {
         let chunk = chunk?;
         let len = chunk.len() as u64;
-        writer.write_all(&chunk).await?;
+        if let Err(e) = writer.write_all(&chunk).await {
+            drop(writer);
+            return Err(write_error(url, e, compression, pipe).await);
+        }
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&chunk);
+        }
         downloaded += len;
         pb.set_position(downloaded);
+        limiter.acquire(len).await;
+        if control::checkpoint().await.is_err() {
+            writer.flush().await?;
+            log::info!("Stopped {} at byte {}", output.display(), downloaded);
+            return Err(interrupted(pb, output));
+        }
 
         if downloaded - last_log >= log_interval {
             log::info!(
@@ -188,19 +1059,72 @@ async fn download_optimized(
         }
     }
 
-    writer.flush().await?;
-    log::info!(
-        "Download complete: {} ({} bytes)",
-        output.display(),
-        downloaded
-    );
+    if let Err(e) = writer.shutdown().await {
+        drop(writer);
+        return Err(write_error(url, e, compression, pipe).await);
+    }
+    drop(writer);
+    if !is_stdout(output) {
+        crate::provenance::mark_partial(output, None);
+    }
+    let written = match pipe {
+        Some(pipe) => Some(pipe.wait().await?),
+        None if compression.is_some() && !is_stdout(output) => {
+            fs::metadata(output).await.ok().map(|meta| meta.len())
+        }
+        None => None,
+    };
+    if let Some(size) = written {
+        log::info!(
+            "Download complete: {} ({} bytes, {} written)",
+            output.display(),
+            downloaded,
+            size
+        );
+    } else {
+        log::info!(
+            "Download complete: {} ({} bytes)",
+            output.display(),
+            downloaded
+        );
+    }
     pb.finish();
-    Ok(())
+    Ok(Transfer {
+        digest: hasher.map(Hasher::finalize),
+        resumed_from: start_byte,
+    })
+}
+
+/// First byte of a `Content-Range: bytes first-last/total` header.
+fn range_start(content_range: &str) -> Option<u64> {
+    let range = content_range.trim().strip_prefix("bytes ")?;
+    range.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Error for a failed write of the response, blaming the pipe command
+/// if it failed and else the decoder.
+///
+/// The writer must be dropped first so the command sees the end of its input.
+async fn write_error(
+    url: &str,
+    e: std::io::Error,
+    compression: Option<Compression>,
+    pipe: Option<Pipe>,
+) -> Box<dyn std::error::Error + Send + Sync> {
+    if let Some(pipe) = pipe
+        && let Err(failed) = pipe.wait().await
+    {
+        return failed.into();
+    }
+    match compression {
+        Some(_) => format!("Failed to decompress {}: {}", url, e).into(),
+        None => e.into(),
+    }
 }
 
 /// Options for parallel download
 struct ParallelOptions<'a> {
-    client: &'a Client,
+    transport: &'a Arc<dyn Transport>,
     url: &'a str,
     output: &'a Path,
     pb: &'a ProgressBar,
@@ -208,13 +1132,19 @@ struct ParallelOptions<'a> {
     total_size: u64,
     workers: usize,
     buffer_size: usize,
+    headers: &'a [(String, String)],
+    limiter: Arc<RateLimiter>,
+    /// Algorithm to hash the file with while the parts are merged
+    hash: Option<Algorithm>,
+    connections: Option<&'a Connections>,
 }
 
+/// Downloads `url` in chunks, taking its digest if one was asked for.
 async fn download_parallel(
     opts: ParallelOptions<'_>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<Transfer, Box<dyn std::error::Error + Send + Sync>> {
     let ParallelOptions {
-        client,
+        transport,
         url,
         output,
         pb,
@@ -222,43 +1152,81 @@ async fn download_parallel(
         total_size,
         workers,
         buffer_size,
+        headers,
+        limiter,
+        hash,
+        connections,
     } = opts;
 
-    let optimal_workers = std::cmp::min(
-        workers,
-        std::cmp::max(1, (total_size / MIN_CHUNK_SIZE) as usize),
+    let state_path = ChunkState::path(output);
+    let saved = if resume {
+        ChunkState::load(&state_path)
+            .await
+            .filter(|state| state.url == url && state.total_size == total_size)
+    } else {
+        None
+    };
+    let mut state = match saved {
+        Some(state) => {
+            log::info!(
+                "Resuming {} chunks from {}",
+                state.chunks.len(),
+                state_path.display()
+            );
+            state
+        }
+        None => ChunkState::new(url, total_size, workers),
+    };
+    log::info!(
+        "Parallel download: {} chunks of up to {} bytes",
+        state.chunks.len(),
+        state.chunks.first().map_or(0, |c| c.end - c.start + 1)
     );
 
-    let chunk_size = total_size.div_ceil(optimal_workers as u64);
-    log::info!(
-        "Parallel download: {} chunks, {} bytes each",
-        optimal_workers,
-        chunk_size
+    // Count what earlier runs already saved so progress starts from there
+    let mut saved = vec![0; state.chunks.len()];
+    if resume {
+        for (i, chunk) in state.chunks.iter().enumerate() {
+            if let Ok(meta) = fs::metadata(output.with_extension(format!("part{}", i))).await {
+                saved[i] = meta.len().min(chunk.end - chunk.start + 1);
+            }
+        }
+    }
+    let saved_bytes = saved.iter().sum();
+    if resume {
+        pb.set_position(saved_bytes);
+    }
+    let connections = connections.cloned().unwrap_or_default();
+    let meters = connections.start(
+        state
+            .chunks
+            .iter()
+            .zip(&saved)
+            .map(|(chunk, &saved)| (chunk.start, chunk.end, saved)),
     );
+    let _logger = ConnectionsLogger::spawn(&connections, output);
+    if let Err(e) = state.save(&state_path).await {
+        log::warn!("Failed to write {}: {}", state_path.display(), e);
+    }
 
     let pb_shared = Arc::new(pb.clone());
 
-    let mut handles = Vec::with_capacity(optimal_workers);
-    let progress_shared = Arc::new(AtomicU64::new(pb.position()));
+    let mut handles = Vec::with_capacity(state.chunks.len());
+    let progress_shared = Arc::new(AtomicU64::new(saved_bytes));
 
-    for i in 0..optimal_workers {
-        let start = i as u64 * chunk_size;
-        let end = std::cmp::min(start + chunk_size - 1, total_size - 1);
-
-        if start > end {
-            continue;
-        }
-
-        let client = client.clone();
+    for (i, chunk) in state.chunks.iter().enumerate() {
+        let (start, end) = (chunk.start, chunk.end);
+        let transport = transport.clone();
         let url = url.to_string();
         let tmp_path = output.with_extension(format!("part{}", i));
         let pb_clone = pb_shared.clone();
         let progress = progress_shared.clone();
+        let meter = meters[i].clone();
 
         log::debug!("Spawning chunk {}: bytes {}-{}", i, start, end);
 
         let chunk_opts = ChunkOptions {
-            client,
+            transport,
             url,
             tmp_path,
             start,
@@ -266,15 +1234,25 @@ async fn download_parallel(
             resume,
             pb: pb_clone,
             progress,
+            meter,
             buffer_size,
+            headers: headers.to_vec(),
+            limiter: limiter.clone(),
         };
 
+        let span =
+            tracing::info_span!("chunk", index = i, range = %format_args!("{}-{}", start, end));
         handles.push(tokio::spawn(
-            async move { download_chunk(chunk_opts).await },
+            async move { download_chunk(chunk_opts).await }.instrument(span),
         ));
     }
 
+    // Wait for every chunk, even after a failure, so that all part files are
+    // flushed before the chunk state is saved
+    let aborts: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+    let _tasks = ChunkTasks(aborts.clone());
     let mut parts = Vec::with_capacity(handles.len());
+    let mut failure = None;
     for (i, handle) in handles.into_iter().enumerate() {
         match handle.await {
             Ok(Ok(path)) => {
@@ -282,14 +1260,36 @@ async fn download_parallel(
                 parts.push((i, path));
             }
             Ok(Err(e)) => {
-                log::error!("Chunk {} failed: {}", i, e);
-                return Err(format!("Chunk {} failed: {}", i, e).into());
+                if !control::is_stopping() {
+                    log::error!("Chunk {} failed: {}", i, e);
+                }
+                failure.get_or_insert_with(|| format!("Chunk {} failed: {}", i, e));
             }
+            Err(e) if e.is_cancelled() => {}
             Err(e) => {
                 log::error!("Chunk {} panicked: {}", i, e);
-                return Err(format!("Chunk {} panicked: {}", i, e).into());
+                failure.get_or_insert_with(|| format!("Chunk {} panicked: {}", i, e));
             }
         }
+        // Chunks that are stopping flush their files, everything else can go
+        if failure.is_some() && !control::is_stopping() {
+            aborts.iter().for_each(|a| a.abort());
+        }
+    }
+
+    if let Some(failure) = failure {
+        for (i, chunk) in state.chunks.iter_mut().enumerate() {
+            chunk.downloaded = fs::metadata(output.with_extension(format!("part{}", i)))
+                .await
+                .map_or(0, |meta| meta.len());
+        }
+        if let Err(e) = state.save(&state_path).await {
+            log::warn!("Failed to write {}: {}", state_path.display(), e);
+        }
+        if control::is_stopping() {
+            return Err(interrupted(pb, output));
+        }
+        return Err(failure.into());
     }
 
     parts.sort_by_key(|(i, _)| *i);
@@ -300,15 +1300,81 @@ async fn download_parallel(
         sorted_parts.len(),
         output.display()
     );
-    merge_parts(output, &sorted_parts, total_size).await?;
+    let digest = merge_parts(output, &sorted_parts, total_size, hash).await?;
+    fs::remove_file(&state_path).await.ok();
 
     pb.finish();
-    Ok(())
+    Ok(Transfer {
+        digest,
+        resumed_from: saved_bytes,
+    })
+}
+
+/// Chunk layout of a parallel download, kept next to the output as
+/// `<output>.dwrs` until the parts are merged.
+///
+/// With `--continue` the saved ranges are reused, so the part files still
+/// line up even when the worker count changed between runs.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct ChunkState {
+    url: String,
+    total_size: u64,
+    chunks: Vec<ChunkProgress>,
+}
+
+/// Inclusive byte range of one part file and how much of it is saved
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct ChunkProgress {
+    start: u64,
+    end: u64,
+    downloaded: u64,
+}
+
+impl ChunkState {
+    /// Splits `total_size` bytes into at most `workers` chunks of at least
+    /// [`MIN_CHUNK_SIZE`] bytes.
+    fn new(url: &str, total_size: u64, workers: usize) -> Self {
+        let optimal_workers = std::cmp::min(
+            workers,
+            std::cmp::max(1, (total_size / MIN_CHUNK_SIZE) as usize),
+        );
+        let chunk_size = total_size.div_ceil(optimal_workers as u64);
+        let chunks = (0..optimal_workers as u64)
+            .map(|i| (i * chunk_size, ((i + 1) * chunk_size).min(total_size) - 1))
+            .filter(|(start, end)| start <= end)
+            .map(|(start, end)| ChunkProgress {
+                start,
+                end,
+                downloaded: 0,
+            })
+            .collect();
+        Self {
+            url: url.to_string(),
+            total_size,
+            chunks,
+        }
+    }
+
+    fn path(output: &Path) -> PathBuf {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".dwrs");
+        PathBuf::from(name)
+    }
+
+    async fn load(path: &Path) -> Option<Self> {
+        let content = fs::read(path).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    async fn save(&self, path: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, content).await
+    }
 }
 
 /// Options for downloading a chunk
 struct ChunkOptions {
-    client: Client,
+    transport: Arc<dyn Transport>,
     url: String,
     tmp_path: PathBuf,
     start: u64,
@@ -316,14 +1382,158 @@ struct ChunkOptions {
     resume: bool,
     pb: Arc<ProgressBar>,
     progress: Arc<AtomicU64>,
+    /// Progress of this chunk alone
+    meter: Arc<ChunkMeter>,
     buffer_size: usize,
+    headers: Vec<(String, String)>,
+    /// Shared by all chunks
+    limiter: Arc<RateLimiter>,
+}
+
+/// Live progress of the connections of a parallel download, one per chunk.
+///
+/// Pass it in [`DownloadOptions::connections`] and take
+/// [`snapshots`](Connections::snapshot) while the download runs, e.g. to
+/// spot a stalled connection holding the rest up. Clones share the state.
+#[derive(Debug, Clone, Default)]
+pub struct Connections {
+    meters: Arc<Mutex<Vec<Arc<ChunkMeter>>>>,
+}
+
+/// Progress of one connection of a parallel download
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionStatus {
+    pub index: usize,
+    /// First byte of the chunk
+    pub start: u64,
+    /// Last byte of the chunk, inclusive
+    pub end: u64,
+    /// Bytes of the chunk saved so far, earlier runs included
+    pub downloaded: u64,
+    /// Bytes per second, smoothed
+    pub speed: f64,
+}
+
+impl ConnectionStatus {
+    /// Size of the chunk in bytes
+    pub fn size(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.downloaded >= self.size()
+    }
+}
+
+#[derive(Debug)]
+struct ChunkMeter {
+    start: u64,
+    end: u64,
+    downloaded: AtomicU64,
+    rate: RateEstimator,
+}
+
+impl Connections {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Progress of each connection of the running or last parallel
+    /// download; empty if there was none.
+    pub fn snapshot(&self) -> Vec<ConnectionStatus> {
+        let meters = self.meters.lock().unwrap_or_else(|e| e.into_inner());
+        meters
+            .iter()
+            .enumerate()
+            .map(|(index, meter)| {
+                let downloaded = meter.downloaded.load(Ordering::Relaxed);
+                ConnectionStatus {
+                    index,
+                    start: meter.start,
+                    end: meter.end,
+                    downloaded,
+                    speed: meter.rate.update(downloaded),
+                }
+            })
+            .collect()
+    }
+
+    /// Replaces the connections with one per `(start, end, saved)` chunk.
+    fn start(&self, chunks: impl Iterator<Item = (u64, u64, u64)>) -> Vec<Arc<ChunkMeter>> {
+        let started: Vec<_> = chunks
+            .map(|(start, end, saved)| {
+                Arc::new(ChunkMeter {
+                    start,
+                    end,
+                    downloaded: AtomicU64::new(saved),
+                    rate: RateEstimator::new(),
+                })
+            })
+            .collect();
+        *self.meters.lock().unwrap_or_else(|e| e.into_inner()) = started.clone();
+        started
+    }
+}
+
+/// Aborts the chunk tasks of a parallel download when it is dropped before
+/// they finished, e.g. by a timeout around it.
+struct ChunkTasks(Vec<tokio::task::AbortHandle>);
+
+impl Drop for ChunkTasks {
+    fn drop(&mut self) {
+        self.0.iter().for_each(|task| task.abort());
+    }
+}
+
+/// Logs the connections of a parallel download now and then, shown with
+/// `-v`; stops when dropped.
+struct ConnectionsLogger(tokio::task::JoinHandle<()>);
+
+impl ConnectionsLogger {
+    fn spawn(connections: &Connections, output: &Path) -> Self {
+        let connections = connections.clone();
+        let name = output.display().to_string();
+        Self(tokio::spawn(async move {
+            let mut ticks = tokio::time::interval_at(
+                tokio::time::Instant::now() + CONNECTIONS_LOG_INTERVAL,
+                CONNECTIONS_LOG_INTERVAL,
+            );
+            loop {
+                ticks.tick().await;
+                let line: Vec<String> = connections
+                    .snapshot()
+                    .iter()
+                    .map(|c| {
+                        let percent = c.downloaded.min(c.size()) * 100 / c.size();
+                        if c.is_complete() {
+                            format!("#{} done", c.index)
+                        } else {
+                            format!(
+                                "#{} {}% {}",
+                                c.index,
+                                percent,
+                                crate::units::format_speed(c.speed)
+                            )
+                        }
+                    })
+                    .collect();
+                log::info!("{} connections: {}", name, line.join(", "));
+            }
+        }))
+    }
+}
+
+impl Drop for ConnectionsLogger {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 async fn download_chunk(
     opts: ChunkOptions,
 ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
     let ChunkOptions {
-        client,
+        transport,
         url,
         tmp_path,
         start,
@@ -331,8 +1541,12 @@ async fn download_chunk(
         resume,
         pb,
         progress,
+        meter,
         buffer_size,
+        headers,
+        limiter,
     } = opts;
+    let _active = control::ActiveGuard::new();
 
     let chunk_size = end.saturating_sub(start) + 1;
     let mut current_start = start;
@@ -357,12 +1571,20 @@ async fn download_chunk(
         }
     }
 
-    let request = client
-        .get(&url)
-        .header("Range", format!("bytes={}-{}", current_start, end))
-        .send()
+    let request = request(Method::GET, &url, &headers)?;
+    let request = transport
+        .execute(with_range(request, current_start, Some(end)))
         .await?
         .error_for_status()?;
+    if request.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(format!(
+            "Server answered the range {}-{} with {} instead of 206",
+            current_start,
+            end,
+            request.status()
+        )
+        .into());
+    }
 
     let file = if resume && current_start > start && tmp_path.exists() {
         fs::OpenOptions::new().append(true).open(&tmp_path).await?
@@ -383,22 +1605,32 @@ async fn download_chunk(
 
         let prev = progress.fetch_add(len, Ordering::Relaxed);
         pb.set_position(prev + len);
+        meter.downloaded.fetch_add(len, Ordering::Relaxed);
+        limiter.acquire(len).await;
+        if let Err(e) = control::checkpoint().await {
+            writer.flush().await?;
+            return Err(e.into());
+        }
     }
 
     writer.flush().await?;
     Ok(tmp_path)
 }
 
+/// Concatenates `parts` into `output`, hashing the bytes on the way if
+/// `hash` is set, and returns the digest.
 async fn merge_parts(
     output: &Path,
     parts: &[PathBuf],
     _total_size: u64,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    hash: Option<Algorithm>,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
     let mut final_file = fs::File::create(output).await?;
 
     let _ = final_file.set_len(_total_size).await;
 
     let mut buffer = vec![0u8; DEFAULT_BUFFER_SIZE];
+    let mut hasher = hash.map(Hasher::new);
 
     for (i, part) in parts.iter().enumerate() {
         log::debug!("Merging part {}: {}", i, part.display());
@@ -411,6 +1643,9 @@ async fn merge_parts(
                 break;
             }
             tokio::io::AsyncWriteExt::write_all(&mut final_file, &buffer[..n]).await?;
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&buffer[..n]);
+            }
         }
 
         fs::remove_file(part).await.ok();
@@ -418,14 +1653,12 @@ async fn merge_parts(
 
     final_file.sync_all().await.ok();
     log::info!("Merge complete: {}", output.display());
-    Ok(())
+    Ok(hasher.map(Hasher::finalize))
 }
 
 #[tokio::test]
 async fn test_download_range_no_range() {
     use httpmock::MockServer;
-    use indicatif::ProgressBar;
-    use reqwest::Client;
     use std::path::PathBuf;
     let server = MockServer::start();
     let body = b"hello world";
@@ -434,24 +1667,487 @@ async fn test_download_range_no_range() {
         then.status(200).header("Content-Length", "11").body(body);
     });
 
-    let client = Client::new();
+    let client = reqwest::Client::new();
     let output = PathBuf::from("test_file.txt");
     let pb = ProgressBar::new(11);
 
-    download_optimized(
-        &client,
-        &format!("{}/file.txt", server.url("")),
-        &output,
-        &pb,
-        false,
-        11,
-        DEFAULT_BUFFER_SIZE,
-    )
+    let url = format!("{}/file.txt", server.url(""));
+    let transfer = download_optimized(SequentialOptions {
+        transport: &client,
+        url: &url,
+        output: &output,
+        pb: &pb,
+        resume: false,
+        total_size: 11,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        headers: &[],
+        payload: None,
+        limiter: &RateLimiter::new(None),
+        type_filter: &TypeFilter::default(),
+        hash: Some(Algorithm::Md5),
+        compression: None,
+        pipe: None,
+        tee: false,
+    })
     .await
     .unwrap();
+    assert_eq!(
+        transfer.digest.as_deref(),
+        Some("5eb63bbbe01eeed093cb22bb8f5acdc3")
+    );
 
     let content = tokio::fs::read(&output).await.unwrap();
     assert_eq!(content, body);
     m.assert();
-    tokio::fs::remove_file(output).await.ok();
+    tokio::fs::remove_file(&output).await.ok();
+
+    let text_only = TypeFilter {
+        accept: vec!["text/*".to_string()],
+        reject: Vec::new(),
+    };
+    let err = download_optimized(SequentialOptions {
+        transport: &client,
+        url: &url,
+        output: &output,
+        pb: &pb,
+        resume: false,
+        total_size: 11,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        headers: &[],
+        payload: None,
+        limiter: &RateLimiter::new(None),
+        type_filter: &text_only,
+        hash: None,
+        compression: None,
+        pipe: None,
+        tee: false,
+    })
+    .await
+    .unwrap_err();
+    assert!(err.is::<Rejected>());
+    assert!(!output.exists());
+}
+
+#[tokio::test]
+async fn test_verify_removes_corrupt_output() {
+    let output = std::env::temp_dir().join("dwrs_test_verify.txt");
+    tokio::fs::write(&output, b"hello world").await.unwrap();
+    let good = Checksum::parse("md5:5eb63bbbe01eeed093cb22bb8f5acdc3").unwrap();
+    assert_eq!(
+        verify(&output, Some(&good), None).await.unwrap(),
+        Some(good)
+    );
+    assert!(output.exists());
+
+    let bad = Checksum::parse("md5:9dd4e461268c8034f5c8564e155c67a6").unwrap();
+    let err = verify(&output, Some(&bad), None).await.unwrap_err();
+    assert!(err.is::<checksum::Mismatch>());
+    assert!(!output.exists());
+}
+
+#[tokio::test]
+async fn test_download_to_writer() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method("GET").path("/file.txt");
+        then.status(200)
+            .header("Content-MD5", "XrY7u+Ae7tCTyyK7j1rNww==")
+            .body("hello world");
+    });
+    server.mock(|when, then| {
+        when.method("GET").path("/corrupt.txt");
+        then.status(200)
+            .header("Content-MD5", "ndTkYSaMgDT1yFZOFVxnpg==")
+            .body("hello world");
+    });
+
+    let client = reqwest::Client::new();
+    let pb = ProgressBar::hidden();
+    let type_filter = TypeFilter::default();
+    let limiter = RateLimiter::new(None);
+    let opts = |url| WriterOptions {
+        transport: &client,
+        url,
+        pb: &pb,
+        headers: &[],
+        payload: None,
+        limiter: &limiter,
+        type_filter: &type_filter,
+        decompress: false,
+    };
+
+    let mut body = Vec::new();
+    let url = server.url("/file.txt");
+    assert_eq!(download_to_writer(opts(&url), &mut body).await.unwrap(), 11);
+    assert_eq!(body, b"hello world");
+
+    let mut capped = CappedBuffer::new(5);
+    let err = download_to_writer(opts(&url), &mut capped)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Response is larger than 5 bytes");
+
+    let url = server.url("/corrupt.txt");
+    let err = download_to_writer(opts(&url), &mut Vec::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Checksum mismatch"));
+}
+
+#[tokio::test]
+async fn test_byte_stream() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method("GET").path("/file.txt");
+        then.status(200)
+            .header("Content-Type", "text/plain")
+            .body("hello world");
+    });
+    let opts = |type_filter| StreamOptions {
+        transport: Arc::new(reqwest::Client::new()),
+        url: server.url("/file.txt"),
+        headers: Vec::new(),
+        payload: None,
+        limiter: Arc::new(RateLimiter::new(None)),
+        type_filter,
+        retries: 3,
+        retry_policy: Arc::new(crate::retry::Exponential::default()),
+    };
+
+    let chunks: Vec<_> = byte_stream(opts(TypeFilter::default())).collect().await;
+    let body: Vec<u8> = chunks
+        .into_iter()
+        .flat_map(|chunk| chunk.unwrap())
+        .collect();
+    assert_eq!(body, b"hello world");
+
+    let images = TypeFilter {
+        accept: vec!["image/*".to_string()],
+        reject: Vec::new(),
+    };
+    let chunks: Vec<_> = byte_stream(opts(images)).collect().await;
+    assert_eq!(chunks.len(), 1);
+    assert!(chunks[0].as_ref().unwrap_err().is::<Rejected>());
+}
+
+#[tokio::test]
+async fn test_download_report() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method("HEAD").path("/resume.txt");
+        then.status(200)
+            .header("Content-Length", "11")
+            .header("Accept-Ranges", "bytes");
+    });
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/resume.txt")
+            .header("Range", "bytes=6-");
+        then.status(206)
+            .header("Content-Range", "bytes 6-10/11")
+            .body("world");
+    });
+    let output = std::env::temp_dir().join(format!("dwrs-report-{}", std::process::id()));
+    tokio::fs::write(&output, "hello ").await.unwrap();
+
+    let transport: Arc<dyn Transport> = Arc::new(reqwest::Client::new());
+    let report = download_file(DownloadOptions {
+        transport: &transport,
+        url: &server.url("/resume.txt"),
+        output: &output,
+        pb: &ProgressBar::hidden(),
+        resume: true,
+        workers: 1,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        min_parallel_size: u64::MAX,
+        headers: &[],
+        payload: None,
+        checksum: None,
+        limiter: Arc::new(RateLimiter::new(None)),
+        type_filter: &TypeFilter::default(),
+        provenance: false,
+        decompress: false,
+        pipe: None,
+        tee: false,
+        connections: None,
+    })
+    .await
+    .unwrap();
+    assert_eq!(tokio::fs::read(&output).await.unwrap(), b"hello world");
+    tokio::fs::remove_file(&output).await.ok();
+    assert_eq!((report.bytes, report.resumed_from), (5, Some(6)));
+    assert_eq!(report.attempts, 1);
+    assert_eq!(
+        report.checksum.as_deref(),
+        Some("sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
+    );
+}
+
+#[tokio::test]
+async fn test_resume_checks_response() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    for path in ["/full.txt", "/shifted.txt"] {
+        server.mock(|when, then| {
+            when.method("HEAD").path(path);
+            then.status(200)
+                .header("Content-Length", "11")
+                .header("Accept-Ranges", "bytes");
+        });
+    }
+    server.mock(|when, then| {
+        when.method("GET").path("/full.txt");
+        then.status(200).body("hello world");
+    });
+    server.mock(|when, then| {
+        when.method("GET").path("/shifted.txt");
+        then.status(206)
+            .header("Content-Range", "bytes 0-10/11")
+            .body("hello world");
+    });
+    let output = std::env::temp_dir().join(format!("dwrs-resume-{}", std::process::id()));
+    let transport: Arc<dyn Transport> = Arc::new(reqwest::Client::new());
+    let download = |url: String| {
+        let transport = &transport;
+        let output = &output;
+        async move {
+            download_file(DownloadOptions {
+                transport,
+                url: &url,
+                output,
+                pb: &ProgressBar::hidden(),
+                resume: true,
+                workers: 1,
+                buffer_size: DEFAULT_BUFFER_SIZE,
+                min_parallel_size: u64::MAX,
+                headers: &[],
+                payload: None,
+                checksum: None,
+                limiter: Arc::new(RateLimiter::new(None)),
+                type_filter: &TypeFilter::default(),
+                provenance: false,
+                decompress: false,
+                pipe: None,
+                tee: false,
+                connections: None,
+            })
+            .await
+        }
+    };
+
+    // A server ignoring the range sends the whole file, which replaces the part
+    tokio::fs::write(&output, "jello ").await.unwrap();
+    let report = download(server.url("/full.txt")).await.unwrap();
+    assert_eq!(tokio::fs::read(&output).await.unwrap(), b"hello world");
+    assert_eq!(report.resumed_from, None);
+
+    tokio::fs::write(&output, "hello ").await.unwrap();
+    assert!(download(server.url("/shifted.txt")).await.is_err());
+    assert_eq!(tokio::fs::read(&output).await.unwrap(), b"hello ");
+    tokio::fs::remove_file(&output).await.ok();
+}
+
+#[tokio::test]
+async fn test_payload() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    let head = server.mock(|when, then| {
+        when.method("HEAD").path("/export");
+        then.status(405);
+    });
+    let export = server.mock(|when, then| {
+        when.method("POST")
+            .path("/export")
+            .header("Content-Type", "application/json")
+            .body(r#"{"format":"csv"}"#);
+        then.status(200).body("a,b\n1,2\n");
+    });
+    let output = std::env::temp_dir().join(format!("dwrs-payload-{}", std::process::id()));
+    tokio::fs::write(&output, "a,b\n").await.unwrap();
+
+    let payload = Payload::new(None, Some(r#"{"format":"csv"}"#))
+        .unwrap()
+        .unwrap();
+    let transport: Arc<dyn Transport> = Arc::new(reqwest::Client::new());
+    let pb = ProgressBar::hidden();
+    let report = download_file(DownloadOptions {
+        transport: &transport,
+        url: &server.url("/export"),
+        output: &output,
+        pb: &pb,
+        resume: true,
+        workers: 4,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        min_parallel_size: 0,
+        headers: &[],
+        payload: Some(&payload),
+        checksum: None,
+        limiter: Arc::new(RateLimiter::new(None)),
+        type_filter: &TypeFilter::default(),
+        provenance: false,
+        decompress: false,
+        pipe: None,
+        tee: false,
+        connections: None,
+    })
+    .await
+    .unwrap();
+    head.assert_calls(0);
+    export.assert();
+    // Not resumed from the partial file, as ranges do not apply
+    assert_eq!(tokio::fs::read(&output).await.unwrap(), b"a,b\n1,2\n");
+    assert_eq!((report.bytes, report.resumed_from), (8, None));
+    assert_eq!(pb.length(), Some(8));
+    tokio::fs::remove_file(&output).await.ok();
+
+    // Downloads into memory and streams send the payload as well
+    let downloader = crate::Downloader::builder()
+        .progress(false)
+        .payload(payload)
+        .build()
+        .unwrap();
+    let url = server.url("/export");
+    let body = downloader.download_bytes(&url, 1024).await.unwrap();
+    assert_eq!(body, "a,b\n1,2\n");
+    let chunks: Vec<_> = downloader.stream(&url).collect().await;
+    let streamed: Vec<u8> = chunks.into_iter().flat_map(Result::unwrap).collect();
+    assert_eq!(streamed, b"a,b\n1,2\n");
+    export.assert_calls(3);
+    head.assert_calls(0);
+}
+
+#[tokio::test]
+async fn test_tee() {
+    let mut file = Vec::new();
+    let mut copy = Vec::new();
+    let mut tee = Tee::new(&mut file, &mut copy);
+    tee.write_all(b"hello ").await.unwrap();
+    tee.write_all(b"world").await.unwrap();
+    tee.shutdown().await.unwrap();
+    drop(tee);
+    assert_eq!(file, b"hello world");
+    assert_eq!(copy, file);
+}
+
+#[tokio::test]
+async fn test_connections() {
+    use httpmock::MockServer;
+    let size = 2 * MIN_CHUNK_SIZE;
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method("HEAD").path("/big.bin");
+        then.status(200)
+            .header("Content-Length", size.to_string())
+            .header("Accept-Ranges", "bytes");
+    });
+    for (start, end) in [(0, MIN_CHUNK_SIZE - 1), (MIN_CHUNK_SIZE, size - 1)] {
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/big.bin")
+                .header("Range", format!("bytes={}-{}", start, end));
+            then.status(206).body(vec![b'x'; MIN_CHUNK_SIZE as usize]);
+        });
+    }
+    let output = std::env::temp_dir().join(format!("dwrs-connections-{}", std::process::id()));
+
+    let connections = Connections::new();
+    let transport: Arc<dyn Transport> = Arc::new(reqwest::Client::new());
+    download_file(DownloadOptions {
+        transport: &transport,
+        url: &server.url("/big.bin"),
+        output: &output,
+        pb: &ProgressBar::hidden(),
+        resume: false,
+        workers: 2,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        min_parallel_size: 0,
+        headers: &[],
+        payload: None,
+        checksum: None,
+        limiter: Arc::new(RateLimiter::new(None)),
+        type_filter: &TypeFilter::default(),
+        provenance: false,
+        decompress: false,
+        pipe: None,
+        tee: false,
+        connections: Some(&connections),
+    })
+    .await
+    .unwrap();
+    assert_eq!(tokio::fs::metadata(&output).await.unwrap().len(), size);
+    tokio::fs::remove_file(&output).await.ok();
+
+    let snapshot = connections.snapshot();
+    let ranges: Vec<_> = snapshot.iter().map(|c| (c.index, c.start, c.end)).collect();
+    assert_eq!(
+        ranges,
+        [(0, 0, MIN_CHUNK_SIZE - 1), (1, MIN_CHUNK_SIZE, size - 1)]
+    );
+    assert!(snapshot.iter().all(ConnectionStatus::is_complete));
+}
+
+#[test]
+fn test_chunk_layout() {
+    let state = ChunkState::new("https://example.com/a", 10 * MIN_CHUNK_SIZE + 1, 4);
+    let ranges: Vec<_> = state.chunks.iter().map(|c| (c.start, c.end)).collect();
+    let size = (10 * MIN_CHUNK_SIZE + 1).div_ceil(4);
+    assert_eq!(ranges.len(), 4);
+    assert_eq!(ranges[0], (0, size - 1));
+    assert_eq!(ranges[3], (3 * size, 10 * MIN_CHUNK_SIZE));
+
+    let small = ChunkState::new("https://example.com/b", MIN_CHUNK_SIZE, 8);
+    assert_eq!(small.chunks.len(), 1);
+    assert_eq!(
+        ChunkState::path(Path::new("dir/a.iso")),
+        Path::new("dir/a.iso.dwrs")
+    );
+}
+
+#[tokio::test]
+async fn test_error_page_is_not_retried() {
+    use httpmock::MockServer;
+    let server = MockServer::start();
+    let page = server.mock(|when, then| {
+        when.method("GET").path("/release.zip");
+        then.status(200)
+            .header("Content-Type", "text/html")
+            .body("<!DOCTYPE html><html><title>Not Found</title></html>");
+    });
+    let downloader = crate::Downloader::builder()
+        .progress(false)
+        .retries(3)
+        .retry_policy(crate::retry::Fixed(Duration::ZERO))
+        .build()
+        .unwrap();
+    let output = std::env::temp_dir().join(format!("dwrs-page-{}.zip", std::process::id()));
+    let err = downloader
+        .download_file(&server.url("/release.zip"), output.clone())
+        .await
+        .unwrap_err();
+    assert!(err.is::<ErrorPage>());
+    assert!(!output.exists());
+    page.assert_calls(1);
+}
+
+#[test]
+fn test_error_page_detection() {
+    let page = b"\xef\xbb\xbf\n<!DOCTYPE html><html><title>404 Not Found</title>";
+    let zip = Path::new("release.zip");
+    assert!(is_error_page(zip, None, 512, page));
+    assert!(is_error_page(
+        zip,
+        Some("text/html; charset=utf-8"),
+        10 * ERROR_PAGE_MAX_SIZE,
+        page
+    ));
+    assert!(!is_error_page(zip, None, 10 * ERROR_PAGE_MAX_SIZE, page));
+    assert!(!is_error_page(zip, Some("text/html"), 512, b"PK\x03\x04"));
+    assert!(!is_error_page(Path::new("index.HTML"), None, 512, page));
+    assert!(!is_error_page(Path::new("download"), None, 512, page));
+    assert!(!is_error_page(Path::new("feed.xml"), None, 512, page));
+    assert!(!is_error_page(Path::new("logo.SVG"), None, 512, page));
+    assert!(sniff_html(b"<!-- mirror --><HTML>"));
 }
@@ -0,0 +1,157 @@
+//! Email about finished batches over SMTP, for headless servers where
+//! desktop notifications reach nobody. Needs the `email` feature and an
+//! `[email]` section in the config, see [`EmailSettings`].
+//!
+//! Messages go out through [`lettre`], which checks the capabilities the
+//! server announces; passwords are only sent over TLS or to a relay on
+//! the same machine, see [`EmailSettings::check`].
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+use crate::config::{EmailSettings, SmtpSecurity};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Time a whole delivery may take, from connecting to QUIT
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sends mail through the server of an `[email]` section
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    settings: EmailSettings,
+}
+
+impl Mailer {
+    /// Checks the settings: a server, a sender, at least one recipient and
+    /// no password over an unencrypted connection to another machine.
+    pub fn new(settings: EmailSettings) -> Result<Self, String> {
+        settings.check()?;
+        Ok(Self { settings })
+    }
+
+    /// Sends a plain text message to every recipient.
+    pub async fn send(&self, subject: &str, body: &str) -> Result<(), Error> {
+        let message = self.message(subject, body)?;
+        let transport = self.transport()?;
+        tokio::time::timeout(TIMEOUT, transport.send(message))
+            .await
+            .map_err(|_| format!("{} did not answer in time", self.settings.server))??;
+        Ok(())
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, Error> {
+        let settings = &self.settings;
+        let tls = match settings.security {
+            SmtpSecurity::Tls => Tls::Wrapper(TlsParameters::new(settings.server.clone())?),
+            SmtpSecurity::StartTls => Tls::Required(TlsParameters::new(settings.server.clone())?),
+            SmtpSecurity::Plain => Tls::None,
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.server)
+            .port(settings.port())
+            .tls(tls)
+            .timeout(Some(TIMEOUT));
+        if let Some(username) = &settings.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                settings.password.clone().unwrap_or_default(),
+            ));
+        }
+        Ok(builder.build())
+    }
+
+    fn message(&self, subject: &str, body: &str) -> Result<Message, Error> {
+        let settings = &self.settings;
+        let mut builder = Message::builder()
+            .from(settings.from.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &settings.to {
+            builder = builder.to(to.parse()?);
+        }
+        Ok(builder.body(body.to_string())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut smtp = BufReader::new(socket);
+            let mut received = Vec::new();
+            smtp.get_mut()
+                .write_all(b"220 test ready\r\n")
+                .await
+                .unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if smtp.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        received.push(line);
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-test\r\n250 AUTH PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 ok\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    smtp.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    received.push(line);
+                    b"250 ok\r\n"
+                };
+                smtp.get_mut().write_all(reply).await.unwrap();
+            }
+            received
+        });
+
+        let settings = EmailSettings {
+            server: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::Plain,
+            username: Some("me".to_string()),
+            password: Some("secret".to_string()),
+            from: "dwrs@example.com".to_string(),
+            to: vec!["me@example.com".to_string()],
+        };
+        let mailer = Mailer::new(settings.clone()).unwrap();
+        mailer
+            .send("dwrs: 2 downloads finished", "a.iso  ok\n")
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(received[0], "MAIL FROM:<dwrs@example.com>");
+        assert_eq!(received[1], "RCPT TO:<me@example.com>");
+        assert!(received.contains(&"Subject: dwrs: 2 downloads finished".to_string()));
+        assert!(received.contains(&"a.iso  ok".to_string()));
+
+        let remote = EmailSettings {
+            server: "mail.example.com".to_string(),
+            ..settings
+        };
+        assert!(Mailer::new(remote).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Progress of all downloads as one stream of events, see
+//! [`Downloader::progress_events`](crate::Downloader::progress_events).
+
+use futures::Stream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::download::DownloadReport;
+use crate::progress::{ProgressBar, RateEstimator};
+use crate::retry::AttemptError;
+
+/// How often the progress of a running download is sampled
+const TICK: Duration = Duration::from_millis(200);
+
+/// Events kept for subscribers that fall behind; older ones are dropped
+const CAPACITY: usize = 1024;
+
+/// Something that happened to one of the downloads of a
+/// [`Downloader`](crate::Downloader).
+///
+/// `id` tells the downloads apart; it is given out when a download starts
+/// and is unrelated to the ids of a [`DownloadQueue`](crate::queue::DownloadQueue).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    Started {
+        id: u64,
+        url: String,
+        output: PathBuf,
+    },
+    /// Sent whenever the position moved, at most every 200ms
+    Progress {
+        id: u64,
+        downloaded: u64,
+        total: Option<u64>,
+        /// Bytes per second, smoothed
+        speed: f64,
+    },
+    Finished {
+        id: u64,
+        report: DownloadReport,
+    },
+    /// The response was turned down by the type filter; nothing was saved
+    Skipped {
+        id: u64,
+        reason: String,
+    },
+    Failed {
+        id: u64,
+        error: String,
+    },
+    /// The download was aborted before it could finish, e.g. by
+    /// `--fail-fast` or by cancelling it in a queue
+    Cancelled {
+        id: u64,
+    },
+}
+
+/// Hands out the events of one downloader to its subscribers
+pub(crate) struct Events {
+    sender: broadcast::Sender<ProgressEvent>,
+    next_id: AtomicU64,
+}
+
+impl Events {
+    pub(crate) fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Events from now on; a subscriber too slow to keep up misses some
+    /// `Progress` events rather than holding up the downloads.
+    pub(crate) fn subscribe(&self) -> impl Stream<Item = ProgressEvent> + use<> {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::debug!("Progress subscriber missed {} events", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Announces a download and starts sampling its progress.
+    pub(crate) fn track(&self, url: &str, output: &std::path::Path) -> Tracker {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.sender
+            .send(ProgressEvent::Started {
+                id,
+                url: url.to_string(),
+                output: output.to_path_buf(),
+            })
+            .ok();
+        let watched = Arc::new(Mutex::new(None::<ProgressBar>));
+        let rate = Arc::new(RateEstimator::new());
+        // Nobody would see the samples; later subscribers get the other events
+        let ticker = (self.sender.receiver_count() > 0).then(|| {
+            let sender = self.sender.clone();
+            let watched = watched.clone();
+            let rate = rate.clone();
+            tokio::spawn(async move {
+                let mut last = None;
+                let mut interval = tokio::time::interval(TICK);
+                loop {
+                    interval.tick().await;
+                    let pb = watched.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                    if let Some(pb) = pb
+                        && last != Some(pb.position())
+                    {
+                        last = Some(pb.position());
+                        sender.send(progress(id, &pb, &rate)).ok();
+                    }
+                }
+            })
+        });
+        Tracker {
+            id,
+            sender: self.sender.clone(),
+            watched,
+            rate,
+            ticker,
+            finished: false,
+        }
+    }
+}
+
+fn progress(id: u64, pb: &ProgressBar, rate: &RateEstimator) -> ProgressEvent {
+    ProgressEvent::Progress {
+        id,
+        downloaded: pb.position(),
+        total: pb.length().filter(|&len| len > 0),
+        speed: rate.update(pb.position()),
+    }
+}
+
+/// Sends the events of one download until it is finished or dropped
+pub(crate) struct Tracker {
+    id: u64,
+    sender: broadcast::Sender<ProgressEvent>,
+    watched: Arc<Mutex<Option<ProgressBar>>>,
+    rate: Arc<RateEstimator>,
+    /// Samples the watched bar, if anyone listened when the download started
+    ticker: Option<JoinHandle<()>>,
+    /// Whether the end was reported; a tracker dropped before that sends
+    /// [`ProgressEvent::Cancelled`]
+    finished: bool,
+}
+
+impl Tracker {
+    /// Samples `pb` from now on, e.g. the bar of a new attempt.
+    pub(crate) fn watch(&self, pb: &ProgressBar) {
+        *self.watched.lock().unwrap_or_else(|e| e.into_inner()) = Some(pb.clone());
+    }
+
+    /// Sends the last position and how the download ended.
+    pub(crate) fn finish(mut self, result: Result<&DownloadReport, &AttemptError>) {
+        self.finished = true;
+        let pb = self
+            .watched
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(pb) = pb {
+            self.sender.send(progress(self.id, &pb, &self.rate)).ok();
+        }
+        let id = self.id;
+        let event = match result {
+            Ok(report) => ProgressEvent::Finished {
+                id,
+                report: report.clone(),
+            },
+            Err(e) if e.is::<crate::download::Rejected>() => ProgressEvent::Skipped {
+                id,
+                reason: e.to_string(),
+            },
+            Err(e) => ProgressEvent::Failed {
+                id,
+                error: e.to_string(),
+            },
+        };
+        self.sender.send(event).ok();
+    }
+
+    /// Reports a failure that came after the transfer or kept it from
+    /// starting.
+    pub(crate) fn fail(self, error: &str) {
+        self.finish(Err(&*Box::<AttemptError>::from(error)));
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if let Some(ticker) = &self.ticker {
+            ticker.abort();
+        }
+        if !self.finished {
+            self.sender
+                .send(ProgressEvent::Cancelled { id: self.id })
+                .ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use httpmock::MockServer;
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/file.txt");
+            then.status(200)
+                .header("Content-Length", "11")
+                .body("hello world");
+        });
+        let downloader = crate::Downloader::builder()
+            .progress(false)
+            .build()
+            .unwrap();
+        let events = downloader.progress_events();
+        let path = std::env::temp_dir().join(format!("dwrs-events-{}", std::process::id()));
+        let url = server.url("/file.txt");
+        downloader.download_file(&url, path.clone()).await.unwrap();
+        std::fs::remove_file(&path).ok();
+        drop(downloader);
+
+        let events: Vec<ProgressEvent> = events.collect().await;
+        assert_eq!(
+            events.first(),
+            Some(&ProgressEvent::Started {
+                id: 1,
+                url,
+                output: path
+            })
+        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ProgressEvent::Progress {
+                id: 1,
+                downloaded: 11,
+                total: Some(11),
+                ..
+            }
+        )));
+        assert!(matches!(
+            events.last(),
+            Some(ProgressEvent::Finished { id: 1, report }) if report.bytes == 11
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_tracker_is_cancelled() {
+        let events = Events::new();
+        let idle = events.track("https://example.com/a", std::path::Path::new("a"));
+        assert!(idle.ticker.is_none());
+        drop(idle);
+
+        let mut stream = Box::pin(events.subscribe());
+        let tracker = events.track("https://example.com/b", std::path::Path::new("b"));
+        assert!(tracker.ticker.is_some());
+        drop(tracker);
+        drop(events);
+        let events: Vec<ProgressEvent> = stream.by_ref().collect().await;
+        assert!(matches!(events[0], ProgressEvent::Started { id: 2, .. }));
+        assert_eq!(events[1], ProgressEvent::Cancelled { id: 2 });
+    }
+}
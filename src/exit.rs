@@ -0,0 +1,79 @@
+/// Exit codes of the `dwrs` binary.
+///
+/// | Code | Meaning                                             |
+/// |------|-----------------------------------------------------|
+/// | 0    | Every download succeeded                            |
+/// | 1    | Every download failed, or another runtime error     |
+/// | 2    | Bad command line arguments or unreadable input list |
+/// | 3    | Invalid configuration file or `DWRS_*` variable     |
+/// | 4    | Some downloads succeeded, some failed               |
+/// | 130  | Interrupted with Ctrl-C, partial files kept         |
+/// | 143  | Stopped with SIGTERM, partial files kept            |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    Failure = 1,
+    Usage = 2,
+    Config = 3,
+    PartialFailure = 4,
+    Interrupted = 130,
+    Terminated = 143,
+}
+
+/// The table above in the form shown at the end of `dwrs --help`
+pub const HELP: &str = "Exit status:
+  0    all downloads succeeded
+  1    all downloads failed, or another error
+  2    bad arguments or unreadable input list
+  3    invalid configuration
+  4    some downloads failed
+  130  interrupted (resume with --continue)
+  143  terminated (resume with --continue)";
+
+impl ExitStatus {
+    /// Status for a batch where `failed` of `total` downloads failed.
+    pub fn from_batch(failed: usize, total: usize) -> Self {
+        match failed {
+            0 => ExitStatus::Success,
+            n if n >= total => ExitStatus::Failure,
+            _ => ExitStatus::PartialFailure,
+        }
+    }
+
+    /// Folds the statuses of several batches into one.
+    ///
+    /// Equal statuses stay as they are; a mix means some work failed.
+    pub fn combine(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            ExitStatus::PartialFailure
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Terminates the process with this status.
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_batch() {
+        assert_eq!(ExitStatus::from_batch(0, 3), ExitStatus::Success);
+        assert_eq!(ExitStatus::from_batch(1, 3), ExitStatus::PartialFailure);
+        assert_eq!(ExitStatus::from_batch(3, 3), ExitStatus::Failure);
+        assert_eq!(
+            ExitStatus::Success.combine(ExitStatus::Failure),
+            ExitStatus::PartialFailure
+        );
+        assert_eq!(ExitStatus::Interrupted.code(), 130);
+    }
+}
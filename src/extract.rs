@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Archive formats `--extract` unpacks, recognized by file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveKind {
+    /// Format of the archive at `path`, `None` if it is not one.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        [
+            (".zip", ArchiveKind::Zip),
+            (".tar", ArchiveKind::Tar),
+            (".tar.gz", ArchiveKind::TarGz),
+            (".tgz", ArchiveKind::TarGz),
+            (".tar.zst", ArchiveKind::TarZst),
+            (".tzst", ArchiveKind::TarZst),
+        ]
+        .into_iter()
+        .find(|(ext, _)| name.len() > ext.len() && name.ends_with(ext))
+        .map(|(_, kind)| kind)
+    }
+}
+
+/// Unpacks `archive` into `dest`, returning the number of files written.
+///
+/// Entries that would end up outside of `dest`, such as `../x` or
+/// absolute paths, are skipped with a warning.
+pub fn extract(archive: &Path, kind: ArchiveKind, dest: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dest)?;
+    let file = BufReader::new(File::open(archive)?);
+    match kind {
+        ArchiveKind::Zip => extract_zip(file, dest),
+        ArchiveKind::Tar => extract_tar(file, dest),
+        ArchiveKind::TarGz => extract_tar(flate2::read::GzDecoder::new(file), dest),
+        ArchiveKind::TarZst => extract_tar(zstd::stream::read::Decoder::new(file)?, dest),
+    }
+}
+
+fn extract_tar(reader: impl Read, dest: &Path) -> io::Result<usize> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !entry.unpack_in(dest)? {
+            log::warn!(
+                "Skipping {}: outside of the output directory",
+                path.display()
+            );
+        } else if entry.header().entry_type().is_file() {
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+fn extract_zip(reader: impl Read + io::Seek, dest: &Path) -> io::Result<usize> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
+    let mut files = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+        let Some(name) = entry.enclosed_name() else {
+            log::warn!("Skipping {}: outside of the output directory", entry.name());
+            continue;
+        };
+        let path: PathBuf = dest.join(name);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&path)?)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        files += 1;
+    }
+    Ok(files)
+}
+
+/// Unpacks a finished download next to itself if it is an archive,
+/// removing it afterwards when `delete` is set.
+///
+/// Returns the number of files extracted, `None` for other files.
+pub async fn extract_download(path: &Path, delete: bool) -> io::Result<Option<usize>> {
+    let Some(kind) = ArchiveKind::detect(path) else {
+        return Ok(None);
+    };
+    let archive = path.to_path_buf();
+    let dest = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    log::info!("Extracting {} into {}", archive.display(), dest.display());
+    let files = tokio::task::spawn_blocking(move || extract(&archive, kind, &dest))
+        .await
+        .map_err(io::Error::other)??;
+    if delete {
+        tokio::fs::remove_file(path).await?;
+    }
+    Ok(Some(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_extract_archives() {
+        assert_eq!(
+            ArchiveKind::detect(Path::new("dl/App-1.0.TAR.GZ")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::detect(Path::new("a.tzst")),
+            Some(ArchiveKind::TarZst)
+        );
+        assert_eq!(ArchiveKind::detect(Path::new("notes.txt")), None);
+        assert_eq!(ArchiveKind::detect(Path::new(".zip")), None);
+
+        let dir = std::env::temp_dir().join(format!("dwrs-extract-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let tgz = dir.join("a.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tgz).unwrap(),
+            flate2::Compression::fast(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "pkg/readme.txt", &b"hello"[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let zip_path = dir.join("b.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("docs/guide.md", options).unwrap();
+        zip.write_all(b"# guide").unwrap();
+        zip.start_file("../escape.txt", options).unwrap();
+        zip.write_all(b"nope").unwrap();
+        zip.finish().unwrap();
+
+        let out = dir.join("out");
+        assert_eq!(extract(&tgz, ArchiveKind::TarGz, &out).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(out.join("pkg/readme.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(extract(&zip_path, ArchiveKind::Zip, &out).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(out.join("docs/guide.md")).unwrap(),
+            "# guide"
+        );
+        assert!(!dir.join("escape.txt").exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
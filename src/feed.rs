@@ -0,0 +1,237 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::file_parser::{CollisionPolicy, DownloadEntry, resolve_collisions};
+use crate::utils::unescape_xml;
+
+static ITEM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<(item|entry)\b[^>]*>(.*?)</(?:item|entry)>").expect("valid item regex")
+});
+static TITLE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<title\b[^>]*>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</title>")
+        .expect("valid title regex")
+});
+static ENCLOSURE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<(?:enclosure|media:content|link)\b[^>]*>"#).expect("valid enclosure regex")
+});
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid attribute regex")
+});
+
+/// A downloadable feed entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    pub title: String,
+    pub url: String,
+}
+
+/// Options for feed (podcatcher) downloads
+#[derive(Debug, Clone)]
+pub struct FeedOptions {
+    /// Directory where enclosures are saved
+    pub output_dir: PathBuf,
+    /// File tracking already downloaded enclosures, `None` for the default location
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("."),
+            state_file: None,
+        }
+    }
+}
+
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    ATTR_RE
+        .captures_iter(tag)
+        .map(|c| {
+            let value = c.get(2).or_else(|| c.get(3)).map_or("", |m| m.as_str());
+            (c[1].to_ascii_lowercase(), unescape_xml(value))
+        })
+        .collect()
+}
+
+fn enclosure_url(item: &str) -> Option<String> {
+    ENCLOSURE_RE.find_iter(item).find_map(|m| {
+        let tag = m.as_str();
+        let attrs = attributes(tag);
+        let get = |name: &str| {
+            attrs
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+        };
+
+        if tag[1..].to_ascii_lowercase().starts_with("link") {
+            (get("rel").as_deref() == Some("enclosure"))
+                .then(|| get("href"))
+                .flatten()
+        } else {
+            get("url")
+        }
+    })
+}
+
+/// Parses an RSS or Atom document and returns the items carrying an enclosure.
+pub fn parse_feed(xml: &str) -> Vec<FeedItem> {
+    ITEM_RE
+        .captures_iter(xml)
+        .filter_map(|c| {
+            let body = &c[2];
+            let url = enclosure_url(body)?;
+            let title = TITLE_RE
+                .captures(body)
+                .map(|t| unescape_xml(t[1].trim()))
+                .unwrap_or_default();
+            Some(FeedItem { title, url })
+        })
+        .collect()
+}
+
+/// Builds an output file name from the item title and the enclosure extension.
+pub fn output_name(item: &FeedItem) -> String {
+    let path = item.url.split(['?', '#']).next().unwrap_or("");
+    let remote = path.rsplit('/').next().unwrap_or("");
+    let ext = remote.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+
+    let title: String = item
+        .title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(150)
+        .collect();
+    let title = title.trim().trim_matches('.');
+
+    let name = match (title.is_empty(), ext.is_empty()) {
+        (true, _) if !remote.is_empty() => remote.to_string(),
+        (true, _) => "episode.bin".to_string(),
+        (false, true) => title.to_string(),
+        (false, false) => format!("{}.{}", title, ext),
+    };
+    crate::sanitize::sanitize(&name)
+}
+
+/// Download entries for `items` under `output_dir`, named by [`output_name`].
+///
+/// Items sharing a title are saved as `title (1).ext`, `title (2).ext`, ...
+/// instead of overwriting each other.
+pub fn entries(items: &[FeedItem], output_dir: &Path) -> Vec<DownloadEntry> {
+    let entries = items
+        .iter()
+        .map(|item| DownloadEntry::new(item.url.as_str(), output_dir.join(output_name(item))))
+        .collect();
+    resolve_collisions(entries, CollisionPolicy::Rename).expect("renaming never fails")
+}
+
+/// Set of enclosure URLs that were already downloaded, persisted one per line.
+pub struct FeedState {
+    path: PathBuf,
+    seen: HashSet<String>,
+}
+
+impl FeedState {
+    /// Default state file: `<data dir>/dwrs/feeds.state`
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("dwrs")
+            .join("feeds.state")
+    }
+
+    pub async fn load(path: &Path) -> Self {
+        let seen = tokio::fs::read_to_string(path)
+            .await
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            seen,
+        }
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.seen.contains(url)
+    }
+
+    pub fn insert(&mut self, url: String) {
+        self.seen.insert(url);
+    }
+
+    pub async fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut lines: Vec<&str> = self.seen.iter().map(String::as_str).collect();
+        lines.sort_unstable();
+        tokio::fs::write(&self.path, lines.join("\n") + "\n").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss_and_atom() {
+        let rss = r#"<rss><channel><title>Show</title>
+            <item><title><![CDATA[Ep 1: Intro]]></title>
+              <enclosure length="1" type="audio/mpeg" url="https://cdn.example.com/ep1.mp3?x=1&amp;y=2"/></item>
+            <item><title>No media</title></item>
+            </channel></rss>"#;
+        let items = parse_feed(rss);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].url, "https://cdn.example.com/ep1.mp3?x=1&y=2");
+        assert_eq!(output_name(&items[0]), "Ep 1_ Intro.mp3");
+
+        let atom = r#"<feed><entry><title>Talk</title>
+            <link rel="alternate" href="https://example.com/talk"/>
+            <link href="https://example.com/talk.ogg" rel="enclosure"/></entry></feed>"#;
+        assert_eq!(
+            parse_feed(atom),
+            vec![FeedItem {
+                title: "Talk".to_string(),
+                url: "https://example.com/talk.ogg".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_entries_with_same_title() {
+        let item = |url: &str| FeedItem {
+            title: "Weekly update".to_string(),
+            url: url.to_string(),
+        };
+        let items = [
+            item("https://cdn.example.com/1.mp3"),
+            item("https://cdn.example.com/2.mp3"),
+            item("https://cdn.example.com/3.ogg"),
+        ];
+        let outputs: Vec<PathBuf> = entries(&items, Path::new("pods"))
+            .into_iter()
+            .map(|entry| entry.output)
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                PathBuf::from("pods/Weekly update.mp3"),
+                PathBuf::from("pods/Weekly update (1).mp3"),
+                PathBuf::from("pods/Weekly update.ogg"),
+            ]
+        );
+    }
+}
@@ -1,12 +1,556 @@
-use colored::Colorize;
-use std::path::PathBuf;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+
+use crate::checksum::Checksum;
+use crate::output_template::OutputTemplate;
+use crate::sanitize::sanitize;
+use crate::utils::apply_color;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
 
 const FILE_BUFFER_SIZE: usize = 1024 * 1024;
+/// Headers [`write_manifest`] leaves out, the manifest being plain text
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// Default failed-downloads file: `<data dir>/dwrs/failed.json`
+pub fn default_failed_file() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("dwrs")
+        .join("failed.json")
+}
+
+/// A single download described by a manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadEntry {
+    pub url: String,
+    pub output: PathBuf,
+    /// Expected checksum verified after the download
+    pub checksum: Option<Checksum>,
+    /// Extra request headers sent with every request for this entry
+    pub headers: Vec<(String, String)>,
+}
+
+impl DownloadEntry {
+    pub fn new(url: impl Into<String>, output: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.into(),
+            output: output.into(),
+            checksum: None,
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// Manifest file formats understood by [`parse_manifest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestFormat {
+    /// One `URL [output]` pair per line
+    Text,
+    /// Array of `{url, output, checksum, headers}` objects
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl ManifestFormat {
+    /// Detects the format from the file extension, defaulting to text.
+    pub fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ManifestFormat::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ManifestFormat::Csv,
+            _ => ManifestFormat::Text,
+        }
+    }
+}
+
+/// Header names of the CSV columns holding each field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvColumns {
+    pub url: String,
+    pub output: String,
+    pub sha256: String,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        Self {
+            url: "url".to_string(),
+            output: "output".to_string(),
+            sha256: "sha256".to_string(),
+        }
+    }
+}
+
+impl CsvColumns {
+    /// Parses a mapping like `url=Link,output=File Name,sha256=Hash`;
+    /// fields that are not mentioned keep their default column name.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut columns = Self::default();
+        for pair in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let (field, column) = pair.split_once('=').ok_or_else(|| {
+                format!("Invalid column mapping '{}', expected field=column", pair)
+            })?;
+            let column = column.trim().to_string();
+            match field.trim() {
+                "url" => columns.url = column,
+                "output" => columns.output = column,
+                "sha256" => columns.sha256 = column,
+                other => return Err(format!("Unknown CSV field '{}'", other)),
+            }
+        }
+        Ok(columns)
+    }
+}
+
+/// Options controlling how manifests are parsed
+#[derive(Debug, Clone, Default)]
+pub struct ManifestOptions {
+    /// Force a format instead of detecting it from the extension
+    pub format: Option<ManifestFormat>,
+    /// Column mapping for CSV manifests
+    pub csv_columns: CsvColumns,
+    /// Base URL that relative entries are joined against
+    pub base: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct JsonEntry {
+    url: String,
+    #[serde(default)]
+    output: Option<String>,
+    #[serde(default)]
+    checksum: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+/// Resolves an entry URL, joining relative paths against `base`.
+///
+/// Absolute `http(s)` URLs and those of the built-in
+/// [resolvers](crate::resolver::SCHEMES), such as `s3://`, are taken as
+/// they are; anything else is only
+/// accepted when a base URL is available. The result is normalized the way
+/// the `url` crate does it: internationalized host names are converted to
+/// punycode and characters that must be percent-encoded are.
+pub fn resolve_url(url: &str, base: Option<&Url>) -> Option<String> {
+    if crate::resolver::has_builtin_scheme(url) {
+        return Url::parse(url).ok().map(|parsed| parsed.to_string());
+    }
+    let absolute = ["http://", "https://"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(scheme))
+    });
+    let mut parsed = if absolute {
+        Url::parse(url).ok()?
+    } else {
+        base?.join(url).ok()?
+    };
+    parsed.set_fragment(None);
+    matches!(parsed.scheme(), "http" | "https").then(|| parsed.to_string())
+}
+
+/// Normalizes an absolute URL: lowercase scheme and host, no default port,
+/// no `.`/`..` segments and no fragment. Returns `None` for invalid URLs.
+pub fn normalize_url(url: &str) -> Option<String> {
+    let mut parsed = Url::parse(url).ok()?;
+    parsed.set_fragment(None);
+    Some(parsed.to_string())
+}
+
+/// Drops entries of a batch whose URL was already queued, comparing
+/// [normalized](normalize_url) URLs.
+#[derive(Debug, Default)]
+pub struct Dedup {
+    /// Output of the first entry seen for each URL
+    seen: HashMap<String, PathBuf>,
+}
+
+impl Dedup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Normalizes the URL of `entry` and returns the entry, or `None` with a
+    /// warning if the same URL came before.
+    pub fn check(&mut self, mut entry: DownloadEntry) -> Option<DownloadEntry> {
+        if let Some(url) = normalize_url(&entry.url) {
+            entry.url = url;
+        }
+        if let Some(first) = self.seen.get(&entry.url) {
+            log::warn!(
+                "Skipping duplicate URL {} (already downloading to {})",
+                entry.url,
+                first.display()
+            );
+            return None;
+        }
+        self.seen.insert(entry.url.clone(), entry.output.clone());
+        Some(entry)
+    }
+}
+
+/// What happens when several entries of a batch have the same output path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollisionPolicy {
+    /// Refuse to start the batch
+    Error,
+    /// Save later entries as `name (1).ext`, `name (2).ext`, ...
+    #[default]
+    Rename,
+    /// Download them one after another into the same file
+    Serialize,
+}
+
+/// `path` without `.` components, so `./a.iso` and `a.iso` compare equal.
+pub(crate) fn path_key(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+/// `path` with ` (n)` added to its file stem.
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, n, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Finds entries sharing an output path and handles them per `policy`.
+///
+/// With [`CollisionPolicy::Error`] the error lists every shared path with
+/// its URLs. Renamed outputs never take a path another entry uses.
+pub fn resolve_collisions(
+    mut entries: Vec<DownloadEntry>,
+    policy: CollisionPolicy,
+) -> Result<Vec<DownloadEntry>, String> {
+    let mut users: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        users.entry(path_key(&entry.output)).or_default().push(i);
+    }
+    users.retain(|_, indices| indices.len() > 1);
+    if users.is_empty() {
+        return Ok(entries);
+    }
+
+    match policy {
+        CollisionPolicy::Error => {
+            let lines: Vec<String> = users
+                .iter()
+                .map(|(path, indices)| {
+                    let urls: Vec<&str> =
+                        indices.iter().map(|&i| entries[i].url.as_str()).collect();
+                    format!("{}: {}", path.display(), urls.join(", "))
+                })
+                .collect();
+            Err(format!(
+                "{} output path(s) used by more than one download:\n{}",
+                lines.len(),
+                lines.join("\n")
+            ))
+        }
+        CollisionPolicy::Rename => {
+            let mut taken: HashSet<PathBuf> = entries.iter().map(|e| path_key(&e.output)).collect();
+            for indices in users.values() {
+                for &i in &indices[1..] {
+                    let output = &entries[i].output;
+                    let renamed = (1..)
+                        .map(|n| numbered(output, n))
+                        .find(|path| !taken.contains(&path_key(path)))
+                        .expect("unbounded range");
+                    log::warn!(
+                        "{} is also the output of another download, saving {} as {}",
+                        output.display(),
+                        entries[i].url,
+                        renamed.display()
+                    );
+                    taken.insert(path_key(&renamed));
+                    entries[i].output = renamed;
+                }
+            }
+            Ok(entries)
+        }
+        CollisionPolicy::Serialize => {
+            for path in users.keys() {
+                log::info!("Downloads to {} will run one after another", path.display());
+            }
+            Ok(entries)
+        }
+    }
+}
+
+/// Output name for `url` when none is given: its last path segment without
+/// the query, percent-decoded and [sanitized](crate::sanitize::sanitize),
+/// or `file.bin`.
+///
+/// With an [`OutputTemplate`](crate::output_template::OutputTemplate)
+/// applied, the rendered template is returned instead.
+pub fn filename_from_url(url: &str) -> String {
+    if let Some(template) = OutputTemplate::current()
+        && let Ok(parsed) = Url::parse(url)
+    {
+        return template.render(&parsed).to_string_lossy().into_owned();
+    }
+    let segment = match Url::parse(url) {
+        Ok(parsed) => parsed
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned()),
+        Err(_) => url.rsplit('/').next().map(str::to_string),
+    };
+    segment
+        .filter(|s| !s.is_empty())
+        .map_or_else(|| "file.bin".to_string(), |s| sanitize(&s))
+}
+
+/// Parses a manifest in the configured format, or the one detected from its extension.
+pub async fn parse_manifest(
+    path: &PathBuf,
+    opts: &ManifestOptions,
+) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    let read = || async {
+        tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Cannot open file {}: {}", path.display(), e))
+    };
+
+    match opts.format.unwrap_or_else(|| ManifestFormat::detect(path)) {
+        ManifestFormat::Text => Ok(parse_text(path, opts.base.as_ref())
+            .await?
+            .into_iter()
+            .map(|(url, output)| DownloadEntry::new(url, output))
+            .collect()),
+        ManifestFormat::Json => parse_json(&read().await?, opts.base.as_ref()),
+        ManifestFormat::Csv => parse_csv(&read().await?, &opts.csv_columns, opts.base.as_ref()),
+    }
+}
+
+/// Parses a CSV manifest with a header row, locating fields through `columns`.
+///
+/// Only the URL column is required; a missing output column derives the name
+/// from the URL and a missing checksum column disables verification.
+pub fn parse_csv(
+    content: &str,
+    columns: &CsvColumns,
+    base: Option<&Url>,
+) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .from_reader(content.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV manifest: {}", e))?
+        .clone();
+    let find = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+
+    let url_idx = find(&columns.url)
+        .ok_or_else(|| format!("CSV manifest has no '{}' column", columns.url))?;
+    let output_idx = find(&columns.output);
+    let sha_idx = find(&columns.sha256);
+
+    let mut entries = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let line_num = i + 2;
+        let record = record.map_err(|e| format!("Invalid CSV manifest: {}", e))?;
+        let field = |idx: Option<usize>| idx.and_then(|i| record.get(i)).filter(|v| !v.is_empty());
+
+        let Some(raw_url) = field(Some(url_idx)) else {
+            continue;
+        };
+        let Some(url) = resolve_url(raw_url, base) else {
+            eprintln!(
+                "{}: line {} - invalid URL: {}",
+                apply_color("Warning", Some("yellow")),
+                line_num,
+                raw_url
+            );
+            continue;
+        };
+
+        let checksum = field(sha_idx)
+            .map(|hex| Checksum::parse(&format!("sha256:{}", hex)))
+            .transpose()
+            .map_err(|e| format!("line {}: {}", line_num, e))?;
+        let output = field(output_idx)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(filename_from_url(&url)));
+
+        entries.push(DownloadEntry {
+            output,
+            url,
+            checksum,
+            headers: Vec::new(),
+        });
+    }
+
+    if entries.is_empty() {
+        return Err("No valid URLs found in file".into());
+    }
+
+    Ok(entries)
+}
+
+/// Parses a JSON manifest: an array of `{url, output, checksum, headers}` objects.
+pub fn parse_json(
+    content: &str,
+    base: Option<&Url>,
+) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    let raw: Vec<JsonEntry> =
+        serde_json::from_str(content).map_err(|e| format!("Invalid JSON manifest: {}", e))?;
+    let mut entries = Vec::with_capacity(raw.len());
+
+    for (i, item) in raw.into_iter().enumerate() {
+        let Some(url) = resolve_url(&item.url, base) else {
+            eprintln!(
+                "{}: entry {} - invalid URL: {}",
+                apply_color("Warning", Some("yellow")),
+                i,
+                item.url
+            );
+            continue;
+        };
+
+        let checksum = item
+            .checksum
+            .as_deref()
+            .map(Checksum::parse)
+            .transpose()
+            .map_err(|e| format!("entry {}: {}", i, e))?;
+        let output = item.output.unwrap_or_else(|| filename_from_url(&url));
+
+        entries.push(DownloadEntry {
+            output: PathBuf::from(output),
+            url,
+            checksum,
+            headers: item.headers.into_iter().collect(),
+        });
+    }
+
+    if entries.is_empty() {
+        return Err("No valid URLs found in file".into());
+    }
+
+    Ok(entries)
+}
+
+/// Writes entries as a JSON manifest readable by [`parse_json`].
+///
+/// `Authorization`, `Proxy-Authorization` and `Cookie` headers are not
+/// written, a host's `auth` and `headers` in the config still apply when
+/// re-running.
+pub async fn write_manifest(path: &Path, entries: &[DownloadEntry]) -> std::io::Result<()> {
+    let items: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            let headers: BTreeMap<&str, &str> = entry
+                .headers
+                .iter()
+                .filter(|(k, _)| !CREDENTIAL_HEADERS.contains(&k.to_ascii_lowercase().as_str()))
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            serde_json::json!({
+                "url": entry.url,
+                "output": entry.output.to_string_lossy(),
+                "checksum": entry.checksum.as_ref().map(|c| c.to_string()),
+                "headers": headers,
+            })
+        })
+        .collect();
+
+    let content = serde_json::to_string_pretty(&items).map_err(std::io::Error::other)?;
+    tokio::fs::write(path, content + "\n").await
+}
+
+/// Parses one `URL [output]` line of a text manifest.
+///
+/// Returns `None` for blank lines, comments and invalid URLs (with a warning).
+pub fn parse_line(line: &str, line_num: usize, base: Option<&Url>) -> Option<(String, String)> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+
+    let mut parts = trimmed.split_whitespace();
+    let raw_url = parts.next()?;
+
+    let Some(url) = resolve_url(raw_url, base) else {
+        eprintln!(
+            "{}: line {} - invalid URL: {}",
+            apply_color("Warning", Some("yellow")),
+            line_num,
+            raw_url
+        );
+        return None;
+    };
+
+    let filename = parts
+        .next()
+        .map_or_else(|| filename_from_url(&url), str::to_string);
+    Some((url, filename))
+}
+
+/// Turns a text manifest arriving on `reader` into a stream of entries.
+///
+/// Entries are yielded as soon as each line is read, so downloads can start
+/// before the input reaches EOF (e.g. `producer | dwrs -f -`). A read error
+/// is logged and ends the stream, see [`try_entry_stream`] to handle it.
+pub fn entry_stream<R>(reader: R, base: Option<Url>) -> impl Stream<Item = DownloadEntry> + Send
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    try_entry_stream(reader, base).filter_map(|entry| async move {
+        entry
+            .inspect_err(|e| log::error!("Failed to read input: {}", e))
+            .ok()
+    })
+}
+
+/// Like [`entry_stream`], but yields the read error that ends the stream.
+pub fn try_entry_stream<R>(
+    reader: R,
+    base: Option<Url>,
+) -> impl Stream<Item = std::io::Result<DownloadEntry>> + Send
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    futures::stream::unfold(Some((reader.lines(), 0usize, base)), |state| async move {
+        let (mut lines, mut line_num, base) = state?;
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    line_num += 1;
+                    if let Some((url, output)) = parse_line(&line, line_num, base.as_ref()) {
+                        let entry = DownloadEntry::new(url, output);
+                        return Some((Ok(entry), Some((lines, line_num, base))));
+                    }
+                }
+                Ok(None) => return None,
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    })
+}
 
 pub async fn parse_file(
     path: &PathBuf,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_text(path, None).await
+}
+
+/// Parses a text manifest, resolving relative entries against `base`.
+pub async fn parse_text(
+    path: &PathBuf,
+    base: Option<&Url>,
 ) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
     let file = File::open(path)
         .await
@@ -20,51 +564,8 @@ pub async fn parse_file(
     while let Some(line) = lines.next_line().await? {
         line_num += 1;
 
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue;
-        }
-
-        let parts: Vec<&str> = trimmed.split_whitespace().collect();
-
-        match parts.len() {
-            0 => continue,
-            1 => {
-                let url = parts[0];
-                let filename = url
-                    .rsplit('/')
-                    .next()
-                    .filter(|s| !s.is_empty())
-                    .unwrap_or("file.bin");
-
-                if !url.starts_with("http://") && !url.starts_with("https://") {
-                    eprintln!(
-                        "{}: line {} - invalid URL: {}",
-                        "Warning".yellow(),
-                        line_num,
-                        url
-                    );
-                    continue;
-                }
-
-                pairs.push((url.to_string(), filename.to_string()));
-            }
-            _ => {
-                let url = parts[0];
-                let filename = parts[1];
-
-                if !url.starts_with("http://") && !url.starts_with("https://") {
-                    eprintln!(
-                        "{}: line {} - invalid URL: {}",
-                        "Warning".yellow(),
-                        line_num,
-                        url
-                    );
-                    continue;
-                }
-
-                pairs.push((url.to_string(), filename.to_string()));
-            }
+        if let Some(pair) = parse_line(&line, line_num, base) {
+            pairs.push(pair);
         }
     }
 
@@ -76,3 +577,181 @@ pub async fn parse_file(
 
     Ok(pairs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_manifest() {
+        let json = r#"[
+            {"url": "https://example.com/a.iso", "output": "dist/a.iso",
+             "checksum": "sha256:E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+             "headers": {"Authorization": "Bearer x"}},
+            {"url": "https://example.com/b.zip"},
+            {"url": "ftp://example.com/c"}
+        ]"#;
+        let entries = parse_json(json, None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].output, PathBuf::from("dist/a.iso"));
+        assert_eq!(
+            entries[0].headers,
+            vec![("Authorization".to_string(), "Bearer x".to_string())]
+        );
+        assert!(
+            entries[0]
+                .checksum
+                .as_ref()
+                .unwrap()
+                .hex
+                .starts_with("e3b0")
+        );
+        assert_eq!(
+            entries[1],
+            DownloadEntry::new("https://example.com/b.zip", "b.zip")
+        );
+
+        assert!(parse_json(r#"[{"url": "https://x/y", "checksum": "nope"}]"#, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_manifest() {
+        let path = std::env::temp_dir().join("dwrs_test_write_manifest.json");
+        let mut entry = DownloadEntry::new("https://example.com/a.iso", "a.iso");
+        entry.headers = vec![
+            ("Authorization".to_string(), "Bearer x".to_string()),
+            ("cookie".to_string(), "id=1".to_string()),
+            ("Accept".to_string(), "*/*".to_string()),
+        ];
+        write_manifest(&path, &[entry]).await.unwrap();
+        let content = tokio::fs::read_to_string(&path).await.unwrap();
+        let entries = parse_json(&content, None).unwrap();
+        assert_eq!(
+            entries[0].headers,
+            vec![("Accept".to_string(), "*/*".to_string())]
+        );
+        tokio::fs::remove_file(&path).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_entry_stream_read_error() {
+        let input: &[u8] = b"https://example.com/a.iso\n\xff\nhttps://example.com/b.iso\n";
+        let entries: Vec<_> = try_entry_stream(input, None).collect().await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].as_ref().unwrap(),
+            &DownloadEntry::new("https://example.com/a.iso", "a.iso")
+        );
+        assert!(entries[1].is_err());
+        assert_eq!(entry_stream(input, None).count().await, 1);
+    }
+
+    #[test]
+    fn test_parse_csv_manifest() {
+        let csv = "Name,Link,Hash\n\
+                   # comment\n\
+                   \"my file.iso\", https://example.com/a.iso ,\n\
+                   ,https://example.com/b.zip,\n";
+        let columns = CsvColumns::parse("url=Link,output=Name,sha256=Hash").unwrap();
+        let entries = parse_csv(csv, &columns, None).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                DownloadEntry::new("https://example.com/a.iso", "my file.iso"),
+                DownloadEntry::new("https://example.com/b.zip", "b.zip"),
+            ]
+        );
+
+        assert!(parse_csv(csv, &CsvColumns::default(), None).is_err());
+        assert!(CsvColumns::parse("link=x").is_err());
+    }
+
+    #[test]
+    fn test_relative_entries_with_base() {
+        let base = Url::parse("https://mirror.example.com/pub/").unwrap();
+        assert_eq!(
+            parse_line("isos/a.iso", 1, Some(&base)),
+            Some((
+                "https://mirror.example.com/pub/isos/a.iso".to_string(),
+                "a.iso".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve_url("https://other.org/x", Some(&base)).as_deref(),
+            Some("https://other.org/x")
+        );
+        assert_eq!(parse_line("isos/a.iso", 1, None), None);
+    }
+
+    #[test]
+    fn test_output_collisions() {
+        let entries = vec![
+            DownloadEntry::new("https://a.example/file.iso", "file.iso"),
+            DownloadEntry::new("https://b.example/file.iso", "./file.iso"),
+            DownloadEntry::new("https://c.example/file.iso", "file (1).iso"),
+            DownloadEntry::new("https://d.example/file.iso", "file.iso"),
+            DownloadEntry::new("https://e.example/readme", "readme"),
+        ];
+        let outputs: Vec<PathBuf> = resolve_collisions(entries.clone(), CollisionPolicy::Rename)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.output)
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                "file.iso",
+                "./file (2).iso",
+                "file (1).iso",
+                "file (3).iso",
+                "readme"
+            ]
+            .map(PathBuf::from)
+        );
+
+        let err = resolve_collisions(entries.clone(), CollisionPolicy::Error).unwrap_err();
+        assert!(err.starts_with("1 output path(s)"), "{}", err);
+        assert!(err.contains("file.iso: https://a.example/file.iso, https://b.example/file.iso, https://d.example/file.iso"));
+        assert_eq!(
+            resolve_collisions(entries.clone(), CollisionPolicy::Serialize).unwrap(),
+            entries
+        );
+    }
+
+    #[test]
+    fn test_encoded_urls() {
+        assert_eq!(
+            resolve_url("HTTPS://bücher.example/a b.pdf", None).as_deref(),
+            Some("https://xn--bcher-kva.example/a%20b.pdf")
+        );
+        assert_eq!(resolve_url("http://exa mple.com/", None), None);
+        assert_eq!(
+            filename_from_url("https://example.com/My%20Report%20%C3%A9.pdf?token=1"),
+            "My Report é.pdf"
+        );
+        assert_eq!(
+            filename_from_url("https://example.com/a%2F..%2Fb.txt"),
+            "a_.._b.txt"
+        );
+        assert_eq!(filename_from_url("https://example.com/dir/"), "file.bin");
+
+        let mut dedup = Dedup::new();
+        let urls = [
+            "https://Example.COM:443/a/./b/../c.iso",
+            "HTTPS://example.com/a/c.iso#top",
+            "https://example.com:8443/a/c.iso",
+        ];
+        let kept: Vec<String> = urls
+            .iter()
+            .filter_map(|url| dedup.check(DownloadEntry::new(*url, "c.iso")))
+            .map(|entry| entry.url)
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                "https://example.com/a/c.iso",
+                "https://example.com:8443/a/c.iso"
+            ]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+
+/// Type assumed for responses without a `Content-Type` header
+const DEFAULT_TYPE: &str = "application/octet-stream";
+
+/// Parses a media range such as `application/pdf`, `image/*` or `*/*`.
+pub fn parse_media_range(value: &str) -> Result<String, String> {
+    let range = value.trim().to_ascii_lowercase();
+    match range.split_once('/') {
+        Some((kind, sub))
+            if !kind.is_empty()
+                && !sub.is_empty()
+                && !sub.contains('/')
+                && (kind != "*" || sub == "*") =>
+        {
+            Ok(range)
+        }
+        _ => Err(format!(
+            "invalid media type '{}' (expected e.g. application/pdf or image/*)",
+            value
+        )),
+    }
+}
+
+/// `content_type` without parameters, e.g. `text/html` for `text/HTML; charset=utf-8`.
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn matches(range: &str, media_type: &str) -> bool {
+    match range.strip_suffix("/*") {
+        Some("*") => true,
+        Some(kind) => media_type
+            .split_once('/')
+            .is_some_and(|(media_kind, _)| media_kind == kind),
+        None => range == media_type,
+    }
+}
+
+/// Accept and reject lists checked against the `Content-Type` of responses
+///
+/// A type is allowed if it matches no reject range and, when there are any,
+/// at least one accept range. Responses without a `Content-Type` are taken
+/// as `application/octet-stream`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeFilter {
+    /// Media ranges a response must match one of, all types if empty
+    pub accept: Vec<String>,
+    /// Media ranges a response must not match
+    pub reject: Vec<String>,
+}
+
+impl TypeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.accept.is_empty() && self.reject.is_empty()
+    }
+
+    /// Whether a response with this `Content-Type` header may be saved.
+    pub fn allows(&self, content_type: Option<&str>) -> bool {
+        let media_type = media_type(content_type.unwrap_or(DEFAULT_TYPE));
+        if self.reject.iter().any(|range| matches(range, &media_type)) {
+            return false;
+        }
+        self.accept.is_empty() || self.accept.iter().any(|range| matches(range, &media_type))
+    }
+}
+
+/// Matches `name` against a glob where `*` stands for any run of
+/// characters and `?` for exactly one, ignoring case.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                star = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    p = after;
+                    n = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Glob accept and reject lists checked against the file name of URLs,
+/// such as `*.iso`
+///
+/// The file name is the percent-decoded last segment of the URL path,
+/// empty for directory URLs. A URL is allowed if its name matches no
+/// reject pattern and, when there are any, at least one accept pattern.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameFilter {
+    /// Patterns a file name must match one of, all names if empty
+    pub accept: Vec<String>,
+    /// Patterns a file name must not match
+    pub reject: Vec<String>,
+}
+
+impl NameFilter {
+    pub fn is_empty(&self) -> bool {
+        self.accept.is_empty() && self.reject.is_empty()
+    }
+
+    /// Whether a file with this name may be downloaded.
+    pub fn allows(&self, name: &str) -> bool {
+        if self.reject.iter().any(|pattern| glob_match(pattern, name)) {
+            return false;
+        }
+        self.accept.is_empty() || self.accept.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Whether the file `url` points to may be downloaded; URLs that do
+    /// not parse are left to fail later.
+    pub fn allows_url(&self, url: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let Ok(url) = Url::parse(url) else {
+            return true;
+        };
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or("");
+        self.allows(&percent_decode_str(name).decode_utf8_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_filter() {
+        assert!(glob_match("*.iso", "Debian-12.ISO"));
+        assert!(glob_match("file-?.t*z", "file-1.tar.gz"));
+        assert!(glob_match("*a*b*", "xaybzb"));
+        assert!(!glob_match("*.iso", "image.iso.tmp"));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("*", ""));
+
+        let filter = NameFilter {
+            accept: vec!["*.iso".to_string(), "*.torrent".to_string()],
+            reject: vec!["*beta*".to_string()],
+        };
+        assert!(filter.allows_url("https://example.com/pub/debian.iso?mirror=1"));
+        assert!(filter.allows_url("https://example.com/My%20Distro.torrent"));
+        assert!(!filter.allows_url("https://example.com/debian-beta.iso"));
+        assert!(!filter.allows_url("https://example.com/pub/"));
+        assert!(NameFilter::default().allows_url("https://example.com/pub/"));
+    }
+
+    #[test]
+    fn test_type_filter() {
+        assert_eq!(parse_media_range(" Image/* ").unwrap(), "image/*");
+        assert!(parse_media_range("pdf").is_err());
+        assert!(parse_media_range("*/html").is_err());
+
+        let filter = TypeFilter {
+            accept: vec!["application/pdf".to_string(), "image/*".to_string()],
+            reject: vec!["image/svg+xml".to_string()],
+        };
+        assert!(filter.allows(Some("application/PDF; name=a.pdf")));
+        assert!(filter.allows(Some("image/png")));
+        assert!(!filter.allows(Some("image/svg+xml")));
+        assert!(!filter.allows(Some("text/html; charset=utf-8")));
+        assert!(!filter.allows(None));
+
+        let filter = TypeFilter {
+            accept: Vec::new(),
+            reject: vec!["text/html".to_string()],
+        };
+        assert!(filter.allows(None));
+        assert!(!filter.allows(Some("text/html")));
+        assert!(TypeFilter::default().allows(Some("text/html")));
+    }
+}
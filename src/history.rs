@@ -0,0 +1,246 @@
+use rusqlite::{Connection, params};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HistoryResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS downloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER,
+    duration_ms INTEGER NOT NULL,
+    checksum TEXT,
+    success INTEGER NOT NULL,
+    error TEXT,
+    finished_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS downloads_url ON downloads (url);";
+
+/// A finished download as stored in the history database
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryRecord {
+    pub url: String,
+    pub path: PathBuf,
+    /// Size of the file on disk, `None` if it does not exist
+    pub size: Option<u64>,
+    pub duration: Duration,
+    /// Digest of the saved file as `algorithm:hex`
+    pub checksum: Option<String>,
+    /// `None` on success, the error message otherwise
+    pub error: Option<String>,
+    /// Unix timestamp in seconds
+    pub finished_at: i64,
+}
+
+impl HistoryRecord {
+    /// Builds a record for a download that just finished, reading its size from disk.
+    pub async fn finished(
+        url: &str,
+        path: &Path,
+        started: std::time::Instant,
+        checksum: Option<String>,
+        result: &Result<(), String>,
+    ) -> Self {
+        let size = tokio::fs::metadata(path).await.ok().map(|m| m.len());
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Self {
+            url: url.to_string(),
+            path: path.to_path_buf(),
+            size,
+            duration: started.elapsed(),
+            checksum,
+            error: result.as_ref().err().cloned(),
+            finished_at,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Filters for [`History::query`]
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// Only return failed downloads
+    pub failed_only: bool,
+    /// Substring matched against the URL and the output path
+    pub search: Option<String>,
+    /// Maximum number of records, newest first (`None` for all)
+    pub limit: Option<usize>,
+}
+
+/// SQLite-backed log of every finished download.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dwrs::history::{History, HistoryQuery};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let history = History::open(&History::default_path())?;
+/// let query = HistoryQuery {
+///     search: Some("ubuntu".to_string()),
+///     ..Default::default()
+/// };
+/// for record in history.query(&query)? {
+///     println!("{} -> {}", record.url, record.path.display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct History {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl History {
+    /// Default database: `<data dir>/dwrs/history.db`
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("dwrs")
+            .join("history.db")
+    }
+
+    /// Opens (and creates if needed) the history database at `path`.
+    pub fn open(path: &Path) -> HistoryResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA)?;
+        log::debug!("Opened download history at {}", path.display());
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record(&self, record: &HistoryRecord) -> HistoryResult<()> {
+        self.lock().execute(
+            "INSERT INTO downloads (url, path, size, duration_ms, checksum, success, error, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.url,
+                record.path.to_string_lossy(),
+                record.size.map(|s| s as i64),
+                record.duration.as_millis() as i64,
+                record.checksum,
+                record.is_success(),
+                record.error,
+                record.finished_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records in the background so that the SQLite write never blocks a download task.
+    pub async fn record_async(&self, record: HistoryRecord) {
+        let history = self.clone();
+        let result = tokio::task::spawn_blocking(move || history.record(&record)).await;
+        match result {
+            Ok(Err(e)) => log::warn!("Failed to write download history: {}", e),
+            Err(e) => log::warn!("Failed to write download history: {}", e),
+            Ok(Ok(())) => {}
+        }
+    }
+
+    /// Returns matching records, newest first.
+    pub fn query(&self, query: &HistoryQuery) -> HistoryResult<Vec<HistoryRecord>> {
+        let pattern = query.search.as_ref().map(|s| {
+            let escaped = s
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{}%", escaped)
+        });
+        let limit = query.limit.map(|l| l as i64).unwrap_or(-1);
+
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT url, path, size, duration_ms, checksum, error, finished_at
+             FROM downloads
+             WHERE (?1 = 0 OR success = 0)
+               AND (?2 IS NULL OR url LIKE ?2 ESCAPE '\\' OR path LIKE ?2 ESCAPE '\\')
+             ORDER BY finished_at DESC, id DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![query.failed_only, pattern, limit], |row| {
+            Ok(HistoryRecord {
+                url: row.get(0)?,
+                path: PathBuf::from(row.get::<_, String>(1)?),
+                size: row.get::<_, Option<i64>>(2)?.map(|s| s as u64),
+                duration: Duration::from_millis(row.get::<_, i64>(3)? as u64),
+                checksum: row.get(4)?,
+                error: row.get(5)?,
+                finished_at: row.get(6)?,
+            })
+        })?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_query() {
+        let path = std::env::temp_dir().join(format!("dwrs-history-{}.db", std::process::id()));
+        let history = History::open(&path).unwrap();
+        let record = |url: &str, error: Option<&str>, finished_at| HistoryRecord {
+            url: url.to_string(),
+            path: PathBuf::from("out.bin"),
+            size: Some(10),
+            duration: Duration::from_millis(1500),
+            checksum: None,
+            error: error.map(String::from),
+            finished_at,
+        };
+        history
+            .record(&record("https://example.com/a_1.iso", None, 1))
+            .unwrap();
+        history
+            .record(&record("https://example.com/b.iso", Some("timeout"), 2))
+            .unwrap();
+        history
+            .record(&record("https://example.com/ab1.iso", None, 3))
+            .unwrap();
+
+        let all = history.query(&HistoryQuery::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].url, "https://example.com/ab1.iso");
+
+        let failed = history
+            .query(&HistoryQuery {
+                failed_only: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            failed,
+            vec![record("https://example.com/b.iso", Some("timeout"), 2)]
+        );
+
+        let search = history
+            .query(&HistoryQuery {
+                search: Some("a_1".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(search.len(), 1);
+
+        std::fs::remove_file(&path).ok();
+    }
+}
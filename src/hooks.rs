@@ -0,0 +1,405 @@
+use reqwest::Method;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::transport::{Transport, request};
+use crate::units::format_bytes;
+use crate::utils::{Token, parse_template, render};
+
+/// Time a webhook endpoint gets to answer before it is given up on
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time a hook command gets to finish before it is killed
+const HOOK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Variables understood in hook commands
+pub const HOOK_VARS: &[&str] = &["url", "path", "name", "size", "error"];
+
+/// Splits a command line into words like a POSIX shell would, honouring
+/// single and double quotes and backslash escapes but nothing else.
+fn split_words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.extend(chars.next());
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Splits `command` into words and parses the `{var}` placeholders in each,
+/// allowing only `vars`.
+pub(crate) fn parse_command(command: &str, vars: &[&str]) -> Result<Vec<Vec<Token>>, String> {
+    let words = split_words(command)?;
+    if words.is_empty() {
+        return Err("command is empty".to_string());
+    }
+    let words: Vec<Vec<Token>> = words.iter().map(|word| parse_template(word)).collect();
+    for token in words.iter().flatten() {
+        if let Token::Var { name, color } = token {
+            if !vars.contains(&name.as_str()) {
+                return Err(format!(
+                    "unknown variable {{{}}} (expected one of {})",
+                    name,
+                    vars.join(", ")
+                ));
+            }
+            if color.is_some() {
+                return Err(format!("{{{}}} cannot have a style in a command", name));
+            }
+        }
+    }
+    Ok(words)
+}
+
+/// What a hook is told about the download it runs for
+#[derive(Debug, Clone, Copy)]
+pub struct HookEvent<'a> {
+    pub url: &'a str,
+    pub output: &'a Path,
+    /// Bytes downloaded
+    pub bytes: u64,
+    /// Time the download took, retries included
+    pub duration: Duration,
+    /// Why the download failed, `None` if it succeeded
+    pub error: Option<&'a str>,
+}
+
+impl HookEvent<'_> {
+    fn vars(&self) -> HashMap<&'static str, Cow<'_, str>> {
+        let name = self
+            .output
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        HashMap::from([
+            ("url", Cow::Borrowed(self.url)),
+            ("path", self.output.to_string_lossy()),
+            ("name", Cow::Owned(name)),
+            ("size", Cow::Owned(self.bytes.to_string())),
+            ("error", Cow::Borrowed(self.error.unwrap_or(""))),
+        ])
+    }
+
+    fn status(&self) -> &'static str {
+        if self.error.is_none() { "ok" } else { "failed" }
+    }
+}
+
+/// Command run for every finished download, such as `clamscan {path}`
+///
+/// The command is split into words once and `{url}`, `{path}`, `{name}`,
+/// `{size}` and `{error}` are filled into each word, so values never need
+/// quoting and cannot inject arguments. No shell is involved; the same
+/// values are also passed as `DWRS_URL`, `DWRS_PATH`, `DWRS_NAME`,
+/// `DWRS_SIZE`, `DWRS_ERROR` and `DWRS_STATUS` (`ok` or `failed`) for
+/// commands such as `sh -c '...'`.
+#[derive(Debug, Clone)]
+pub struct Hook {
+    words: Vec<Vec<Token>>,
+}
+
+impl Hook {
+    /// Parses a command, rejecting unknown variables and unbalanced quotes.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        Ok(Self {
+            words: parse_command(command, HOOK_VARS)?,
+        })
+    }
+
+    /// Program and arguments for `event`.
+    fn args(&self, event: &HookEvent<'_>) -> Vec<String> {
+        let vars = event.vars();
+        self.words.iter().map(|word| render(word, &vars)).collect()
+    }
+
+    /// Runs the command for `event` and waits for it.
+    ///
+    /// What the command prints goes to the log rather than the terminal, so
+    /// it cannot draw over the progress bars. A command still running after
+    /// five minutes is killed. Failures to start it, timeouts and non-zero
+    /// exit codes are logged; they never change the outcome of the download.
+    pub async fn run(&self, event: &HookEvent<'_>) {
+        self.run_within(event, HOOK_TIMEOUT).await;
+    }
+
+    async fn run_within(&self, event: &HookEvent<'_>, timeout: Duration) {
+        let args = self.args(event);
+        let vars = event.vars();
+        log::debug!("Running hook {:?}", args);
+        let child = tokio::process::Command::new(&args[0])
+            .args(&args[1..])
+            .envs(
+                HOOK_VARS
+                    .iter()
+                    .map(|var| (format!("DWRS_{}", var.to_uppercase()), vars[var].as_ref())),
+            )
+            .env("DWRS_STATUS", event.status())
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                log::warn!("Cannot run hook {}: {}", args[0], e);
+                return;
+            }
+        };
+        let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                log::warn!("Cannot wait for hook {}: {}", args[0], e);
+                return;
+            }
+            Err(_) => {
+                log::warn!(
+                    "Hook {} for {} timed out after {}s and was killed",
+                    args[0],
+                    event.url,
+                    timeout.as_secs()
+                );
+                return;
+            }
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            log::info!("{}: {}", args[0], line);
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            log::warn!("{}: {}", args[0], line);
+        }
+        if !output.status.success() {
+            log::warn!(
+                "Hook {} for {} exited with {}",
+                args[0],
+                event.url,
+                output.status
+            );
+        }
+    }
+}
+
+/// Endpoint that every finished download is POSTed to as JSON, such as
+/// a Home Assistant, Slack or Discord webhook receiver.
+///
+/// The payload holds `url`, `path`, `name`, `status` (`ok` or `failed`),
+/// `size` in bytes, `duration` in seconds and `error`, plus a `text` line
+/// for chat services that show that field. Discord takes it at the `/slack`
+/// variant of its webhook URL.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    endpoint: reqwest::Url,
+}
+
+impl Webhook {
+    /// Parses the endpoint, which must be an http or https URL.
+    pub fn parse(endpoint: &str) -> Result<Self, String> {
+        let endpoint = reqwest::Url::parse(endpoint)
+            .map_err(|e| format!("invalid URL '{}': {}", endpoint, e))?;
+        if !matches!(endpoint.scheme(), "http" | "https") {
+            return Err(format!("{}: is not an http(s) URL", endpoint.scheme()));
+        }
+        Ok(Self { endpoint })
+    }
+
+    /// Endpoint with its path and query replaced by `***`, for logs.
+    pub fn redacted(&self) -> String {
+        redact_endpoint(&self.endpoint)
+    }
+
+    /// JSON body sent for `event`.
+    pub fn payload(event: &HookEvent<'_>) -> serde_json::Value {
+        let name = event.vars()["name"].to_string();
+        let text = match event.error {
+            None => format!("Downloaded {} ({})", name, format_bytes(event.bytes)),
+            Some(error) => format!("Download of {} failed: {}", name, error),
+        };
+        serde_json::json!({
+            "url": event.url,
+            "path": event.output,
+            "name": name,
+            "status": event.status(),
+            "size": event.bytes,
+            "duration": event.duration.as_secs_f64(),
+            "error": event.error,
+            "text": text,
+        })
+    }
+
+    /// Posts `event` to the endpoint through `transport`.
+    ///
+    /// Like hook commands, failures are logged and never change the outcome
+    /// of the download.
+    pub async fn send(&self, transport: &dyn Transport, event: &HookEvent<'_>) {
+        let content_type = [("Content-Type".to_string(), "application/json".to_string())];
+        let mut post = match request(Method::POST, self.endpoint.as_str(), &content_type) {
+            Ok(post) => post,
+            Err(e) => {
+                log::warn!("Cannot build webhook request: {}", e);
+                return;
+            }
+        };
+        *post.body_mut() = Some(Self::payload(event).to_string().into());
+        match tokio::time::timeout(WEBHOOK_TIMEOUT, transport.execute(post)).await {
+            Ok(Ok(response)) if response.status().is_success() => {}
+            Ok(Ok(response)) => log::warn!(
+                "Webhook {} answered {} for {}",
+                self.redacted(),
+                response.status(),
+                event.url
+            ),
+            Ok(Err(e)) => {
+                // reqwest errors name the full URL
+                let e = match e.downcast::<reqwest::Error>() {
+                    Ok(e) => e.without_url().to_string(),
+                    Err(e) => e.to_string(),
+                };
+                log::warn!("Cannot reach webhook {}: {}", self.redacted(), e)
+            }
+            Err(_) => log::warn!("Webhook {} timed out", self.redacted()),
+        }
+    }
+}
+
+/// Scheme, host and port of `endpoint`, with the rest replaced by `***`.
+///
+/// Webhook URLs usually carry their secret in the path (Slack, Discord) or
+/// the query, so only the part that says where they go is shown.
+pub fn redact_endpoint(endpoint: &reqwest::Url) -> String {
+    let origin = endpoint.origin().ascii_serialization();
+    match (endpoint.path(), endpoint.query()) {
+        ("/" | "", None) => origin,
+        _ => format!("{}/***", origin),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_arguments() {
+        assert_eq!(
+            split_words(r#"scan --label "a \"b\"" 'c d'\ e"#).unwrap(),
+            vec!["scan", "--label", "a \"b\"", "c d e"]
+        );
+        assert!(split_words("echo 'open").is_err());
+
+        let hook = Hook::parse("notify 'done: {name}' {path} --size={size}").unwrap();
+        let event = HookEvent {
+            url: "https://example.com/a b.iso",
+            output: Path::new("dl/a b; rm -rf ~.iso"),
+            bytes: 2048,
+            duration: Duration::from_secs(2),
+            error: None,
+        };
+        assert_eq!(
+            hook.args(&event),
+            vec![
+                "notify",
+                "done: a b; rm -rf ~.iso",
+                "dl/a b; rm -rf ~.iso",
+                "--size=2048"
+            ]
+        );
+
+        assert!(Hook::parse("echo {file}").is_err());
+        assert!(Hook::parse("  ").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hook_timeout() {
+        let hook = Hook::parse("sleep 5").unwrap();
+        let event = HookEvent {
+            url: "https://example.com/a.iso",
+            output: Path::new("a.iso"),
+            bytes: 0,
+            duration: Duration::ZERO,
+            error: None,
+        };
+        let started = std::time::Instant::now();
+        hook.run_within(&event, Duration::from_millis(100)).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        use httpmock::MockServer;
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/hook")
+                .header("Content-Type", "application/json")
+                .json_body_includes(r#"{"status": "failed", "size": 10, "error": "HTTP 500"}"#);
+            then.status(204);
+        });
+        let webhook = Webhook::parse(&server.url("/hook")).unwrap();
+        let event = HookEvent {
+            url: "https://example.com/a.iso",
+            output: Path::new("a.iso"),
+            bytes: 10,
+            duration: Duration::from_millis(1500),
+            error: Some("HTTP 500"),
+        };
+        webhook.send(&reqwest::Client::new(), &event).await;
+        mock.assert();
+
+        assert_eq!(Webhook::payload(&event)["duration"], 1.5);
+        let slack = Webhook::parse("https://hooks.slack.com/services/T000/B000/XXXX").unwrap();
+        assert_eq!(slack.redacted(), "https://hooks.slack.com/***");
+        let bare = Webhook::parse("http://user:pw@localhost:8123").unwrap();
+        assert_eq!(bare.redacted(), "http://localhost:8123");
+        assert!(Webhook::parse("ftp://example.com/hook").is_err());
+    }
+}
@@ -35,6 +35,21 @@
 //! }
 //! ```
 //!
+//! # Tracing
+//!
+//! Every download runs inside a `download` [`tracing`] span with `url`,
+//! `output` and `attempt` fields, and each parallel chunk inside a child
+//! `chunk` span with its `index` and byte `range`. Install any `tracing`
+//! subscriber to collect them; log messages can be attached to the current
+//! span with `tracing_log::LogTracer`.
+//!
+//! # Headless builds
+//!
+//! Progress bars and colors come from the default `terminal` feature.
+//! Servers can drop it with `default-features = false` to leave out the
+//! terminal crates; [`progress::ProgressBar`] then only tracks positions,
+//! which still show up in [`queue::DownloadQueue`] snapshots.
+//!
 //! # Configuration
 //!
 //! Use [`DownloadConfig`] to customize behavior:
@@ -50,26 +65,96 @@
 //! };
 //! ```
 
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+rust_i18n::i18n!(
+    "locales",
+    fallback = "en",
+    backend = crate::localization::UserLocales::load()
+);
+
+pub mod bandwidth;
+pub mod bell;
+pub mod bench;
+pub mod blocking;
+pub mod builder;
+pub mod checksum;
 pub mod cli;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod color;
 pub mod config;
+pub mod control;
+pub mod crawler;
+pub mod decompress;
+pub mod deferred;
 pub mod download;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod events;
+pub mod exit;
+#[cfg(feature = "extract")]
+pub mod extract;
+pub mod feed;
 pub mod file_parser;
+pub mod filter;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod hooks;
+pub mod localization;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
 #[cfg(feature = "notify")]
 pub mod notifications;
+pub mod notifier;
+pub mod output_template;
+pub mod overwrite;
+#[cfg(feature = "tui")]
+pub mod picker;
+pub mod pipe;
+pub mod preflight;
 pub mod progress;
+pub mod provenance;
+pub mod queue;
+pub mod report;
+pub mod resolver;
+pub mod retry;
+pub mod rewrite;
+pub mod robots;
+pub mod rpc;
+pub mod sanitize;
+pub mod sitemap;
+pub mod spider;
+pub mod status;
+pub mod storage;
+pub mod summary;
+#[cfg(unix)]
+pub mod systemd;
+pub mod title;
+pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod units;
+pub mod user_agent;
 pub mod utils;
+pub mod watch_dir;
+#[cfg(feature = "webui")]
+pub mod webui;
 
-use futures::stream::{FuturesUnordered, StreamExt};
-use indicatif::MultiProgress;
+use futures::FutureExt;
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
 use reqwest::{Client, ClientBuilder};
-use tokio::sync::{Semaphore, mpsc};
+use tracing::Instrument;
 
-pub use download::download_file;
-pub use file_parser::parse_file;
+pub use builder::{DownloadConfigBuilder, DownloaderBuilder};
+pub use download::{DownloadReport, download_file};
+pub use file_parser::{DownloadEntry, parse_file, parse_manifest};
 
 /// Creates an optimized HTTP client with connection pooling and compression.
 ///
@@ -83,13 +168,19 @@ pub use file_parser::parse_file;
 /// - Gzip, Brotli, and Deflate compression
 /// - TCP_NODELAY for reduced latency
 /// - Automatic redirects (up to 10 hops)
-/// - Custom user agent
+/// - `dwrs/<version>` as user agent, see [`user_agent::DEFAULT`]
 ///
 /// # Timeouts
 ///
 /// - Connection timeout: 30 seconds
 /// - Request timeout: 5 minutes
 pub fn create_optimized_client(pool_size: usize) -> Client {
+    client_builder(pool_size, user_agent::DEFAULT)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+fn client_builder(pool_size: usize, user_agent: &str) -> ClientBuilder {
     ClientBuilder::new()
         .pool_max_idle_per_host(pool_size)
         .timeout(Duration::from_secs(300))
@@ -99,9 +190,40 @@ pub fn create_optimized_client(pool_size: usize) -> Client {
         .deflate(true)
         .tcp_nodelay(true)
         .redirect(reqwest::redirect::Policy::limited(10))
-        .user_agent(concat!("dwrs/", env!("CARGO_PKG_VERSION")))
-        .build()
-        .expect("Failed to build HTTP client")
+        .user_agent(user_agent)
+}
+
+/// Writes the failed entries of a batch to `path` with absolute outputs, so
+/// `--retry-failed` works from any directory, or removes `path` if none failed.
+async fn write_failed(path: &std::path::Path, mut failed: Vec<DownloadEntry>) {
+    if failed.is_empty() {
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => log::info!("No failed downloads, removed {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to remove {}: {}", path.display(), e),
+        }
+        return;
+    }
+    for entry in &mut failed {
+        if let Ok(output) = std::path::absolute(&entry.output) {
+            entry.output = output;
+        }
+    }
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && let Err(e) = tokio::fs::create_dir_all(parent).await
+    {
+        log::error!("Failed to create {}: {}", parent.display(), e);
+        return;
+    }
+    match file_parser::write_manifest(path, &failed).await {
+        Ok(()) => log::info!(
+            "Wrote {} failed downloads to {}",
+            failed.len(),
+            path.display()
+        ),
+        Err(e) => log::error!("Failed to write {}: {}", path.display(), e),
+    }
 }
 
 /// Configuration for download operations.
@@ -128,6 +250,18 @@ pub fn create_optimized_client(pool_size: usize) -> Client {
 ///     ..Default::default()
 /// };
 /// ```
+///
+/// The same with [`DownloadConfig::builder`], which keeps compiling as
+/// fields are added and checks the values:
+/// ```
+/// use dwrs::DownloadConfig;
+///
+/// let config = DownloadConfig::builder()
+///     .workers(8)
+///     .buffer_size(1024 * 1024)
+///     .build()
+///     .unwrap();
+/// ```
 #[derive(Debug, Clone)]
 pub struct DownloadConfig {
     /// Number of parallel workers (chunks) per file download.
@@ -162,6 +296,10 @@ pub struct DownloadConfig {
     /// - `{elapsed_precise}` - Elapsed time
     /// - `{bar}` - Progress bar
     /// - `{pos}` / `{len}` - Current/total bytes
+    /// - `{size}` / `{total_size}` / `{speed}` - Current/total size and
+    ///   speed in the configured [`units`]
+    /// - `{eta}` - Time left; like `{speed}` smoothed over the last
+    ///   seconds, see [`progress::RateEstimator`]
     /// - `{percent}` - Percentage complete
     /// - `{msg}` - Custom message
     ///
@@ -199,11 +337,17 @@ pub struct DownloadConfig {
 
     /// Number of retry attempts for failed downloads.
     ///
-    /// Retries use exponential backoff: 2^attempt seconds delay.
+    /// How long to wait in between is up to [`DownloadConfig::retry_policy`].
     ///
     /// Default: 3
     pub retries: usize,
 
+    /// Decides after each failed attempt whether to try again and how long
+    /// to wait first, see [`retry::RetryPolicy`].
+    ///
+    /// Default: [`retry::Exponential`], waiting 2, 4, 8... seconds
+    pub retry_policy: Arc<dyn retry::RetryPolicy>,
+
     /// Minimum file size in bytes to trigger parallel chunk downloading.
     ///
     /// Files smaller than this use single-threaded download.
@@ -219,6 +363,363 @@ pub struct DownloadConfig {
     ///
     /// Default: None (auto)
     pub max_concurrent_files: Option<usize>,
+
+    /// File where failed batch entries are written as a JSON manifest,
+    /// removed again by a batch without failures.
+    ///
+    /// The file can be fed back with `--retry-failed` (or parsed with
+    /// [`parse_manifest`]) to re-run only the failures. Credential headers
+    /// are left out, see [`file_parser::write_manifest`].
+    ///
+    /// Default: None (disabled)
+    pub failed_file: Option<PathBuf>,
+
+    /// `User-Agent` of every request, HEAD and ranged chunks included,
+    /// see [`user_agent`]. Headers of a host override it.
+    ///
+    /// Default: `dwrs/<version>`
+    pub user_agent: String,
+
+    /// `Referer` sent with every request, unless the entry or its host
+    /// brings its own, e.g. the page a crawled link was found on.
+    ///
+    /// Default: None
+    pub referer: Option<String>,
+
+    /// Method and body of every download instead of a plain `GET`, e.g. to
+    /// fetch the file of an export endpoint, whether saved to a file,
+    /// written to a writer or streamed. Such downloads are neither split
+    /// into chunks nor resumed. A failed attempt sends the method and body
+    /// again on every retry, so set [`DownloadConfig::retries`] to `0` when
+    /// the request is not safe to repeat.
+    ///
+    /// Default: None
+    pub payload: Option<transport::Payload>,
+
+    /// Proxy URL used for all requests (`http://`, `https://` or `socks5://`).
+    ///
+    /// Default: None (system proxy environment variables apply)
+    pub proxy: Option<String>,
+
+    /// Maximum transfer rate per file in bytes per second.
+    ///
+    /// Default: None (unlimited)
+    pub rate_limit: Option<u64>,
+
+    /// Daily time windows with their own rate limit.
+    ///
+    /// While the local time falls into a window, its limit replaces
+    /// [`rate_limit`](Self::rate_limit) and any per-host limit. Running
+    /// downloads pick up changes within a second. The CLI leaves the
+    /// windows out when `--limit-rate` is given.
+    ///
+    /// Default: empty
+    pub schedule: Vec<bandwidth::BandwidthWindow>,
+
+    /// Maximum transfer rate in bytes per second shared by the downloads
+    /// from one host, and by the pages the crawler reads from it.
+    ///
+    /// Default: None (unlimited)
+    pub host_rate_limit: Option<u64>,
+
+    /// Maximum transfer rate in bytes per second shared by all downloads.
+    ///
+    /// Default: None (unlimited)
+    pub total_rate_limit: Option<u64>,
+
+    /// Bytes a rate limit lets through at full speed after an idle spell,
+    /// see [`bandwidth::RateLimiter::burst`].
+    ///
+    /// Default: None (one second's worth of the limit)
+    pub rate_burst: Option<u64>,
+
+    /// Total bytes a run may download before no new downloads are started.
+    ///
+    /// Downloads already running when the budget runs out are finished;
+    /// the queued ones fail as skipped so they can be retried later.
+    ///
+    /// Default: None (unlimited)
+    pub quota: Option<u64>,
+
+    /// Content types downloads must have, checked against the response
+    /// before anything is written.
+    ///
+    /// Queued downloads of other types are skipped rather than failed.
+    ///
+    /// Default: empty (all types)
+    pub type_filter: filter::TypeFilter,
+
+    /// SQLite database where every finished download is recorded.
+    ///
+    /// Default: None (disabled)
+    #[cfg(feature = "history")]
+    pub history_file: Option<PathBuf>,
+
+    /// Draw progress bars on stderr.
+    ///
+    /// When stderr is not a terminal, as in CI or cron jobs, a line per
+    /// download is printed every [`progress_interval`](Self::progress_interval)
+    /// instead, if set.
+    ///
+    /// Default: true
+    pub progress: bool,
+
+    /// How often running downloads are printed to stderr when bars cannot
+    /// be drawn. The CLI sets it; embedders keep their stderr to themselves.
+    ///
+    /// Default: None (no progress lines)
+    pub progress_interval: Option<Duration>,
+
+    /// Show the overall progress of a batch in the terminal window title,
+    /// see [`title`].
+    ///
+    /// Default: false
+    pub terminal_title: bool,
+
+    /// Ring the terminal bell when a batch is done, see [`bell`].
+    ///
+    /// Default: false
+    pub bell: bool,
+
+    /// Sound file played on this machine when a batch is done.
+    ///
+    /// Default: None
+    pub bell_sound: Option<PathBuf>,
+
+    /// Cancel the rest of a batch as soon as one download fails for good.
+    ///
+    /// Queued entries are dropped and running ones interrupted; they are
+    /// reported as cancelled. When false, the batch keeps going and all
+    /// failures are reported at the end.
+    ///
+    /// Default: false
+    pub fail_fast: bool,
+
+    /// Check every URL of a batch with a HEAD request before it starts.
+    ///
+    /// The sizes drive a bar for the whole batch, a check that the disk
+    /// has room and the order of the queue, largest files first. URLs
+    /// answering 404 or 410 fail without being downloaded. Only applies to
+    /// [`Downloader::download_entries`], whose entries are known up front.
+    ///
+    /// Default: false
+    pub preflight: bool,
+
+    /// Print a per-file summary table to stderr after each batch.
+    ///
+    /// Default: false
+    pub summary: bool,
+
+    /// Where a JSON report of each batch is written (`-` for stdout).
+    ///
+    /// See [`report::to_json`] for the layout.
+    ///
+    /// Default: None (disabled)
+    pub report_json: Option<PathBuf>,
+
+    /// Write the SHA-256 of every file downloaded in a batch, to a
+    /// `SHA256SUMS` in the directory holding them or to a `.sha256` file
+    /// next to each download.
+    ///
+    /// Default: None (disabled)
+    pub write_checksums: Option<checksum::ChecksumOutput>,
+
+    /// Record the source URL, `ETag` and download time of finished files
+    /// in extended attributes, such as `user.xdg.origin.url`. The URL is
+    /// stored without credentials and query, see [`provenance`].
+    ///
+    /// Only on Unix file systems that support them.
+    ///
+    /// Default: true
+    pub provenance: bool,
+
+    /// Strip a gzip or zstd layer from downloads while writing them, so
+    /// `data.json.gz` is saved decompressed.
+    ///
+    /// Output names are taken as given, see [`decompress::output_path`]
+    /// for the usual renaming. Such downloads are never split into chunks
+    /// or resumed, and their progress counts the compressed bytes.
+    ///
+    /// Default: false
+    pub decompress: bool,
+
+    /// Command every download is streamed through, its output being what
+    /// is saved, e.g. `gpg --decrypt`.
+    ///
+    /// Runs after [`decompress`](Self::decompress); like it, such
+    /// downloads are never split into chunks or resumed.
+    ///
+    /// Default: None
+    pub pipe: Option<pipe::PipeCommand>,
+
+    /// Also write every download to stdout while saving it, like
+    /// `curl | tee`, so a consumer can start before it is complete.
+    ///
+    /// Meant for a single download; such downloads are never split into
+    /// chunks, resumed or retried once bytes have been written.
+    ///
+    /// Default: false
+    pub tee: bool,
+
+    /// Unpack downloaded archives (`.zip`, `.tar`, `.tar.gz`, `.tar.zst`)
+    /// into the directory they were saved to, once they are verified.
+    ///
+    /// Requires the `extract` feature.
+    ///
+    /// Default: false
+    #[cfg(feature = "extract")]
+    pub extract: bool,
+
+    /// Remove archives after unpacking them with [`extract`](Self::extract).
+    ///
+    /// Default: false
+    #[cfg(feature = "extract")]
+    pub delete_archive: bool,
+
+    /// Command run after every successful download, of a batch or of
+    /// [`Downloader::download_file`].
+    ///
+    /// Default: None
+    pub on_complete: Option<hooks::Hook>,
+
+    /// Command run after every failed download, of a batch or of
+    /// [`Downloader::download_file`].
+    ///
+    /// Default: None
+    pub on_error: Option<hooks::Hook>,
+
+    /// Templates for the bodies of notifications, see
+    /// [`notifier::Messages`].
+    ///
+    /// Default: the translated messages of the UI language
+    pub notify_messages: notifier::Messages,
+
+    /// Duration or size a download must reach to be notified about, see
+    /// [`notifier::Threshold`]. Failures are always notified.
+    ///
+    /// Default: no limit (every download is notified)
+    pub notify_threshold: notifier::Threshold,
+
+    /// Endpoint every finished download, of a batch or of
+    /// [`Downloader::download_file`], is POSTed to as JSON.
+    ///
+    /// Default: None
+    pub notify_webhook: Option<hooks::Webhook>,
+
+    /// Mail a summary of every finished batch.
+    ///
+    /// Requires the `email` feature to be enabled.
+    ///
+    /// Default: None
+    #[cfg(feature = "email")]
+    pub email: Option<email::Mailer>,
+
+    /// Rules and command that map every URL to the one downloaded.
+    ///
+    /// Applied right before each download, so output names still follow
+    /// the original URL.
+    ///
+    /// Default: empty (URLs are downloaded as given)
+    pub rewrite: rewrite::Rewriter,
+
+    /// State file kept up to date while a queue runs, read by `dwrs status`.
+    ///
+    /// See [`status::StateWriter`]; the file is removed once the queue is done.
+    ///
+    /// Default: None (disabled)
+    pub status_file: Option<PathBuf>,
+
+    /// Per-host overrides from `[host."name"]` config sections.
+    ///
+    /// Keys are host names or `*.domain` patterns, see [`config::match_host`].
+    ///
+    /// Default: empty
+    pub hosts: BTreeMap<String, config::HostConfig>,
+}
+
+/// Settings for one URL after merging in its `[host]` section.
+struct HostOverrides {
+    headers: Vec<(String, String)>,
+    workers: usize,
+    rate_limit: Option<u64>,
+    retries: usize,
+}
+
+impl DownloadConfig {
+    /// How long to wait before another attempt at a download that failed
+    /// with `e` on attempt `attempt` of `retries`, if there is to be one.
+    fn retry_delay(
+        &self,
+        e: &retry::AttemptError,
+        attempt: usize,
+        retries: usize,
+    ) -> Option<Duration> {
+        if attempt >= retries
+            || e.is::<download::Rejected>()
+            || e.is::<download::ErrorPage>()
+            || control::is_stopping()
+        {
+            return None;
+        }
+        self.retry_policy.should_retry(e, attempt)
+    }
+
+    /// Bar of the download of `url` to `output`, reported in log lines
+    /// instead if it would be drawn on something other than a terminal.
+    fn progress_bar(
+        &self,
+        mp: &progress::MultiProgress,
+        url: &str,
+        output: &str,
+    ) -> progress::ProgressBar {
+        let pb = progress::create_progress_bar(
+            mp,
+            &self.template,
+            &self.msg_template,
+            &self.chars,
+            url,
+            output,
+        );
+        if self.progress
+            && let Some(interval) = self.progress_interval
+            && !std::io::stderr().is_terminal()
+        {
+            progress::log_progress(&pb, output.to_string(), interval);
+        }
+        pb
+    }
+
+    fn for_url(&self, url: &str, headers: &[(String, String)]) -> HostOverrides {
+        let mut overrides = self.host_overrides(url, headers);
+        if let Some(referer) = &self.referer
+            && !overrides
+                .headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("referer"))
+        {
+            overrides
+                .headers
+                .insert(0, ("Referer".to_string(), referer.clone()));
+        }
+        overrides
+    }
+
+    fn host_overrides(&self, url: &str, headers: &[(String, String)]) -> HostOverrides {
+        match config::match_host(&self.hosts, url) {
+            Some(host) => HostOverrides {
+                headers: host.request_headers(headers),
+                workers: host.workers.unwrap_or(self.workers),
+                rate_limit: host.rate_limit.or(self.rate_limit),
+                retries: host.retries.unwrap_or(self.retries),
+            },
+            None => HostOverrides {
+                headers: headers.to_vec(),
+                workers: self.workers,
+                rate_limit: self.rate_limit,
+                retries: self.retries,
+            },
+        }
+    }
 }
 
 impl Default for DownloadConfig {
@@ -234,12 +735,91 @@ impl Default for DownloadConfig {
             buffer_size: 256 * 1024,
             pool_size: 100,
             retries: 3,
+            retry_policy: Arc::new(retry::Exponential::default()),
             min_parallel_size: 5 * 1024 * 1024,
             max_concurrent_files: None,
+            failed_file: None,
+            user_agent: user_agent::DEFAULT.to_string(),
+            referer: None,
+            payload: None,
+            proxy: None,
+            rate_limit: None,
+            schedule: Vec::new(),
+            host_rate_limit: None,
+            total_rate_limit: None,
+            rate_burst: None,
+            quota: None,
+            type_filter: filter::TypeFilter::default(),
+            #[cfg(feature = "history")]
+            history_file: None,
+            progress: true,
+            progress_interval: None,
+            terminal_title: false,
+            bell: false,
+            bell_sound: None,
+            fail_fast: false,
+            preflight: false,
+            summary: false,
+            report_json: None,
+            write_checksums: None,
+            provenance: true,
+            decompress: false,
+            pipe: None,
+            tee: false,
+            #[cfg(feature = "extract")]
+            extract: false,
+            #[cfg(feature = "extract")]
+            delete_archive: false,
+            on_complete: None,
+            on_error: None,
+            notify_messages: notifier::Messages::default(),
+            notify_threshold: notifier::Threshold::default(),
+            notify_webhook: None,
+            #[cfg(feature = "email")]
+            email: None,
+            rewrite: rewrite::Rewriter::default(),
+            status_file: None,
+            hosts: BTreeMap::new(),
         }
     }
 }
 
+/// Result of a single entry in a batch download.
+#[derive(Debug)]
+struct BatchOutcome {
+    entry: DownloadEntry,
+    result: Result<(), String>,
+    bytes: u64,
+    duration: Duration,
+    attempts: usize,
+    report: Option<DownloadReport>,
+}
+
+/// Error returned when one or more downloads of a batch failed.
+///
+/// Comes back boxed from the batch methods; use `downcast_ref` to get at
+/// the counts, e.g. to pick an exit code with [`exit::ExitStatus::from_batch`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchError {
+    pub failed: usize,
+    pub total: usize,
+    /// One `url: error` line per failed download
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:\n{}",
+            localization::plural("batch.failed", self.failed, &[("total", self.total)]),
+            self.errors.join("\n")
+        )
+    }
+}
+
+impl std::error::Error for BatchError {}
+
 /// Main downloader struct managing HTTP client and configuration.
 ///
 /// [`Downloader`] is the primary interface for downloading files.
@@ -293,7 +873,23 @@ impl Default for DownloadConfig {
 /// ```
 pub struct Downloader {
     config: DownloadConfig,
+    /// Fetches pages for the crawler, sitemaps and feeds
     client: Client,
+    /// Sends the requests of downloads and URL checks, through `middleware`
+    transport: Arc<dyn transport::Transport>,
+    /// Transport at the end of the middleware chain
+    backend: Arc<dyn transport::Transport>,
+    middleware: Vec<Arc<dyn middleware::Middleware>>,
+    /// Where finished downloads and batches are announced
+    notifiers: Vec<Arc<dyn notifier::Notifier>>,
+    /// Rate limits shared between downloads
+    limits: Arc<bandwidth::SharedLimits>,
+    events: Arc<events::Events>,
+    /// Where the bars are drawn, instead of a container per run
+    multi_progress: Option<progress::MultiProgress>,
+    resolvers: resolver::Resolvers,
+    #[cfg(feature = "history")]
+    history: Option<history::History>,
 }
 
 impl Downloader {
@@ -304,7 +900,9 @@ impl Downloader {
     ///
     /// # Panics
     ///
-    /// Panics if the HTTP client fails to build (extremely rare).
+    /// Panics if the HTTP client fails to build (extremely rare). An
+    /// invalid [`DownloadConfig::user_agent`] is logged and replaced by
+    /// [`user_agent::DEFAULT`]; [`Downloader::builder`] rejects it instead.
     ///
     /// # Examples
     ///
@@ -321,8 +919,173 @@ impl Downloader {
             config.buffer_size,
             config.pool_size
         );
-        let client = create_optimized_client(config.pool_size);
-        Self { config, client }
+        let user_agent = match user_agent::check(&config.user_agent) {
+            Ok(()) => config.user_agent.as_str(),
+            Err(e) => {
+                log::error!("Ignoring {}", e);
+                user_agent::DEFAULT
+            }
+        };
+        let mut builder = client_builder(config.pool_size, user_agent);
+        if let Some(proxy) = &config.proxy {
+            match reqwest::Proxy::all(proxy) {
+                Ok(proxy) => builder = builder.proxy(proxy),
+                Err(e) => log::error!("Ignoring invalid proxy {}: {}", proxy, e),
+            }
+        }
+        let client = builder.build().expect("Failed to build HTTP client");
+        #[cfg(feature = "history")]
+        let history = config
+            .history_file
+            .as_deref()
+            .and_then(|path| match history::History::open(path) {
+                Ok(history) => Some(history),
+                Err(e) => {
+                    log::warn!("Download history disabled: {}", e);
+                    None
+                }
+            });
+        let limits = bandwidth::SharedLimits::new(
+            config.host_rate_limit,
+            config.total_rate_limit,
+            config.rate_burst,
+        );
+        #[cfg(feature = "notify")]
+        let notifiers: Vec<Arc<dyn notifier::Notifier>> = if config.notify {
+            vec![Arc::new(notifier::Desktop)]
+        } else {
+            Vec::new()
+        };
+        #[cfg(not(feature = "notify"))]
+        let notifiers = Vec::new();
+        Self {
+            config,
+            transport: Arc::new(client.clone()),
+            backend: Arc::new(client.clone()),
+            middleware: Vec::new(),
+            notifiers,
+            limits: Arc::new(limits),
+            events: Arc::new(events::Events::new()),
+            multi_progress: None,
+            client,
+            resolvers: resolver::Resolvers::builtin(),
+            #[cfg(feature = "history")]
+            history,
+        }
+    }
+
+    /// Registers a resolver for URLs of a scheme or host, such as `ipfs://`.
+    ///
+    /// It is asked before the resolvers registered earlier and the
+    /// built-in ones, see [`resolver::Resolver`].
+    pub fn register_resolver(&mut self, resolver: impl resolver::Resolver + 'static) {
+        self.resolvers.register(Arc::new(resolver));
+    }
+
+    /// Sends the requests of downloads and URL checks through `transport`
+    /// instead of the built-in HTTP client, see [`transport::Transport`].
+    ///
+    /// Pages read by the crawler and for sitemaps and feeds still go through
+    /// the built-in client.
+    pub fn set_transport(&mut self, transport: impl transport::Transport + 'static) {
+        self.use_transport(Arc::new(transport));
+    }
+
+    /// Runs every request of downloads and URL checks through `middleware`,
+    /// after the middleware added before it, see [`middleware::Middleware`].
+    pub fn add_middleware(&mut self, middleware: impl middleware::Middleware + 'static) {
+        self.middleware.push(Arc::new(middleware));
+        self.use_transport(self.backend.clone());
+    }
+
+    /// Announces finished downloads and batches through `notifier` as
+    /// well, see [`notifier::Notifier`].
+    pub fn add_notifier(&mut self, notifier: impl notifier::Notifier + 'static) {
+        self.notifiers.push(Arc::new(notifier));
+    }
+
+    /// Sends `notification` through every notifier, logging failures.
+    async fn notify(&self, notification: notifier::Notification) {
+        for notifier in &self.notifiers {
+            let send = notifier.notify(&*self.transport, &notification);
+            match tokio::time::timeout(notifier::NOTIFY_TIMEOUT, send).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => log::warn!("Failed to send notification: {}", e),
+                Err(_) => log::warn!("Sending a notification timed out"),
+            }
+        }
+    }
+
+    fn use_transport(&mut self, transport: Arc<dyn transport::Transport>) {
+        self.backend = transport.clone();
+        self.transport = if self.middleware.is_empty() {
+            transport
+        } else {
+            Arc::new(middleware::Layered {
+                middleware: self.middleware.clone(),
+                transport,
+            })
+        };
+    }
+
+    /// Draws the progress bars of this downloader in `mp`, together with
+    /// the bars of the application and of other downloaders sharing it.
+    ///
+    /// Bars stay hidden if [`DownloadConfig::progress`] is off.
+    ///
+    /// ```
+    /// use dwrs::Downloader;
+    /// use dwrs::progress::MultiProgress;
+    ///
+    /// let mp = MultiProgress::new();
+    /// let images = Downloader::new_default().with_multi_progress(mp.clone());
+    /// let videos = Downloader::new_default().with_multi_progress(mp);
+    /// ```
+    pub fn with_multi_progress(mut self, mp: progress::MultiProgress) -> Self {
+        self.multi_progress = Some(mp);
+        self
+    }
+
+    /// Container for the bars of one run, the shared one if set.
+    fn multi_progress(&self) -> progress::MultiProgress {
+        match &self.multi_progress {
+            Some(mp) if self.config.progress => mp.clone(),
+            _ => progress::multi_progress(self.config.progress),
+        }
+    }
+
+    /// Events of all downloads of this downloader from now on, as they
+    /// start, make progress and end.
+    ///
+    /// Covers single and batch downloads to files; the stream ends once
+    /// the downloader is dropped and its downloads are done. `Progress`
+    /// events are only sampled for downloads started after subscribing.
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::events::ProgressEvent;
+    /// use futures::StreamExt;
+    ///
+    /// # async fn example(downloader: &Downloader) {
+    /// let mut events = downloader.progress_events();
+    /// tokio::spawn(async move {
+    ///     while let Some(event) = events.next().await {
+    ///         if let ProgressEvent::Progress { id, downloaded, .. } = event {
+    ///             println!("#{}: {} bytes", id, downloaded);
+    ///         }
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn progress_events(&self) -> impl Stream<Item = events::ProgressEvent> + Unpin + use<> {
+        Box::pin(self.events.subscribe())
+    }
+
+    /// What `url` is downloaded from, after [`DownloadConfig::rewrite`] and
+    /// the registered resolvers.
+    pub async fn plan(&self, url: &str) -> Result<resolver::DownloadPlan, String> {
+        let url = self.config.rewrite.rewrite(url).await;
+        self.resolvers.resolve(&url).await
     }
 
     /// Creates a [`Downloader`] with default configuration.
@@ -353,7 +1116,8 @@ impl Downloader {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(())` on success, or an error with the last failure reason.
+    /// Returns a [`DownloadReport`] on success, or an error with the last
+    /// failure reason.
     ///
     /// # Examples
     ///
@@ -363,10 +1127,11 @@ impl Downloader {
     ///
     /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     /// let downloader = Downloader::new_default();
-    /// downloader.download_file(
+    /// let report = downloader.download_file(
     ///     "https://example.com/file.zip",
     ///     PathBuf::from("downloads/file.zip")
     /// ).await?;
+    /// println!("{} bytes at {:.0} B/s", report.bytes, report.avg_speed);
     /// # Ok(())
     /// # }
     /// ```
@@ -374,107 +1139,429 @@ impl Downloader {
         &self,
         url: &str,
         output_path: PathBuf,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<DownloadReport, Box<dyn std::error::Error + Send + Sync>> {
         log::info!(
             "Downloading single file: {} -> {}",
             url,
             output_path.display()
         );
-        let mut last_error = None;
+        #[cfg(feature = "history")]
+        let started = std::time::Instant::now();
+        let tracker = self.events.track(url, &output_path);
 
-        for attempt in 0..self.config.retries {
-            if attempt > 0 {
-                let delay = 2u64.pow(attempt as u32);
-                log::warn!(
-                    "Retrying {} (attempt {}/{}), waiting {}s",
-                    url,
-                    attempt + 1,
-                    self.config.retries,
-                    delay
-                );
-                tokio::time::sleep(Duration::from_secs(delay)).await;
+        let result = match self.plan(url).await {
+            Ok(plan) => {
+                self.download_with_retries(&plan.url, &output_path, &plan.headers, &tracker)
+                    .await
             }
+            Err(e) => Err(e.into()),
+        };
+        #[cfg(feature = "extract")]
+        let result = match result {
+            Ok(report) if self.config.extract => {
+                extract::extract_download(&output_path, self.config.delete_archive)
+                    .await
+                    .map(|_| report)
+                    .map_err(|e| format!("Extraction failed: {}", e).into())
+            }
+            result => result,
+        };
+        tracker.finish(result.as_ref().map_err(|e| &**e));
 
-            match self.try_download_single(url, &output_path).await {
-                Ok(_) => {
-                    log::info!("Download successful: {}", url);
-                    return Ok(());
-                }
-                Err(e) => {
-                    log::error!("Attempt {} failed for {}: {}", attempt + 1, url, e);
-                    last_error = Some(e);
-
-                    if attempt == 0
-                        && output_path.exists()
-                        && let Ok(meta) = tokio::fs::metadata(&output_path).await
-                        && let Ok(head) = self.client.head(url).send().await
-                        && let Some(len) = head.headers().get(reqwest::header::CONTENT_LENGTH)
-                        && let Ok(total) = len.to_str().unwrap_or("0").parse::<u64>()
-                        && meta.len() == total
-                    {
-                        log::info!("File already complete, skipping: {}", url);
-                        return Ok(());
-                    }
-                }
+        let error = result.as_ref().err().map(|e| e.to_string());
+        let hook = match &error {
+            None => self.config.on_complete.as_ref(),
+            Some(_) => self.config.on_error.as_ref(),
+        };
+        if hook.is_some() || self.config.notify_webhook.is_some() {
+            let report = result.as_ref().ok();
+            let event = hooks::HookEvent {
+                url,
+                output: &output_path,
+                bytes: report.map_or(0, |r| r.bytes),
+                duration: report.map_or(Duration::ZERO, |r| r.duration),
+                error: error.as_deref(),
+            };
+            if let Some(hook) = hook {
+                hook.run(&event).await;
+            }
+            if let Some(webhook) = &self.config.notify_webhook {
+                webhook.send(&*self.transport, &event).await;
             }
         }
 
-        Err(last_error.unwrap_or_else(|| "Unknown error".into()))
+        #[cfg(feature = "history")]
+        if let Some(history) = &self.history {
+            let outcome = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+            let checksum = result.as_ref().ok().and_then(|r| r.checksum.clone());
+            let record =
+                history::HistoryRecord::finished(url, &output_path, started, checksum, &outcome)
+                    .await;
+            history.record_async(record).await;
+        }
+
+        result
     }
 
-    /// Internal method for single download attempt.
+    /// Streams a download into `writer` instead of a file, returning the
+    /// number of bytes received.
     ///
-    /// Creates progress bar and delegates to [`download::download_file`].
-    /// Handles notification on completion if enabled.
-    async fn try_download_single(
+    /// Useful to send a file on to a socket, an encryption layer or an
+    /// archive without touching the filesystem. Host overrides, rate
+    /// limits, the type filter and [`DownloadConfig::decompress`] apply as
+    /// usual, but the download always runs as a single request, see
+    /// [`download::download_to_writer`]. Failed attempts are retried as
+    /// long as nothing was written yet. Pass `&mut writer` to keep using
+    /// it afterwards; it is shut down once the download is complete.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let mut body = Vec::new();
+    /// downloader
+    ///     .download_to_writer("https://example.com/notes.txt", &mut body)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_to_writer<W>(
         &self,
         url: &str,
-        output_path: &PathBuf,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use download::DownloadOptions;
-
-        let mp = Arc::new(MultiProgress::new());
-        let pb = progress::create_progress_bar(
-            &mp,
-            &self.config.template,
-            &self.config.msg_template,
-            &self.config.chars,
-            url,
-            output_path.to_str().unwrap_or("file"),
-        );
+        mut writer: W,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        let plan = self.plan(url).await?;
+        let url = plan.url.as_str();
+        let host = self.config.for_url(url, &plan.headers);
+        let mp = self.multi_progress();
+        let pb = self.config.progress_bar(&mp, url, url);
+        let span = tracing::info_span!("download", url, attempt = tracing::field::Empty);
 
-        let opts = DownloadOptions {
-            client: &self.client,
-            url,
-            output: output_path,
-            pb: &pb,
-            resume: self.config.continue_download,
-            workers: self.config.workers,
-            buffer_size: self.config.buffer_size,
-            min_parallel_size: self.config.min_parallel_size,
-        };
+        let limiter = self
+            .limits
+            .download(url, host.rate_limit, &self.config.schedule);
 
-        let result = download::download_file(opts).await;
+        let mut attempt = 0;
+        loop {
+            span.record("attempt", attempt + 1);
+            let opts = download::WriterOptions {
+                transport: &*self.transport,
+                url,
+                pb: &pb,
+                headers: &host.headers,
+                payload: self.config.payload.as_ref(),
+                limiter: &limiter,
+                type_filter: &self.config.type_filter,
+                decompress: self.config.decompress,
+            };
+            let result = download::download_to_writer(opts, &mut writer)
+                .instrument(span.clone())
+                .await;
+            attempt += 1;
+            let e = match result {
+                Ok(size) => return Ok(size),
+                Err(e) => e,
+            };
+            // Bytes already written cannot be taken back
+            let delay = match self.config.retry_delay(&*e, attempt, host.retries) {
+                Some(delay) if pb.position() == 0 => delay,
+                _ => return Err(e),
+            };
+            log::warn!(
+                "Retrying {} (attempt {}/{}), waiting {}s: {}",
+                url,
+                attempt + 1,
+                host.retries,
+                delay.as_secs_f64(),
+                e
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
 
-        #[cfg(feature = "notify")]
-        if self.config.notify {
-            use notify_rust::Notification;
-            match &result {
-                Ok(_) => {
-                    Notification::new()
-                        .summary("Download Complete")
-                        .body(&format!("Finished: {}", output_path.display()))
-                        .show()
-                        .ok();
+    /// Downloads a small file into memory, failing once it grows past
+    /// `limit` bytes.
+    ///
+    /// Goes through [`download_to_writer`](Self::download_to_writer), so
+    /// retries, progress and the checks of the config apply; with
+    /// [`DownloadConfig::decompress`] the limit counts decompressed bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let manifest = downloader
+    ///     .download_bytes("https://example.com/manifest.json", 1024 * 1024)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_bytes(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>> {
+        let mut body = download::CappedBuffer::new(limit);
+        self.download_to_writer(url, &mut body).await?;
+        Ok(body.into_inner().into())
+    }
+
+    /// Like [`download_bytes`](Self::download_bytes), returning a `Vec`.
+    pub async fn download_to_vec(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut body = download::CappedBuffer::new(limit);
+        self.download_to_writer(url, &mut body).await?;
+        Ok(body.into_inner())
+    }
+
+    /// Downloads `url` into `name` of a [`storage::Storage`] instead of a
+    /// local path.
+    ///
+    /// Goes through [`download_to_writer`](Self::download_to_writer), so
+    /// retries, progress and the checks of the config apply. What a failed
+    /// download wrote is removed from the storage again.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::storage::Memory;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let storage = Memory::default();
+    /// downloader
+    ///     .download_to_storage("https://example.com/notes.txt", &storage, "notes.txt")
+    ///     .await?;
+    /// let notes = storage.get("notes.txt");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_to_storage(
+        &self,
+        url: &str,
+        storage: &dyn storage::Storage,
+        name: &str,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let writer = storage.create(name).await?;
+        let result = self.download_to_writer(url, writer).await;
+        if result.is_err()
+            && let Err(e) = storage.remove(name).await
+        {
+            log::warn!("Failed to remove {} from storage: {}", name, e);
+        }
+        result
+    }
+
+    /// Streams the body of a download chunk by chunk, to process it while
+    /// it arrives.
+    ///
+    /// The URL is rewritten and resolved first, and host overrides, rate
+    /// limits and the type filter apply. A dropped connection is retried
+    /// from the byte it stopped at, unless [`DownloadConfig::payload`] is
+    /// set, see [`download::byte_stream`]. The bytes are passed on as
+    /// received, without decompression or checksum verification.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use futures::StreamExt;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let mut stream = std::pin::pin!(downloader.stream("https://example.com/log.txt"));
+    /// let mut lines = 0;
+    /// while let Some(chunk) = stream.next().await {
+    ///     lines += chunk?.iter().filter(|&&b| b == b'\n').count();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream<'a>(
+        &'a self,
+        url: &str,
+    ) -> impl futures::Stream<Item = Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>>>
+    + use<'a> {
+        use futures::StreamExt;
+
+        let url = url.to_string();
+        futures::stream::once(async move {
+            let plan = self.plan(&url).await?;
+            let host = self.config.for_url(&plan.url, &plan.headers);
+            Ok(download::StreamOptions {
+                transport: self.transport.clone(),
+                limiter: self
+                    .limits
+                    .download(&plan.url, host.rate_limit, &self.config.schedule),
+                url: plan.url,
+                headers: host.headers,
+                payload: self.config.payload.clone(),
+                type_filter: self.config.type_filter.clone(),
+                retries: host.retries,
+                retry_policy: self.config.retry_policy.clone(),
+            })
+        })
+        .flat_map(|opts| match opts {
+            Ok(opts) => download::byte_stream(opts).left_stream(),
+            Err(e) => futures::stream::once(async { Err(e) }).right_stream(),
+        })
+    }
+
+    async fn download_with_retries(
+        &self,
+        url: &str,
+        output_path: &PathBuf,
+        headers: &[(String, String)],
+        tracker: &events::Tracker,
+    ) -> Result<DownloadReport, Box<dyn std::error::Error + Send + Sync>> {
+        let host = self.config.for_url(url, headers);
+        let started = std::time::Instant::now();
+        let span = tracing::info_span!(
+            "download",
+            url,
+            output = %output_path.display(),
+            attempt = tracing::field::Empty
+        );
+
+        let mut attempt = 0;
+        loop {
+            span.record("attempt", attempt + 1);
+            let e = match self
+                .try_download_single(url, output_path, &host, tracker)
+                .instrument(span.clone())
+                .await
+            {
+                Ok(report) => {
+                    log::info!("Download successful: {}", url);
+                    return Ok(DownloadReport {
+                        attempts: attempt + 1,
+                        ..report
+                    });
+                }
+                Err(e) => e,
+            };
+            attempt += 1;
+            log::error!("Attempt {} failed for {}: {}", attempt, url, e);
+            if control::is_stopping() {
+                return Err(e);
+            }
+
+            if attempt == 1
+                && self.config.payload.is_none()
+                && !e.is::<checksum::Mismatch>()
+                && output_path.exists()
+                && let Ok(meta) = tokio::fs::metadata(output_path).await
+                && let Ok(request) = transport::request(reqwest::Method::HEAD, url, &host.headers)
+                && let Ok(head) = self.transport.execute(request).await
+                && let Some(len) = head.headers().get(reqwest::header::CONTENT_LENGTH)
+                && let Ok(total) = len.to_str().unwrap_or("0").parse::<u64>()
+                && meta.len() == total
+            {
+                log::info!("File already complete, skipping: {}", url);
+                return Ok(DownloadReport {
+                    attempts: attempt,
+                    ..DownloadReport::new(0, started.elapsed(), total, None)
+                });
+            }
+
+            let Some(delay) = self.config.retry_delay(&*e, attempt, host.retries) else {
+                return Err(e);
+            };
+            log::warn!(
+                "Retrying {} (attempt {}/{}), waiting {}s",
+                url,
+                attempt + 1,
+                host.retries,
+                delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Internal method for single download attempt.
+    ///
+    /// Creates progress bar and delegates to [`download::download_file`].
+    /// Handles notification on completion if enabled.
+    async fn try_download_single(
+        &self,
+        url: &str,
+        output_path: &PathBuf,
+        host: &HostOverrides,
+        tracker: &events::Tracker,
+    ) -> Result<DownloadReport, Box<dyn std::error::Error + Send + Sync>> {
+        use download::DownloadOptions;
+
+        let mp = Arc::new(self.multi_progress());
+        let pb = self
+            .config
+            .progress_bar(&mp, url, output_path.to_str().unwrap_or("file"));
+        tracker.watch(&pb);
+
+        let opts = DownloadOptions {
+            transport: &self.transport,
+            url,
+            output: output_path,
+            pb: &pb,
+            resume: self.config.continue_download,
+            workers: host.workers,
+            buffer_size: self.config.buffer_size,
+            min_parallel_size: self.config.min_parallel_size,
+            headers: &host.headers,
+            payload: self.config.payload.as_ref(),
+            checksum: None,
+            limiter: self
+                .limits
+                .download(url, host.rate_limit, &self.config.schedule),
+            type_filter: &self.config.type_filter,
+            provenance: self.config.provenance,
+            decompress: self.config.decompress,
+            pipe: self.config.pipe.as_ref(),
+            tee: self.config.tee,
+            connections: None,
+        };
+
+        let started = std::time::Instant::now();
+        let result = download::download_file(opts).await;
+
+        if !self.notifiers.is_empty() {
+            let notification = match &result {
+                Ok(report)
+                    if !self
+                        .config
+                        .notify_threshold
+                        .reached(report.bytes, started.elapsed()) =>
+                {
+                    return result;
                 }
+                Ok(report) => self.config.notify_messages.completed(
+                    output_path,
+                    report.bytes,
+                    started.elapsed(),
+                ),
                 Err(e) => {
-                    Notification::new()
-                        .summary("Download Failed")
-                        .body(&format!("{}: {}", output_path.display(), e))
-                        .show()
-                        .ok();
+                    let bytes = std::fs::metadata(output_path).map_or(0, |meta| meta.len());
+                    self.config.notify_messages.failed(
+                        output_path,
+                        bytes,
+                        started.elapsed(),
+                        &e.to_string(),
+                    )
                 }
-            }
+            };
+            self.notify(notification).await;
         }
 
         result
@@ -518,99 +1605,137 @@ impl Downloader {
         &self,
         downloads: Vec<(&str, PathBuf)>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use download::DownloadOptions;
+        let entries = downloads
+            .into_iter()
+            .map(|(url, output)| DownloadEntry::new(url, output))
+            .collect();
+        self.download_entries(entries).await
+    }
 
+    /// Downloads manifest entries in parallel, honouring per-entry headers
+    /// and verifying checksums when present.
+    ///
+    /// This is what [`Downloader::download_multiple`] and
+    /// [`Downloader::download_from_file`] use under the hood.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::{DownloadEntry, Downloader};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let mut entry = DownloadEntry::new("https://example.com/a.zip", "a.zip");
+    /// entry.headers.push(("Authorization".into(), "Bearer token".into()));
+    /// downloader.download_entries(vec![entry]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_entries(
+        &self,
+        downloads: Vec<DownloadEntry>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if downloads.is_empty() {
             log::warn!("No downloads to process");
             return Ok(());
         }
+        if self.config.preflight {
+            return self.download_checked(downloads).await;
+        }
 
-        log::info!("Starting batch download: {} files", downloads.len());
-        let mp = Arc::new(MultiProgress::new());
-
-        let max_concurrent = self.config.max_concurrent_files.unwrap_or_else(|| {
-            let calculated = (16 / std::cmp::max(1, self.config.workers)).clamp(1, 8);
-            log::debug!("Auto-calculated max_concurrent_files: {}", calculated);
-            calculated
-        });
-
-        let semaphore = Arc::new(Semaphore::new(max_concurrent));
-        let (tx, mut rx) = mpsc::unbounded_channel::<Result<(), String>>();
-
-        let mut tasks = FuturesUnordered::new();
-        let total = downloads.len();
-        let mut errors = Vec::new();
-
-        for (url, output_path) in downloads {
-            let sem = semaphore.clone();
-            let client = self.client.clone();
-            let mp = mp.clone();
-            let config = self.config.clone();
-            let tx = tx.clone();
-            let url_owned = url.to_string();
-
-            let task = tokio::spawn(async move {
-                let _permit = sem.acquire().await.unwrap();
-
-                let pb = progress::create_progress_bar(
-                    &mp,
-                    &config.template,
-                    &config.msg_template,
-                    &config.chars,
-                    &url_owned,
-                    &output_path.to_string_lossy(),
-                );
-
-                let opts = DownloadOptions {
-                    client: &client,
-                    url: &url_owned,
-                    output: &output_path,
-                    pb: &pb,
-                    resume: config.continue_download,
-                    workers: config.workers,
-                    buffer_size: config.buffer_size,
-                    min_parallel_size: config.min_parallel_size,
-                };
+        self.download_stream(futures::stream::iter(downloads)).await
+    }
 
-                let result = download::download_file(opts).await;
+    /// Checks every URL with [`Downloader::preflight`] and downloads the
+    /// batch in the order it suggests, unless the disk is too small.
+    async fn download_checked(
+        &self,
+        downloads: Vec<DownloadEntry>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        log::info!("Checking {} URL(s) before starting", downloads.len());
+        let mut dedup = file_parser::Dedup::new();
+        let downloads = downloads.into_iter().filter_map(|entry| dedup.check(entry));
+        let preflight = self.preflight(downloads.collect()).await;
+        log::info!("Preflight: {}", preflight.summary());
+        for (entry, reason) in &preflight.dead {
+            log::warn!("{}: {}", entry.url, reason);
+        }
+        if let Some((dir, needed, free)) = preflight.lacking_space().into_iter().next() {
+            return Err(format!(
+                "Not enough disk space in {}: {} needed, {} free",
+                dir.display(),
+                units::format_bytes(needed),
+                units::format_bytes(free)
+            )
+            .into());
+        }
 
-                match result {
-                    Ok(_) => {
-                        pb.finish_with_message(format!("✓ {}", output_path.display()));
-                        let _ = tx.send(Ok(()));
-                    }
-                    Err(e) => {
-                        let error_msg = format!("✗ {}: {}", output_path.display(), e);
-                        pb.finish_with_message(error_msg);
-                        let _ = tx.send(Err(format!("{}: {}", url_owned, e)));
-                    }
-                }
-            });
+        let queue = queue::DownloadQueue::new(self.max_concurrent_files());
+        preflight.enqueue(&queue);
+        queue.close();
+        self.download_queue(&queue).await
+    }
 
-            tasks.push(task);
-        }
+    /// Downloads entries as they arrive from an async stream.
+    ///
+    /// Each entry starts as soon as it is received and a download slot is
+    /// free, so producers such as stdin don't have to reach EOF first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::file_parser::entry_stream;
+    /// use tokio::io::BufReader;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let entries = entry_stream(BufReader::new(tokio::io::stdin()), None);
+    /// downloader.download_stream(entries).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_stream<S>(
+        &self,
+        downloads: S,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        S: Stream<Item = DownloadEntry>,
+    {
+        let queue = queue::DownloadQueue::new(self.max_concurrent_files());
+        let ((), result) = tokio::join!(Self::feed(&queue, downloads), self.download_queue(&queue));
+        result
+    }
 
-        drop(tx);
+    /// Processes `queue` like [`download_stream`](Self::download_stream) until
+    /// it is closed and drained, then reports the batch.
+    ///
+    /// Unlike [`run_queue`](Self::run_queue) this prints the summary, writes
+    /// the failed-downloads file and returns an error if any entry failed.
+    /// Useful when something else, such as an interactive interface, keeps
+    /// adding to or reordering the queue while it runs.
+    pub async fn download_queue(
+        &self,
+        queue: &queue::DownloadQueue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let started = std::time::Instant::now();
+        self.run_queue(queue).await;
+        let outcomes = Self::outcomes(queue);
+        let total = outcomes.len();
 
-        while let Some(result) = tasks.next().await {
-            if let Err(e) = result {
-                log::error!("Task panicked: {}", e);
-                errors.push(format!("Task panicked: {}", e));
-            }
+        self.report_batch(&outcomes, started.elapsed()).await;
 
-            while let Ok(msg) = rx.try_recv() {
-                if let Err(e) = msg {
-                    log::error!("Download failed: {}", e);
-                    errors.push(e);
-                }
+        let mut errors = Vec::new();
+        let mut failed = Vec::new();
+        for outcome in outcomes {
+            if let Err(e) = outcome.result {
+                errors.push(format!("{}: {}", outcome.entry.url, e));
+                failed.push(outcome.entry);
             }
         }
 
-        while let Some(msg) = rx.recv().await {
-            if let Err(e) = msg {
-                log::error!("Download failed: {}", e);
-                errors.push(e);
-            }
+        if let Some(path) = &self.config.failed_file {
+            write_failed(path, failed).await;
         }
 
         if !errors.is_empty() {
@@ -619,12 +1744,11 @@ impl Downloader {
                 errors.len(),
                 total
             );
-            return Err(format!(
-                "{}/{} downloads failed:\n{}",
-                errors.len(),
+            return Err(BatchError {
+                failed: errors.len(),
                 total,
-                errors.join("\n")
-            )
+                errors,
+            }
             .into());
         }
 
@@ -636,10 +1760,610 @@ impl Downloader {
         Ok(())
     }
 
-    /// Downloads files listed in a text file.
+    /// Notification for a finished batch; desktop actions open the file,
+    /// or the folder holding all of them.
+    fn batch_notification(
+        &self,
+        outcomes: &[BatchOutcome],
+        elapsed: Duration,
+    ) -> notifier::Notification {
+        let messages = &self.config.notify_messages;
+        let done: Vec<&BatchOutcome> = outcomes.iter().filter(|o| o.result.is_ok()).collect();
+        let failed = outcomes.len() - done.len();
+        let bytes = done.iter().map(|outcome| outcome.bytes).sum();
+        match (outcomes, done.as_slice()) {
+            ([outcome], []) => messages.failed(
+                &outcome.entry.output,
+                outcome.bytes,
+                outcome.duration,
+                outcome.result.as_ref().err().map_or("", String::as_str),
+            ),
+            ([outcome], [_]) => {
+                messages.completed(&outcome.entry.output, outcome.bytes, outcome.duration)
+            }
+            (_, []) => {
+                let dir = utils::common_dir(outcomes.iter().map(|outcome| &*outcome.entry.output));
+                let mut vars = notifier::variables(&dir, 0, elapsed);
+                vars.insert("failed", failed.to_string().into());
+                notifier::Notification::failed(notifier::fill_plural(
+                    "notify.batch_failed",
+                    failed,
+                    &vars,
+                ))
+            }
+            _ => {
+                let dir = utils::common_dir(done.iter().map(|outcome| &*outcome.entry.output));
+                let mut vars = notifier::variables(&dir, bytes, elapsed);
+                vars.insert("count", done.len().to_string().into());
+                vars.insert("failed", failed.to_string().into());
+                let key = if failed > 0 {
+                    "notify.batch_partial"
+                } else {
+                    "notify.batch_complete"
+                };
+                let body = notifier::fill_plural(key, done.len(), &vars);
+                notifier::Notification::completed(body, dir)
+            }
+        }
+    }
+
+    fn ring_bell(&self) {
+        if self.config.bell {
+            bell::ring();
+        }
+        if let Some(sound) = &self.config.bell_sound
+            && let Err(e) = bell::play(sound)
+        {
+            log::warn!("Cannot play {}: {}", sound.display(), e);
+        }
+    }
+
+    /// Prints the summary table and writes the JSON report, as configured.
+    async fn report_batch(&self, outcomes: &[BatchOutcome], elapsed: Duration) {
+        if let Some(output) = self.config.write_checksums {
+            let files: Vec<(PathBuf, Option<checksum::Checksum>)> = outcomes
+                .iter()
+                .filter(|outcome| outcome.result.is_ok() && outcome.entry.output.is_file())
+                .map(|outcome| {
+                    // The digest taken while downloading saves hashing the file again
+                    let digest = outcome
+                        .report
+                        .as_ref()
+                        .and_then(|report| report.checksum.as_deref())
+                        .and_then(|digest| checksum::Checksum::parse(digest).ok());
+                    let checksum = digest.or_else(|| outcome.entry.checksum.clone());
+                    (outcome.entry.output.clone(), checksum)
+                })
+                .collect();
+            if let Err(e) = checksum::write_checksums(&files, output).await {
+                log::error!("Failed to write checksums: {}", e);
+            }
+        }
+        if !outcomes.is_empty() {
+            self.ring_bell();
+        }
+        let bytes = outcomes.iter().map(|outcome| outcome.bytes).sum();
+        let failed = outcomes.iter().any(|outcome| outcome.result.is_err());
+        if !self.notifiers.is_empty()
+            && !outcomes.is_empty()
+            && (failed || self.config.notify_threshold.reached(bytes, elapsed))
+        {
+            self.notify(self.batch_notification(outcomes, elapsed))
+                .await;
+        }
+        #[cfg(feature = "email")]
+        let email = self.config.email.as_ref();
+        #[cfg(not(feature = "email"))]
+        let email: Option<&()> = None;
+        if outcomes.is_empty()
+            || (!self.config.summary && self.config.report_json.is_none() && email.is_none())
+        {
+            return;
+        }
+        let files: Vec<summary::FileSummary> = outcomes
+            .iter()
+            .map(|outcome| summary::FileSummary {
+                url: outcome.entry.url.clone(),
+                output: outcome.entry.output.clone(),
+                bytes: outcome.bytes,
+                duration: outcome.duration,
+                attempts: outcome.attempts,
+                checksum: outcome
+                    .report
+                    .as_ref()
+                    .and_then(|report| report.checksum.clone())
+                    .or_else(|| outcome.entry.checksum.as_ref().map(ToString::to_string)),
+                error: outcome.result.clone().err(),
+            })
+            .collect();
+
+        if self.config.summary {
+            eprint!("\n{}", summary::render(&files, elapsed));
+        }
+        #[cfg(feature = "email")]
+        if let Some(mailer) = email {
+            let failed = files.iter().filter(|file| file.error.is_some()).count();
+            let subject = match failed {
+                0 => localization::plural("batch.finished", files.len(), &[]),
+                _ => localization::plural("batch.failed", failed, &[("total", files.len())]),
+            };
+            let subject = format!("dwrs: {}", subject);
+            match mailer
+                .send(&subject, &summary::render(&files, elapsed))
+                .await
+            {
+                Ok(()) => log::info!("Mailed the batch summary"),
+                Err(e) => log::error!("Cannot mail the batch summary: {}", e),
+            }
+        }
+        if let Some(path) = &self.config.report_json {
+            let report = report::to_json(&files, elapsed);
+            if let Err(e) = report::write(path, &report).await {
+                log::error!("Failed to write report {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Fetches the status and headers of `url` without downloading its body.
+    ///
+    /// Uses the downloader's client (proxy included), checks the URL from
+    /// [`Downloader::plan`] and merges in the per-host headers from
+    /// [`DownloadConfig::hosts`].
+    pub async fn check_url(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<spider::UrlStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let plan = self.plan(url).await?;
+        let headers = self
+            .config
+            .for_url(&plan.url, &plan.request_headers(headers))
+            .headers;
+        spider::check(&*self.transport, &plan.url, &headers).await
+    }
+
+    /// Measures the throughput of `url` with the worker count and buffer
+    /// size of `case` for at most `limit`, see [`bench`].
+    ///
+    /// Downloads into a temporary file through the downloader's transport
+    /// and per-host headers like a download, but ignores its rate limits.
+    pub async fn bench(
+        &self,
+        url: &str,
+        case: bench::BenchCase,
+        limit: Duration,
+    ) -> Result<bench::BenchResult, Box<dyn std::error::Error + Send + Sync>> {
+        let plan = self.plan(url).await?;
+        let headers = self
+            .config
+            .for_url(&plan.url, &plan.request_headers(&[]))
+            .headers;
+        bench::measure(&self.transport, &plan.url, &headers, case, limit).await
+    }
+
+    /// Checks every entry with [`Downloader::check_url`] instead of downloading it.
     ///
-    /// File format: one URL per line, optionally followed by output filename.
-    /// Lines starting with `#` are treated as comments.
+    /// Results come back in input order; per-host headers from
+    /// [`DownloadConfig::hosts`] are sent along with the entry headers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::{DownloadEntry, Downloader};
+    ///
+    /// # async fn example() {
+    /// let downloader = Downloader::new_default();
+    /// let entries = vec![DownloadEntry::new("https://example.com/a.zip", "a.zip")];
+    /// for (entry, result) in downloader.spider(futures::stream::iter(entries)).await {
+    ///     match result {
+    ///         Ok(status) if status.is_alive() => println!("ok   {}", entry.url),
+    ///         Ok(status) => println!("{} {}", status.status, entry.url),
+    ///         Err(e) => println!("dead {}: {}", entry.url, e),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn spider<S>(
+        &self,
+        entries: S,
+    ) -> Vec<(DownloadEntry, Result<spider::UrlStatus, String>)>
+    where
+        S: Stream<Item = DownloadEntry>,
+    {
+        entries
+            .map(|entry| async move {
+                let result = self
+                    .check_url(&entry.url, &entry.headers)
+                    .await
+                    .map_err(|e| e.to_string());
+                (entry, result)
+            })
+            .buffered(self.max_concurrent_files().max(8))
+            .collect()
+            .await
+    }
+
+    /// Checks every entry with [`Downloader::spider`] and sorts the results
+    /// for downloading, see [`preflight`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::{DownloadEntry, Downloader};
+    ///
+    /// # async fn example() {
+    /// let downloader = Downloader::new_default();
+    /// let entries = vec![DownloadEntry::new("https://example.com/a.iso", "a.iso")];
+    /// let preflight = downloader.preflight(entries).await;
+    /// println!("{}", preflight.summary());
+    /// # }
+    /// ```
+    pub async fn preflight(&self, entries: Vec<DownloadEntry>) -> preflight::Preflight {
+        preflight::Preflight::new(self.spider(futures::stream::iter(entries)).await)
+    }
+
+    /// Runs a batch through a [`DownloadQueue`](queue::DownloadQueue) and
+    /// reports the outcome of every entry.
+    async fn run_batch<S>(&self, downloads: S) -> Vec<BatchOutcome>
+    where
+        S: Stream<Item = DownloadEntry>,
+    {
+        let queue = queue::DownloadQueue::new(self.max_concurrent_files());
+        tokio::join!(Self::feed(&queue, downloads), self.run_queue(&queue));
+        Self::outcomes(&queue)
+    }
+
+    /// Pushes every entry of `downloads` to `queue`, then closes it.
+    ///
+    /// URLs are normalized and repeated ones skipped with a warning.
+    async fn feed<S>(queue: &queue::DownloadQueue, downloads: S)
+    where
+        S: Stream<Item = DownloadEntry>,
+    {
+        log::info!("Starting batch download");
+        let mut downloads = std::pin::pin!(downloads);
+        let mut dedup = file_parser::Dedup::new();
+        while let Some(entry) = downloads.next().await {
+            if let Some(entry) = dedup.check(entry) {
+                queue.push(entry, 0);
+            }
+        }
+        queue.close();
+    }
+
+    /// Takes the finished items of `queue` as batch outcomes.
+    fn outcomes(queue: &queue::DownloadQueue) -> Vec<BatchOutcome> {
+        let outcomes: Vec<BatchOutcome> = queue
+            .take_finished()
+            .into_iter()
+            .map(|(entry, status)| BatchOutcome {
+                entry,
+                bytes: status.downloaded,
+                duration: status.elapsed,
+                attempts: status.attempts,
+                report: status.report,
+                result: match status.state {
+                    queue::QueueState::Completed => Ok(()),
+                    queue::QueueState::Failed(e) => Err(e),
+                    queue::QueueState::Removed => Err("Cancelled".to_string()),
+                    state => Err(format!("Not completed: {:?}", state)),
+                },
+            })
+            .collect();
+
+        log::info!("Batch finished: {} files processed", outcomes.len());
+        outcomes
+    }
+
+    /// Downloads run at the same time, from the config or derived from the worker count.
+    pub fn max_concurrent_files(&self) -> usize {
+        self.config.max_concurrent_files.unwrap_or_else(|| {
+            let calculated = (16 / std::cmp::max(1, self.config.workers)).clamp(1, 8);
+            log::debug!("Auto-calculated max_concurrent_files: {}", calculated);
+            calculated
+        })
+    }
+
+    /// Processes a [`DownloadQueue`](queue::DownloadQueue) until it is closed and drained.
+    ///
+    /// Items start in priority order whenever one of the queue's active slots
+    /// is free. With [`DownloadConfig::fail_fast`] the first failure aborts
+    /// the whole queue, and once [`DownloadConfig::quota`] is used up the
+    /// queued items are skipped. Their progress and final state can be observed through
+    /// [`DownloadQueue::snapshot`](queue::DownloadQueue::snapshot) while this runs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::{DownloadEntry, Downloader};
+    /// use dwrs::queue::DownloadQueue;
+    ///
+    /// # async fn example() {
+    /// let downloader = Downloader::new_default();
+    /// let queue = DownloadQueue::new(2);
+    /// queue.push(DownloadEntry::new("https://example.com/big.iso", "big.iso"), 0);
+    /// queue.push(DownloadEntry::new("https://example.com/urgent.txt", "urgent.txt"), 10);
+    /// queue.close();
+    ///
+    /// downloader.run_queue(&queue).await;
+    /// for item in queue.snapshot() {
+    ///     println!("{} {:?}", item.url, item.state);
+    /// }
+    /// # }
+    /// ```
+    pub async fn run_queue(&self, queue: &queue::DownloadQueue) {
+        use download::DownloadOptions;
+
+        let mp = Arc::new(self.multi_progress());
+        let _total = queue.expected_size().map(|total| {
+            let pb = progress::create_progress_bar(
+                &mp,
+                &self.config.template,
+                "{output}",
+                &self.config.chars,
+                "",
+                "Total",
+            );
+            preflight::TotalBar::spawn(pb, queue.clone(), total)
+        });
+        let _title = self
+            .config
+            .terminal_title
+            .then(|| title::TitleUpdater::spawn(queue.clone()))
+            .flatten();
+        let _state = self
+            .config
+            .status_file
+            .as_ref()
+            .map(|path| status::StateWriter::spawn(path, queue.clone()));
+        let mut tasks = FuturesUnordered::new();
+
+        while let Some((id, entry)) = queue.next().await {
+            if let Some(quota) = self.config.quota
+                && queue.downloaded() >= quota
+            {
+                let reason = format!("Skipped: quota of {} exceeded", units::format_bytes(quota));
+                queue.finish(id, Err(reason.clone()));
+                let skipped = queue.skip_queued(&reason) + 1;
+                log::warn!(
+                    "Download quota of {} reached, skipped {} download(s)",
+                    units::format_bytes(quota),
+                    skipped
+                );
+                continue;
+            }
+
+            let transport = self.transport.clone();
+            let limits = self.limits.clone();
+            let events = self.events.clone();
+            let mp = mp.clone();
+            let config = self.config.clone();
+            let resolvers = self.resolvers.clone();
+            let queue = queue.clone();
+            let fail_fast = self.config.fail_fast;
+            let hooks = (
+                self.config.on_complete.clone(),
+                self.config.on_error.clone(),
+            );
+            let webhook = self
+                .config
+                .notify_webhook
+                .clone()
+                .map(|webhook| (webhook, self.transport.clone()));
+            let (url, output) = (entry.url.clone(), entry.output.clone());
+
+            #[cfg(feature = "history")]
+            let record = self.history.clone().map(|history| {
+                (
+                    history,
+                    entry.url.clone(),
+                    entry.output.clone(),
+                    std::time::Instant::now(),
+                )
+            });
+
+            let span = tracing::info_span!(
+                "download",
+                id,
+                url = %entry.url,
+                output = %entry.output.display(),
+                attempt = tracing::field::Empty
+            );
+            let download = tokio::spawn({
+                let queue = queue.clone();
+                async move {
+                    let tracker = events.track(&entry.url, &entry.output);
+                    let rewritten = config.rewrite.rewrite(&entry.url).await;
+                    let plan = match resolvers.resolve(&rewritten).await {
+                        Ok(plan) => plan,
+                        Err(e) => {
+                            log::error!("Download failed: {}: {}", entry.url, e);
+                            tracker.fail(&e);
+                            return Err(e);
+                        }
+                    };
+                    let url = plan.url.as_str();
+                    let output_path = &entry.output;
+
+                    let pb = config.progress_bar(&mp, url, &output_path.to_string_lossy());
+                    queue.attach_progress(id, pb.clone());
+                    tracker.watch(&pb);
+                    let host = config.for_url(url, &plan.request_headers(&entry.headers));
+                    let connections = queue.connections(id);
+
+                    let mut attempt = 0;
+                    let result = loop {
+                        let opts = DownloadOptions {
+                            transport: &transport,
+                            url,
+                            output: output_path,
+                            pb: &pb,
+                            resume: config.continue_download,
+                            workers: host.workers,
+                            buffer_size: config.buffer_size,
+                            min_parallel_size: config.min_parallel_size,
+                            headers: &host.headers,
+                            payload: config.payload.as_ref(),
+                            checksum: entry.checksum.as_ref(),
+                            limiter: limits.download(url, host.rate_limit, &config.schedule),
+                            type_filter: &config.type_filter,
+                            provenance: config.provenance,
+                            decompress: config.decompress,
+                            pipe: config.pipe.as_ref(),
+                            tee: config.tee,
+                            connections: Some(&connections),
+                        };
+                        queue.record_attempt(id);
+                        tracing::Span::current().record("attempt", attempt + 1);
+                        let result = download::download_file(opts).await;
+                        attempt += 1;
+                        let Err(e) = &result else {
+                            break result;
+                        };
+                        // Bytes already sent to stdout cannot be taken back
+                        let streamed =
+                            (config.tee || download::is_stdout(output_path)) && pb.position() > 0;
+                        let delay = match config.retry_delay(&**e, attempt, host.retries) {
+                            Some(delay) if !streamed => delay,
+                            _ => break result,
+                        };
+                        log::warn!(
+                            "Retrying {} (attempt {}/{}), waiting {}s",
+                            url,
+                            attempt + 1,
+                            host.retries,
+                            delay.as_secs_f64()
+                        );
+                        tokio::time::sleep(delay).await;
+                    };
+
+                    match result {
+                        Ok(report) => {
+                            #[cfg(feature = "extract")]
+                            if config.extract
+                                && let Err(e) =
+                                    extract::extract_download(output_path, config.delete_archive)
+                                        .await
+                            {
+                                let error_msg = format!(
+                                    "✗ {}: extraction failed: {}",
+                                    output_path.display(),
+                                    e
+                                );
+                                pb.finish_with_message(error_msg);
+                                log::error!("Extraction failed: {}: {}", output_path.display(), e);
+                                let error = format!("Extraction failed: {}", e);
+                                tracker.fail(&error);
+                                return Err(error);
+                            }
+                            let report = DownloadReport {
+                                attempts: attempt,
+                                ..report
+                            };
+                            tracker.finish(Ok(&report));
+                            queue.set_report(id, report);
+                            pb.finish_with_message(format!("✓ {}", output_path.display()));
+                            Ok(())
+                        }
+                        Err(e) if e.is::<download::Rejected>() => {
+                            tracker.finish(Err(&*e));
+                            pb.finish_with_message(format!("⊘ {}: {}", output_path.display(), e));
+                            log::info!("Skipped {}: {}", url, e);
+                            Ok(())
+                        }
+                        Err(e) => {
+                            tracker.finish(Err(&*e));
+                            // Interrupted downloads already marked their bar
+                            if control::is_stopping() {
+                                log::info!("Download stopped: {}", url);
+                            } else {
+                                let error_msg = format!("✗ {}: {}", output_path.display(), e);
+                                pb.finish_with_message(error_msg);
+                                log::error!("Download failed: {}: {}", url, e);
+                            }
+                            Err(e.to_string())
+                        }
+                    }
+                }
+                .instrument(span)
+            });
+
+            queue.attach_task(id, download.abort_handle());
+
+            tasks.push(tokio::spawn(async move {
+                let result = download.await.unwrap_or_else(|e| {
+                    if e.is_cancelled() {
+                        log::info!("Download #{} interrupted", id);
+                        return Err("Interrupted".to_string());
+                    }
+                    log::error!("Task panicked: {}", e);
+                    Err(format!("Task panicked: {}", e))
+                });
+                let outcome = result.clone();
+                queue.finish(id, result);
+
+                if fail_fast
+                    && queue
+                        .status(id)
+                        .is_some_and(|s| matches!(s.state, queue::QueueState::Failed(_)))
+                {
+                    let cancelled = queue.abort();
+                    log::warn!(
+                        "Download #{} failed, cancelled {} remaining download(s)",
+                        id,
+                        cancelled
+                    );
+                }
+
+                let (hook, report) = match &outcome {
+                    // Skipped downloads succeed without a file
+                    Ok(()) => (hooks.0.as_ref(), output.exists()),
+                    Err(_) => (hooks.1.as_ref(), !control::is_stopping()),
+                };
+                if report {
+                    let status = queue.status(id);
+                    let event = hooks::HookEvent {
+                        url: &url,
+                        output: &output,
+                        bytes: status.as_ref().map_or(0, |s| s.downloaded),
+                        duration: status.as_ref().map_or(Duration::ZERO, |s| s.elapsed),
+                        error: outcome.as_ref().err().map(String::as_str),
+                    };
+                    if let Some(hook) = hook {
+                        hook.run(&event).await;
+                    }
+                    if let Some((webhook, transport)) = &webhook {
+                        webhook.send(&**transport, &event).await;
+                    }
+                }
+
+                #[cfg(feature = "history")]
+                if let Some((history, url, output, started)) = record
+                    && let Some(status) = queue.status(id)
+                    && status.state.is_finished()
+                {
+                    // The digest taken of the file, not the one it was expected to have
+                    let checksum = status.report.and_then(|report| report.checksum);
+                    let record = history::HistoryRecord::finished(
+                        &url, &output, started, checksum, &outcome,
+                    )
+                    .await;
+                    history.record_async(record).await;
+                }
+            }));
+
+            while let Some(Some(_)) = tasks.next().now_or_never() {}
+        }
+
+        while tasks.next().await.is_some() {}
+    }
+
+    /// Downloads files listed in a manifest file.
+    ///
+    /// Text format: one URL per line, optionally followed by output filename.
+    /// Lines starting with `#` are treated as comments. Files with a `.json`
+    /// extension are parsed as JSON manifests (see [`file_parser::parse_json`])
+    /// and `.csv` files as CSV manifests (see [`file_parser::parse_csv`]).
     ///
     /// # File Format Example
     ///
@@ -654,7 +2378,7 @@ impl Downloader {
     ///
     /// # Arguments
     ///
-    /// * `file_path` - Path to text file containing URL list
+    /// * `file_path` - Path to manifest file containing URL list
     ///
     /// # Returns
     ///
@@ -678,16 +2402,210 @@ impl Downloader {
         file_path: PathBuf,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         log::info!("Loading URLs from file: {}", file_path.display());
-        let pairs = parse_file(&file_path).await?;
-        log::info!("Loaded {} URLs from file", pairs.len());
+        let entries = parse_manifest(&file_path, &Default::default()).await?;
+        log::info!("Loaded {} URLs from file", entries.len());
+
+        self.download_entries(entries).await
+    }
+
+    /// Recursively mirrors a site subtree, wget `-r` style.
+    ///
+    /// HTML pages reachable from `url` are fetched and scanned for links up to
+    /// [`CrawlOptions::max_depth`](crawler::CrawlOptions::max_depth) levels deep;
+    /// everything else is downloaded through [`Downloader::download_multiple`],
+    /// one at a time and a `Crawl-delay` apart if robots.txt sets one.
+    /// The remote directory structure is rebuilt under
+    /// [`CrawlOptions::output_dir`](crawler::CrawlOptions::output_dir) as `host/path`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::crawler::CrawlOptions;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let opts = CrawlOptions {
+    ///     max_depth: 2,
+    ///     no_parent: true,
+    ///     ..Default::default()
+    /// };
+    /// downloader.mirror("https://example.com/pub/", opts).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn mirror(
+        &self,
+        url: &str,
+        opts: crawler::CrawlOptions,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        log::info!("Mirroring {} (depth={})", url, opts.max_depth);
+        let mut crawler = self.crawler(url, opts);
+        let resources = crawler.crawl(url).await?;
+        let Some(delay) = crawler.crawl_delay().filter(|_| !resources.is_empty()) else {
+            return self.download_entries(resources).await;
+        };
+        // One download at a time, started a crawl delay apart
+        log::info!(
+            "Downloading {} resources {}s apart, as robots.txt asks",
+            resources.len(),
+            delay.as_secs_f64()
+        );
+        let queue = queue::DownloadQueue::new(1);
+        let mut start = std::time::SystemTime::now();
+        for entry in resources {
+            start += delay;
+            queue.push_at(entry, 0, Some(start));
+        }
+        queue.close();
+        self.download_queue(&queue).await
+    }
+
+    /// Crawls the site subtree below `url` without downloading anything.
+    ///
+    /// Returns every found resource with the output path [`Downloader::mirror`]
+    /// would save it to and the page linking to it as `Referer`, so callers
+    /// can filter the list first.
+    pub async fn crawl(
+        &self,
+        url: &str,
+        opts: crawler::CrawlOptions,
+    ) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        self.crawler(url, opts).crawl(url).await
+    }
+
+    fn crawler(&self, url: &str, opts: crawler::CrawlOptions) -> crawler::Crawler<'_> {
+        crawler::Crawler::new(&self.client, opts)
+            .limiter(self.limits.shared(url))
+            .agent(&self.config.user_agent)
+            .referer(self.config.referer.clone())
+    }
+
+    /// Downloads every page listed in a `sitemap.xml`.
+    ///
+    /// Sitemap indexes are followed recursively and the resulting URLs are
+    /// filtered through [`SitemapOptions`](sitemap::SitemapOptions). Outputs
+    /// are laid out as `host/path` in the current directory, like [`Downloader::mirror`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::sitemap::SitemapOptions;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// let opts = SitemapOptions {
+    ///     include: Some(regex::Regex::new("/docs/")?),
+    ///     ..Default::default()
+    /// };
+    /// downloader.download_sitemap("https://example.com/sitemap.xml", &opts).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_sitemap(
+        &self,
+        url: &str,
+        opts: &sitemap::SitemapOptions,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let urls = sitemap::fetch_sitemap(&self.client, url, opts).await?;
+        log::info!("Loaded {} URLs from sitemap {}", urls.len(), url);
 
-        let downloads: Vec<(&str, PathBuf)> = pairs
+        let downloads: Vec<(&str, PathBuf)> = urls
             .iter()
-            .map(|(url, output)| (url.as_str(), PathBuf::from(output)))
+            .filter_map(|u| {
+                let parsed = reqwest::Url::parse(u).ok()?;
+                Some((
+                    u.as_str(),
+                    crawler::local_path(&parsed, std::path::Path::new(".")),
+                ))
+            })
             .collect();
 
         self.download_multiple(downloads).await
     }
+
+    /// Downloads new enclosures from an RSS or Atom feed (podcatcher mode).
+    ///
+    /// Items whose enclosure URL is recorded in the feed state file are
+    /// skipped; successfully downloaded ones are added to it. Output files
+    /// are named after the item titles, numbered when several items share
+    /// one, see [`feed::entries`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dwrs::Downloader;
+    /// use dwrs::feed::FeedOptions;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let downloader = Downloader::new_default();
+    /// downloader
+    ///     .download_feed("https://example.com/podcast.xml", &FeedOptions::default())
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_feed(
+        &self,
+        url: &str,
+        opts: &feed::FeedOptions,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        log::info!("Fetching feed: {}", url);
+        let body = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let items = feed::parse_feed(&body);
+        log::info!("Feed {} lists {} enclosures", url, items.len());
+
+        let state_path = opts
+            .state_file
+            .clone()
+            .unwrap_or_else(feed::FeedState::default_path);
+        let mut state = feed::FeedState::load(&state_path).await;
+
+        let new_items: Vec<_> = items
+            .into_iter()
+            .filter(|item| !state.contains(&item.url))
+            .collect();
+
+        if new_items.is_empty() {
+            log::info!("No new episodes in {}", url);
+            return Ok(());
+        }
+
+        let downloads = feed::entries(&new_items, &opts.output_dir);
+
+        let total = downloads.len();
+        let started = std::time::Instant::now();
+        let outcomes = self.run_batch(futures::stream::iter(downloads)).await;
+        self.report_batch(&outcomes, started.elapsed()).await;
+
+        let mut errors = Vec::new();
+        for outcome in outcomes {
+            match outcome.result {
+                Ok(()) => state.insert(outcome.entry.url),
+                Err(e) => errors.push(format!("{}: {}", outcome.entry.url, e)),
+            }
+        }
+        state.save().await?;
+
+        if !errors.is_empty() {
+            return Err(BatchError {
+                failed: errors.len(),
+                total,
+                errors,
+            }
+            .into());
+        }
+        Ok(())
+    }
 }
 
 /// Initializes the library logging system.
@@ -702,7 +2620,21 @@ impl Downloader {
 /// dwrs::init();
 /// ```
 pub fn init() {
-    let _ = env_logger::try_init();
+    init_with_level(log::LevelFilter::Error);
+}
+
+/// Like [`init`], but logs everything up to `level`.
+///
+/// `RUST_LOG` still takes precedence when set.
+///
+/// # Examples
+///
+/// ```
+/// dwrs::init_with_level(log::LevelFilter::Debug);
+/// ```
+pub fn init_with_level(level: log::LevelFilter) {
+    logging::init(level);
+    localization::init();
     log::info!("dwrs initialized");
 }
 
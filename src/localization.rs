@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Problems [`UserLocales::load`] ran into, kept for [`load_warnings`]
+/// because it runs when the first text is translated, which may be
+/// before the logger is set up
+static LOAD_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Locales bundled from `locales/app.yml` and those added by the files in
+/// [`user_dir`]
+pub fn available_locales() -> Vec<&'static str> {
+    rust_i18n::available_locales!()
+}
+
+/// Where translation files are picked up from at startup:
+/// `<config dir>/dwrs/locales`
+pub fn user_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("dwrs").join("locales"))
+}
+
+/// Translations from files in [`user_dir`], used before the bundled ones
+/// so they can fix a message or add a whole language.
+///
+/// Files are YAML, JSON or TOML. Either they hold every language like the
+/// bundled `app.yml` (`_version: 2`, then `key: {de: text}`), or they are
+/// named after their language, such as `de.yml`, and map keys to texts,
+/// nested or dotted: `notify: {complete: Fertig}`. Files that cannot be
+/// read are skipped with a warning.
+#[derive(Debug, Default)]
+pub struct UserLocales {
+    /// Text by key by locale
+    translations: HashMap<String, HashMap<String, String>>,
+}
+
+impl UserLocales {
+    /// Reads the files in [`user_dir`], if there are any, keeping the
+    /// problems for [`load_warnings`].
+    pub fn load() -> Self {
+        let Some(dir) = user_dir().filter(|dir| dir.is_dir()) else {
+            return Self::default();
+        };
+        let (locales, warnings) = Self::load_dir(&dir);
+        LOAD_WARNINGS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(warnings);
+        locales
+    }
+
+    /// Reads every translation file in `dir`, in name order so a later
+    /// file wins over an earlier one, and says why files were skipped.
+    pub fn load_dir(dir: &Path) -> (Self, Vec<String>) {
+        let mut locales = Self::default();
+        let mut warnings = Vec::new();
+        let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(e) => {
+                warnings.push(format!("Cannot read {}: {}", dir.display(), e));
+                return (locales, warnings);
+            }
+        };
+        files.sort();
+        for path in files {
+            let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+                continue;
+            };
+            if !matches!(ext, "yml" | "yaml" | "json" | "toml") {
+                continue;
+            }
+            match locales.add_file(&path, ext) {
+                Ok(()) => log::debug!("Loaded translations from {}", path.display()),
+                Err(e) => warnings.push(format!(
+                    "Ignoring translations in {}: {}",
+                    path.display(),
+                    e
+                )),
+            }
+        }
+        (locales, warnings)
+    }
+
+    fn add_file(&mut self, path: &Path, ext: &str) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let value: serde_json::Value = match ext {
+            "json" => serde_json::from_str(&content).map_err(|e| e.to_string())?,
+            "toml" => toml::from_str(&content).map_err(|e| e.to_string())?,
+            _ => serde_yml::from_str(&content).map_err(|e| e.to_string())?,
+        };
+        let mut texts = Vec::new();
+        flatten("", &value, &mut texts);
+        if value.get("_version").and_then(|v| v.as_u64()) == Some(2) {
+            // `key.locale` as flattened from `key: {locale: text}`
+            for (key, text) in texts {
+                if let Some((key, locale)) = key.rsplit_once('.') {
+                    self.add(locale, key, text);
+                }
+            }
+        } else {
+            let locale = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.rsplit('.').next())
+                .ok_or("the file name does not name a language")?;
+            for (key, text) in texts {
+                self.add(locale, &key, text);
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, locale: &str, key: &str, text: String) {
+        if key == "_version" {
+            return;
+        }
+        self.translations
+            .entry(locale.to_string())
+            .or_default()
+            .insert(key.to_string(), text);
+    }
+}
+
+/// Collects the texts of `value` under dotted keys.
+fn flatten(prefix: &str, value: &serde_json::Value, out: &mut Vec<(String, String)>) {
+    let key = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, value) in map {
+                flatten(&key(name), value, out);
+            }
+        }
+        serde_json::Value::String(text) => out.push((prefix.to_string(), text.clone())),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => {
+            out.push((prefix.to_string(), value.to_string()))
+        }
+        _ => {}
+    }
+}
+
+impl rust_i18n::Backend for UserLocales {
+    fn available_locales(&self) -> Vec<&str> {
+        self.translations.keys().map(String::as_str).collect()
+    }
+
+    fn translate(&self, locale: &str, key: &str) -> Option<&str> {
+        self.translations.get(locale)?.get(key).map(String::as_str)
+    }
+}
+
+/// Why translation files in [`user_dir`] were skipped; each problem is
+/// only returned once.
+///
+/// Loads the translations if nothing was translated yet.
+pub fn load_warnings() -> Vec<String> {
+    available_locales();
+    std::mem::take(&mut *LOAD_WARNINGS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Picks the UI language from the system locale, falling back to English.
+///
+/// `de-AT` falls back to `de` when only the language is translated.
+pub fn init() {
+    let Some(system) = sys_locale::get_locale() else {
+        return;
+    };
+    if let Some(locale) = matching(&system, &available_locales()) {
+        log::debug!("Using locale {}", locale);
+        rust_i18n::set_locale(locale);
+    }
+}
+
+/// Uses `lang` as the UI language instead of the system locale, for
+/// `--lang`, the `lang` config key and `DWRS_LANG`.
+///
+/// Fails if `lang` is not translated, not even its language alone.
+pub fn set_language(lang: &str) -> Result<(), String> {
+    let locale = resolve(lang)?;
+    log::debug!("Using locale {}", locale);
+    rust_i18n::set_locale(locale);
+    Ok(())
+}
+
+/// The bundled locale [`set_language`] would use for `lang`.
+pub fn resolve(lang: &str) -> Result<&'static str, String> {
+    let available = available_locales();
+    matching(lang, &available).ok_or_else(|| {
+        format!(
+            "unknown language '{}' (available: {})",
+            lang,
+            available.join(", ")
+        )
+    })
+}
+
+/// The message of `key` for `count` in the UI language, with `%{count}`
+/// and the `%{name}` patterns of `args` filled in.
+///
+/// Translations give one form per CLDR plural category as `key.one`,
+/// `key.few`, `key.many` and `key.other`; languages without a form for the
+/// category of `count` use `key.other`.
+///
+/// ```
+/// let message = dwrs::localization::plural("batch.failed", 1, &[("total", 3)]);
+/// assert_eq!(message, "1 of 3 downloads failed");
+/// ```
+pub fn plural(key: &str, count: usize, args: &[(&str, usize)]) -> String {
+    plural_in(&rust_i18n::locale(), key, count, args)
+}
+
+fn plural_in(locale: &str, key: &str, count: usize, args: &[(&str, usize)]) -> String {
+    let category = plural_category(locale, count as u64);
+    let mut message = [category, "other"]
+        .into_iter()
+        .find_map(|category| translation(locale, &format!("{key}.{category}")))
+        .unwrap_or_else(|| format!("{locale}.{key}"))
+        .replace("%{count}", &count.to_string());
+    for (name, value) in args {
+        message = message.replace(&format!("%{{{name}}}"), &value.to_string());
+    }
+    message
+}
+
+/// Text of `key` in `locale` or its fallbacks, `None` if it is not
+/// translated.
+fn translation(locale: &str, key: &str) -> Option<String> {
+    let text = rust_i18n::t!(key, locale = locale);
+    // `t!` answers a missing key with the key itself
+    (text != key).then(|| text.into_owned())
+}
+
+/// CLDR plural category of the whole number `n` in the language of
+/// `locale`: `one`, `few`, `many` or `other`.
+pub fn plural_category(locale: &str, n: u64) -> &'static str {
+    let language = locale.split(['-', '_']).next().unwrap_or_default();
+    let (rem10, rem100) = (n % 10, n % 100);
+    match language {
+        "ru" | "uk" | "be" => match (rem10, rem100) {
+            (1, r) if r != 11 => "one",
+            (2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        "pl" => match (n, rem10, rem100) {
+            (1, _, _) => "one",
+            (_, 2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        "cs" | "sk" => match n {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        "fr" | "pt" => {
+            if n <= 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+        "ja" | "ko" | "zh" | "th" | "vi" | "id" => "other",
+        _ => {
+            if n == 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+    }
+}
+
+/// The available locale for `requested`, such as `de_AT.UTF-8`: the full
+/// locale if it is translated, otherwise its language.
+fn matching<'a>(requested: &str, available: &[&'a str]) -> Option<&'a str> {
+    let requested = requested.replace('_', "-");
+    let language = requested.split(['-', '.']).next().unwrap_or_default();
+    [requested.as_str(), language]
+        .into_iter()
+        .find_map(|candidate| {
+            available
+                .iter()
+                .find(|locale| locale.eq_ignore_ascii_case(candidate))
+                .copied()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching() {
+        let available = ["en", "ru", "pt-BR"];
+        assert_eq!(matching("ru", &available), Some("ru"));
+        assert_eq!(matching("ru_RU.UTF-8", &available), Some("ru"));
+        assert_eq!(matching("pt_BR", &available), Some("pt-BR"));
+        assert_eq!(matching("EN-us", &available), Some("en"));
+        assert_eq!(matching("de", &available), None);
+        assert_eq!(resolve("ru_RU"), Ok("ru"));
+        assert!(set_language("xx").is_err());
+    }
+
+    #[test]
+    fn test_plural() {
+        let categories: Vec<_> = [1, 2, 5, 11, 21, 22, 112]
+            .iter()
+            .map(|&n| plural_category("ru", n))
+            .collect();
+        assert_eq!(
+            categories,
+            ["one", "few", "many", "many", "one", "few", "many"]
+        );
+        assert_eq!(plural_category("en-US", 1), "one");
+        assert_eq!(plural_category("en", 0), "other");
+        assert_eq!(plural_category("fr", 0), "one");
+
+        assert_eq!(plural_in("en", "title.files", 1, &[]), "1 file");
+        assert_eq!(plural_in("ru", "title.files", 3, &[]), "3 файла");
+        assert_eq!(plural_in("ru", "title.files", 25, &[]), "25 файлов");
+        assert_eq!(
+            plural_in("ru", "batch.failed", 2, &[("total", 5)]),
+            "2 из 5 загрузок завершились ошибкой"
+        );
+        // Only the English forms: `few` falls back to `other`
+        assert_eq!(plural_in("cs", "title.files", 3, &[]), "3 files");
+    }
+
+    #[test]
+    fn test_user_locales() {
+        use rust_i18n::Backend;
+
+        let dir = std::env::temp_dir().join(format!("dwrs-locales-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("app.yml"),
+            "_version: 2\nsummary.title:\n  en: Report\n  de: Bericht\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("de.toml"), "[notify]\ncomplete = \"Fertig\"\n").unwrap();
+        std::fs::write(dir.join("fr.json"), "{ not json").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (locales, warnings) = UserLocales::load_dir(&dir);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("fr.json"), "{}", warnings[0]);
+        assert_eq!(locales.translate("en", "summary.title"), Some("Report"));
+        assert_eq!(locales.translate("de", "summary.title"), Some("Bericht"));
+        assert_eq!(locales.translate("de", "notify.complete"), Some("Fertig"));
+        assert_eq!(locales.translate("de", "_version"), None);
+        let mut available = locales.available_locales();
+        available.sort();
+        assert_eq!(available, ["de", "en"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
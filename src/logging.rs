@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Size at which the log file is rotated
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// Rotated files kept next to the current one (`dwrs.log.1` ... `dwrs.log.N`)
+pub const DEFAULT_KEEP: usize = 3;
+
+/// Log file that moves itself aside once it grows past `max_size`.
+///
+/// On rotation `path` becomes `path.1`, `path.1` becomes `path.2` and so on;
+/// the oldest file beyond `keep` is deleted.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, creating it and its directory if needed.
+    pub fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            file,
+            size,
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            fs::remove_file(self.rotated(self.keep)).ok();
+            for n in (1..self.keep).rev() {
+                fs::rename(self.rotated(n), self.rotated(n + 1)).ok();
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+/// Terminal records kept in memory while something else owns the screen
+static CAPTURED: Mutex<Option<VecDeque<String>>> = Mutex::new(None);
+/// Captured records kept before the oldest is dropped
+const CAPTURE_LINES: usize = 500;
+
+/// Sink for the file logger, writing to whatever [`set_log_file`] installed.
+struct FileSink;
+
+impl Write for FileSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        match file.as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        file.as_mut().map_or(Ok(()), Write::flush)
+    }
+}
+
+/// Sends records to the terminal at the chosen level and, once a log file
+/// is set, everything from dwrs at debug level to that file.
+struct Logger {
+    terminal: env_logger::Logger,
+    file: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.terminal.enabled(metadata) || (has_log_file() && self.file.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.terminal.matches(record) && !capture(record) {
+            self.terminal.log(record);
+        }
+        if has_log_file() && self.file.matches(record) {
+            self.file.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+        self.file.flush();
+    }
+}
+
+/// Stores `record` if the terminal is captured and tells whether it was.
+fn capture(record: &log::Record) -> bool {
+    let mut captured = CAPTURED.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(lines) = captured.as_mut() else {
+        return false;
+    };
+    if lines.len() == CAPTURE_LINES {
+        lines.pop_front();
+    }
+    lines.push_back(format!("{:<5} {}", record.level(), record.args()));
+    true
+}
+
+/// Keeps terminal log records in memory instead of printing them, e.g. while
+/// a full-screen interface is shown, until [`release_terminal`].
+pub fn capture_terminal() {
+    *CAPTURED.lock().unwrap_or_else(|e| e.into_inner()) = Some(VecDeque::new());
+}
+
+/// Prints terminal log records again and drops the captured ones.
+pub fn release_terminal() {
+    *CAPTURED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Records captured since [`capture_terminal`], oldest first.
+pub fn captured() -> Vec<String> {
+    CAPTURED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn has_log_file() -> bool {
+    LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Installs the global logger. Does nothing if one is already set.
+///
+/// `level` applies to the terminal; `RUST_LOG` overrides it.
+pub fn init(level: log::LevelFilter) {
+    let terminal = env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .build();
+    let file = env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .filter_module("dwrs", log::LevelFilter::Debug)
+        .write_style(env_logger::WriteStyle::Never)
+        .target(env_logger::Target::Pipe(Box::new(FileSink)))
+        .build();
+    let max_level = terminal.filter();
+    if log::set_boxed_logger(Box::new(Logger { terminal, file })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Also writes detailed logs to `path`, rotating it at `max_size` bytes.
+///
+/// Takes effect for the logger installed by [`init`](crate::init).
+pub fn set_log_file(path: &Path, max_size: u64, keep: usize) -> io::Result<()> {
+    let file = RotatingFile::open(path, max_size, keep)?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    if log::max_level() < log::LevelFilter::Debug {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("dwrs-log-{}", std::process::id()));
+        let path = dir.join("dwrs.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("dwrs.log.1")).unwrap(),
+            "cccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("dwrs.log.2")).unwrap(),
+            "bbbbbbbb\n"
+        );
+        assert!(!dir.join("dwrs.log.3").exists());
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,124 +1,1671 @@
 use clap::Parser;
 use colored::Colorize;
-use dwrs::cli::Args;
-use dwrs::config::Config;
-use dwrs::{Downloader, init};
+use dwrs::builder::DownloaderBuilder;
+use dwrs::checksum::{Checksum, FileCheck};
+#[cfg(feature = "history")]
+use dwrs::cli::HistoryArgs;
+#[cfg(feature = "clipboard")]
+use dwrs::cli::WatchClipboardArgs;
+use dwrs::cli::{
+    Args, BenchArgs, Command, ConfigAction, ConfigArgs, DaemonArgs, HeadArgs, StatusArgs,
+    VerifyArgs, WatchDirArgs,
+};
+#[cfg(feature = "clipboard")]
+use dwrs::clipboard::ClipboardOptions;
+use dwrs::config::{Config, ConfigSource, NotifyBackend, Severity};
+use dwrs::crawler::{CrawlOptions, DirLayout};
+use dwrs::download;
+use dwrs::exit::ExitStatus;
+use dwrs::feed::FeedOptions;
+use dwrs::file_parser::{
+    CsvColumns, Dedup, ManifestFormat, ManifestOptions, filename_from_url, parse_csv, parse_json,
+    resolve_collisions, resolve_url, try_entry_stream,
+};
+use dwrs::filter::{NameFilter, TypeFilter};
+#[cfg(feature = "history")]
+use dwrs::history::{History, HistoryQuery};
+use dwrs::hooks::{Hook, Webhook};
+use dwrs::logging;
+use dwrs::notifier::{Matrix, Telegram};
+use dwrs::output_template::OutputTemplate;
+use dwrs::overwrite::OverwritePolicy;
+use dwrs::pipe::PipeCommand;
+use dwrs::queue::DownloadQueue;
+use dwrs::rewrite::{RewriteCommand, RewriteRule, Rewriter};
+use dwrs::rpc::{self, RpcOptions};
+use dwrs::sitemap::SitemapOptions;
+use dwrs::status;
+use dwrs::transport::Payload;
+use dwrs::watch_dir::WatchDirOptions;
+use dwrs::{BatchError, DownloadEntry, Downloader, init_with_level};
+use futures::{Stream, StreamExt};
 use log::{error, info};
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 #[tokio::main]
 async fn main() {
-    init();
+    let mut args = Args::parse();
+    args.color.apply();
+    init_with_level(args.log_level());
     info!("Logger initialized");
 
-    let args = Args::parse();
-    let mut cfg = Config::load_from_config_dir();
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!(
+                "\n{}",
+                "Interrupted, saving partial downloads for --continue (Ctrl-C again to quit now)"
+                    .yellow()
+                    .bold()
+            );
+            tokio::select! {
+                _ = shutdown(ExitStatus::Interrupted) => {}
+                _ = tokio::signal::ctrl_c() => {
+                    restore_cursor();
+                    ExitStatus::Interrupted.exit();
+                }
+            }
+        }
+    });
+    #[cfg(unix)]
+    tokio::spawn(handle_signals());
 
-    if let Some(config_path) = args.config {
-        cfg = Config::load(&config_path);
+    let mut daemon = None;
+    let mut watch_dir = None;
+    #[cfg(feature = "clipboard")]
+    let mut clipboard = None;
+    let mut head = None;
+    let mut bench = None;
+    let mut verify_command = None;
+    let mut config_command = None;
+    match args.command.take() {
+        Some(Command::Get(get)) => {
+            args.url = get.url;
+            args.output.extend(get.output);
+        }
+        Some(Command::Resume(get)) => {
+            args.url = get.url;
+            args.output.extend(get.output);
+            args.continue_ = true;
+        }
+        Some(Command::Batch(batch)) => args.file = Some(batch.file),
+        Some(Command::Verify(verify_args)) => verify_command = Some(verify_args),
+        #[cfg(feature = "history")]
+        Some(Command::History(history_args)) => {
+            show_history(&history_args);
+            return;
+        }
+        Some(Command::Status(status_args)) => {
+            show_status(&status_args).await;
+            return;
+        }
+        Some(Command::Head(head_args)) => head = Some(head_args),
+        Some(Command::Bench(bench_args)) => bench = Some(bench_args),
+        Some(Command::Daemon(daemon_args)) => daemon = Some(daemon_args),
+        Some(Command::WatchDir(watch_args)) => watch_dir = Some(watch_args),
+        #[cfg(feature = "clipboard")]
+        Some(Command::WatchClipboard(watch_args)) => clipboard = Some(watch_args),
+        Some(Command::Config(config_args)) => config_command = Some(config_args),
+        None => {}
     }
 
-    let workers = if args.workers != 4 {
-        args.workers
-    } else {
-        cfg.workers
-    };
-    let buffer_size = args
-        .buffer_size
-        .map(|kb| kb * 1024)
-        .unwrap_or(cfg.buffer_size);
-    let pool_size = if args.pool_size != 100 {
-        args.pool_size
-    } else {
-        cfg.pool_size
-    };
-    let retries = if args.retries != 3 {
-        args.retries
-    } else {
-        cfg.retries
+    let config_path = args
+        .config
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(Config::default_path);
+
+    if let Some(ConfigArgs { action }) = &config_command {
+        if let ConfigAction::Init { force } = action {
+            config_init(config_path.as_deref(), *force);
+            return;
+        }
+        if let ConfigAction::Path = action {
+            match &config_path {
+                Some(path) => println!("{}", path.display()),
+                None => println!("No config directory found"),
+            }
+            return;
+        }
+        if let ConfigAction::Validate = action {
+            config_validate(config_path.as_deref(), args.profile.as_deref());
+            return;
+        }
+    }
+
+    let mut cfg = match &config_path {
+        Some(path) if path.exists() => match Config::try_load(path, args.profile.as_deref()) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                eprintln!("{}: {}", "Config error".red().bold(), e);
+                eprintln!("Run `dwrs config validate` to list every problem");
+                ExitStatus::Config.exit();
+            }
+        },
+        _ if args.profile.is_some() => {
+            eprintln!("{}", "--profile needs a config file".red().bold());
+            ExitStatus::Config.exit();
+        }
+        _ => Config::default(),
     };
-    let min_parallel_size = if args.min_parallel_size != 5 {
-        args.min_parallel_size * 1024 * 1024
+    if let Err(e) = cfg.apply_env() {
+        eprintln!("{}: {}", "Config error".red().bold(), e);
+        ExitStatus::Config.exit();
+    }
+    cfg.apply_args(&args);
+
+    if let Some(path) = &cfg.log_file
+        && let Err(e) =
+            logging::set_log_file(path, logging::DEFAULT_MAX_SIZE, logging::DEFAULT_KEEP)
+    {
+        eprintln!(
+            "{}: {}: {}",
+            "Cannot open log file".red().bold(),
+            path.display(),
+            e
+        );
+        ExitStatus::Config.exit();
+    }
+
+    for warning in dwrs::localization::load_warnings() {
+        eprintln!("{}: {}", "Warning".yellow().bold(), warning);
+    }
+    if let Some(lang) = &cfg.lang
+        && let Err(e) = dwrs::localization::set_language(lang)
+    {
+        eprintln!("{}: {}", "Invalid language".red().bold(), e);
+        ExitStatus::Config.exit();
+    }
+    cfg.filename_mode.apply();
+    cfg.units.apply();
+    match cfg.output_template.as_deref().map(OutputTemplate::parse) {
+        Some(Ok(template)) => OutputTemplate::apply(Some(template)),
+        Some(Err(e)) => {
+            eprintln!("{}: {}", "Invalid output template".red().bold(), e);
+            ExitStatus::Config.exit();
+        }
+        None => {}
+    }
+
+    if config_command.is_some() {
+        config_show(&cfg, config_path.as_deref(), args.profile.as_deref());
+        return;
+    }
+
+    if let Some(proxy) = &cfg.proxy
+        && let Err(e) = reqwest::Proxy::all(proxy)
+    {
+        eprintln!("{}: {}: {}", "Invalid proxy".red().bold(), proxy, e);
+        ExitStatus::Config.exit();
+    }
+
+    // Long-running modes always report their progress to `dwrs status`
+    #[cfg(feature = "clipboard")]
+    let watching = daemon.is_some() || watch_dir.is_some() || clipboard.is_some();
+    #[cfg(not(feature = "clipboard"))]
+    let watching = daemon.is_some() || watch_dir.is_some();
+
+    #[cfg(feature = "tui")]
+    let tui = args.tui;
+    #[cfg(not(feature = "tui"))]
+    let tui = false;
+    #[cfg(feature = "tui")]
+    let pick = args.pick;
+    #[cfg(not(feature = "tui"))]
+    let pick = false;
+    if (tui || pick) && !std::io::stdout().is_terminal() {
+        let flag = if tui { "--tui" } else { "--pick" };
+        eprintln!("{}", format!("{} needs a terminal", flag).red().bold());
+        ExitStatus::Usage.exit();
+    }
+
+    // Resumed files are meant to be appended to, so only --no keeps them
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    let overwrite = if args.no {
+        OverwritePolicy::Never
+    } else if args.yes || args.continue_ || !interactive {
+        OverwritePolicy::Always
     } else {
-        cfg.min_parallel_size
+        OverwritePolicy::Ask
     };
 
-    let download_config = dwrs::DownloadConfig {
-        workers,
-        msg_template: cfg.msg_template,
-        template: cfg.template,
-        chars: cfg.bar_chars,
-        continue_download: args.continue_,
-        #[cfg(feature = "notify")]
-        notify: args.notify,
-        buffer_size,
-        pool_size,
-        retries,
-        min_parallel_size,
-        max_concurrent_files: args.max_files,
+    let type_filter = TypeFilter {
+        accept: args.accept_type.clone(),
+        reject: args.reject_type.clone(),
     };
+    let name_filter = NameFilter {
+        accept: args.accept.clone(),
+        reject: args.reject.clone(),
+    };
+    // Choosing backends in the config turns notifications on by itself
+    let notify = args.notify || *cfg.source("notify_backend") != ConfigSource::Default;
+    let backend = |backend| notify && cfg.notify_backend.contains(&backend);
+    let desktop = backend(NotifyBackend::Desktop);
+    let telegram =
+        backend(NotifyBackend::Telegram).then(|| chat_settings(&cfg.telegram, "telegram"));
+    let matrix = backend(NotifyBackend::Matrix).then(|| chat_settings(&cfg.matrix, "matrix"));
+    let builder = Downloader::builder()
+        .workers(cfg.workers)
+        .msg_template(cfg.msg_template)
+        .template(cfg.template)
+        .chars(cfg.bar_chars)
+        .continue_download(args.continue_)
+        .buffer_size(cfg.buffer_size)
+        .pool_size(cfg.pool_size)
+        .retries(cfg.retries)
+        .retry_policy(dwrs::retry::Exponential::default())
+        .min_parallel_size(cfg.min_parallel_size)
+        .user_agent(dwrs::user_agent::resolve(&cfg.user_agent))
+        .maybe(args.referer.clone(), DownloaderBuilder::referer)
+        .maybe(
+            payload(args.method.clone(), args.data.as_deref()),
+            DownloaderBuilder::payload,
+        )
+        .maybe(args.max_files, DownloaderBuilder::max_concurrent_files)
+        .failed_file(failed_file(&args))
+        .maybe(cfg.proxy, DownloaderBuilder::proxy)
+        .maybe(cfg.rate_limit, DownloaderBuilder::rate_limit)
+        // An explicit --limit-rate wins over the windows of the config
+        .schedule(match args.limit_rate {
+            Some(_) => Vec::new(),
+            None => cfg.schedule,
+        })
+        .maybe(args.limit_rate_host, DownloaderBuilder::host_rate_limit)
+        .maybe(args.limit_rate_total, DownloaderBuilder::total_rate_limit)
+        .maybe(args.rate_burst, DownloaderBuilder::rate_burst)
+        .maybe(args.quota, DownloaderBuilder::quota)
+        .type_filter(type_filter.clone())
+        .fail_fast(args.fail_fast)
+        .preflight(args.preflight)
+        .progress(!args.quiet && !tui)
+        .progress_interval(args.progress_interval)
+        .terminal_title(cfg.terminal_title)
+        .bell(cfg.bell)
+        .maybe(cfg.bell_sound.clone(), DownloaderBuilder::bell_sound)
+        .summary(!args.quiet)
+        .maybe(args.report_json.clone(), DownloaderBuilder::report_json)
+        .maybe(args.write_checksums, DownloaderBuilder::write_checksums)
+        .provenance(!args.no_xattr)
+        .decompress(args.decompress)
+        .maybe(
+            args.pipe.as_deref().map(pipe_command),
+            DownloaderBuilder::pipe,
+        )
+        .tee(args.tee)
+        .maybe(
+            hook("on_complete", cfg.on_complete.as_deref()),
+            DownloaderBuilder::on_complete,
+        )
+        .maybe(
+            hook("on_error", cfg.on_error.as_deref()),
+            DownloaderBuilder::on_error,
+        )
+        .notify_messages(dwrs::notifier::Messages {
+            complete: cfg.notify_template.clone(),
+            failed: cfg.notify_error_template.clone(),
+        })
+        .notify_threshold(dwrs::notifier::Threshold {
+            min_duration: cfg.notify_min_duration.map(std::time::Duration::from_secs),
+            min_size: cfg.notify_min_size,
+        })
+        .maybe(
+            webhook(cfg.notify_webhook.as_deref()),
+            DownloaderBuilder::notify_webhook,
+        )
+        .rewrite(rewriter(
+            cfg.rewrite,
+            cfg.rewrite_command.as_deref(),
+            &args.rewrite,
+        ))
+        .maybe(
+            (watching || std::env::var_os(status::BACKGROUND_ENV).is_some())
+                .then(status::default_path),
+            DownloaderBuilder::status_file,
+        )
+        .hosts(cfg.hosts);
+    #[cfg(feature = "notify")]
+    let builder = builder.notify(desktop);
+    #[cfg(feature = "history")]
+    let builder = builder.maybe(
+        (!args.no_history).then(History::default_path),
+        DownloaderBuilder::history_file,
+    );
+    #[cfg(feature = "extract")]
+    let builder = builder
+        .extract(args.extract)
+        .delete_archive(args.delete_archive);
+    #[cfg(feature = "email")]
+    let builder = builder.maybe(mailer(cfg.email.clone()), DownloaderBuilder::email);
 
-    let downloader = Downloader::new(download_config);
+    let mut downloader = builder.build().unwrap_or_else(|e| {
+        eprintln!("{}: {}", "Invalid configuration".red().bold(), e);
+        ExitStatus::Config.exit();
+    });
+    if let Some(settings) = telegram {
+        downloader.add_notifier(Telegram::new(settings));
+    }
+    if let Some(settings) = matrix {
+        downloader.add_notifier(Matrix::new(settings));
+    }
+    #[cfg(not(feature = "email"))]
+    if cfg.email.is_some() {
+        log::warn!("Ignoring [email]: dwrs was built without the email feature");
+    }
+    // Only a background process is still around when a button is clicked
+    #[cfg(feature = "notify")]
+    if desktop && std::env::var_os(status::BACKGROUND_ENV).is_some() {
+        dwrs::notifications::enable_actions();
+    }
 
     if args.background {
         #[cfg(feature = "notify")]
         if let Err(e) = dwrs::spawn_background_process() {
             error!("Failed to spawn background process: {}", e);
+            ExitStatus::Failure.exit();
         }
         #[cfg(not(feature = "notify"))]
-        error!("Background mode requires 'notify' feature");
+        {
+            error!("Background mode requires 'notify' feature");
+            ExitStatus::Usage.exit();
+        }
+        #[cfg(feature = "notify")]
         return;
     }
 
-    let downloads: Vec<(String, PathBuf)> = if let Some(file_path) = args.file {
-        match dwrs::parse_file(&file_path).await {
-            Ok(pairs) => pairs
-                .into_iter()
-                .map(|(url, path)| (url, PathBuf::from(path)))
-                .collect(),
+    if let Some(head_args) = head {
+        show_head(&downloader, &head_args).await;
+        return;
+    }
+    if let Some(bench_args) = bench {
+        run_bench(&downloader, &bench_args).await;
+        return;
+    }
+    if let Some(verify_args) = verify_command {
+        verify(&downloader, &verify_args).await;
+        return;
+    }
+
+    let start_at = args.start_time();
+    if let Some(daemon_args) = daemon {
+        run_daemon(&downloader, &daemon_args, args.max_files, start_at).await;
+        return;
+    }
+    if let Some(at) = start_at {
+        dwrs::deferred::wait_until(at, !args.quiet).await;
+    }
+
+    if let Some(watch_args) = watch_dir {
+        watch_manifests(&downloader, &watch_args, args.max_files).await;
+        return;
+    }
+    #[cfg(feature = "clipboard")]
+    if let Some(watch_args) = clipboard {
+        watch_clipboard(&downloader, &watch_args, args.max_files).await;
+        return;
+    }
+
+    if let Some(retry_path) = &args.retry_failed {
+        let retry_path = &retry_path.clone().unwrap_or_else(|| failed_file(&args));
+        let opts = ManifestOptions {
+            format: Some(ManifestFormat::Json),
+            ..Default::default()
+        };
+        let entries = match dwrs::parse_manifest(retry_path, &opts).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("{}: {}", "Error reading file".red().bold(), e);
+                ExitStatus::Usage.exit();
+            }
+        };
+        info!("Retrying {} failed download(s)", entries.len());
+        match downloader.download_entries(entries).await {
+            Ok(_) => {
+                info!("All retried downloads completed successfully");
+                tokio::fs::remove_file(retry_path).await.ok();
+            }
+            Err(e) => {
+                error!("Error during downloads: {}", e);
+                download_failed(&*e).exit();
+            }
+        }
+        return;
+    }
+
+    if let Some(sitemap_url) = &args.sitemap {
+        let compile = |pattern: &Option<String>| match pattern.as_deref().map(regex::Regex::new) {
+            Some(Ok(re)) => Some(re),
+            Some(Err(e)) => {
+                eprintln!("{}: {}", "Invalid filter pattern".red().bold(), e);
+                ExitStatus::Usage.exit();
+            }
+            None => None,
+        };
+        let opts = SitemapOptions {
+            include: compile(&args.include),
+            exclude: compile(&args.exclude),
+        };
+        if let Err(e) = downloader.download_sitemap(sitemap_url, &opts).await {
+            error!("Sitemap download failed: {}", e);
+            eprintln!("{}: {}", "Sitemap download failed".red().bold(), e);
+            download_failed(&*e).exit();
+        }
+        return;
+    }
+
+    if let Some(feed_url) = &args.feed {
+        let opts = FeedOptions {
+            state_file: args.feed_state.clone(),
+            ..Default::default()
+        };
+        if let Err(e) = downloader.download_feed(feed_url, &opts).await {
+            error!("Feed download failed: {}", e);
+            eprintln!("{}: {}", "Feed download failed".red().bold(), e);
+            download_failed(&*e).exit();
+        }
+        return;
+    }
+
+    let placement = Placement {
+        force_directories: args.force_directories,
+        layout: DirLayout {
+            no_host: args.no_host_directories,
+            cut_dirs: args.cut_dirs,
+        },
+        prefix: args.directory_prefix.clone(),
+        decompress: args.decompress,
+    };
+
+    if args.recursive {
+        let mut status = None;
+        for url in &args.url {
+            let opts = CrawlOptions {
+                max_depth: args.level,
+                no_parent: args.no_parent,
+                respect_robots: !args.no_robots,
+                output_dir: placement
+                    .prefix
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(".")),
+                layout: placement.layout,
+                type_filter: type_filter.clone(),
+                name_filter: name_filter.clone(),
+            };
+            #[cfg(feature = "tui")]
+            let result = if pick {
+                mirror_picked(&downloader, url, opts).await
+            } else {
+                downloader.mirror(url, opts).await
+            };
+            #[cfg(not(feature = "tui"))]
+            let result = downloader.mirror(url, opts).await;
+            let url_status = match result {
+                Ok(()) => ExitStatus::Success,
+                Err(e) => {
+                    error!("Mirroring {} failed: {}", url, e);
+                    eprintln!("{}: {}", "Mirroring failed".red().bold(), e);
+                    download_failed(&*e)
+                }
+            };
+            status = Some(status.map_or(url_status, |s: ExitStatus| s.combine(url_status)));
+        }
+        status.unwrap_or(ExitStatus::Success).exit();
+    }
+
+    let base = match args.base.as_deref().map(reqwest::Url::parse) {
+        Some(Ok(url)) => Some(url),
+        Some(Err(e)) => {
+            eprintln!("{}: {}", "Invalid --base URL".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+        None => None,
+    };
+
+    let csv_columns = match args.csv_columns.as_deref().map(CsvColumns::parse) {
+        Some(Ok(columns)) => columns,
+        Some(Err(e)) => {
+            eprintln!("{}: {}", "Invalid --csv-columns".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+        None => CsvColumns::default(),
+    };
+
+    let read_stdin = match &args.file {
+        Some(path) => path.as_os_str() == "-",
+        None => args.url.is_empty() && !std::io::stdin().is_terminal(),
+    };
+    if read_stdin {
+        info!("Reading URLs from stdin");
+        let read_error = Arc::new(Mutex::new(None::<std::io::Error>));
+        let entries: Pin<Box<dyn Stream<Item = DownloadEntry> + Send>> = match args.format {
+            // Only lines can be downloaded before the input ends
+            None | Some(ManifestFormat::Text) => {
+                let read_error = read_error.clone();
+                Box::pin(
+                    try_entry_stream(tokio::io::BufReader::new(tokio::io::stdin()), base)
+                        .filter_map(move |entry| {
+                            let entry = entry
+                                .map_err(|e| *read_error.lock().unwrap() = Some(e))
+                                .ok();
+                            std::future::ready(entry)
+                        }),
+                )
+            }
+            Some(format) => {
+                use tokio::io::AsyncReadExt;
+                let mut content = String::new();
+                if let Err(e) = tokio::io::stdin().read_to_string(&mut content).await {
+                    eprintln!("{}: {}", "Error reading stdin".red().bold(), e);
+                    ExitStatus::Usage.exit();
+                }
+                let parsed = match format {
+                    ManifestFormat::Csv => parse_csv(&content, &csv_columns, base.as_ref()),
+                    _ => parse_json(&content, base.as_ref()),
+                };
+                match parsed {
+                    Ok(entries) => Box::pin(futures::stream::iter(entries)),
+                    Err(e) => {
+                        eprintln!("{}: {}", "Error reading stdin".red().bold(), e);
+                        ExitStatus::Usage.exit();
+                    }
+                }
+            }
+        };
+        let entries = entries
+            .filter(move |entry| std::future::ready(accepted(&name_filter, entry)))
+            .map(move |entry| placement.place(entry))
+            .filter(move |entry| {
+                let keep = overwrite != OverwritePolicy::Never || !entry.output.exists();
+                if !keep {
+                    info!("Keeping existing file {}", entry.output.display());
+                }
+                std::future::ready(keep)
+            });
+        let status = if args.spider {
+            spider(&downloader, entries).await
+        } else {
+            match downloader.download_stream(entries).await {
+                Ok(()) => ExitStatus::Success,
+                Err(e) => {
+                    error!("Error during downloads: {}", e);
+                    download_failed(&*e)
+                }
+            }
+        };
+        // Entries after a read error were never seen, so the batch is incomplete
+        if let Some(e) = read_error.lock().unwrap().take() {
+            eprintln!("{}: {}", "Error reading stdin".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+        if status != ExitStatus::Success {
+            status.exit();
+        }
+        return;
+    }
+
+    if args.url.is_empty() && args.file.is_none() {
+        eprintln!(
+            "{}",
+            "No input given: pass URLs, --file, --sitemap or --feed"
+                .red()
+                .bold()
+        );
+        ExitStatus::Usage.exit();
+    }
+
+    let downloads: Vec<DownloadEntry> = if let Some(file_path) = args.file {
+        let manifest_opts = ManifestOptions {
+            format: args.format,
+            csv_columns,
+            base,
+        };
+        match dwrs::parse_manifest(&file_path, &manifest_opts).await {
+            Ok(entries) => entries,
             Err(e) => {
                 eprintln!("{}: {}", "Error reading file".red().bold(), e);
-                std::process::exit(1);
+                ExitStatus::Usage.exit();
             }
         }
     } else {
         let mut pairs = Vec::new();
         for (i, url) in args.url.iter().enumerate() {
+            let url = cli_url(url);
             let output = if let Some(path) = args.output.get(i) {
                 PathBuf::from(path)
             } else {
-                PathBuf::from(url.split('/').next_back().unwrap_or("file.bin"))
+                PathBuf::from(filename_from_url(&url))
             };
-            pairs.push((url.clone(), output));
+            pairs.push(DownloadEntry::new(url, output));
         }
 
+        if args.url.len() > 1 && args.output.iter().any(|o| o == download::STDOUT) {
+            eprintln!(
+                "{}",
+                "-o - writes to stdout and needs exactly one URL"
+                    .red()
+                    .bold()
+            );
+            ExitStatus::Usage.exit();
+        }
+        if args.url.len() > 1 && args.tee {
+            eprintln!(
+                "{}",
+                "--tee writes to stdout and needs exactly one URL"
+                    .red()
+                    .bold()
+            );
+            ExitStatus::Usage.exit();
+        }
         if !args.output.is_empty() && args.output.len() != args.url.len() {
             error!("Error: number of output files does not match number of URLs");
             eprintln!("{}", "Error: count mismatch".red().bold());
-            std::process::exit(1);
+            ExitStatus::Usage.exit();
         }
         pairs
     };
 
     if downloads.is_empty() {
         eprintln!("{}", "No downloads to process".red().bold());
-        std::process::exit(1);
+        ExitStatus::Usage.exit();
+    }
+    // Before asking about existing files, so nothing is asked twice
+    let mut dedup = Dedup::new();
+    let downloads: Vec<DownloadEntry> = downloads
+        .into_iter()
+        .filter(|entry| accepted(&name_filter, entry))
+        .filter_map(|entry| dedup.check(entry))
+        .map(|entry| placement.place(entry))
+        .collect();
+
+    if downloads.is_empty() {
+        println!("Nothing to download: no URL matches --accept/--reject");
+        return;
+    }
+
+    if args.spider {
+        spider(&downloader, futures::stream::iter(downloads))
+            .await
+            .exit();
+    }
+
+    let downloads = match resolve_collisions(downloads, args.on_collision) {
+        Ok(downloads) => downloads,
+        Err(e) => {
+            eprintln!("{}: {}", "Output collision".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+    };
+    let downloads = match dwrs::overwrite::resolve(&downloader, downloads, overwrite).await {
+        Ok(downloads) => downloads,
+        Err(e) => {
+            eprintln!("{}: {}", "Cannot ask about existing files".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+    };
+    if downloads.is_empty() {
+        println!("Nothing to download: all output files exist");
+        return;
     }
 
     info!("Starting {} download(s)", downloads.len());
 
-    let downloads_refs: Vec<(&str, PathBuf)> = downloads
-        .iter()
-        .map(|(url, path)| (url.as_str(), path.clone()))
-        .collect();
+    #[cfg(feature = "tui")]
+    if tui {
+        let queue = DownloadQueue::new(downloader.max_concurrent_files());
+        for entry in downloads {
+            queue.push(entry, 0);
+        }
+        let dashboard = async {
+            let result = dwrs::tui::run(queue.clone()).await;
+            queue.close();
+            result
+        };
+        let (result, dashboard) = tokio::join!(downloader.download_queue(&queue), dashboard);
+        if let Err(e) = dashboard {
+            error!("Cannot show the dashboard: {}", e);
+        }
+        if dwrs::control::is_stopping() {
+            STOPPED_BY.get_or_init(|| ExitStatus::Interrupted);
+        }
+        if let Err(e) = result {
+            error!("Error during downloads: {}", e);
+            download_failed(&*e).exit();
+        }
+        return;
+    }
 
-    match downloader.download_multiple(downloads_refs).await {
+    let result = downloader.download_entries(downloads).await;
+    #[cfg(feature = "notify")]
+    dwrs::notifications::wait_for_actions(NOTIFICATION_ACTIONS_TIMEOUT).await;
+    match result {
         Ok(_) => {
             info!("All downloads completed successfully");
         }
         Err(e) => {
             error!("Error during downloads: {}", e);
-            std::process::exit(1);
+            download_failed(&*e).exit();
+        }
+    }
+}
+
+/// Pauses on SIGUSR1, resumes on SIGUSR2 and on SIGTERM stops every
+/// transfer with its partial files flushed so `--continue` can pick them up.
+#[cfg(unix)]
+async fn handle_signals() {
+    use dwrs::control;
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let (Ok(mut pause), Ok(mut resume), Ok(mut terminate)) = (
+        signal(SignalKind::user_defined1()),
+        signal(SignalKind::user_defined2()),
+        signal(SignalKind::terminate()),
+    ) else {
+        error!("Failed to install signal handlers");
+        return;
+    };
+    loop {
+        tokio::select! {
+            _ = pause.recv() => {
+                if control::pause() {
+                    log::warn!("Paused by SIGUSR1, send SIGUSR2 to resume");
+                }
+            }
+            _ = resume.recv() => {
+                if control::resume() {
+                    log::warn!("Resumed by SIGUSR2");
+                }
+            }
+            _ = terminate.recv() => {
+                eprintln!("\n{}", "Terminated, saving partial downloads for --continue".yellow().bold());
+                shutdown(ExitStatus::Terminated).await;
+            }
+        }
+    }
+}
+
+/// How long a background process waits for the buttons of its last
+/// notification to be clicked
+#[cfg(feature = "notify")]
+const NOTIFICATION_ACTIONS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Why the downloads were stopped, once Ctrl-C or SIGTERM arrived
+static STOPPED_BY: std::sync::OnceLock<ExitStatus> = std::sync::OnceLock::new();
+
+/// Stops every transfer, waits for them to save their partial files and
+/// progress bars, then exits with `status`.
+async fn shutdown(status: ExitStatus) -> ! {
+    STOPPED_BY.get_or_init(|| status);
+    dwrs::control::stop();
+    if !dwrs::control::wait_idle(std::time::Duration::from_secs(10)).await {
+        error!("Downloads did not stop in time");
+    }
+    #[cfg(feature = "tui")]
+    dwrs::tui::restore();
+    restore_cursor();
+    status.exit();
+}
+
+/// Shows the cursor again in case a progress bar was hidden mid-draw.
+fn restore_cursor() {
+    let term = console::Term::stderr();
+    if term.is_term() {
+        term.show_cursor().ok();
+    }
+}
+
+/// Where `-x`, `--cut-dirs` and `-P` put batch downloads
+struct Placement {
+    force_directories: bool,
+    layout: DirLayout,
+    prefix: Option<PathBuf>,
+    /// Drop the `.gz`/`.zst` extension of outputs for `--decompress`
+    decompress: bool,
+}
+
+impl Placement {
+    /// Recreates the URL path for outputs derived from the URL, then puts
+    /// relative outputs under the prefix and names them after their
+    /// decompressed content.
+    fn place(&self, mut entry: DownloadEntry) -> DownloadEntry {
+        if download::is_stdout(&entry.output) {
+            return entry;
+        }
+        if self.force_directories
+            && entry.output == Path::new(&filename_from_url(&entry.url))
+            && let Ok(url) = reqwest::Url::parse(&entry.url)
+        {
+            entry.output = self.layout.path(&url, Path::new(""));
+        }
+        if let Some(prefix) = &self.prefix
+            && entry.output.is_relative()
+        {
+            entry.output = prefix.join(&entry.output);
+        }
+        if self.decompress {
+            entry.output = dwrs::decompress::output_path(&entry.output);
+        }
+        entry
+    }
+}
+
+/// Parses the hook command set with `key`, exiting if it is invalid.
+fn hook(key: &str, command: Option<&str>) -> Option<Hook> {
+    match Hook::parse(command?) {
+        Ok(hook) => Some(hook),
+        Err(e) => {
+            eprintln!("{}: {}: {}", "Invalid hook".red().bold(), key, e);
+            ExitStatus::Config.exit();
+        }
+    }
+}
+
+/// Parses the webhook endpoint, exiting if it is invalid.
+fn webhook(endpoint: Option<&str>) -> Option<Webhook> {
+    match Webhook::parse(endpoint?) {
+        Ok(webhook) => Some(webhook),
+        Err(e) => {
+            eprintln!("{}: {}", "Invalid notify_webhook".red().bold(), e);
+            ExitStatus::Config.exit();
+        }
+    }
+}
+
+/// Checks the `[email]` section, exiting if it is invalid.
+#[cfg(feature = "email")]
+fn mailer(settings: Option<dwrs::config::EmailSettings>) -> Option<dwrs::email::Mailer> {
+    match dwrs::email::Mailer::new(settings?) {
+        Ok(mailer) => Some(mailer),
+        Err(e) => {
+            eprintln!("{}: email: {}", "Invalid config".red().bold(), e);
+            ExitStatus::Config.exit();
+        }
+    }
+}
+
+/// The section setting up the chat notify backend `name`, exiting if it
+/// is missing.
+fn chat_settings<T: Clone>(section: &Option<T>, name: &str) -> T {
+    section.clone().unwrap_or_else(|| {
+        eprintln!(
+            "{}: notify_backend: '{}' needs a [{}] section",
+            "Invalid config".red().bold(),
+            name,
+            name
+        );
+        ExitStatus::Config.exit();
+    })
+}
+
+/// Parses the `--pipe` command, exiting if it is invalid.
+fn pipe_command(command: &str) -> PipeCommand {
+    PipeCommand::parse(command).unwrap_or_else(|e| {
+        eprintln!("{}: {}", "Invalid --pipe".red().bold(), e);
+        ExitStatus::Usage.exit();
+    })
+}
+
+/// `--failed-file`, or the default one in the data dir.
+fn failed_file(args: &Args) -> PathBuf {
+    args.failed_file
+        .clone()
+        .unwrap_or_else(dwrs::file_parser::default_failed_file)
+}
+
+/// Method and body of `--method` and `--data`, exiting if the body cannot
+/// be read.
+fn payload(method: Option<reqwest::Method>, data: Option<&str>) -> Option<Payload> {
+    Payload::new(method, data).unwrap_or_else(|e| {
+        eprintln!("{}: {}", "Invalid --data".red().bold(), e);
+        ExitStatus::Usage.exit();
+    })
+}
+
+/// Combines the `[[rewrite]]` rules and command of the config with the
+/// `--rewrite` pairs, exiting if one of them is invalid.
+fn rewriter(mut rules: Vec<RewriteRule>, command: Option<&str>, pairs: &[String]) -> Rewriter {
+    for pair in pairs.chunks(2) {
+        match RewriteRule::new(&pair[0], &pair[1]) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => {
+                eprintln!("{}: {}", "Invalid --rewrite".red().bold(), e);
+                ExitStatus::Usage.exit();
+            }
+        }
+    }
+    let command = command.map(|command| {
+        RewriteCommand::parse(command).unwrap_or_else(|e| {
+            eprintln!("{}: rewrite_command: {}", "Invalid command".red().bold(), e);
+            ExitStatus::Config.exit();
+        })
+    });
+    Rewriter { rules, command }
+}
+
+/// Whether `entry` passes `--accept`/`--reject`, logging the ones that do not.
+fn accepted(filter: &NameFilter, entry: &DownloadEntry) -> bool {
+    let accepted = filter.allows_url(&entry.url);
+    if !accepted {
+        info!("Rejected by name: {}", entry.url);
+    }
+    accepted
+}
+
+/// Normalizes a URL given on the command line, exiting if it is not a valid `http(s)` URL.
+fn cli_url(url: &str) -> String {
+    resolve_url(url, None).unwrap_or_else(|| {
+        eprintln!("{}: {}", "Invalid URL".red().bold(), url);
+        ExitStatus::Usage.exit();
+    })
+}
+
+/// Exit status for an error returned by one of the batch download methods.
+fn download_failed(e: &(dyn std::error::Error + 'static)) -> ExitStatus {
+    if let Some(status) = STOPPED_BY.get() {
+        return *status;
+    }
+    match e.downcast_ref::<BatchError>() {
+        Some(batch) => ExitStatus::from_batch(batch.failed, batch.total),
+        None => ExitStatus::Failure,
+    }
+}
+
+async fn show_head(downloader: &Downloader, args: &HeadArgs) {
+    let mut failed = 0;
+    let mut results = Vec::new();
+    for url in &args.url {
+        match downloader.check_url(url, &[]).await {
+            Ok(status) => results.push(Ok(status)),
+            Err(e) => {
+                failed += 1;
+                results.push(Err((url, e.to_string())));
+            }
+        }
+    }
+
+    if args.json {
+        let json: Vec<serde_json::Value> = results
+            .iter()
+            .map(|result| match result {
+                Ok(status) => {
+                    let mut headers = serde_json::Map::new();
+                    for (name, value) in &status.headers {
+                        let value = match headers.get(name).and_then(|v| v.as_str()) {
+                            Some(previous) => format!("{}, {}", previous, value),
+                            None => value.clone(),
+                        };
+                        headers.insert(name.clone(), value.into());
+                    }
+                    serde_json::json!({
+                        "url": status.url,
+                        "final_url": status.final_url,
+                        "status": status.status,
+                        "size": status.size,
+                        "content_type": status.content_type,
+                        "resumable": status.resumable,
+                        "headers": headers,
+                    })
+                }
+                Err((url, error)) => serde_json::json!({ "url": url, "error": error }),
+            })
+            .collect();
+        let json = if json.len() == 1 {
+            json[0].clone()
+        } else {
+            serde_json::Value::Array(json)
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        );
+    } else {
+        for (i, result) in results.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let status = match result {
+                Ok(status) => status,
+                Err((url, error)) => {
+                    println!("{} {}: {}", "✗".red(), url.red(), error);
+                    continue;
+                }
+            };
+            let code = reqwest::StatusCode::from_u16(status.status)
+                .map(|code| code.to_string())
+                .unwrap_or_else(|_| status.status.to_string());
+            let code = if status.is_alive() {
+                code.green().bold()
+            } else {
+                code.red().bold()
+            };
+            println!("{} {}", "HTTP".bold(), code);
+            println!("{:<11}{}", "URL:", status.url);
+            if status.final_url != status.url {
+                println!("{:<11}{}", "Final URL:", status.final_url);
+            }
+            match status.size {
+                Some(size) => println!(
+                    "{:<11}{} ({} bytes)",
+                    "Size:",
+                    dwrs::units::format_bytes(size),
+                    size
+                ),
+                None => println!("{:<11}unknown", "Size:"),
+            }
+            println!(
+                "{:<11}{}",
+                "Type:",
+                status.content_type.as_deref().unwrap_or("unknown")
+            );
+            println!(
+                "{:<11}{}",
+                "Resumable:",
+                if status.resumable { "yes" } else { "no" }
+            );
+            println!();
+            for (name, value) in &status.headers {
+                println!("{}: {}", name.dimmed(), value);
+            }
+        }
+    }
+
+    ExitStatus::from_batch(failed, results.len()).exit();
+}
+
+async fn run_bench(downloader: &Downloader, args: &BenchArgs) {
+    let buffer_sizes: Vec<usize> = args.buffer_sizes.iter().map(|&s| s as usize).collect();
+    let cases = dwrs::bench::BenchCase::grid(&args.workers, &buffer_sizes);
+    println!(
+        "Measuring {} for up to {} per run\n",
+        args.url,
+        dwrs::summary::format_duration(args.duration)
+    );
+    println!("{}", dwrs::bench::render_header().bold());
+    let mut results = Vec::new();
+    for case in cases {
+        match downloader.bench(&args.url, case, args.duration).await {
+            Ok(result) => {
+                println!("{}", dwrs::bench::render_row(&result));
+                results.push(result);
+            }
+            Err(e) => println!("{:>7}  {}", case.workers, format!("✗ {}", e).red()),
+        }
+    }
+    let Some(best) = dwrs::bench::best(&results) else {
+        ExitStatus::Failure.exit();
+    };
+    println!(
+        "\n{} {} workers, {} buffer: set `workers = {}` and `buffer_size = {}` in the config",
+        "Fastest:".green().bold(),
+        best.case.workers,
+        dwrs::units::format_bytes(best.case.buffer_size as u64),
+        best.case.workers,
+        best.case.buffer_size
+    );
+}
+
+/// Checks `entries` without downloading them and prints whether they are
+/// alive, returning the exit status for the result.
+async fn spider(
+    downloader: &Downloader,
+    entries: impl futures::Stream<Item = DownloadEntry>,
+) -> ExitStatus {
+    let results = downloader.spider(entries).await;
+    let total = results.len();
+    let mut dead = 0;
+    for (entry, result) in results {
+        match result {
+            Ok(status) => {
+                let size = status
+                    .size
+                    .map(dwrs::units::format_bytes)
+                    .unwrap_or_else(|| "-".to_string());
+                let line = format!(
+                    "{} {:>10}  {}  {}  {}",
+                    status.status,
+                    size,
+                    status.content_type.as_deref().unwrap_or("-"),
+                    if status.resumable {
+                        "resumable"
+                    } else {
+                        "no-ranges"
+                    },
+                    entry.url
+                );
+                if status.is_alive() {
+                    println!("{} {}", "✓".green(), line);
+                } else {
+                    dead += 1;
+                    println!("{} {}", "✗".red(), line.red());
+                }
+                if status.final_url != entry.url {
+                    println!("    {} {}", "→".dimmed(), status.final_url.dimmed());
+                }
+            }
+            Err(e) => {
+                dead += 1;
+                println!("{} {}: {}", "✗".red(), entry.url.red(), e);
+            }
+        }
+    }
+
+    println!("{}/{} URL(s) alive", total - dead, total);
+    ExitStatus::from_batch(dead, total)
+}
+
+#[cfg(feature = "history")]
+fn show_history(args: &HistoryArgs) {
+    let path = History::default_path();
+    let records = History::open(&path).and_then(|history| {
+        history.query(&HistoryQuery {
+            failed_only: args.failed,
+            search: args.search.clone(),
+            limit: (args.limit > 0).then_some(args.limit),
+        })
+    });
+    let records = match records {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("{}: {}", "Error reading history".red().bold(), e);
+            ExitStatus::Failure.exit();
+        }
+    };
+
+    if records.is_empty() {
+        println!("No downloads recorded in {}", path.display());
+        return;
+    }
+
+    for record in records.iter().rev() {
+        let status = if record.is_success() {
+            "✓".green().bold()
+        } else {
+            "✗".red().bold()
+        };
+        let size = record
+            .size
+            .map(dwrs::units::format_bytes)
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{} UTC {} {:>10} {:>8} {} → {}",
+            dwrs::utils::format_unix_time(record.finished_at),
+            status,
+            size,
+            format!("{:.1}s", record.duration.as_secs_f64()),
+            record.url,
+            record.path.display()
+        );
+        if let Some(error) = &record.error {
+            println!("    {}", error.red());
+        }
+        if let Some(checksum) = &record.checksum {
+            println!("    {}", checksum.dimmed());
+        }
+    }
+}
+
+async fn show_status(args: &StatusArgs) {
+    let dir = status::state_dir();
+    let states = status::read_all(&dir);
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&states).unwrap_or_default()
+        );
+        return;
+    }
+    if states.is_empty() {
+        println!("No background or daemon downloads running");
+        return;
+    }
+    if !args.watch {
+        print!("{}", status::render(&states));
+        return;
+    }
+
+    let term = console::Term::stdout();
+    let mut states = states;
+    let mut lines = 0;
+    while !states.is_empty() {
+        let text = status::render(&states);
+        term.clear_last_lines(lines).ok();
+        print!("{}", text);
+        lines = text.lines().count();
+        tokio::time::sleep(status::UPDATE_INTERVAL).await;
+        states = status::read_all(&dir);
+    }
+    println!("All downloads finished");
+}
+
+/// Runs the queue until stopped. URLs given on the command line wait for
+/// `start_at`; the rest of the daemon does not.
+async fn run_daemon(
+    downloader: &Downloader,
+    args: &DaemonArgs,
+    max_files: Option<usize>,
+    start_at: Option<SystemTime>,
+) {
+    let queue = DownloadQueue::new(max_files.unwrap_or(4));
+    for url in &args.url {
+        let url = cli_url(url);
+        let output = PathBuf::from(filename_from_url(&url));
+        queue.push_at(DownloadEntry::new(url, output), 0, start_at);
+    }
+
+    #[cfg(unix)]
+    let activated = dwrs::systemd::take_listener();
+    #[cfg(not(unix))]
+    let activated: Option<std::net::TcpListener> = None;
+
+    if !args.enable_rpc && activated.is_none() {
+        notify_ready(&queue);
+        downloader.run_queue(&queue).await;
+        return;
+    }
+
+    if args.rpc_listen_all && args.rpc_secret.is_none() {
+        eprintln!(
+            "{}: --rpc-listen-all needs --rpc-secret",
+            "Refusing to start".red().bold()
+        );
+        ExitStatus::Usage.exit();
+    }
+    let ip = if args.rpc_listen_all {
+        [0, 0, 0, 0]
+    } else {
+        [127, 0, 0, 1]
+    };
+    let opts = RpcOptions {
+        addr: SocketAddr::from((ip, args.rpc_listen_port)),
+        secret: args.rpc_secret.clone(),
+        ..Default::default()
+    };
+    let listener = match activated {
+        Some(listener) => tokio::net::TcpListener::from_std(listener),
+        None => tokio::net::TcpListener::bind(opts.addr).await,
+    };
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to listen on {}: {}", opts.addr, e);
+            eprintln!("{}: {}", "JSON-RPC server failed".red().bold(), e);
+            ExitStatus::Failure.exit();
+        }
+    };
+    notify_ready(&queue);
+
+    let listener = tokio::spawn(rpc::serve_on(listener, queue.clone(), opts));
+    tokio::select! {
+        _ = downloader.run_queue(&queue) => {}
+        result = listener => {
+            if let Ok(Err(e)) = result {
+                error!("JSON-RPC server failed: {}", e);
+                eprintln!("{}: {}", "JSON-RPC server failed".red().bold(), e);
+                ExitStatus::Failure.exit();
+            }
+        }
+    }
+}
+
+async fn watch_manifests(downloader: &Downloader, args: &WatchDirArgs, max_files: Option<usize>) {
+    if !args.path.is_dir() {
+        eprintln!(
+            "{}: {}",
+            "Not a directory".red().bold(),
+            args.path.display()
+        );
+        ExitStatus::Usage.exit();
+    }
+    let queue = DownloadQueue::new(max_files.unwrap_or(4));
+    let opts = WatchDirOptions {
+        dir: args.path.clone(),
+        output_dir: args.dir.clone(),
+        interval: std::time::Duration::from_secs(args.interval.max(1)),
+    };
+    eprintln!(
+        "Watching {} for manifests, press Ctrl-C to stop",
+        args.path.display()
+    );
+    tokio::select! {
+        _ = downloader.run_queue(&queue) => {}
+        result = dwrs::watch_dir::watch(queue.clone(), opts) => {
+            if let Err(e) = result {
+                error!("Watching {} failed: {}", args.path.display(), e);
+                eprintln!("{}: {}", "Watching failed".red().bold(), e);
+                ExitStatus::Failure.exit();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "clipboard")]
+async fn watch_clipboard(
+    downloader: &Downloader,
+    args: &WatchClipboardArgs,
+    max_files: Option<usize>,
+) {
+    let pattern = match args.pattern.as_deref().map(regex::Regex::new) {
+        Some(Ok(re)) => Some(re),
+        Some(Err(e)) => {
+            eprintln!("{}: {}", "Invalid pattern".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+        None => None,
+    };
+    let queue = DownloadQueue::new(max_files.unwrap_or(4));
+    let opts = ClipboardOptions {
+        pattern,
+        dir: args.dir.clone(),
+        interval: std::time::Duration::from_millis(args.interval.max(50)),
+    };
+    if let Err(e) = dwrs::clipboard::watch(queue.clone(), opts) {
+        error!("Cannot read the clipboard: {}", e);
+        eprintln!("{}: {}", "Cannot read the clipboard".red().bold(), e);
+        ExitStatus::Failure.exit();
+    }
+    eprintln!("Watching the clipboard for URLs, press Ctrl-C to stop");
+    downloader.run_queue(&queue).await;
+}
+
+/// Crawls `url` and downloads only the links picked from the found ones.
+#[cfg(feature = "tui")]
+async fn mirror_picked(
+    downloader: &Downloader,
+    url: &str,
+    opts: CrawlOptions,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let resources = downloader.crawl(url, opts).await?;
+    let links = resources.iter().map(|entry| entry.url.clone()).collect();
+    let picked = match dwrs::picker::pick(links).await? {
+        Some(picked) if !picked.is_empty() => picked,
+        _ => {
+            println!("No links picked from {}", url);
+            return Ok(());
+        }
+    };
+    let downloads = picked.into_iter().map(|i| resources[i].clone()).collect();
+    downloader.download_entries(downloads).await
+}
+
+/// Tells systemd the daemon is up and keeps its status line current.
+fn notify_ready(queue: &DownloadQueue) {
+    #[cfg(unix)]
+    {
+        use dwrs::systemd;
+        if !systemd::notify("READY=1") {
+            return;
+        }
+        let queue = queue.clone();
+        tokio::spawn(async move {
+            loop {
+                let status = systemd::status_line(&queue.snapshot());
+                systemd::notify(&format!("STATUS={}", status));
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            }
+        });
+    }
+    #[cfg(not(unix))]
+    let _ = queue;
+}
+
+async fn verify(downloader: &Downloader, args: &VerifyArgs) {
+    let (Some(file), Some(checksum)) = (&args.file, &args.checksum) else {
+        verify_files(downloader, args).await;
+        return;
+    };
+    let expected = match Checksum::parse(checksum) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            eprintln!("{}: {}", "Invalid checksum".red().bold(), e);
+            ExitStatus::Usage.exit();
+        }
+    };
+    match dwrs::checksum::verify_file(file, &expected).await {
+        Ok(()) => println!("{} {}", "✓".green().bold(), file.display()),
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            ExitStatus::Failure.exit();
+        }
+    }
+}
+
+/// Checks every file of `--manifest` or `--sums` and lists the missing and
+/// corrupt ones, downloading them again with `--redownload`.
+async fn verify_files(downloader: &Downloader, args: &VerifyArgs) {
+    let entries: Vec<DownloadEntry> = if let Some(path) = &args.manifest {
+        match dwrs::parse_manifest(path, &ManifestOptions::default()).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("{}: {}", "Error reading file".red().bold(), e);
+                ExitStatus::Usage.exit();
+            }
+        }
+    } else if let Some(path) = &args.sums {
+        match dwrs::checksum::read_sums(path).await {
+            Ok(sums) => sums
+                .into_iter()
+                .map(|(output, checksum)| DownloadEntry {
+                    checksum: Some(checksum),
+                    ..DownloadEntry::new("", output)
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("{}: {}: {}", "Cannot read".red().bold(), path.display(), e);
+                ExitStatus::Usage.exit();
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let (mut ok, mut unchecked) = (0, 0);
+    let mut bad = Vec::new();
+    for entry in entries {
+        let Some(expected) = &entry.checksum else {
+            unchecked += 1;
+            continue;
+        };
+        match dwrs::checksum::check_file(&entry.output, expected).await {
+            Ok(FileCheck::Ok) => {
+                ok += 1;
+                println!("{} {}", "✓".green().bold(), entry.output.display());
+            }
+            Ok(FileCheck::Missing) => {
+                println!(
+                    "{} {}: missing",
+                    "?".yellow().bold(),
+                    entry.output.display()
+                );
+                bad.push(entry);
+            }
+            Ok(FileCheck::Mismatch(actual)) => {
+                println!(
+                    "{} {}: expected {}, got {}:{}",
+                    "✗".red().bold(),
+                    entry.output.display(),
+                    expected,
+                    expected.algorithm,
+                    actual
+                );
+                bad.push(entry);
+            }
+            Err(e) => {
+                println!("{} {}: {}", "✗".red().bold(), entry.output.display(), e);
+                bad.push(entry);
+            }
+        }
+    }
+    println!(
+        "{} ok, {} missing or corrupt, {} without checksum",
+        ok,
+        bad.len(),
+        unchecked
+    );
+
+    if bad.is_empty() {
+        return;
+    }
+    if !args.redownload {
+        ExitStatus::Failure.exit();
+    }
+    info!("Downloading {} file(s) again", bad.len());
+    if let Err(e) = downloader.download_entries(bad).await {
+        error!("Error during downloads: {}", e);
+        download_failed(&*e).exit();
+    }
+}
+
+fn config_init(path: Option<&Path>, force: bool) {
+    let Some(path) = path else {
+        eprintln!(
+            "{}",
+            "No config directory found, pass --config PATH".red().bold()
+        );
+        ExitStatus::Config.exit();
+    };
+    if path.exists() && !force {
+        eprintln!(
+            "{}: {} (use --force to overwrite)",
+            "Config already exists".red().bold(),
+            path.display()
+        );
+        ExitStatus::Failure.exit();
+    }
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(path, Config::default_toml()));
+    match result {
+        Ok(()) => println!("Wrote {}", path.display()),
+        Err(e) => {
+            eprintln!("{}: {}", "Failed to write config".red().bold(), e);
+            ExitStatus::Failure.exit();
+        }
+    }
+}
+
+fn config_validate(path: Option<&Path>, profile: Option<&str>) {
+    let Some(path) = path else {
+        eprintln!(
+            "{}",
+            "No config directory found, pass --config PATH".red().bold()
+        );
+        ExitStatus::Config.exit();
+    };
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("{}: {}: {}", "Error".red().bold(), path.display(), e);
+            ExitStatus::Config.exit();
+        }
+    };
+
+    let diagnostics = dwrs::config::validate(&content);
+    let warnings = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .count();
+    let mut errors = diagnostics.len() - warnings;
+    for diagnostic in &diagnostics {
+        let label = match diagnostic.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        let position = match (diagnostic.line, diagnostic.column) {
+            (Some(line), Some(column)) => format!(":{}:{}", line, column),
+            _ => String::new(),
+        };
+        println!(
+            "{}{}: {}: {}",
+            path.display(),
+            position,
+            label,
+            diagnostic.message
+        );
+    }
+    if errors == 0
+        && let Some(profile) = profile
+        && let Err(e) = Config::try_load(path, Some(profile))
+    {
+        println!("{}: {}", "error".red().bold(), e);
+        errors += 1;
+    }
+
+    if errors > 0 {
+        eprintln!("{} error(s), {} warning(s)", errors, warnings);
+        ExitStatus::Config.exit();
+    }
+    println!(
+        "{} {} is valid{}",
+        "✓".green(),
+        path.display(),
+        if warnings == 0 {
+            String::new()
+        } else {
+            format!(" ({} warning(s))", warnings)
+        }
+    );
+}
+
+fn config_show(cfg: &Config, path: Option<&Path>, profile: Option<&str>) {
+    match path {
+        Some(path) if path.exists() => {
+            if let Err(e) = Config::try_load(path, profile) {
+                eprintln!("{}: {}", "Warning".yellow().bold(), e);
+            }
+            println!("{}", format!("# {}", path.display()).dimmed());
+        }
+        Some(path) => println!("{}", format!("# {} (not found)", path.display()).dimmed()),
+        None => {}
+    }
+    for key in dwrs::config::KEYS {
+        match cfg.value(key) {
+            // The secret of a webhook is its URL
+            Some(_) if *key == "notify_webhook" => println!(
+                "{} = {}  {}",
+                key,
+                Webhook::parse(cfg.notify_webhook.as_deref().unwrap_or_default())
+                    .map_or_else(|_| "\"***\"".to_string(), |w| format!("{:?}", w.redacted()))
+                    .dimmed(),
+                format!("# {}", cfg.source(key)).dimmed()
+            ),
+            Some(value) => println!(
+                "{} = {}  {}",
+                key,
+                value,
+                format!("# {}", cfg.source(key)).dimmed()
+            ),
+            None => println!("{}", format!("# {} is not set", key).dimmed()),
+        }
+    }
+    for window in &cfg.schedule {
+        println!(
+            "\n[[schedule]]  {}",
+            format!("# {}", cfg.source("schedule")).dimmed()
+        );
+        println!("from = \"{}\"", dwrs::bandwidth::format_time(window.from));
+        println!("to = \"{}\"", dwrs::bandwidth::format_time(window.to));
+        println!("rate_limit = {}", window.rate_limit.unwrap_or(0));
+    }
+    for (name, host) in &cfg.hosts {
+        println!("\n[host.\"{}\"]", name);
+        for (key, value) in [
+            ("workers", host.workers.map(|v| v.to_string())),
+            ("rate_limit", host.rate_limit.map(|v| v.to_string())),
+            ("retries", host.retries.map(|v| v.to_string())),
+        ] {
+            if let Some(value) = value {
+                println!("{} = {}", key, value);
+            }
+        }
+        for (header, _) in &host.headers {
+            println!("headers.{:?} = {}", header, "\"***\"".dimmed());
+        }
+        if host.auth.is_some() {
+            println!("auth = {}", "\"***\"".dimmed());
         }
     }
 }
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::queue::{QueueItemStatus, QueueState};
+
+/// Counters kept by a [`DownloadQueue`](crate::queue::DownloadQueue) for
+/// the daemon's Prometheus endpoint at `/metrics`.
+///
+/// Gauges such as active downloads and queue depth are read from the queue
+/// itself when rendering; only totals that outlive queue items live here.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    downloaded_bytes: AtomicU64,
+    completed: AtomicU64,
+    retries: AtomicU64,
+    /// Failed downloads keyed by host
+    failures: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    /// Counts a download that just completed or failed.
+    pub(crate) fn record(&self, url: &str, bytes: u64, attempts: usize, failed: bool) {
+        self.downloaded_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.retries
+            .fetch_add(attempts.saturating_sub(1) as u64, Ordering::Relaxed);
+        if failed {
+            let host = reqwest::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string))
+                .unwrap_or_default();
+            *self
+                .failures
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(host)
+                .or_default() += 1;
+        } else {
+            self.completed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Renders the counters plus gauges for `items` in the Prometheus text format.
+    pub fn render(&self, items: &[QueueItemStatus]) -> String {
+        let count = |f: fn(&QueueState) -> bool| items.iter().filter(|i| f(&i.state)).count();
+        let speed: f64 = items.iter().map(|i| i.speed).sum();
+
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            if !value.is_empty() {
+                let _ = writeln!(out, "{}", value);
+            }
+        };
+        metric(
+            "dwrs_active_downloads",
+            "gauge",
+            "Downloads currently running",
+            format!(
+                "dwrs_active_downloads {}",
+                count(|s| *s == QueueState::Active)
+            ),
+        );
+        metric(
+            "dwrs_queued_downloads",
+            "gauge",
+            "Downloads waiting for a free slot",
+            format!(
+                "dwrs_queued_downloads {}",
+                count(|s| *s == QueueState::Queued)
+            ),
+        );
+        metric(
+            "dwrs_paused_downloads",
+            "gauge",
+            "Downloads held back until resumed",
+            format!(
+                "dwrs_paused_downloads {}",
+                count(|s| *s == QueueState::Paused)
+            ),
+        );
+        metric(
+            "dwrs_download_speed_bytes",
+            "gauge",
+            "Combined transfer rate in bytes per second",
+            format!("dwrs_download_speed_bytes {}", speed as u64),
+        );
+        metric(
+            "dwrs_downloaded_bytes_total",
+            "counter",
+            "Bytes on disk for finished downloads",
+            format!(
+                "dwrs_downloaded_bytes_total {}",
+                self.downloaded_bytes.load(Ordering::Relaxed)
+            ),
+        );
+        metric(
+            "dwrs_downloads_completed_total",
+            "counter",
+            "Downloads finished successfully",
+            format!(
+                "dwrs_downloads_completed_total {}",
+                self.completed.load(Ordering::Relaxed)
+            ),
+        );
+        metric(
+            "dwrs_retries_total",
+            "counter",
+            "Download attempts beyond the first",
+            format!(
+                "dwrs_retries_total {}",
+                self.retries.load(Ordering::Relaxed)
+            ),
+        );
+        let failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        metric(
+            "dwrs_download_failures_total",
+            "counter",
+            "Downloads that failed after all retries, by host",
+            failures
+                .iter()
+                .map(|(host, n)| {
+                    format!(
+                        "dwrs_download_failures_total{{host=\"{}\"}} {}",
+                        host.replace('\\', "\\\\").replace('"', "\\\""),
+                        n
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record("https://a.example.com/x", 100, 1, false);
+        metrics.record("https://b.example.com/y", 10, 3, true);
+        metrics.record("https://b.example.com/z", 0, 1, true);
+
+        let text = metrics.render(&[]);
+        assert!(text.contains("\ndwrs_active_downloads 0\n"));
+        assert!(text.contains("\ndwrs_downloaded_bytes_total 110\n"));
+        assert!(text.contains("\ndwrs_downloads_completed_total 1\n"));
+        assert!(text.contains("\ndwrs_retries_total 2\n"));
+        assert!(text.contains("\ndwrs_download_failures_total{host=\"b.example.com\"} 2\n"));
+    }
+}
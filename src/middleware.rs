@@ -0,0 +1,154 @@
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use std::sync::Arc;
+
+use crate::transport::{Transport, TransportError};
+
+/// Sees every request of downloads and URL checks before it is sent, and
+/// its response before dwrs reads it.
+///
+/// Middleware is added with
+/// [`Downloader::add_middleware`](crate::Downloader::add_middleware) and
+/// applies to `HEAD` requests, whole `GET` requests and the ranged ones of
+/// resumed and parallel downloads alike. The first one added runs first;
+/// each passes the request on with [`Next::run`], or answers it itself,
+/// e.g. from a cache:
+///
+/// ```
+/// use dwrs::middleware::{Middleware, Next};
+/// use dwrs::transport::TransportError;
+/// use futures::future::BoxFuture;
+///
+/// struct Sign(String);
+///
+/// impl Middleware for Sign {
+///     fn handle<'a>(
+///         &'a self,
+///         mut request: reqwest::Request,
+///         next: Next<'a>,
+///     ) -> BoxFuture<'a, Result<reqwest::Response, TransportError>> {
+///         request
+///             .headers_mut()
+///             .insert("x-signature", self.0.parse().unwrap());
+///         Box::pin(async move {
+///             let response = next.run(request).await?;
+///             log::debug!("{} answered {}", response.url(), response.status());
+///             Ok(response)
+///         })
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Handles `request`, usually by changing it and calling `next`.
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, TransportError>>;
+}
+
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, TransportError>> {
+        (**self).handle(request, next)
+    }
+}
+
+/// The rest of the chain after a [`Middleware`], ending at the transport
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn Transport,
+}
+
+impl<'a> Next<'a> {
+    /// Passes `request` to the next middleware, or sends it once none is left.
+    pub fn run(self, request: Request) -> BoxFuture<'a, Result<Response, TransportError>> {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(
+                request,
+                Next {
+                    middleware: rest,
+                    transport: self.transport,
+                },
+            ),
+            None => self.transport.execute(request),
+        }
+    }
+}
+
+/// A transport with middleware in front of it
+pub(crate) struct Layered {
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) transport: Arc<dyn Transport>,
+}
+
+impl Transport for Layered {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+        Next {
+            middleware: &self.middleware,
+            transport: &*self.transport,
+        }
+        .run(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use std::sync::Mutex;
+
+    /// Adds a header to each request and records what was sent
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Middleware for Recorder {
+        fn handle<'a>(
+            &'a self,
+            mut request: Request,
+            next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, TransportError>> {
+            request
+                .headers_mut()
+                .insert("x-signed", "yes".parse().unwrap());
+            Box::pin(async move {
+                let method = request.method().to_string();
+                let response = next.run(request).await?;
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", method, response.status().as_u16()));
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/file.txt").header("x-signed", "yes");
+            then.status(200)
+                .header("Content-Length", "11")
+                .body("hello world");
+        });
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        let mut downloader = crate::Downloader::builder()
+            .progress(false)
+            .build()
+            .unwrap();
+        downloader.add_middleware(recorder.clone());
+
+        let path = std::env::temp_dir().join(format!("dwrs-middleware-{}", std::process::id()));
+        downloader
+            .download_file(&server.url("/file.txt"), path.clone())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(*recorder.0.lock().unwrap(), ["HEAD 200", "GET 200"]);
+    }
+}
@@ -1,4 +1,11 @@
 use notify_rust::Notification;
+use rust_i18n::t;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+static ACTIONS: AtomicBool = AtomicBool::new(false);
+static PENDING: AtomicUsize = AtomicUsize::new(0);
 
 pub fn spawn_background_process() -> std::io::Result<()> {
     let args: Vec<String> = std::env::args().filter(|a| a != "--background").collect();
@@ -12,19 +19,202 @@ pub fn spawn_background_process() -> std::io::Result<()> {
 
     let child = std::process::Command::new(&args[0])
         .args(&args[1..])
+        .env(crate::status::BACKGROUND_ENV, "1")
         .stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .spawn()?;
 
     println!("Download started in background (PID: {})", child.id());
+    println!("Run `dwrs status` to follow its progress");
     Ok(())
 }
 
 pub fn notify_send(msg: &str) {
-    if std::env::var("DISPLAY").is_ok() || std::env::var("WAYLAND_DISPLAY").is_ok() {
-        let _ = Notification::new().summary("dwrs").body(msg).show();
+    if available() {
+        show(notification("dwrs", msg));
     } else {
         println!("{}", msg);
     }
 }
+
+/// Whether notifications can be shown: always on Windows and macOS, which
+/// deliver them through the Action Center and Notification Center, and on
+/// other systems when there is a graphical session or a session bus to
+/// reach a notification server through.
+pub fn available() -> bool {
+    has_session(|name| std::env::var_os(name))
+}
+
+fn has_session(var: impl Fn(&str) -> Option<std::ffi::OsString>) -> bool {
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY", "DBUS_SESSION_BUS_ADDRESS"]
+        .iter()
+        .any(|name| var(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// A notification from dwrs as the platform shows it best.
+fn notification(summary: &str, body: &str) -> Notification {
+    let mut notification = Notification::new();
+    // Ignored on macOS, where the sending application is named instead
+    notification.appname("dwrs").summary(summary).body(body);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.icon("folder-download");
+    notification
+}
+
+fn show(notification: Notification) {
+    if let Err(e) = notification.show() {
+        log::debug!("Cannot show notification: {}", e);
+    }
+}
+
+/// Lets completion notifications carry "Open" and "Show in folder"
+/// buttons. Only worth it when the process stays around to act on them,
+/// see [`wait_for_actions`].
+pub fn enable_actions() {
+    ACTIONS.store(true, Ordering::Relaxed);
+}
+
+/// Notifies that `path` was downloaded, with actions to open it or its
+/// folder where the notification server supports them.
+pub fn notify_complete(path: &Path, body: &str) {
+    if !available() {
+        log::debug!("No notification server for: {}", body);
+        return;
+    }
+    let mut notification = notification(&t!("notify.complete"), body);
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if ACTIONS.load(Ordering::Relaxed) {
+        notification
+            .action("default", "Open")
+            .action("open", "Open")
+            .action("show", "Show in folder");
+        match notification.show() {
+            Ok(handle) => {
+                let path = path.to_path_buf();
+                PENDING.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    handle.wait_for_action(|action| {
+                        let result = match action {
+                            "default" | "open" => open_path(&path),
+                            "show" => show_in_folder(&path),
+                            _ => Ok(()),
+                        };
+                        if let Err(e) = result {
+                            log::warn!("Cannot open {}: {}", path.display(), e);
+                        }
+                    });
+                    PENDING.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(e) => log::debug!("Cannot show notification: {}", e),
+        }
+        return;
+    }
+    let _ = path;
+    show(notification);
+}
+
+/// Notifies that a download or a batch failed.
+pub fn notify_failed(body: &str) {
+    if !available() {
+        log::debug!("No notification server for: {}", body);
+        return;
+    }
+    show(notification(&t!("notify.failed"), body));
+}
+
+/// Waits until every notification with actions was acted on or closed,
+/// but at most `timeout`.
+pub async fn wait_for_actions(timeout: Duration) {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while PENDING.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Opens `path` with the default application of the desktop.
+pub fn open_path(path: &Path) -> std::io::Result<()> {
+    opener(path, false).spawn().map(drop)
+}
+
+/// Opens the folder of `path` in the file manager, with the file selected
+/// where the platform allows it.
+pub fn show_in_folder(path: &Path) -> std::io::Result<()> {
+    opener(path, true).spawn().map(drop)
+}
+
+fn opener(path: &Path, folder: bool) -> std::process::Command {
+    #[cfg(target_os = "windows")]
+    let command = {
+        let mut command = std::process::Command::new("explorer");
+        if folder {
+            let mut select = std::ffi::OsString::from("/select,");
+            select.push(path);
+            command.arg(select);
+        } else {
+            command.arg(path);
+        }
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let command = {
+        let mut command = std::process::Command::new("open");
+        if folder {
+            command.arg("-R");
+        }
+        command.arg(path);
+        command
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let command = {
+        let mut command = std::process::Command::new("xdg-open");
+        match path
+            .parent()
+            .filter(|dir| folder && !dir.as_os_str().is_empty())
+        {
+            Some(dir) => command.arg(dir),
+            None if folder => command.arg("."),
+            None => command.arg(path),
+        };
+        command
+    };
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_session() {
+        let none = |_: &str| None;
+        let wayland = |name: &str| (name == "WAYLAND_DISPLAY").then(|| "wayland-0".into());
+        assert!(has_session(wayland));
+        if cfg!(any(target_os = "windows", target_os = "macos")) {
+            assert!(has_session(none));
+        } else {
+            assert!(!has_session(none));
+            assert!(!has_session(|_: &str| Some("".into())));
+        }
+    }
+
+    #[test]
+    fn test_opener() {
+        let command = opener(Path::new("downloads/file.zip"), true);
+        let args: Vec<_> = command.get_args().collect();
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        assert_eq!(
+            (command.get_program(), args),
+            ("xdg-open".as_ref(), vec!["downloads".as_ref()])
+        );
+        #[cfg(target_os = "macos")]
+        assert_eq!(args, ["-R", "downloads/file.zip"]);
+        #[cfg(target_os = "windows")]
+        assert_eq!(args, ["/select,downloads/file.zip"]);
+    }
+}
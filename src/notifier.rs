@@ -0,0 +1,411 @@
+//! Where notifications about finished downloads and batches go.
+//!
+//! Every backend implements [`Notifier`] and is added with
+//! [`Downloader::add_notifier`](crate::Downloader::add_notifier); the CLI
+//! picks them with `notify_backend` in the config. Besides the desktop,
+//! messages can go to a Telegram chat through a bot or to a Matrix room.
+
+use futures::future::BoxFuture;
+use reqwest::{Method, Response};
+use rust_i18n::t;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::config::{MatrixSettings, TelegramSettings};
+use crate::summary::format_duration;
+use crate::transport::{Transport, request};
+use crate::units::format_bytes;
+use crate::utils::{parse_template, render};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Time a notifier gets to deliver a notification before it is given up on
+pub(crate) const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a notification tells
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub body: String,
+    pub failed: bool,
+    /// File, or folder of a batch, that desktop actions open
+    pub path: Option<PathBuf>,
+}
+
+impl Notification {
+    pub fn completed(body: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            body: body.into(),
+            failed: false,
+            path: Some(path.into()),
+        }
+    }
+
+    pub fn failed(body: impl Into<String>) -> Self {
+        Self {
+            body: body.into(),
+            failed: true,
+            path: None,
+        }
+    }
+
+    /// `Download Complete` or `Download Failed`, in the UI language
+    pub fn summary(&self) -> String {
+        if self.failed {
+            t!("notify.failed").to_string()
+        } else {
+            t!("notify.complete").to_string()
+        }
+    }
+
+    /// Summary and body on two lines, for chat messages.
+    pub fn text(&self) -> String {
+        format!("{}\n{}", self.summary(), self.body)
+    }
+}
+
+/// Templates for the bodies of notifications about single downloads,
+/// with the variables `{file}`, `{size}`, `{duration}` and `{error}`.
+///
+/// Unset templates use the translation for the UI language, e.g.
+/// `Finished: {file} ({size})`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Messages {
+    pub complete: Option<String>,
+    pub failed: Option<String>,
+}
+
+impl Messages {
+    /// Notification that `file` was downloaded.
+    pub fn completed(&self, file: &Path, bytes: u64, duration: Duration) -> Notification {
+        let vars = variables(file, bytes, duration);
+        let body = fill(self.complete.as_deref(), "notify.complete_body", &vars);
+        Notification::completed(body, file)
+    }
+
+    /// Notification that downloading `file` failed with `error`.
+    pub fn failed(&self, file: &Path, bytes: u64, duration: Duration, error: &str) -> Notification {
+        let mut vars = variables(file, bytes, duration);
+        vars.insert("error", error.into());
+        Notification::failed(fill(self.failed.as_deref(), "notify.failed_body", &vars))
+    }
+}
+
+/// `{file}`, `{size}` and `{duration}` of a download
+pub(crate) fn variables(
+    file: &Path,
+    bytes: u64,
+    duration: Duration,
+) -> HashMap<&'static str, Cow<'_, str>> {
+    HashMap::from([
+        ("file", file.to_string_lossy()),
+        ("size", format_bytes(bytes).into()),
+        ("duration", format_duration(duration).into()),
+    ])
+}
+
+/// Renders `template`, or else the translation of `key`, with `vars`.
+pub(crate) fn fill(
+    template: Option<&str>,
+    key: &str,
+    vars: &HashMap<&str, Cow<'_, str>>,
+) -> String {
+    match template {
+        Some(template) => render(&parse_template(template), vars),
+        None => render(&parse_template(&t!(key)), vars),
+    }
+}
+
+/// Renders the form of the translation of `key` for `count`, see
+/// [`plural`](crate::localization::plural), with `vars`.
+pub(crate) fn fill_plural(key: &str, count: usize, vars: &HashMap<&str, Cow<'_, str>>) -> String {
+    render(
+        &parse_template(&crate::localization::plural(key, count, &[])),
+        vars,
+    )
+}
+
+/// What a download must reach to be announced, so quick small ones do not
+/// flood the desktop. Failures are announced regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Threshold {
+    /// Announce downloads that took at least this long
+    pub min_duration: Option<Duration>,
+    /// Announce downloads of at least this many bytes
+    pub min_size: Option<u64>,
+}
+
+impl Threshold {
+    /// Whether a download of `bytes` that took `duration` is announced:
+    /// always without limits, otherwise when it reaches either of them.
+    pub fn reached(&self, bytes: u64, duration: Duration) -> bool {
+        match (self.min_duration, self.min_size) {
+            (None, None) => true,
+            (min_duration, min_size) => {
+                min_duration.is_some_and(|min| duration >= min)
+                    || min_size.is_some_and(|min| bytes >= min)
+            }
+        }
+    }
+}
+
+/// Delivers notifications somewhere, e.g. to a chat.
+///
+/// `transport` is the one of the downloader, proxy and middleware
+/// included. The caller gives a notifier ten seconds; errors and timeouts
+/// are logged and never change the outcome of a download.
+///
+/// ```
+/// use dwrs::notifier::{Notification, Notifier};
+/// use futures::future::BoxFuture;
+///
+/// struct Log;
+///
+/// impl Notifier for Log {
+///     fn notify<'a>(
+///         &'a self,
+///         _transport: &'a dyn dwrs::transport::Transport,
+///         notification: &'a Notification,
+///     ) -> BoxFuture<'a, Result<(), Box<dyn std::error::Error + Send + Sync>>> {
+///         Box::pin(async move {
+///             log::info!("{}", notification.text());
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+pub trait Notifier: Send + Sync {
+    fn notify<'a>(
+        &'a self,
+        transport: &'a dyn Transport,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Sends `body` as JSON to `url` through `transport`, keeping the URL,
+/// which may hold a token, out of errors.
+async fn send_json(
+    transport: &dyn Transport,
+    method: Method,
+    url: &str,
+    headers: &[(String, String)],
+    body: serde_json::Value,
+) -> Result<Response, Error> {
+    let mut headers = headers.to_vec();
+    headers.push(("Content-Type".to_string(), "application/json".to_string()));
+    let mut req = request(method, url, &headers).map_err(|_| "invalid notification URL")?;
+    *req.body_mut() = Some(body.to_string().into());
+    transport
+        .execute(req)
+        .await
+        .map_err(|e| match e.downcast::<reqwest::Error>() {
+            Ok(e) => e.without_url().into(),
+            Err(e) => e,
+        })
+}
+
+/// Desktop notifications through the notification server of the session,
+/// with actions to open the file where supported, see
+/// [`notifications`](crate::notifications).
+#[cfg(feature = "notify")]
+pub struct Desktop;
+
+#[cfg(feature = "notify")]
+impl Notifier for Desktop {
+    fn notify<'a>(
+        &'a self,
+        _transport: &'a dyn Transport,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        match (&notification.path, notification.failed) {
+            (Some(path), false) => crate::notifications::notify_complete(path, &notification.body),
+            _ => crate::notifications::notify_failed(&notification.body),
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Messages to a Telegram chat, sent by a bot made with @BotFather
+pub struct Telegram {
+    settings: TelegramSettings,
+}
+
+impl Telegram {
+    pub fn new(settings: TelegramSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Notifier for Telegram {
+    fn notify<'a>(
+        &'a self,
+        transport: &'a dyn Transport,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let settings = &self.settings;
+            let url = format!(
+                "{}/bot{}/sendMessage",
+                settings.api_url.trim_end_matches('/'),
+                settings.token
+            );
+            let body = serde_json::json!({
+                "chat_id": settings.chat_id,
+                "text": notification.text(),
+            });
+            let response = send_json(transport, Method::POST, &url, &[], body).await?;
+            if !response.status().is_success() {
+                return Err(format!("Telegram answered {}", response.status()).into());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Messages to a Matrix room, sent as the user of an access token
+pub struct Matrix {
+    settings: MatrixSettings,
+    /// Makes the transaction ids of one run unique
+    sent: AtomicU64,
+}
+
+impl Matrix {
+    pub fn new(settings: MatrixSettings) -> Self {
+        Self {
+            settings,
+            sent: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Notifier for Matrix {
+    fn notify<'a>(
+        &'a self,
+        transport: &'a dyn Transport,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let settings = &self.settings;
+            let txn = format!(
+                "dwrs-{}-{}",
+                chrono::Utc::now().timestamp_millis(),
+                self.sent.fetch_add(1, Ordering::Relaxed)
+            );
+            let room = percent_encoding::utf8_percent_encode(
+                &settings.room_id,
+                percent_encoding::NON_ALPHANUMERIC,
+            );
+            let url = format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+                settings.homeserver.trim_end_matches('/'),
+                room,
+                txn
+            );
+            let auth = [(
+                "Authorization".to_string(),
+                format!("Bearer {}", settings.access_token),
+            )];
+            let body = serde_json::json!({
+                "msgtype": "m.text",
+                "body": notification.text(),
+            });
+            let response = send_json(transport, Method::PUT, &url, &auth, body).await?;
+            if !response.status().is_success() {
+                return Err(format!("Matrix answered {}", response.status()).into());
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[test]
+    fn test_threshold() {
+        let second = Duration::from_secs(1);
+        assert!(Threshold::default().reached(0, Duration::ZERO));
+        let threshold = Threshold {
+            min_duration: Some(30 * second),
+            min_size: Some(1024),
+        };
+        assert!(!threshold.reached(1023, 29 * second));
+        assert!(threshold.reached(1024, second));
+        assert!(threshold.reached(0, 30 * second));
+        let threshold = Threshold {
+            min_size: Some(1024),
+            ..Threshold::default()
+        };
+        assert!(!threshold.reached(10, 3600 * second));
+    }
+
+    #[test]
+    fn test_messages() {
+        let second = Duration::from_secs(1);
+        let messages = Messages::default();
+        let notification = messages.completed(Path::new("a.iso"), 2048, 3 * second);
+        assert_eq!(notification.body, "Finished: a.iso (2.00 KiB)");
+        let notification = messages.failed(Path::new("b.iso"), 0, second, "HTTP 404");
+        assert_eq!(notification.text(), "Download Failed\nb.iso: HTTP 404");
+
+        let messages = Messages {
+            complete: Some("{file} done in {duration}".to_string()),
+            failed: Some("{file} ({size}) failed: {error}".to_string()),
+        };
+        let notification = messages.completed(Path::new("a.iso"), 2048, 3 * second);
+        assert_eq!(notification.body, "a.iso done in 3.0s");
+        let notification = messages.failed(Path::new("b.iso"), 10, second, "timeout");
+        assert_eq!(notification.body, "b.iso (10 B) failed: timeout");
+    }
+
+    #[tokio::test]
+    async fn test_chat_notifiers() {
+        let server = MockServer::start();
+        let telegram = server.mock(|when, then| {
+            when.method("POST")
+                .path("/bot123:abc/sendMessage")
+                .json_body(serde_json::json!({
+                    "chat_id": "-10042",
+                    "text": "Download Complete\na.iso (1 KiB)",
+                }));
+            then.status(200);
+        });
+        let matrix = server.mock(|when, then| {
+            when.method("PUT")
+                .path_includes("/_matrix/client/v3/rooms/")
+                .path_includes("/send/m.room.message/dwrs-")
+                .header("Authorization", "Bearer secret");
+            then.status(200);
+        });
+        let client = reqwest::Client::new();
+        let notification = Notification::completed("a.iso (1 KiB)", "a.iso");
+
+        let notifier = Telegram::new(TelegramSettings {
+            token: "123:abc".to_string(),
+            chat_id: "-10042".to_string(),
+            api_url: server.base_url(),
+        });
+        notifier.notify(&client, &notification).await.unwrap();
+        telegram.assert();
+
+        let notifier = Matrix::new(MatrixSettings {
+            homeserver: server.base_url(),
+            access_token: "secret".to_string(),
+            room_id: "!room:example.org".to_string(),
+        });
+        notifier.notify(&client, &notification).await.unwrap();
+        matrix.assert();
+
+        let failed = Notification::failed("b.iso: HTTP 404");
+        let notifier = Telegram::new(TelegramSettings {
+            token: "123:abc".to_string(),
+            chat_id: "1".to_string(),
+            api_url: server.base_url(),
+        });
+        assert!(notifier.notify(&client, &failed).await.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, PathBuf};
+use std::sync::RwLock;
+
+use crate::sanitize::sanitize;
+use crate::utils::{Token, parse_template, render};
+
+/// Variables understood by `output_template`
+pub const OUTPUT_VARS: &[&str] = &["host", "dirname", "filename", "stem", "ext", "date"];
+
+static CURRENT: Lazy<RwLock<Option<OutputTemplate>>> = Lazy::new(|| RwLock::new(None));
+
+/// Layout of output paths derived from URLs, such as `{host}/{dirname}/{filename}`
+///
+/// Variables:
+/// - `{host}`: host name of the URL
+/// - `{dirname}`: directories of the URL path, may be empty
+/// - `{filename}`: last path segment, as used without a template
+/// - `{stem}`, `{ext}`: `{filename}` without and only its extension
+/// - `{date}`: today's local date as `YYYY-MM-DD`
+///
+/// Every value is percent-decoded and [sanitized](crate::sanitize), so only
+/// the `/` written in the template itself creates directories.
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    tokens: Vec<Token>,
+    absolute: bool,
+}
+
+impl OutputTemplate {
+    /// Parses a template, rejecting unknown variables and color styles.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        if template.trim().is_empty() {
+            return Err("output template is empty".to_string());
+        }
+        let tokens = parse_template(template);
+        for token in &tokens {
+            if let Token::Var { name, color } = token {
+                if !OUTPUT_VARS.contains(&name.as_str()) {
+                    return Err(format!(
+                        "unknown variable {{{}}} (expected one of {})",
+                        name,
+                        OUTPUT_VARS.join(", ")
+                    ));
+                }
+                if color.is_some() {
+                    return Err(format!("{{{}}} cannot have a style in a path", name));
+                }
+            }
+        }
+        Ok(Self {
+            tokens,
+            absolute: template.starts_with('/'),
+        })
+    }
+
+    /// Makes `template` the layout used by
+    /// [`filename_from_url`](crate::file_parser::filename_from_url).
+    pub fn apply(template: Option<OutputTemplate>) {
+        *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = template;
+    }
+
+    /// Template last set with [`OutputTemplate::apply`].
+    pub fn current() -> Option<OutputTemplate> {
+        CURRENT.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Output path for `url`.
+    ///
+    /// Empty segments, e.g. from an empty `{dirname}`, are dropped; the path
+    /// is only absolute if the template starts with `/`.
+    pub fn render(&self, url: &Url) -> PathBuf {
+        let mut segments: Vec<String> = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+            .collect();
+        let filename = segments
+            .pop()
+            .filter(|s| !s.is_empty())
+            .map_or_else(|| "file.bin".to_string(), |s| sanitize(&s));
+        let segments: Vec<String> = segments
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| sanitize(s))
+            .collect();
+        let (stem, ext) = match filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), ext.to_string()),
+            _ => (filename.clone(), String::new()),
+        };
+
+        let vars: HashMap<&str, Cow<'_, str>> = HashMap::from([
+            (
+                "host",
+                Cow::Owned(sanitize(url.host_str().unwrap_or("unknown"))),
+            ),
+            ("dirname", Cow::Owned(segments.join("/"))),
+            ("stem", Cow::Owned(stem)),
+            ("ext", Cow::Owned(ext)),
+            ("filename", Cow::Owned(filename)),
+            (
+                "date",
+                Cow::Owned(chrono::Local::now().format("%Y-%m-%d").to_string()),
+            ),
+        ]);
+        let rendered = render(&self.tokens, &vars);
+
+        let mut path = PathBuf::from(if self.absolute { "/" } else { "" });
+        path.extend(
+            PathBuf::from(rendered)
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_) | Component::ParentDir)),
+        );
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_output_template() {
+        let url = Url::parse("https://Example.com/pub/My%20Docs/report.tar.gz?x=1").unwrap();
+        let template = OutputTemplate::parse("{host}/{dirname}/{stem}-v1.{ext}").unwrap();
+        assert_eq!(
+            template.render(&url),
+            PathBuf::from("example.com/pub/My Docs/report.tar-v1.gz")
+        );
+
+        let url = Url::parse("https://example.com/a%2F..%2Fb").unwrap();
+        let template = OutputTemplate::parse("{dirname}/{filename}").unwrap();
+        assert_eq!(template.render(&url), PathBuf::from("a_.._b"));
+        let dir = Url::parse("https://example.com/pub//dir/").unwrap();
+        assert_eq!(template.render(&dir), PathBuf::from("pub/dir/file.bin"));
+
+        let template = OutputTemplate::parse("{date}-{filename}").unwrap();
+        let name = template.render(&url).to_string_lossy().into_owned();
+        assert!(name.ends_with("-a_.._b") && name.len() == 17, "{}", name);
+
+        assert!(OutputTemplate::parse("{size}/{filename}").is_err());
+        assert!(OutputTemplate::parse("{filename:red}").is_err());
+    }
+}
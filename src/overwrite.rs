@@ -0,0 +1,227 @@
+use crate::units::format_bytes;
+use chrono::DateTime;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::Downloader;
+use crate::deferred::format_local;
+use crate::download::is_stdout;
+use crate::file_parser::DownloadEntry;
+use crate::spider::UrlStatus;
+
+/// What happens to downloads whose output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Ask on the terminal for every existing file
+    Ask,
+    /// Overwrite without asking
+    Always,
+    /// Skip the download and keep the existing file
+    Never,
+}
+
+/// Answer to an overwrite question
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No,
+    /// Overwrite this and every following file
+    All,
+    /// Keep this and every following file
+    None,
+}
+
+impl Answer {
+    /// Parses a typed answer; an empty line means no.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "y" | "yes" => Some(Self::Yes),
+            "" | "n" | "no" => Some(Self::No),
+            "a" | "all" => Some(Self::All),
+            "s" | "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Size and modification time of one side of an overwrite
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileInfo {
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileInfo {
+    /// Reads the size and modification time of an existing file.
+    pub fn local(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            size: Some(metadata.len()),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Takes the size and `Last-Modified` date a server reported.
+    pub fn remote(status: &UrlStatus) -> Self {
+        let modified = status
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("last-modified"))
+            .and_then(|(_, value)| DateTime::parse_from_rfc2822(value).ok())
+            .map(SystemTime::from);
+        Self {
+            size: status.size,
+            modified,
+        }
+    }
+}
+
+impl std::fmt::Display for FileInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.size {
+            Some(size) => write!(f, "{}", format_bytes(size))?,
+            None => write!(f, "unknown size")?,
+        }
+        match self.modified {
+            Some(modified) => write!(f, ", modified {}", format_local(modified)),
+            None => write!(f, ", unknown date"),
+        }
+    }
+}
+
+/// The question asked before `path` is overwritten by the download of `url`.
+pub fn question(path: &Path, local: &FileInfo, url: &str, remote: Option<&FileInfo>) -> String {
+    let remote = remote.map_or_else(|| "cannot be checked".to_string(), ToString::to_string);
+    format!(
+        "{} already exists\n  existing: {}\n  new:      {} ({})\nOverwrite? [y]es, [N]o, [a]ll, [s]kip all: ",
+        path.display(),
+        local,
+        remote,
+        url
+    )
+}
+
+/// Prints `question` on stderr and reads answers from stdin until one is valid.
+///
+/// End of input counts as no.
+fn ask(question: &str) -> std::io::Result<Answer> {
+    let mut stderr = std::io::stderr();
+    let mut stdin = std::io::stdin().lock();
+    loop {
+        write!(stderr, "{}", question)?;
+        stderr.flush()?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            writeln!(stderr)?;
+            return Ok(Answer::No);
+        }
+        if let Some(answer) = Answer::parse(&line) {
+            return Ok(answer);
+        }
+    }
+}
+
+/// Drops the entries whose output exists and must not be overwritten.
+///
+/// With [`OverwritePolicy::Ask`] every existing file is shown next to what
+/// the server reports for its URL before asking. Entries are kept in order.
+pub async fn resolve(
+    downloader: &Downloader,
+    entries: Vec<DownloadEntry>,
+    mut policy: OverwritePolicy,
+) -> std::io::Result<Vec<DownloadEntry>> {
+    let mut kept = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if is_stdout(&entry.output) {
+            kept.push(entry);
+            continue;
+        }
+        let Ok(local) = FileInfo::local(&entry.output) else {
+            kept.push(entry);
+            continue;
+        };
+        let overwrite = match policy {
+            OverwritePolicy::Always => true,
+            OverwritePolicy::Never => false,
+            OverwritePolicy::Ask => {
+                let remote = downloader
+                    .check_url(&entry.url, &entry.headers)
+                    .await
+                    .ok()
+                    .filter(UrlStatus::is_alive)
+                    .map(|status| FileInfo::remote(&status));
+                let question = question(&entry.output, &local, &entry.url, remote.as_ref());
+                let answer = tokio::task::spawn_blocking(move || ask(&question))
+                    .await
+                    .map_err(std::io::Error::other)??;
+                match answer {
+                    Answer::All => policy = OverwritePolicy::Always,
+                    Answer::None => policy = OverwritePolicy::Never,
+                    Answer::Yes | Answer::No => {}
+                }
+                matches!(answer, Answer::Yes | Answer::All)
+            }
+        };
+        if overwrite {
+            kept.push(entry);
+        } else {
+            log::info!("Keeping existing file {}", entry.output.display());
+        }
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_existing_files_are_skipped() {
+        assert_eq!(Answer::parse(" Y\n"), Some(Answer::Yes));
+        assert_eq!(Answer::parse("\n"), Some(Answer::No));
+        assert_eq!(Answer::parse("s"), Some(Answer::None));
+        assert_eq!(Answer::parse("maybe"), None);
+
+        let status = UrlStatus {
+            url: "https://example.com/a.iso".to_string(),
+            final_url: "https://example.com/a.iso".to_string(),
+            status: 200,
+            size: Some(2048),
+            content_type: None,
+            resumable: false,
+            headers: vec![(
+                "last-modified".to_string(),
+                "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+            )],
+        };
+        let remote = FileInfo::remote(&status);
+        assert_eq!(
+            remote.modified,
+            Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_445_412_480))
+        );
+        assert!(
+            remote
+                .to_string()
+                .starts_with("2.00 KiB, modified 2015-10-2")
+        );
+
+        let dir = std::env::temp_dir().join(format!("dwrs-overwrite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("old.iso"), "old").unwrap();
+        let entries = vec![
+            DownloadEntry::new("https://example.com/old.iso", dir.join("old.iso")),
+            DownloadEntry::new("https://example.com/new.iso", dir.join("new.iso")),
+        ];
+        let downloader = Downloader::new_default();
+        let kept = resolve(&downloader, entries.clone(), OverwritePolicy::Never)
+            .await
+            .unwrap();
+        assert_eq!(kept, entries[1..]);
+        let kept = resolve(&downloader, entries.clone(), OverwritePolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(kept, entries);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
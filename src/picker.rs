@@ -0,0 +1,177 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState};
+use ratatui::{DefaultTerminal, Frame};
+
+const HELP: &str =
+    "type to filter  ↑↓ move  Space toggle  Ctrl-A toggle shown  Enter download  Esc cancel";
+
+/// What a key press did to the picker
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Continue,
+    Done,
+    Cancelled,
+}
+
+/// Filterable multi-select list of links
+struct Picker {
+    links: Vec<String>,
+    picked: Vec<bool>,
+    filter: String,
+    /// Position of the cursor within the shown links
+    cursor: usize,
+}
+
+impl Picker {
+    fn new(links: Vec<String>) -> Self {
+        let picked = vec![false; links.len()];
+        Self {
+            links,
+            picked,
+            filter: String::new(),
+            cursor: 0,
+        }
+    }
+
+    /// Indices of the links containing the filter, ignoring case.
+    fn shown(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        (0..self.links.len())
+            .filter(|&i| self.links[i].to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn picked(&self) -> Vec<usize> {
+        (0..self.links.len()).filter(|&i| self.picked[i]).collect()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let shown = self.shown();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Outcome::Cancelled,
+            KeyCode::Char('a') if ctrl => {
+                let all = shown.iter().all(|&i| self.picked[i]);
+                for &i in &shown {
+                    self.picked[i] = !all;
+                }
+            }
+            KeyCode::Esc => return Outcome::Cancelled,
+            KeyCode::Enter => return Outcome::Done,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor += 1,
+            KeyCode::Char(' ') => {
+                if let Some(&i) = shown.get(self.cursor) {
+                    self.picked[i] = !self.picked[i];
+                }
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.filter.push(c);
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+        self.cursor = self.cursor.min(self.shown().len().saturating_sub(1));
+        Outcome::Continue
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [filter_area, list_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Line::from(format!("Filter: {}█", self.filter)), filter_area);
+
+        let shown = self.shown();
+        let items: Vec<ListItem> = shown
+            .iter()
+            .map(|&i| {
+                let mark = if self.picked[i] { "[x]" } else { "[ ]" };
+                ListItem::new(format!("{} {}", mark, self.links[i]))
+            })
+            .collect();
+        let title = format!(
+            " {} of {} links picked, {} shown ",
+            self.picked().len(),
+            self.links.len(),
+            shown.len()
+        );
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().reversed());
+        let mut state =
+            ListState::default().with_selected((!shown.is_empty()).then_some(self.cursor));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        frame.render_widget(Line::from(HELP).dark_gray(), footer_area);
+    }
+}
+
+fn run_picker(
+    terminal: &mut DefaultTerminal,
+    links: Vec<String>,
+) -> std::io::Result<Option<Vec<usize>>> {
+    let mut picker = Picker::new(links);
+    loop {
+        terminal.draw(|frame| picker.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match picker.handle_key(key) {
+                Outcome::Continue => {}
+                Outcome::Done => return Ok(Some(picker.picked())),
+                Outcome::Cancelled => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Lets the user pick links from a filterable full-screen list.
+///
+/// Returns the indices of the picked links in their original order, or
+/// `None` if the user cancelled.
+pub async fn pick(links: Vec<String>) -> std::io::Result<Option<Vec<usize>>> {
+    crate::tui::with_terminal(move |terminal| run_picker(terminal, links)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_and_toggle() {
+        let mut picker = Picker::new(vec![
+            "https://example.com/a.iso".to_string(),
+            "https://example.com/b.zip".to_string(),
+            "https://example.com/c.iso".to_string(),
+        ]);
+        for c in ".ISO".chars() {
+            picker.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(picker.shown(), vec![0, 2]);
+        picker.handle_key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        picker.handle_key(KeyEvent::from(KeyCode::Down));
+        picker.handle_key(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(picker.picked(), vec![0]);
+
+        for _ in 0..4 {
+            picker.handle_key(KeyEvent::from(KeyCode::Backspace));
+        }
+        picker.handle_key(KeyEvent::from(KeyCode::Down));
+        picker.handle_key(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(picker.picked(), vec![0, 2]);
+        assert_eq!(
+            picker.handle_key(KeyEvent::from(KeyCode::Enter)),
+            Outcome::Done
+        );
+    }
+}
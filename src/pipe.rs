@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin};
+use tokio::task::JoinHandle;
+
+use crate::hooks::parse_command;
+use crate::utils::{Token, render};
+
+/// Variables understood in pipe commands
+pub const PIPE_VARS: &[&str] = &["url", "path"];
+
+/// Command a download is streamed through, such as `gpg --decrypt`
+///
+/// The response goes to the command's standard input and whatever it
+/// writes to standard output is saved as the file, so nothing is stored
+/// twice. `{url}` and `{path}` are filled in like for
+/// [hooks](crate::hooks::Hook) and also passed as `DWRS_URL` and
+/// `DWRS_PATH`; standard error is left on the terminal.
+#[derive(Debug, Clone)]
+pub struct PipeCommand {
+    words: Vec<Vec<Token>>,
+}
+
+impl PipeCommand {
+    /// Parses a command, rejecting unknown variables and unbalanced quotes.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        Ok(Self {
+            words: parse_command(command, PIPE_VARS)?,
+        })
+    }
+
+    fn args(&self, url: &str, output: &Path) -> Vec<String> {
+        let vars = HashMap::from([
+            ("url", Cow::Borrowed(url)),
+            ("path", output.to_string_lossy()),
+        ]);
+        self.words.iter().map(|word| render(word, &vars)).collect()
+    }
+
+    /// Starts the command with its output copied into `sink`.
+    ///
+    /// Returns the command's standard input, which must be dropped once
+    /// everything is written, and the [`Pipe`] to wait on afterwards. The
+    /// command is killed if the `Pipe` is dropped first.
+    pub fn start<W>(
+        &self,
+        url: &str,
+        output: &Path,
+        mut sink: W,
+    ) -> Result<(ChildStdin, Pipe), String>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let args = self.args(url, output);
+        log::debug!("Piping {} through {:?}", url, args);
+        let mut child = tokio::process::Command::new(&args[0])
+            .args(&args[1..])
+            .env("DWRS_URL", url)
+            .env("DWRS_PATH", output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Cannot run {}: {}", args[0], e))?;
+        let (Some(stdin), Some(mut stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Cannot connect to {}", args[0]));
+        };
+        let copy = tokio::spawn(async move {
+            let copied = tokio::io::copy(&mut stdout, &mut sink).await?;
+            sink.shutdown().await?;
+            Ok(copied)
+        });
+        let pipe = Pipe {
+            program: args[0].clone(),
+            child,
+            copy,
+        };
+        Ok((stdin, pipe))
+    }
+}
+
+/// A running [`PipeCommand`]
+pub struct Pipe {
+    program: String,
+    child: Child,
+    copy: JoinHandle<std::io::Result<u64>>,
+}
+
+impl Pipe {
+    /// Waits for the command to exit and its output to be saved,
+    /// returning the number of bytes it wrote.
+    pub async fn wait(mut self) -> Result<u64, String> {
+        let status = self
+            .child
+            .wait()
+            .await
+            .map_err(|e| format!("{}: {}", self.program, e))?;
+        let copied = (&mut self.copy)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Cannot save the output of {}: {}", self.program, e))?;
+        if !status.success() {
+            return Err(format!("{} exited with {}", self.program, status));
+        }
+        Ok(copied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pipe_command() {
+        let command = PipeCommand::parse("gpg --decrypt --output - {path}.sig").unwrap();
+        assert_eq!(
+            command.args("https://example.com/a", Path::new("dl/a b.tar")),
+            vec!["gpg", "--decrypt", "--output", "-", "dl/a b.tar.sig"]
+        );
+        assert!(PipeCommand::parse("tr {size}").is_err());
+
+        let (mut stdin, pipe) = PipeCommand::parse("tr a-z A-Z")
+            .unwrap()
+            .start("https://example.com/a", Path::new("a"), Vec::new())
+            .unwrap();
+        stdin.write_all(b"hello").await.unwrap();
+        drop(stdin);
+        assert_eq!(pipe.wait().await, Ok(5));
+
+        let (stdin, pipe) = PipeCommand::parse("false")
+            .unwrap()
+            .start("https://example.com/a", Path::new("a"), Vec::new())
+            .unwrap();
+        drop(stdin);
+        assert!(pipe.wait().await.unwrap_err().contains("false exited with"));
+    }
+}
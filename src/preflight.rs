@@ -0,0 +1,307 @@
+//! Checks every URL of a batch before it starts, for `--preflight`.
+//!
+//! The HEAD requests of [`spider::check`](crate::spider::check) tell the
+//! sizes, dead links and range support up front, so the batch can show one
+//! bar for its total, refuse to start when the disk is too small and queue
+//! the largest files first, which keeps a long download from being the only
+//! one left at the end.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::DownloadEntry;
+use crate::progress::ProgressBar;
+use crate::queue::DownloadQueue;
+use crate::spider::UrlStatus;
+use crate::units::format_bytes;
+
+/// How often the bar of the whole batch is brought up to date
+const TICK: Duration = Duration::from_millis(200);
+
+/// What the HEAD requests told about a batch
+#[derive(Debug, Clone, Default)]
+pub struct Preflight {
+    /// Entries to download with their announced size, largest first and
+    /// those of unknown size last
+    pub live: Vec<(DownloadEntry, Option<u64>)>,
+    /// Entries the server says are gone, with the reason
+    pub dead: Vec<(DownloadEntry, String)>,
+    /// Sum of the announced sizes
+    pub total: u64,
+    /// Live entries whose size was not announced
+    pub unknown: usize,
+    /// Live entries that cannot be resumed or split between workers
+    pub no_ranges: usize,
+}
+
+impl Preflight {
+    /// Sorts the results of [`Downloader::spider`](crate::Downloader::spider).
+    ///
+    /// Only 404 and 410 count as dead: servers refuse HEAD requests and
+    /// signed URLs in many other ways that a GET still gets past, and a
+    /// request that failed outright is left to the retries of the download.
+    pub fn new(results: Vec<(DownloadEntry, Result<UrlStatus, String>)>) -> Self {
+        let mut preflight = Self::default();
+        for (entry, result) in results {
+            match result {
+                Ok(status) if matches!(status.status, 404 | 410) => {
+                    let reason = format!("Dead link: HTTP {}", status.status);
+                    preflight.dead.push((entry, reason));
+                }
+                Ok(status) => {
+                    let size = status.size.filter(|_| status.is_alive());
+                    match size {
+                        Some(size) => preflight.total += size,
+                        None => preflight.unknown += 1,
+                    }
+                    if !status.resumable {
+                        preflight.no_ranges += 1;
+                    }
+                    preflight.live.push((entry, size));
+                }
+                Err(e) => {
+                    log::debug!("Preflight check of {} failed: {}", entry.url, e);
+                    preflight.unknown += 1;
+                    preflight.no_ranges += 1;
+                    preflight.live.push((entry, None));
+                }
+            }
+        }
+        // Stable, so files of the same size keep their input order
+        preflight
+            .live
+            .sort_by_key(|(_, size)| std::cmp::Reverse(size.map_or(0, |size| size + 1)));
+        preflight
+    }
+
+    /// `12 files, 3.20 GiB (2 of unknown size), 1 dead, 3 without range support`
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} files, {}",
+            self.live.len() + self.dead.len(),
+            format_bytes(self.total)
+        );
+        if self.unknown > 0 {
+            summary.push_str(&format!(" ({} of unknown size)", self.unknown));
+        }
+        if !self.dead.is_empty() {
+            summary.push_str(&format!(", {} dead", self.dead.len()));
+        }
+        if self.no_ranges > 0 {
+            summary.push_str(&format!(", {} without range support", self.no_ranges));
+        }
+        summary
+    }
+
+    /// Directories without room for the files going into them, as
+    /// `(directory, bytes needed, bytes free)`.
+    ///
+    /// Existing output files count towards what is already there, so a
+    /// resumed batch only needs room for the rest. Directories whose free
+    /// space cannot be told are assumed to be large enough.
+    pub fn lacking_space(&self) -> Vec<(PathBuf, u64, u64)> {
+        let mut needed: BTreeMap<PathBuf, u64> = BTreeMap::new();
+        for (entry, size) in &self.live {
+            let Some(size) = size else {
+                continue;
+            };
+            let existing = std::fs::metadata(&entry.output).map_or(0, |meta| meta.len());
+            *needed.entry(existing_dir(&entry.output)).or_default() +=
+                size.saturating_sub(existing);
+        }
+        needed
+            .into_iter()
+            .filter_map(|(dir, needed)| {
+                let free = free_space(&dir)?;
+                (needed > free).then_some((dir, needed, free))
+            })
+            .collect()
+    }
+
+    /// Queues the live entries largest first and fails the dead ones, so
+    /// they still show up in the outcome of the batch.
+    pub fn enqueue(self, queue: &DownloadQueue) {
+        if self.total > 0 {
+            queue.set_expected_size(Some(self.total));
+        }
+        for (entry, _) in self.live {
+            queue.push(entry, 0);
+        }
+        for (entry, reason) in self.dead {
+            let id = queue.push(entry, 0);
+            queue.fail(id, &reason);
+        }
+    }
+}
+
+/// Closest ancestor of `output` that already exists.
+fn existing_dir(output: &Path) -> PathBuf {
+    output
+        .ancestors()
+        .skip(1)
+        .map(|dir| {
+            if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            }
+        })
+        .find(|dir| dir.is_dir())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+/// Bytes an unprivileged user may still write to the filesystem of `dir`.
+#[cfg(unix)]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read once
+    // statvfs reported that it filled it in.
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Bytes an unprivileged user may still write to the filesystem of `dir`.
+#[cfg(not(unix))]
+pub fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// Bar over the whole batch, kept at the bytes downloaded by the queue
+/// until it is dropped.
+pub(crate) struct TotalBar {
+    pb: ProgressBar,
+    ticker: JoinHandle<()>,
+}
+
+impl TotalBar {
+    pub(crate) fn spawn(pb: ProgressBar, queue: DownloadQueue, total: u64) -> Self {
+        pb.set_length(total);
+        let ticker = tokio::spawn({
+            let pb = pb.clone();
+            async move {
+                let mut interval = tokio::time::interval(TICK);
+                loop {
+                    interval.tick().await;
+                    pb.set_position(queue.downloaded().min(total));
+                }
+            }
+        });
+        Self { pb, ticker }
+    }
+}
+
+impl Drop for TotalBar {
+    fn drop(&mut self) {
+        self.ticker.abort();
+        self.pb.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16, size: Option<u64>, resumable: bool) -> Result<UrlStatus, String> {
+        Ok(UrlStatus {
+            url: String::new(),
+            final_url: String::new(),
+            status: code,
+            size,
+            content_type: None,
+            resumable,
+            headers: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_preflight_orders_and_sorts_out() {
+        let entry = |name: &str| DownloadEntry::new(format!("https://example.com/{name}"), name);
+        let preflight = Preflight::new(vec![
+            (entry("small"), status(200, Some(10), true)),
+            (entry("unknown"), status(200, None, false)),
+            (entry("gone"), status(404, None, false)),
+            (entry("big"), status(200, Some(1000), true)),
+            (entry("offline"), Err("connection refused".to_string())),
+        ]);
+        let order: Vec<_> = preflight
+            .live
+            .iter()
+            .map(|(entry, _)| entry.output.to_str().unwrap())
+            .collect();
+        assert_eq!(order, ["big", "small", "unknown", "offline"]);
+        assert_eq!(preflight.dead.len(), 1);
+        assert_eq!(preflight.total, 1010);
+        assert_eq!(
+            preflight.summary(),
+            "5 files, 1010 B (2 of unknown size), 1 dead, 2 without range support"
+        );
+
+        let queue = DownloadQueue::new(1);
+        preflight.enqueue(&queue);
+        assert_eq!(queue.expected_size(), Some(1010));
+        let failed = queue
+            .snapshot()
+            .into_iter()
+            .filter(|item| item.state.is_finished());
+        assert_eq!(failed.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_with_preflight() {
+        use httpmock::MockServer;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/file.txt");
+            then.status(200)
+                .header("Content-Length", "11")
+                .body("hello world");
+        });
+        server.mock(|when, then| {
+            when.path("/gone.txt");
+            then.status(404);
+        });
+        let downloader = crate::Downloader::builder()
+            .progress(false)
+            .preflight(true)
+            .build()
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("dwrs-preflight-{}", std::process::id()));
+        let result = downloader
+            .download_entries(vec![
+                DownloadEntry::new(server.url("/gone.txt"), dir.join("gone.txt")),
+                DownloadEntry::new(server.url("/file.txt"), dir.join("file.txt")),
+            ])
+            .await;
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Dead link: HTTP 404")
+        );
+        assert_eq!(std::fs::read(dir.join("file.txt")).unwrap(), b"hello world");
+        assert!(!dir.join("gone.txt").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_existing_dir() {
+        let dir = std::env::temp_dir();
+        assert_eq!(existing_dir(&dir.join("missing/sub/file.bin")), dir);
+        assert_eq!(existing_dir(Path::new("file.bin")), Path::new("."));
+        #[cfg(unix)]
+        assert!(free_space(&dir).is_some());
+    }
+}
@@ -1,9 +1,112 @@
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+//! Progress bars of downloads.
+//!
+//! With the `terminal` feature these are the `indicatif` types drawn on
+//! stderr. Without it, the `headless` types with the same methods keep
+//! track of the positions without drawing anything or pulling in
+//! terminal crates.
 
+#[cfg(not(feature = "terminal"))]
+pub mod headless;
+
+#[cfg(not(feature = "terminal"))]
+pub use headless::{MultiProgress, ProgressBar, WeakProgressBar};
+#[cfg(feature = "terminal")]
+pub use indicatif::{MultiProgress, ProgressBar, WeakProgressBar};
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{borrow::Cow, collections::HashMap};
 
+use crate::units::{format_bytes, format_speed};
 use crate::utils::{parse_template, render};
 
+/// Container for the progress bars of one run; draws nothing unless `visible`.
+pub fn multi_progress(visible: bool) -> MultiProgress {
+    #[cfg(feature = "terminal")]
+    if !visible {
+        return MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
+    #[cfg(not(feature = "terminal"))]
+    let _ = visible;
+    MultiProgress::new()
+}
+
+/// Time after which a rate sample counts half as much as a new one
+const RATE_HALF_LIFE: Duration = Duration::from_secs(3);
+/// Samples closer together than this are merged into the next one
+const MIN_SAMPLE: Duration = Duration::from_millis(250);
+
+/// Transfer rate smoothed with an exponential moving average.
+///
+/// The rate indicatif derives jumps around when several connections write
+/// to one bar in bursts; this one weighs each sample by the time it covers,
+/// so it settles quickly but does not jitter, however often it is updated.
+#[derive(Debug, Default)]
+pub struct RateEstimator {
+    state: Mutex<RateState>,
+}
+
+#[derive(Debug, Default)]
+struct RateState {
+    last: Option<(Instant, u64)>,
+    rate: Option<f64>,
+}
+
+impl RateEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `position` bytes have been transferred by now and
+    /// returns the smoothed rate in bytes per second.
+    pub fn update(&self, position: u64) -> f64 {
+        self.update_at(position, Instant::now())
+    }
+
+    fn update_at(&self, position: u64, now: Instant) -> f64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((since, from)) = state.last else {
+            state.last = Some((now, position));
+            return 0.0;
+        };
+        if position < from {
+            // The download started over, e.g. after a failed attempt
+            *state = RateState {
+                last: Some((now, position)),
+                rate: None,
+            };
+            return 0.0;
+        }
+        let elapsed = now.duration_since(since);
+        if elapsed < MIN_SAMPLE {
+            return state.rate.unwrap_or(0.0);
+        }
+        let sample = (position - from) as f64 / elapsed.as_secs_f64();
+        let weight = 1.0 - 0.5f64.powf(elapsed.as_secs_f64() / RATE_HALF_LIFE.as_secs_f64());
+        let rate = state
+            .rate
+            .map_or(sample, |rate| rate + weight * (sample - rate));
+        *state = RateState {
+            last: Some((now, position)),
+            rate: Some(rate),
+        };
+        rate
+    }
+
+    /// Smoothed rate as of the last update, in bytes per second.
+    pub fn rate(&self) -> f64 {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.rate.unwrap_or(0.0)
+    }
+
+    /// Time left to transfer `length` bytes at the smoothed rate, after
+    /// recording `position`.
+    pub fn eta(&self, position: u64, length: u64) -> Option<Duration> {
+        let rate = self.update(position);
+        (rate > 0.0).then(|| Duration::from_secs_f64(length.saturating_sub(position) as f64 / rate))
+    }
+}
+
 pub fn create_progress_bar(
     mp: &MultiProgress,
     template: &str,
@@ -14,11 +117,47 @@ pub fn create_progress_bar(
 ) -> ProgressBar {
     let pb = mp.add(ProgressBar::new_spinner());
 
+    #[cfg(feature = "terminal")]
+    let rate = std::sync::Arc::new(RateEstimator::new());
+    #[cfg(feature = "terminal")]
     pb.set_style(
-        ProgressStyle::with_template(template)
-            .unwrap_or_else(|_| ProgressStyle::default_bar())
-            .progress_chars(chars),
+        indicatif::ProgressStyle::with_template(template)
+            .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar())
+            .progress_chars(chars)
+            .with_key(
+                "size",
+                |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    write!(w, "{}", format_bytes(state.pos())).ok();
+                },
+            )
+            .with_key(
+                "total_size",
+                |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    if let Some(len) = state.len() {
+                        write!(w, "{}", format_bytes(len)).ok();
+                    }
+                },
+            )
+            .with_key("speed", {
+                let rate = rate.clone();
+                move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    write!(w, "{}", format_speed(rate.update(state.pos()))).ok();
+                }
+            })
+            .with_key(
+                "eta",
+                move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                    let eta = state.len().and_then(|len| rate.eta(state.pos(), len));
+                    match eta {
+                        Some(eta) => write!(w, "{}", crate::summary::format_duration(eta)),
+                        None => write!(w, "?"),
+                    }
+                    .ok();
+                },
+            ),
     );
+    #[cfg(not(feature = "terminal"))]
+    let _ = (template, chars);
 
     let tokens = parse_template(msg_template);
 
@@ -33,3 +172,106 @@ pub fn create_progress_bar(
 
     pb
 }
+
+/// Prints the progress of `pb` as a timestamped line on stderr every
+/// `interval` until it is finished, for logs where bars cannot be drawn.
+pub fn log_progress(pb: &ProgressBar, name: String, interval: Duration) {
+    let weak = pb.downgrade();
+    let interval = interval.max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let rate = RateEstimator::new();
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            ticks.tick().await;
+            let Some(pb) = weak.upgrade().filter(|pb| !pb.is_finished()) else {
+                break;
+            };
+            eprintln!(
+                "{} {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                status_line(&name, &pb, &rate)
+            );
+        }
+    });
+}
+
+/// `file.zip 45% 23.00 MiB/s ETA 2m05s`, or the bytes so far when the
+/// size is unknown.
+fn status_line(name: &str, pb: &ProgressBar, rate: &RateEstimator) -> String {
+    let speed = format_speed(rate.update(pb.position()));
+    match pb.length().filter(|&len| len > 0) {
+        Some(len) => {
+            let position = pb.position().min(len);
+            let eta = rate
+                .eta(position, len)
+                .map_or("?".to_string(), crate::summary::format_duration);
+            format!("{} {}% {} ETA {}", name, position * 100 / len, speed, eta)
+        }
+        None => format!("{} {} {}", name, format_bytes(pb.position()), speed),
+    }
+}
+
+/// Spinner showing a single changing message, hidden unless `visible`.
+pub fn spinner(visible: bool) -> ProgressBar {
+    #[cfg(feature = "terminal")]
+    if visible {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(indicatif::ProgressStyle::with_template("{spinner} {msg}").unwrap());
+        return pb;
+    }
+    #[cfg(not(feature = "terminal"))]
+    let _ = visible;
+    ProgressBar::hidden()
+}
+
+/// Checks a progress bar template; any template passes without the
+/// `terminal` feature since nothing is drawn.
+pub fn check_template(template: &str) -> Result<(), String> {
+    #[cfg(feature = "terminal")]
+    if let Err(e) = indicatif::ProgressStyle::with_template(template) {
+        return Err(e.to_string());
+    }
+    #[cfg(not(feature = "terminal"))]
+    let _ = template;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_line() {
+        let pb = ProgressBar::hidden();
+        let rate = RateEstimator::new();
+        pb.set_position(3 * 1024 * 1024);
+        assert!(status_line("file.zip", &pb, &rate).starts_with("file.zip 3.00 MiB "));
+
+        pb.set_length(4 * 1024 * 1024);
+        let line = status_line("file.zip", &pb, &rate);
+        assert!(line.starts_with("file.zip 75% "), "{}", line);
+        assert!(line.contains("/s ETA "), "{}", line);
+    }
+
+    #[test]
+    fn test_rate_estimator() {
+        let rate = RateEstimator::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(rate.update_at(0, start), 0.0);
+        // Updates closer together than MIN_SAMPLE are merged
+        assert_eq!(rate.update_at(1000, at(100)), 0.0);
+        let first = rate.update_at(300_000, at(300));
+        assert_eq!(first, 1_000_000.0);
+
+        // A stall only slowly pulls the rate down
+        let stalled = rate.update_at(300_000, at(600));
+        assert!(stalled < first && stalled > first * 0.8, "{}", stalled);
+        assert_eq!(rate.rate(), stalled);
+        assert!(rate.eta(300_000, 300_000).unwrap().is_zero());
+
+        // Starting over forgets the old rate
+        assert_eq!(rate.update(0), 0.0);
+        assert_eq!(rate.eta(0, 100), None);
+    }
+}
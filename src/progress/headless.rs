@@ -0,0 +1,148 @@
+//! Stand-ins for the `indicatif` types used without the `terminal`
+//! feature: nothing is drawn, but positions are still tracked so queue
+//! snapshots, the status file and retries see how far downloads got.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+
+/// Length of a bar that has none, like a spinner
+const NO_LENGTH: u64 = u64::MAX;
+
+#[derive(Debug)]
+struct State {
+    position: AtomicU64,
+    length: AtomicU64,
+    finished: AtomicBool,
+    started: Instant,
+}
+
+/// Progress of one download, shared by its clones
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    state: Arc<State>,
+}
+
+impl ProgressBar {
+    pub fn new(length: u64) -> Self {
+        Self {
+            state: Arc::new(State {
+                position: AtomicU64::new(0),
+                length: AtomicU64::new(length),
+                finished: AtomicBool::new(false),
+                started: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn new_spinner() -> Self {
+        Self::new(NO_LENGTH)
+    }
+
+    pub fn hidden() -> Self {
+        Self::new_spinner()
+    }
+
+    pub fn position(&self) -> u64 {
+        self.state.position.load(Ordering::Relaxed)
+    }
+
+    pub fn set_position(&self, position: u64) {
+        self.state.position.store(position, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.state.position.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn length(&self) -> Option<u64> {
+        Some(self.state.length.load(Ordering::Relaxed)).filter(|&len| len != NO_LENGTH)
+    }
+
+    pub fn set_length(&self, length: u64) {
+        self.state.length.store(length, Ordering::Relaxed);
+    }
+
+    /// Average bytes per second since the bar was created.
+    pub fn per_sec(&self) -> f64 {
+        let secs = self.state.started.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            self.position() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Relaxed)
+    }
+
+    pub fn downgrade(&self) -> WeakProgressBar {
+        WeakProgressBar {
+            state: Arc::downgrade(&self.state),
+        }
+    }
+
+    pub fn set_message(&self, _message: impl Into<Cow<'static, str>>) {}
+
+    pub fn tick(&self) {}
+
+    pub fn finish(&self) {
+        self.state.finished.store(true, Ordering::Relaxed);
+    }
+
+    pub fn finish_with_message(&self, _message: impl Into<Cow<'static, str>>) {
+        self.finish();
+    }
+
+    pub fn finish_and_clear(&self) {
+        self.finish();
+    }
+
+    pub fn abandon_with_message(&self, _message: impl Into<Cow<'static, str>>) {
+        self.finish();
+    }
+}
+
+/// Handle to a bar that does not keep it alive
+#[derive(Debug, Clone)]
+pub struct WeakProgressBar {
+    state: Weak<State>,
+}
+
+impl WeakProgressBar {
+    pub fn upgrade(&self) -> Option<ProgressBar> {
+        self.state.upgrade().map(|state| ProgressBar { state })
+    }
+}
+
+/// Group of progress bars; only hands the bars back
+#[derive(Debug, Default, Clone)]
+pub struct MultiProgress;
+
+impl MultiProgress {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn add(&self, pb: ProgressBar) -> ProgressBar {
+        pb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_progress() {
+        let pb = MultiProgress::new().add(ProgressBar::new_spinner());
+        assert_eq!(pb.length(), None);
+        pb.set_length(10);
+        pb.clone().inc(4);
+        assert_eq!((pb.position(), pb.length()), (4, Some(10)));
+        pb.finish_with_message("done");
+        assert!(pb.is_finished());
+    }
+}
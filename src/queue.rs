@@ -0,0 +1,687 @@
+use crate::progress::{ProgressBar, RateEstimator};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+
+use crate::download::{ConnectionStatus, Connections, DownloadReport};
+use crate::file_parser::{DownloadEntry, path_key};
+
+/// Identifier assigned to every queued download
+pub type DownloadId = u64;
+
+/// Lifecycle of a queued download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueueState {
+    Queued,
+    /// Held back until [`DownloadQueue::resume`] is called
+    Paused,
+    Active,
+    Completed,
+    Failed(String),
+    /// Cancelled with [`DownloadQueue::cancel`]
+    Removed,
+}
+
+impl QueueState {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            QueueState::Completed | QueueState::Failed(_) | QueueState::Removed
+        )
+    }
+}
+
+/// Point-in-time view of a queued download
+#[derive(Debug, Clone)]
+pub struct QueueItemStatus {
+    pub id: DownloadId,
+    pub url: String,
+    pub output: PathBuf,
+    pub priority: i32,
+    pub state: QueueState,
+    /// Bytes downloaded so far
+    pub downloaded: u64,
+    /// Total size in bytes, `0` if unknown
+    pub total: u64,
+    /// Current transfer rate in bytes per second, smoothed
+    pub speed: f64,
+    /// Time spent active, so far or in total once finished
+    pub elapsed: Duration,
+    /// Download attempts started, retries included
+    pub attempts: usize,
+    /// Earliest time the download may start
+    pub start_at: Option<SystemTime>,
+    /// What the download did, once it completed
+    pub report: Option<DownloadReport>,
+    /// Connections of an active parallel download, one per chunk
+    pub connections: Vec<ConnectionStatus>,
+}
+
+impl QueueItemStatus {
+    /// Whether the item is queued but waiting for its start time.
+    pub fn is_scheduled(&self) -> bool {
+        self.state == QueueState::Queued && self.start_at.is_some_and(|at| at > SystemTime::now())
+    }
+}
+
+struct Item {
+    entry: DownloadEntry,
+    priority: i32,
+    seq: u64,
+    state: QueueState,
+    progress: Option<ProgressBar>,
+    /// Smooths the speed seen in snapshots
+    rate: RateEstimator,
+    connections: Connections,
+    task: Option<AbortHandle>,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    attempts: usize,
+    start_at: Option<SystemTime>,
+    report: Option<DownloadReport>,
+}
+
+struct Inner {
+    items: HashMap<DownloadId, Item>,
+    next_id: DownloadId,
+    active: usize,
+    max_active: usize,
+    closed: bool,
+    aborted: bool,
+    expected_size: Option<u64>,
+}
+
+/// Priority queue of downloads with a limited number of active slots.
+///
+/// Items with a higher priority start first; equal priorities keep FIFO
+/// order. The queue is cheap to clone and all clones share state, so it can
+/// be filled from one task while [`Downloader::run_queue`](crate::Downloader::run_queue)
+/// drains it in another.
+///
+/// # Examples
+///
+/// ```
+/// use dwrs::DownloadEntry;
+/// use dwrs::queue::DownloadQueue;
+///
+/// let queue = DownloadQueue::new(2);
+/// let low = queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+/// let high = queue.push(DownloadEntry::new("https://example.com/b", "b"), 10);
+/// queue.set_priority(low, 20);
+/// assert_eq!(queue.snapshot()[0].id, low);
+/// assert!(queue.remove(high));
+/// ```
+#[derive(Clone)]
+pub struct DownloadQueue {
+    inner: Arc<Mutex<Inner>>,
+    notify: Arc<Notify>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<crate::metrics::Metrics>,
+}
+
+impl DownloadQueue {
+    /// Creates a queue running at most `max_active` downloads at once.
+    pub fn new(max_active: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                items: HashMap::new(),
+                next_id: 1,
+                active: 0,
+                max_active: max_active.max(1),
+                closed: false,
+                aborted: false,
+                expected_size: None,
+            })),
+            notify: Arc::new(Notify::new()),
+            #[cfg(feature = "metrics")]
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Totals exported by the daemon at `/metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &crate::metrics::Metrics {
+        &self.metrics
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds a download and returns its id.
+    pub fn push(&self, entry: DownloadEntry, priority: i32) -> DownloadId {
+        self.push_at(entry, priority, None)
+    }
+
+    /// Adds a download that does not start before `start_at` and returns its id.
+    ///
+    /// Until then it is skipped in favour of items that may start.
+    pub fn push_at(
+        &self,
+        entry: DownloadEntry,
+        priority: i32,
+        start_at: Option<SystemTime>,
+    ) -> DownloadId {
+        let id = {
+            let mut inner = self.lock();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            log::debug!("Queued #{} {} (priority {})", id, entry.url, priority);
+            let state = if inner.aborted {
+                QueueState::Removed
+            } else {
+                QueueState::Queued
+            };
+            inner.items.insert(
+                id,
+                Item {
+                    entry,
+                    priority,
+                    seq: id,
+                    state,
+                    progress: None,
+                    rate: RateEstimator::new(),
+                    connections: Connections::new(),
+                    task: None,
+                    started: None,
+                    finished: None,
+                    attempts: 0,
+                    start_at,
+                    report: None,
+                },
+            );
+            id
+        };
+        self.notify.notify_waiters();
+        id
+    }
+
+    /// Changes the priority of a queued item. Returns `false` if it already started.
+    pub fn set_priority(&self, id: DownloadId, priority: i32) -> bool {
+        let mut inner = self.lock();
+        match inner.items.get_mut(&id) {
+            Some(item) if item.state == QueueState::Queued => {
+                item.priority = priority;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes an item that has not started yet.
+    pub fn remove(&self, id: DownloadId) -> bool {
+        let mut inner = self.lock();
+        match inner.items.get(&id) {
+            Some(item) if item.state == QueueState::Queued => {
+                inner.items.remove(&id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Holds an item back. Active downloads are interrupted and re-queued
+    /// from scratch (or from the partial file with resume) on [`resume`](Self::resume).
+    pub fn pause(&self, id: DownloadId) -> bool {
+        let mut inner = self.lock();
+        match inner.items.get_mut(&id) {
+            Some(item) if matches!(item.state, QueueState::Queued | QueueState::Active) => {
+                if let Some(task) = item.task.take() {
+                    task.abort();
+                }
+                if item.state == QueueState::Active {
+                    item.finished = Some(Instant::now());
+                }
+                item.state = QueueState::Paused;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Puts a paused item back into the queue.
+    pub fn resume(&self, id: DownloadId) -> bool {
+        let resumed = {
+            let mut inner = self.lock();
+            match inner.items.get_mut(&id) {
+                Some(item) if item.state == QueueState::Paused => {
+                    item.state = QueueState::Queued;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if resumed {
+            self.notify.notify_waiters();
+        }
+        resumed
+    }
+
+    /// Stops an item whatever its state and keeps it as [`QueueState::Removed`].
+    pub fn cancel(&self, id: DownloadId) -> bool {
+        let mut inner = self.lock();
+        match inner.items.get_mut(&id) {
+            Some(item) if !item.state.is_finished() => {
+                if let Some(task) = item.task.take() {
+                    task.abort();
+                }
+                if item.state == QueueState::Active {
+                    item.finished = Some(Instant::now());
+                }
+                item.state = QueueState::Removed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancels every unfinished item and closes the queue.
+    ///
+    /// Returns how many items were cancelled. Items pushed afterwards are
+    /// cancelled right away by [`push`](Self::push).
+    pub fn abort(&self) -> usize {
+        let cancelled = {
+            let mut inner = self.lock();
+            inner.closed = true;
+            inner.aborted = true;
+            let mut cancelled = 0;
+            for item in inner.items.values_mut() {
+                if item.state.is_finished() {
+                    continue;
+                }
+                if let Some(task) = item.task.take() {
+                    task.abort();
+                }
+                if item.state == QueueState::Active {
+                    item.finished = Some(Instant::now());
+                }
+                item.state = QueueState::Removed;
+                cancelled += 1;
+            }
+            cancelled
+        };
+        self.notify.notify_waiters();
+        cancelled
+    }
+
+    /// Fails every queued item with `reason` without starting it.
+    ///
+    /// Returns how many items were skipped. Active and paused items are left
+    /// alone and the queue stays open.
+    pub fn skip_queued(&self, reason: &str) -> usize {
+        let skipped = {
+            let mut inner = self.lock();
+            let mut skipped = 0;
+            for item in inner.items.values_mut() {
+                if item.state == QueueState::Queued {
+                    item.state = QueueState::Failed(reason.to_string());
+                    skipped += 1;
+                }
+            }
+            skipped
+        };
+        self.notify.notify_waiters();
+        skipped
+    }
+
+    /// Fails a queued item with `reason` without starting it. Returns
+    /// `false` if it already started.
+    pub fn fail(&self, id: DownloadId, reason: &str) -> bool {
+        let failed = {
+            let mut inner = self.lock();
+            match inner.items.get_mut(&id) {
+                Some(item) if item.state == QueueState::Queued => {
+                    item.state = QueueState::Failed(reason.to_string());
+                    true
+                }
+                _ => false,
+            }
+        };
+        if failed {
+            self.notify.notify_waiters();
+        }
+        failed
+    }
+
+    /// Announces the size of everything in the queue, e.g. as learned by a
+    /// [`Preflight`](crate::preflight::Preflight);
+    /// [`Downloader::run_queue`](crate::Downloader::run_queue) then draws a
+    /// bar for the whole batch.
+    pub fn set_expected_size(&self, bytes: Option<u64>) {
+        self.lock().expected_size = bytes;
+    }
+
+    /// Size set with [`set_expected_size`](Self::set_expected_size).
+    pub fn expected_size(&self) -> Option<u64> {
+        self.lock().expected_size
+    }
+
+    /// Bytes downloaded by the items still in the queue, finished ones included.
+    pub fn downloaded(&self) -> u64 {
+        self.snapshot().iter().map(|status| status.downloaded).sum()
+    }
+
+    /// Changes how many downloads may run at the same time.
+    pub fn set_max_active(&self, max_active: usize) {
+        self.lock().max_active = max_active.max(1);
+        self.notify.notify_waiters();
+    }
+
+    /// Marks the queue as complete: runners return once it is drained.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.notify.notify_waiters();
+    }
+
+    /// Status of a single item.
+    pub fn status(&self, id: DownloadId) -> Option<QueueItemStatus> {
+        let inner = self.lock();
+        inner.items.get(&id).map(|item| Self::describe(id, item))
+    }
+
+    /// Status of all items, in the order they will start (finished ones last).
+    pub fn snapshot(&self) -> Vec<QueueItemStatus> {
+        let inner = self.lock();
+        let mut items: Vec<_> = inner.items.iter().collect();
+        items.sort_by_key(|(_, item)| Self::order_key(item));
+        items
+            .into_iter()
+            .map(|(id, item)| Self::describe(*id, item))
+            .collect()
+    }
+
+    /// Drops finished items and returns them with their final state.
+    pub fn take_finished(&self) -> Vec<(DownloadEntry, QueueItemStatus)> {
+        let mut inner = self.lock();
+        let mut ids: Vec<DownloadId> = inner
+            .items
+            .iter()
+            .filter(|(_, item)| item.state.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter()
+            .filter_map(|id| {
+                let item = inner.items.remove(&id)?;
+                let status = Self::describe(id, &item);
+                Some((item.entry, status))
+            })
+            .collect()
+    }
+
+    fn order_key(item: &Item) -> (u8, i32, u64) {
+        let rank = match item.state {
+            QueueState::Active => 0,
+            QueueState::Queued => 1,
+            QueueState::Paused => 2,
+            _ => 3,
+        };
+        (rank, -item.priority, item.seq)
+    }
+
+    fn describe(id: DownloadId, item: &Item) -> QueueItemStatus {
+        let (downloaded, total, speed) = item
+            .progress
+            .as_ref()
+            .map(|pb| {
+                let speed = if item.state == QueueState::Active {
+                    item.rate.update(pb.position())
+                } else {
+                    0.0
+                };
+                (pb.position(), pb.length().unwrap_or(0), speed)
+            })
+            .unwrap_or((0, 0, 0.0));
+        let elapsed = match (item.started, item.finished) {
+            (Some(started), Some(finished)) => finished.duration_since(started),
+            (Some(started), None) => started.elapsed(),
+            _ => Duration::ZERO,
+        };
+        QueueItemStatus {
+            id,
+            url: item.entry.url.clone(),
+            output: item.entry.output.clone(),
+            priority: item.priority,
+            state: item.state.clone(),
+            downloaded,
+            total,
+            speed,
+            elapsed,
+            attempts: item.attempts,
+            start_at: item.start_at,
+            report: item.report.clone(),
+            connections: if item.state == QueueState::Active {
+                item.connections.snapshot()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// Waits for a free slot and the highest priority queued item whose
+    /// start time has come.
+    ///
+    /// Items writing to the same output as an active item wait until it
+    /// finished, so two downloads never write one file at once.
+    ///
+    /// Returns `None` once the queue is closed and nothing is left to start.
+    pub(crate) async fn next(&self) -> Option<(DownloadId, DownloadEntry)> {
+        loop {
+            let notified = self.notify.notified();
+            let wake_at = {
+                let mut inner = self.lock();
+                let now = SystemTime::now();
+                let has_slot = inner.active < inner.max_active;
+                let queued = || {
+                    inner
+                        .items
+                        .iter()
+                        .filter(|(_, item)| item.state == QueueState::Queued)
+                };
+                let busy: HashSet<PathBuf> = inner
+                    .items
+                    .values()
+                    .filter(|item| item.state == QueueState::Active)
+                    .map(|item| path_key(&item.entry.output))
+                    .collect();
+                let next = queued()
+                    .filter(|(_, item)| item.start_at.is_none_or(|at| at <= now))
+                    .filter(|(_, item)| !busy.contains(&path_key(&item.entry.output)))
+                    .min_by_key(|(_, item)| (-item.priority, item.seq))
+                    .map(|(id, _)| *id);
+                let wake_at = queued()
+                    .filter_map(|(_, item)| item.start_at)
+                    .filter(|at| *at > now)
+                    .min();
+
+                match next {
+                    Some(id) if has_slot => {
+                        inner.active += 1;
+                        let item = inner.items.get_mut(&id)?;
+                        item.state = QueueState::Active;
+                        item.started = Some(Instant::now());
+                        item.finished = None;
+                        return Some((id, item.entry.clone()));
+                    }
+                    None if inner.closed && queued().next().is_none() => return None,
+                    _ => {}
+                }
+                wake_at
+            };
+            match wake_at.and_then(|at| at.duration_since(SystemTime::now()).ok()) {
+                Some(delay) => {
+                    let _ = tokio::time::timeout(delay, notified).await;
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    pub(crate) fn attach_progress(&self, id: DownloadId, pb: ProgressBar) {
+        if let Some(item) = self.lock().items.get_mut(&id) {
+            item.progress = Some(pb);
+        }
+    }
+
+    /// Where the download of `id` reports its connections.
+    pub(crate) fn connections(&self, id: DownloadId) -> Connections {
+        self.lock()
+            .items
+            .get(&id)
+            .map(|item| item.connections.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn record_attempt(&self, id: DownloadId) {
+        if let Some(item) = self.lock().items.get_mut(&id) {
+            item.attempts += 1;
+        }
+    }
+
+    /// Keeps the report of a completed download for its status.
+    pub(crate) fn set_report(&self, id: DownloadId, report: DownloadReport) {
+        if let Some(item) = self.lock().items.get_mut(&id) {
+            item.report = Some(report);
+        }
+    }
+
+    /// Registers the running task so that pausing or cancelling can abort it.
+    pub(crate) fn attach_task(&self, id: DownloadId, task: AbortHandle) {
+        if let Some(item) = self.lock().items.get_mut(&id) {
+            if item.state == QueueState::Active {
+                item.task = Some(task);
+            } else {
+                task.abort();
+            }
+        }
+    }
+
+    pub(crate) fn finish(&self, id: DownloadId, result: Result<(), String>) {
+        {
+            let mut inner = self.lock();
+            inner.active = inner.active.saturating_sub(1);
+            if let Some(item) = inner.items.get_mut(&id)
+                && item.state == QueueState::Active
+            {
+                item.task = None;
+                item.finished = Some(Instant::now());
+                #[cfg(feature = "metrics")]
+                self.metrics.record(
+                    &item.entry.url,
+                    item.progress.as_ref().map_or(0, ProgressBar::position),
+                    item.attempts,
+                    result.is_err(),
+                );
+                item.state = match result {
+                    Ok(()) => QueueState::Completed,
+                    Err(e) => QueueState::Failed(e),
+                };
+            }
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn test_next_respects_priority_and_slots() {
+        let queue = DownloadQueue::new(1);
+        let a = queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+        let b = queue.push(DownloadEntry::new("https://example.com/b", "b"), 5);
+        queue.close();
+
+        let (first, _) = queue.next().await.unwrap();
+        assert_eq!(first, b);
+        assert!(queue.next().now_or_never().is_none(), "slot limit ignored");
+
+        queue.finish(first, Err("boom".to_string()));
+        let (second, _) = queue.next().await.unwrap();
+        assert_eq!(second, a);
+        queue.finish(second, Ok(()));
+        assert!(queue.next().await.is_none());
+
+        let finished = queue.take_finished();
+        assert_eq!(finished[0].1.state, QueueState::Completed);
+        assert_eq!(finished[1].1.state, QueueState::Failed("boom".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_items_wait_for_start() {
+        let queue = DownloadQueue::new(2);
+        let later = queue.push_at(
+            DownloadEntry::new("https://example.com/a", "a"),
+            10,
+            Some(SystemTime::now() + Duration::from_millis(200)),
+        );
+        let now = queue.push(DownloadEntry::new("https://example.com/b", "b"), 0);
+        queue.close();
+
+        assert!(queue.status(later).unwrap().is_scheduled());
+        let (first, _) = queue.next().await.unwrap();
+        assert_eq!(first, now);
+        assert!(queue.next().now_or_never().is_none(), "started too early");
+        let (second, _) = queue.next().await.unwrap();
+        assert_eq!(second, later);
+    }
+
+    #[tokio::test]
+    async fn test_same_output_waits_for_active() {
+        let queue = DownloadQueue::new(2);
+        let a = queue.push(DownloadEntry::new("https://example.com/a", "out.iso"), 0);
+        let b = queue.push(DownloadEntry::new("https://example.com/b", "./out.iso"), 0);
+        let c = queue.push(DownloadEntry::new("https://example.com/c", "c.iso"), 0);
+        queue.close();
+
+        assert_eq!(queue.next().await.unwrap().0, a);
+        assert_eq!(queue.next().await.unwrap().0, c);
+        queue.finish(c, Ok(()));
+        assert!(
+            queue.next().now_or_never().is_none(),
+            "same output started twice"
+        );
+        queue.finish(a, Ok(()));
+        assert_eq!(queue.next().await.unwrap().0, b);
+    }
+
+    #[tokio::test]
+    async fn test_abort_cancels_unfinished() {
+        let queue = DownloadQueue::new(1);
+        let a = queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+        let b = queue.push(DownloadEntry::new("https://example.com/b", "b"), 0);
+        let (first, _) = queue.next().await.unwrap();
+        assert_eq!(first, a);
+
+        assert_eq!(queue.abort(), 2);
+        let c = queue.push(DownloadEntry::new("https://example.com/c", "c"), 0);
+        assert!(queue.next().await.is_none());
+        for id in [a, b, c] {
+            assert_eq!(queue.status(id).unwrap().state, QueueState::Removed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skip_queued_keeps_active() {
+        let queue = DownloadQueue::new(1);
+        let a = queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+        let b = queue.push(DownloadEntry::new("https://example.com/b", "b"), 0);
+        let (first, _) = queue.next().await.unwrap();
+        assert_eq!(first, a);
+
+        assert_eq!(queue.skip_queued("Skipped"), 1);
+        assert_eq!(queue.status(a).unwrap().state, QueueState::Active);
+        assert_eq!(
+            queue.status(b).unwrap().state,
+            QueueState::Failed("Skipped".to_string())
+        );
+        queue.finish(a, Ok(()));
+        queue.close();
+        assert!(queue.next().await.is_none());
+    }
+}
@@ -0,0 +1,98 @@
+use serde_json::{Value, json};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::summary::FileSummary;
+
+/// Builds the `--report-json` document for a finished batch.
+///
+/// Every file is listed with its outcome, size, duration, retry count and
+/// checksum, followed by totals, so scripts don't have to parse log lines.
+pub fn to_json(files: &[FileSummary], elapsed: Duration) -> Value {
+    let failed = files.iter().filter(|f| f.error.is_some()).count();
+    let bytes: u64 = files.iter().map(|f| f.bytes).sum();
+    let finished_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let entries: Vec<Value> = files
+        .iter()
+        .map(|file| {
+            json!({
+                "url": file.url,
+                "output": file.output.to_string_lossy(),
+                "status": if file.error.is_none() { "ok" } else { "failed" },
+                "bytes": file.bytes,
+                "duration_secs": file.duration.as_secs_f64(),
+                "speed": file.speed() as u64,
+                "retries": file.attempts.saturating_sub(1),
+                "checksum": file.checksum,
+                "error": file.error,
+            })
+        })
+        .collect();
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "finished_at": finished_at,
+        "duration_secs": elapsed.as_secs_f64(),
+        "total": files.len(),
+        "succeeded": files.len() - failed,
+        "failed": failed,
+        "bytes": bytes,
+        "files": entries,
+    })
+}
+
+/// Writes a report as pretty JSON to `path`, or to stdout when it is `-`.
+pub async fn write(path: &Path, report: &Value) -> std::io::Result<()> {
+    let content = serde_json::to_string_pretty(report).map_err(std::io::Error::other)? + "\n";
+    if path.as_os_str() == "-" {
+        use tokio::io::AsyncWriteExt;
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(content.as_bytes()).await?;
+        return stdout.flush().await;
+    }
+    tokio::fs::write(path, content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_to_json() {
+        let files = vec![
+            FileSummary {
+                url: "https://example.com/a.iso".to_string(),
+                output: PathBuf::from("a.iso"),
+                bytes: 100,
+                duration: Duration::from_secs(4),
+                attempts: 2,
+                checksum: Some("sha256:abcd".to_string()),
+                error: None,
+            },
+            FileSummary {
+                url: "https://example.com/b".to_string(),
+                output: PathBuf::from("b"),
+                bytes: 0,
+                duration: Duration::ZERO,
+                attempts: 1,
+                checksum: None,
+                error: Some("404".to_string()),
+            },
+        ];
+        let report = to_json(&files, Duration::from_secs(4));
+        assert_eq!(report["total"], 2);
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["bytes"], 100);
+        assert_eq!(report["files"][0]["status"], "ok");
+        assert_eq!(report["files"][0]["retries"], 1);
+        assert_eq!(report["files"][0]["speed"], 25);
+        assert_eq!(report["files"][0]["checksum"], "sha256:abcd");
+        assert_eq!(report["files"][1]["status"], "failed");
+        assert_eq!(report["files"][1]["error"], "404");
+    }
+}
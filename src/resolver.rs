@@ -0,0 +1,386 @@
+use futures::future::BoxFuture;
+use reqwest::Url;
+use std::sync::Arc;
+
+/// URL schemes handled by the built-in resolvers, accepted wherever an
+/// `http(s)` URL is
+pub const SCHEMES: &[&str] = &["hf", "s3"];
+
+/// Whether `url` starts with one of the [`SCHEMES`].
+pub fn has_builtin_scheme(url: &str) -> bool {
+    url.split_once("://")
+        .is_some_and(|(scheme, _)| SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme)))
+}
+
+/// What a URL is actually downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadPlan {
+    /// `http(s)` URL the request goes to
+    pub url: String,
+    /// Headers sent along, such as an `Authorization` token for the service
+    pub headers: Vec<(String, String)>,
+}
+
+impl DownloadPlan {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds `Authorization: Bearer <token>` if `var` is set and not empty.
+    pub fn with_token_from(mut self, var: &str) -> Self {
+        if let Some(token) = std::env::var(var).ok().filter(|t| !t.is_empty()) {
+            self.headers
+                .push(("Authorization".to_string(), format!("Bearer {}", token)));
+        }
+        self
+    }
+
+    /// The plan's headers followed by `overrides`, minus the names
+    /// `overrides` already sets.
+    pub fn request_headers(&self, overrides: &[(String, String)]) -> Vec<(String, String)> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| !overrides.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)))
+            .chain(overrides)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Turns URLs of a scheme or host into a [`DownloadPlan`]
+///
+/// Resolvers are registered on the [`Downloader`](crate::Downloader) with
+/// [`register_resolver`](crate::Downloader::register_resolver) and asked in
+/// turn, the last registered first; the first one whose
+/// [`matches`](Resolver::matches) returns true resolves the URL. URLs no
+/// resolver matches are downloaded as they are.
+///
+/// ```
+/// use dwrs::resolver::{DownloadPlan, Resolver};
+/// use futures::future::BoxFuture;
+///
+/// struct Ipfs;
+///
+/// impl Resolver for Ipfs {
+///     fn name(&self) -> &str {
+///         "ipfs"
+///     }
+///
+///     fn matches(&self, url: &str) -> bool {
+///         url.starts_with("ipfs://")
+///     }
+///
+///     fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>> {
+///         let cid = &url["ipfs://".len()..];
+///         Box::pin(async move { Ok(DownloadPlan::new(format!("https://ipfs.io/ipfs/{}", cid))) })
+///     }
+/// }
+/// ```
+pub trait Resolver: Send + Sync {
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+
+    /// Whether this resolver handles `url`; must not do any I/O.
+    fn matches(&self, url: &str) -> bool;
+
+    /// Plan for downloading `url`, which [`matches`](Resolver::matches) accepted.
+    ///
+    /// Runs once per download, before the first attempt; an error fails
+    /// the download without retrying.
+    fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>>;
+}
+
+/// Resolvers registered on a [`Downloader`](crate::Downloader)
+#[derive(Clone)]
+pub struct Resolvers {
+    resolvers: Vec<Arc<dyn Resolver>>,
+}
+
+impl Resolvers {
+    /// No resolvers at all, so every URL is downloaded as it is.
+    pub fn empty() -> Self {
+        Self {
+            resolvers: Vec::new(),
+        }
+    }
+
+    /// [`GitHub`], [`HuggingFace`] and [`S3`].
+    pub fn builtin() -> Self {
+        let mut resolvers = Self::empty();
+        resolvers.register(Arc::new(S3));
+        resolvers.register(Arc::new(HuggingFace));
+        resolvers.register(Arc::new(GitHub));
+        resolvers
+    }
+
+    /// Adds `resolver` in front of the ones registered so far.
+    pub fn register(&mut self, resolver: Arc<dyn Resolver>) {
+        self.resolvers.insert(0, resolver);
+    }
+
+    /// Names of the registered resolvers, in the order they are asked.
+    pub fn names(&self) -> Vec<&str> {
+        self.resolvers.iter().map(|r| r.name()).collect()
+    }
+
+    /// The resolver that handles `url`, if any.
+    pub fn find(&self, url: &str) -> Option<&dyn Resolver> {
+        self.resolvers
+            .iter()
+            .find(|r| r.matches(url))
+            .map(|r| r.as_ref())
+    }
+
+    /// Plan for `url`; a plain download of it if no resolver matches.
+    pub async fn resolve(&self, url: &str) -> Result<DownloadPlan, String> {
+        let Some(resolver) = self.find(url) else {
+            return Ok(DownloadPlan::new(url));
+        };
+        let plan = resolver
+            .resolve(url)
+            .await
+            .map_err(|e| format!("{} resolver: {}", resolver.name(), e))?;
+        if plan.url != url {
+            log::info!("Resolved {} -> {} ({})", url, plan.url, resolver.name());
+        }
+        Ok(plan)
+    }
+}
+
+impl Default for Resolvers {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl std::fmt::Debug for Resolvers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Strips `scheme://` from `url`, ignoring the case of the scheme.
+fn strip_scheme<'a>(url: &'a str, scheme: &str) -> Option<&'a str> {
+    let (prefix, rest) = url.split_once("://")?;
+    prefix.eq_ignore_ascii_case(scheme).then_some(rest)
+}
+
+fn host_of(url: &str) -> Option<String> {
+    Some(Url::parse(url).ok()?.host_str()?.to_ascii_lowercase())
+}
+
+/// GitHub file pages and raw files
+///
+/// `https://github.com/<owner>/<repo>/blob/<ref>/<path>` is fetched from
+/// `raw.githubusercontent.com` instead of as an HTML page. Requests to
+/// GitHub carry `GITHUB_TOKEN` when it is set, for private repositories
+/// and release assets.
+#[derive(Debug, Clone, Copy)]
+pub struct GitHub;
+
+impl Resolver for GitHub {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        host_of(url).is_some_and(|host| {
+            matches!(
+                host.as_str(),
+                "github.com" | "api.github.com" | "raw.githubusercontent.com"
+            )
+        })
+    }
+
+    fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>> {
+        Box::pin(async move {
+            let mut parsed = Url::parse(url).map_err(|e| e.to_string())?;
+            let segments: Vec<String> = parsed
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect();
+            if parsed.host_str() == Some("github.com")
+                && segments.len() > 4
+                && segments[2] == "blob"
+            {
+                let path = [&segments[..2], &segments[3..]].concat().join("/");
+                parsed = Url::parse(&format!("https://raw.githubusercontent.com/{}", path))
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(DownloadPlan::new(parsed.to_string()).with_token_from("GITHUB_TOKEN"))
+        })
+    }
+}
+
+/// Hugging Face Hub files
+///
+/// `hf://<owner>/<repo>[@<revision>]/<path>` downloads a file of a model
+/// repository, `hf://datasets/...` and `hf://spaces/...` one of a dataset
+/// or space; the revision defaults to `main`. File pages
+/// (`https://huggingface.co/.../blob/...`) are fetched through `resolve`
+/// instead. Requests carry `HF_TOKEN` when it is set, for gated and
+/// private repositories.
+#[derive(Debug, Clone, Copy)]
+pub struct HuggingFace;
+
+impl HuggingFace {
+    fn url(rest: &str) -> Result<String, String> {
+        let (kind, rest) = match rest.split_once('/') {
+            Some((kind @ ("datasets" | "spaces"), rest)) => (format!("{}/", kind), rest),
+            _ => (String::new(), rest),
+        };
+        let mut parts = rest.splitn(3, '/');
+        let (Some(owner), Some(repo), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("expected hf://<owner>/<repo>[@<revision>]/<path>".to_string());
+        };
+        let (repo, revision) = repo.split_once('@').unwrap_or((repo, "main"));
+        if owner.is_empty() || repo.is_empty() || revision.is_empty() || path.is_empty() {
+            return Err("expected hf://<owner>/<repo>[@<revision>]/<path>".to_string());
+        }
+        Ok(format!(
+            "https://huggingface.co/{}{}/{}/resolve/{}/{}",
+            kind, owner, repo, revision, path
+        ))
+    }
+}
+
+impl Resolver for HuggingFace {
+    fn name(&self) -> &str {
+        "huggingface"
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        strip_scheme(url, "hf").is_some() || host_of(url).is_some_and(|h| h == "huggingface.co")
+    }
+
+    fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>> {
+        Box::pin(async move {
+            let url = match strip_scheme(url, "hf") {
+                Some(rest) => Self::url(rest)?,
+                None => url.replacen("/blob/", "/resolve/", 1),
+            };
+            Ok(DownloadPlan::new(url).with_token_from("HF_TOKEN"))
+        })
+    }
+}
+
+/// Public Amazon S3 objects
+///
+/// `s3://<bucket>/<key>` is downloaded from
+/// `https://<bucket>.s3.amazonaws.com/<key>`, or from
+/// `<AWS_ENDPOINT_URL>/<bucket>/<key>` when that variable points to another
+/// S3-compatible service. Requests are not signed; private objects need a
+/// presigned URL or a resolver of their own.
+#[derive(Debug, Clone, Copy)]
+pub struct S3;
+
+impl S3 {
+    fn url(rest: &str, endpoint: Option<&str>) -> Result<String, String> {
+        let (bucket, key) = rest
+            .split_once('/')
+            .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+            .ok_or_else(|| "expected s3://<bucket>/<key>".to_string())?;
+        Ok(match endpoint {
+            Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+            None => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+        })
+    }
+}
+
+impl Resolver for S3 {
+    fn name(&self) -> &str {
+        "s3"
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        strip_scheme(url, "s3").is_some()
+    }
+
+    fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>> {
+        Box::pin(async move {
+            let rest = strip_scheme(url, "s3").unwrap_or(url);
+            let endpoint = std::env::var("AWS_ENDPOINT_URL").ok();
+            Ok(DownloadPlan::new(Self::url(
+                rest,
+                endpoint.as_deref().filter(|e| !e.is_empty()),
+            )?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_builtin_resolvers() {
+        let resolvers = Resolvers::builtin();
+        let url = |url: &'static str| {
+            let resolvers = resolvers.clone();
+            async move { resolvers.resolve(url).await.map(|plan| plan.url) }
+        };
+
+        assert_eq!(
+            url("https://github.com/owner/repo/blob/v1.0/docs/a.md").await,
+            Ok("https://raw.githubusercontent.com/owner/repo/v1.0/docs/a.md".to_string())
+        );
+        assert_eq!(
+            url("hf://datasets/org/set@v2/data/train.parquet").await,
+            Ok("https://huggingface.co/datasets/org/set/resolve/v2/data/train.parquet".to_string())
+        );
+        assert_eq!(
+            url("hf://org/model/config.json").await,
+            Ok("https://huggingface.co/org/model/resolve/main/config.json".to_string())
+        );
+        assert!(url("hf://org/model").await.is_err());
+        assert_eq!(
+            S3::url("bucket/dir/a.tar", None).as_deref(),
+            Ok("https://bucket.s3.amazonaws.com/dir/a.tar")
+        );
+        assert_eq!(
+            S3::url("bucket/a.tar", Some("http://localhost:9000/")).as_deref(),
+            Ok("http://localhost:9000/bucket/a.tar")
+        );
+        assert_eq!(
+            url("https://example.com/a.zip").await,
+            Ok("https://example.com/a.zip".to_string())
+        );
+
+        struct Mirror;
+        impl Resolver for Mirror {
+            fn name(&self) -> &str {
+                "mirror"
+            }
+            fn matches(&self, url: &str) -> bool {
+                url.starts_with("s3://mirrored/")
+            }
+            fn resolve<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<DownloadPlan, String>> {
+                Box::pin(async move {
+                    let key = &url["s3://mirrored/".len()..];
+                    Ok(DownloadPlan {
+                        url: format!("https://mirror.example/{}", key),
+                        headers: vec![("X-Key".to_string(), "a".to_string())],
+                    })
+                })
+            }
+        }
+        let mut resolvers = Resolvers::builtin();
+        resolvers.register(Arc::new(Mirror));
+        assert_eq!(
+            resolvers.names(),
+            vec!["mirror", "github", "huggingface", "s3"]
+        );
+        let plan = resolvers.resolve("s3://mirrored/a.bin").await.unwrap();
+        assert_eq!(plan.url, "https://mirror.example/a.bin");
+        assert_eq!(
+            plan.request_headers(&[("x-key".to_string(), "b".to_string())]),
+            vec![("x-key".to_string(), "b".to_string())]
+        );
+    }
+}
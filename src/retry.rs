@@ -0,0 +1,180 @@
+use std::error::Error;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Error of a failed download attempt
+pub type AttemptError = dyn Error + Send + Sync + 'static;
+
+/// Decides whether a failed download is tried again, and after how long.
+///
+/// Downloads are attempted at most [`DownloadConfig::retries`] times in
+/// any case, and errors that another attempt cannot fix, such as a
+/// rejected content type, end them right away. Between those bounds the
+/// policy of [`DownloadConfig::retry_policy`] is asked after each failure:
+///
+/// ```
+/// use dwrs::retry::{AttemptError, RetryPolicy};
+/// use std::time::Duration;
+///
+/// /// Retries after a second, but only errors of the connection
+/// #[derive(Debug)]
+/// struct NetworkOnly;
+///
+/// impl RetryPolicy for NetworkOnly {
+///     fn should_retry(&self, error: &AttemptError, _attempt: usize) -> Option<Duration> {
+///         let reqwest = error.downcast_ref::<reqwest::Error>()?;
+///         (reqwest.is_connect() || reqwest.is_timeout()).then_some(Duration::from_secs(1))
+///     }
+/// }
+/// ```
+///
+/// [`DownloadConfig::retries`]: crate::DownloadConfig::retries
+/// [`DownloadConfig::retry_policy`]: crate::DownloadConfig::retry_policy
+pub trait RetryPolicy: Debug + Send + Sync {
+    /// How long to wait before trying again after `error` ended attempt
+    /// number `attempt`, counted from 1, or `None` to give up.
+    fn should_retry(&self, error: &AttemptError, attempt: usize) -> Option<Duration>;
+}
+
+/// Doubles the delay after every attempt, up to `max`
+#[derive(Debug, Clone, Copy)]
+pub struct Exponential {
+    /// Half the delay after the first attempt
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Default for Exponential {
+    /// Waits 2, 4, 8... seconds, up to 5 minutes.
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(1),
+            max: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy for Exponential {
+    fn should_retry(&self, _error: &AttemptError, attempt: usize) -> Option<Duration> {
+        let factor = 2u32.checked_pow(attempt as u32).unwrap_or(u32::MAX);
+        Some(self.base.saturating_mul(factor).min(self.max))
+    }
+}
+
+/// Waits the same time after every attempt
+#[derive(Debug, Clone, Copy)]
+pub struct Fixed(pub Duration);
+
+impl RetryPolicy for Fixed {
+    fn should_retry(&self, _error: &AttemptError, _attempt: usize) -> Option<Duration> {
+        Some(self.0)
+    }
+}
+
+/// Grows the delay along the Fibonacci sequence, `base` times 1, 1, 2, 3,
+/// 5..., up to `max`; slower than [`Exponential`]
+#[derive(Debug, Clone, Copy)]
+pub struct Fibonacci {
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Default for Fibonacci {
+    /// Waits 1, 1, 2, 3, 5... seconds, up to 5 minutes.
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(1),
+            max: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy for Fibonacci {
+    fn should_retry(&self, _error: &AttemptError, attempt: usize) -> Option<Duration> {
+        let (mut a, mut b) = (1u32, 1u32);
+        for _ in 1..attempt {
+            (a, b) = (b, a.saturating_add(b));
+        }
+        Some(self.base.saturating_mul(a).min(self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policies() {
+        let error: Box<AttemptError> = "connection reset".into();
+        let delays = |policy: &dyn RetryPolicy| {
+            (1..=6)
+                .map(|attempt| policy.should_retry(&*error, attempt).unwrap().as_secs())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(delays(&Exponential::default()), [2, 4, 8, 16, 32, 64]);
+        assert_eq!(delays(&Fibonacci::default()), [1, 1, 2, 3, 5, 8]);
+        assert_eq!(delays(&Fixed(Duration::from_secs(3))), [3; 6]);
+
+        let capped = Exponential {
+            base: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+        };
+        assert_eq!(
+            capped.should_retry(&*error, 100),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    /// Fails the first GET, then sends requests on
+    struct Flaky(std::sync::atomic::AtomicBool);
+
+    impl crate::transport::Transport for Flaky {
+        fn execute(
+            &self,
+            request: reqwest::Request,
+        ) -> futures::future::BoxFuture<
+            '_,
+            Result<reqwest::Response, crate::transport::TransportError>,
+        > {
+            let fail = request.method() == reqwest::Method::GET
+                && !self.0.swap(true, std::sync::atomic::Ordering::Relaxed);
+            Box::pin(async move {
+                if fail {
+                    return Err("connection reset".into());
+                }
+                Ok(reqwest::Client::new().execute(request).await?)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_reports_attempts() {
+        use crate::file_parser::DownloadEntry;
+        use crate::queue::DownloadQueue;
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.path("/a.txt");
+            then.status(200).body("hello world");
+        });
+        let downloader = crate::Downloader::builder()
+            .progress(false)
+            .retries(3)
+            .retry_policy(Fixed(Duration::ZERO))
+            .transport(Flaky(Default::default()))
+            .build()
+            .unwrap();
+        let output = std::env::temp_dir().join(format!("dwrs-attempts-{}", std::process::id()));
+        let queue = DownloadQueue::new(1);
+        let id = queue.push(DownloadEntry::new(server.url("/a.txt"), &output), 0);
+        queue.close();
+        downloader.run_queue(&queue).await;
+        std::fs::remove_file(&output).ok();
+
+        let report = queue.status(id).unwrap().report.unwrap();
+        assert_eq!(report.attempts, 2);
+        assert_eq!(
+            report.checksum.as_deref(),
+            Some("sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::file_parser::resolve_url;
+use crate::hooks::parse_command;
+use crate::utils::{Token, render};
+
+/// Variables understood in rewrite commands
+pub const REWRITE_VARS: &[&str] = &["url"];
+
+/// Regular expression whose first match in a URL is replaced, such as
+/// `^https://dead-mirror\.org/` → `https://mirror.example.net/`
+///
+/// The replacement may refer to capture groups as `$1` or `${name}`.
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RewriteRule {
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self, String> {
+        let pattern =
+            Regex::new(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+        Ok(Self {
+            pattern,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    fn apply<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        self.pattern.replace(url, self.replacement.as_str())
+    }
+}
+
+/// External command that maps a URL to the one to download, such as
+/// `mirror-map {url}`
+///
+/// The URL is filled into `{url}` and passed as `DWRS_URL`; the first line
+/// the command prints is used instead. No output, a non-zero exit code or
+/// something that is not an `http(s)` URL keeps the URL as it was.
+#[derive(Debug, Clone)]
+pub struct RewriteCommand {
+    words: Vec<Vec<Token>>,
+}
+
+impl RewriteCommand {
+    /// Parses a command, rejecting unknown variables and unbalanced quotes.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        Ok(Self {
+            words: parse_command(command, REWRITE_VARS)?,
+        })
+    }
+
+    fn args(&self, url: &str) -> Vec<String> {
+        let vars = HashMap::from([("url", Cow::Borrowed(url))]);
+        self.words.iter().map(|word| render(word, &vars)).collect()
+    }
+
+    async fn run(&self, url: &str) -> Result<Option<String>, String> {
+        let args = self.args(url);
+        let output = tokio::process::Command::new(&args[0])
+            .args(&args[1..])
+            .env("DWRS_URL", url)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .await
+            .map_err(|e| format!("cannot run {}: {}", args[0], e))?;
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", args[0], output.status));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some(line) = stdout.lines().map(str::trim).find(|l| !l.is_empty()) else {
+            return Ok(None);
+        };
+        resolve_url(line, None)
+            .map(Some)
+            .ok_or_else(|| format!("{} printed an invalid URL '{}'", args[0], line))
+    }
+}
+
+/// Rules and command applied to every URL before it is downloaded
+///
+/// The rules run first, in order, each on the result of the previous one;
+/// the command then sees the rewritten URL.
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    pub rules: Vec<RewriteRule>,
+    pub command: Option<RewriteCommand>,
+}
+
+impl Rewriter {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.command.is_none()
+    }
+
+    /// `url` after the rules, without running the command.
+    pub fn apply_rules(&self, url: &str) -> String {
+        self.rules
+            .iter()
+            .fold(url.to_string(), |url, rule| rule.apply(&url).into_owned())
+    }
+
+    /// URL to download instead of `url`.
+    ///
+    /// Command failures are logged and leave the URL from the rules.
+    pub async fn rewrite(&self, url: &str) -> String {
+        if self.is_empty() {
+            return url.to_string();
+        }
+        let mut rewritten = self.apply_rules(url);
+        if let Some(command) = &self.command {
+            match command.run(&rewritten).await {
+                Ok(Some(new)) => rewritten = new,
+                Ok(None) => {}
+                Err(e) => log::warn!("Cannot rewrite {}: {}", rewritten, e),
+            }
+        }
+        if rewritten != url {
+            log::info!("Rewrote {} -> {}", url, rewritten);
+        }
+        rewritten
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_rules() {
+        let rewriter = Rewriter {
+            rules: vec![
+                RewriteRule::new(
+                    r"^https?://dead\.example\.org/",
+                    "https://mirror.example.net/",
+                )
+                .unwrap(),
+                RewriteRule::new(r"/releases/(?<version>[\d.]+)/", "/v${version}/").unwrap(),
+            ],
+            command: None,
+        };
+        assert_eq!(
+            rewriter.apply_rules("http://dead.example.org/releases/1.2/app.tar.gz"),
+            "https://mirror.example.net/v1.2/app.tar.gz"
+        );
+        assert_eq!(
+            rewriter.apply_rules("https://other.example.org/releases/x/a"),
+            "https://other.example.org/releases/x/a"
+        );
+        assert!(RewriteRule::new("(", "x").is_err());
+
+        let command = RewriteCommand::parse("map --from {url}").unwrap();
+        assert_eq!(
+            command.args("https://a.example/x y"),
+            vec!["map", "--from", "https://a.example/x y"]
+        );
+        assert!(RewriteCommand::parse("map {path}").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use reqwest::{Client, Url};
+use std::time::Duration;
+
+/// User agent token matched against `User-agent:` lines
+pub const AGENT: &str = "dwrs";
+/// Longest `Crawl-delay` honoured; longer ones are cut down to it so a
+/// single line cannot stall a crawl for hours
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+
+/// Product token of a `User-Agent`, matched against `User-agent:` lines,
+/// e.g. `curl` for `curl/8.5.0`.
+pub fn agent_token(user_agent: &str) -> &str {
+    user_agent
+        .split(['/', ' '])
+        .find(|token| !token.is_empty())
+        .unwrap_or(AGENT)
+}
+
+/// Rules from a robots.txt that apply to dwrs
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+fn rule_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut rest = &path[first.len()..];
+    let pieces: Vec<&str> = parts.collect();
+
+    for (i, piece) in pieces.iter().enumerate() {
+        if anchored && i == pieces.len() - 1 {
+            return rest.ends_with(piece);
+        }
+        match rest.find(piece) {
+            Some(pos) => rest = &rest[pos + piece.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+impl Robots {
+    /// Parses robots.txt and keeps the group for `agent`, falling back to `*`.
+    pub fn parse(text: &str, agent: &str) -> Self {
+        let agent = agent.to_ascii_lowercase();
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agents = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push(Group::default());
+                    }
+                    in_agents = true;
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_agents = false;
+                    if let Some(group) = groups.last_mut()
+                        && !value.is_empty()
+                    {
+                        group.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    if let Some(group) = groups.last_mut() {
+                        // Negative, infinite and NaN delays are ignored
+                        group.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(|v| Duration::try_from_secs_f64(v).ok())
+                            .map(|delay| delay.min(MAX_CRAWL_DELAY));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let specific = groups.iter().position(|g| {
+            g.agents
+                .iter()
+                .any(|a| a != "*" && agent.contains(a.as_str()))
+        });
+        let chosen = specific.or_else(|| {
+            groups
+                .iter()
+                .position(|g| g.agents.iter().any(|a| a == "*"))
+        });
+
+        match chosen {
+            Some(i) => {
+                let group = groups.swap_remove(i);
+                Self {
+                    rules: group.rules,
+                    crawl_delay: group.crawl_delay,
+                }
+            }
+            None => Self::default(),
+        }
+    }
+
+    /// Fetches `/robots.txt` for the host of `url` and keeps the group for
+    /// `agent`; unreachable files allow everything.
+    pub async fn fetch(client: &Client, url: &Url, agent: &str) -> Self {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return Self::default();
+        };
+        log::debug!("Fetching {}", robots_url);
+
+        match client.get(robots_url.clone()).send().await {
+            Ok(resp) if resp.status().is_success() => match resp.text().await {
+                Ok(text) => Self::parse(&text, agent),
+                Err(e) => {
+                    log::warn!("Failed to read {}: {}", robots_url, e);
+                    Self::default()
+                }
+            },
+            Ok(resp) => {
+                log::debug!("{} returned {}, no restrictions", robots_url, resp.status());
+                Self::default()
+            }
+            Err(e) => {
+                log::warn!("Failed to fetch {}: {}", robots_url, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Checks a URL path (with query) using longest-match precedence, `Allow` winning ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| rule_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_rules() {
+        let text = "User-agent: *\nDisallow: /\n\nUser-agent: dwrs\nUser-agent: wget\nDisallow: /private/\nAllow: /private/public*.html$\nDisallow: /*.tmp$\nCrawl-delay: 1.5\n";
+        let robots = Robots::parse(text, AGENT);
+        assert!(robots.is_allowed("/pub/file.zip"));
+        assert!(!robots.is_allowed("/private/data"));
+        assert!(robots.is_allowed("/private/public-index.html"));
+        assert!(!robots.is_allowed("/x/y.tmp"));
+        assert!(robots.is_allowed("/x/y.tmp.zip"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(1500)));
+
+        let other = Robots::parse(text, "curl");
+        assert!(!other.is_allowed("/pub/file.zip"));
+
+        for delay in ["-1", "inf", "NaN"] {
+            let text = format!("User-agent: *\nCrawl-delay: {}\n", delay);
+            assert_eq!(Robots::parse(&text, AGENT).crawl_delay(), None);
+        }
+        let text = "User-agent: *\nCrawl-delay: 86400\n";
+        assert_eq!(
+            Robots::parse(text, AGENT).crawl_delay(),
+            Some(MAX_CRAWL_DELAY)
+        );
+
+        assert_eq!(agent_token("dwrs/0.3.1"), "dwrs");
+        assert_eq!(agent_token(crate::user_agent::PRESETS[1].1), "Mozilla");
+        assert_eq!(agent_token("MyBot"), "MyBot");
+        assert_eq!(agent_token(""), AGENT);
+    }
+}
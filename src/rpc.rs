@@ -0,0 +1,720 @@
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::checksum::Checksum;
+use crate::deferred;
+use crate::file_parser::DownloadEntry;
+use crate::queue::{DownloadId, DownloadQueue, QueueItemStatus, QueueState};
+
+/// Default aria2 RPC port, so frontends work without extra configuration
+pub const DEFAULT_PORT: u16 = 6800;
+
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// Methods answered by [`handle`]
+pub const METHODS: &[&str] = &[
+    "aria2.addUri",
+    "aria2.tellStatus",
+    "aria2.pause",
+    "aria2.forcePause",
+    "aria2.unpause",
+    "aria2.remove",
+    "aria2.forceRemove",
+    "aria2.tellActive",
+    "aria2.tellWaiting",
+    "aria2.tellStopped",
+    "aria2.getGlobalStat",
+    "aria2.getVersion",
+    "system.listMethods",
+    "system.multicall",
+];
+
+/// Options for the aria2-compatible JSON-RPC server
+#[derive(Debug, Clone)]
+pub struct RpcOptions {
+    pub addr: SocketAddr,
+    /// Secret expected as `"token:<secret>"` in the first parameter;
+    /// required unless the server only listens on a loopback address
+    pub secret: Option<String>,
+    /// Directory downloads are saved in; a `dir` option of `addUri` can
+    /// only name one of its subdirectories
+    pub dir: PathBuf,
+}
+
+impl Default for RpcOptions {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT)),
+            secret: None,
+            dir: PathBuf::from("."),
+        }
+    }
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            code: 1,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+}
+
+fn gid(id: DownloadId) -> String {
+    format!("{:016x}", id)
+}
+
+fn parse_gid(value: Option<&Value>) -> Result<DownloadId, RpcError> {
+    value
+        .and_then(Value::as_str)
+        .and_then(|s| DownloadId::from_str_radix(s, 16).ok())
+        .ok_or_else(|| RpcError::invalid_params("Invalid GID"))
+}
+
+fn status_name(state: &QueueState) -> &'static str {
+    match state {
+        QueueState::Queued => "waiting",
+        QueueState::Paused => "paused",
+        QueueState::Active => "active",
+        QueueState::Completed => "complete",
+        QueueState::Failed(_) => "error",
+        QueueState::Removed => "removed",
+    }
+}
+
+/// Renders a queue item as an aria2 status struct, limited to `keys` if given.
+fn status_json(item: &QueueItemStatus, keys: Option<&Value>) -> Value {
+    let dir = item
+        .output
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut status = json!({
+        "gid": gid(item.id),
+        "status": status_name(&item.state),
+        "totalLength": item.total.to_string(),
+        "completedLength": item.downloaded.to_string(),
+        "uploadLength": "0",
+        "downloadSpeed": (item.speed as u64).to_string(),
+        "uploadSpeed": "0",
+        "connections": if item.state == QueueState::Active { "1" } else { "0" },
+        "dir": dir,
+        "files": [{
+            "index": "1",
+            "path": item.output.to_string_lossy(),
+            "length": item.total.to_string(),
+            "completedLength": item.downloaded.to_string(),
+            "selected": "true",
+            "uris": [{ "uri": item.url, "status": "used" }],
+        }],
+    });
+    if let QueueState::Failed(e) = &item.state {
+        status["errorCode"] = json!("1");
+        status["errorMessage"] = json!(e);
+    }
+
+    match keys.and_then(Value::as_array) {
+        Some(keys) if !keys.is_empty() => {
+            let wanted: Vec<&str> = keys.iter().filter_map(Value::as_str).collect();
+            let filtered = status
+                .as_object()
+                .map(|map| {
+                    map.iter()
+                        .filter(|(k, _)| wanted.contains(&k.as_str()))
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Value::Object(filtered)
+        }
+        _ => status,
+    }
+}
+
+/// Builds a queue entry from `addUri` parameters: `[uris, options?, position?]`.
+fn entry_from_params(params: &[Value], default_dir: &Path) -> Result<DownloadEntry, RpcError> {
+    let url = params
+        .first()
+        .and_then(Value::as_array)
+        .and_then(|uris| uris.first())
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params("addUri expects a list of URIs"))?;
+    let options = params.get(1).cloned().unwrap_or_else(|| json!({}));
+    let option = |name: &str| options.get(name).and_then(Value::as_str);
+
+    // Clients choose paths relative to the download directory only
+    let confine = |base: &Path, path: &str| {
+        crate::sanitize::confine(base, Path::new(path)).map_err(RpcError::invalid_params)
+    };
+    let dir = match option("dir") {
+        Some(dir) => confine(default_dir, dir)?,
+        None => default_dir.to_path_buf(),
+    };
+    let name = match option("out") {
+        Some(out) => out.to_string(),
+        None => reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| {
+                let last = u.path_segments()?.next_back()?;
+                Some(
+                    percent_encoding::percent_decode_str(last)
+                        .decode_utf8_lossy()
+                        .into_owned(),
+                )
+            })
+            .filter(|s| !s.is_empty())
+            .map(|s| crate::sanitize::sanitize(&s))
+            .unwrap_or_else(|| "index.html".to_string()),
+    };
+
+    let mut entry = DownloadEntry::new(url, confine(&dir, &name)?);
+
+    let headers = match options.get("header") {
+        Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(value)) => vec![value.as_str()],
+        _ => Vec::new(),
+    };
+    entry.headers = headers
+        .into_iter()
+        .filter_map(|h| h.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    if let Some(spec) = option("checksum") {
+        // aria2 writes checksums as "sha-256=<hex>"
+        entry.checksum =
+            Some(Checksum::parse(&spec.replacen('=', ":", 1)).map_err(RpcError::invalid_params)?);
+    }
+
+    Ok(entry)
+}
+
+/// Reads the dwrs-specific `start-at` (`HH:MM` or a date) or `start-in`
+/// (`1h30m`) option of `addUri`.
+fn start_from_params(params: &[Value]) -> Result<Option<SystemTime>, RpcError> {
+    let option = |name: &str| {
+        params
+            .get(1)
+            .and_then(|options| options.get(name))
+            .and_then(Value::as_str)
+    };
+    match (option("start-at"), option("start-in")) {
+        (Some(at), _) => deferred::parse_start_at_now(at).map(Some),
+        (None, Some(delay)) => {
+            deferred::parse_duration(delay).map(|delay| Some(SystemTime::now() + delay))
+        }
+        (None, None) => Ok(None),
+    }
+    .map_err(RpcError::invalid_params)
+}
+
+fn call(
+    method: &str,
+    params: &[Value],
+    queue: &DownloadQueue,
+    opts: &RpcOptions,
+) -> Result<Value, RpcError> {
+    if method == "system.listMethods" {
+        return Ok(json!(METHODS));
+    }
+    if method == "system.multicall" {
+        let calls = params
+            .first()
+            .and_then(Value::as_array)
+            .ok_or_else(|| RpcError::invalid_params("multicall expects a list of calls"))?;
+        let results = calls
+            .iter()
+            .map(|c| {
+                let method = c.get("methodName").and_then(Value::as_str).unwrap_or("");
+                let params = c
+                    .get("params")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or(&[]);
+                match call(method, params, queue, opts) {
+                    Ok(v) => json!([v]),
+                    Err(e) => json!({ "code": e.code, "message": e.message }),
+                }
+            })
+            .collect::<Vec<_>>();
+        return Ok(json!(results));
+    }
+
+    let params = match (&opts.secret, params.first().and_then(Value::as_str)) {
+        (Some(secret), Some(token)) if token.strip_prefix("token:") == Some(secret.as_str()) => {
+            &params[1..]
+        }
+        (Some(_), _) => return Err(RpcError::new("Unauthorized")),
+        (None, Some(token)) if token.starts_with("token:") => &params[1..],
+        (None, _) => params,
+    };
+
+    match method {
+        "aria2.addUri" => {
+            let entry = entry_from_params(params, &opts.dir)?;
+            let start_at = start_from_params(params)?;
+            log::info!("RPC addUri {} -> {}", entry.url, entry.output.display());
+            Ok(json!(gid(queue.push_at(entry, 0, start_at))))
+        }
+        "aria2.tellStatus" => {
+            let id = parse_gid(params.first())?;
+            queue
+                .status(id)
+                .map(|item| status_json(&item, params.get(1)))
+                .ok_or_else(|| RpcError::new(format!("GID {} is not found", gid(id))))
+        }
+        "aria2.pause" | "aria2.forcePause" => {
+            let id = parse_gid(params.first())?;
+            if queue.pause(id) {
+                Ok(json!(gid(id)))
+            } else {
+                Err(RpcError::new(format!("GID {} cannot be paused", gid(id))))
+            }
+        }
+        "aria2.unpause" => {
+            let id = parse_gid(params.first())?;
+            if queue.resume(id) {
+                Ok(json!(gid(id)))
+            } else {
+                Err(RpcError::new(format!("GID {} is not paused", gid(id))))
+            }
+        }
+        "aria2.remove" | "aria2.forceRemove" => {
+            let id = parse_gid(params.first())?;
+            if queue.cancel(id) {
+                Ok(json!(gid(id)))
+            } else {
+                Err(RpcError::new(format!("GID {} cannot be removed", gid(id))))
+            }
+        }
+        "aria2.tellActive" => Ok(json!(
+            queue
+                .snapshot()
+                .iter()
+                .filter(|item| item.state == QueueState::Active)
+                .map(|item| status_json(item, params.first()))
+                .collect::<Vec<_>>()
+        )),
+        "aria2.tellWaiting" | "aria2.tellStopped" => {
+            let offset = params.first().and_then(Value::as_u64).unwrap_or(0) as usize;
+            let num = params.get(1).and_then(Value::as_u64).unwrap_or(1000) as usize;
+            let stopped = method == "aria2.tellStopped";
+            Ok(json!(
+                queue
+                    .snapshot()
+                    .iter()
+                    .filter(|item| match item.state {
+                        QueueState::Queued | QueueState::Paused => !stopped,
+                        QueueState::Active => false,
+                        _ => stopped,
+                    })
+                    .skip(offset)
+                    .take(num)
+                    .map(|item| status_json(item, params.get(2)))
+                    .collect::<Vec<_>>()
+            ))
+        }
+        "aria2.getGlobalStat" => {
+            let items = queue.snapshot();
+            let count = |f: fn(&QueueState) -> bool| {
+                items.iter().filter(|i| f(&i.state)).count().to_string()
+            };
+            let speed: f64 = items.iter().map(|i| i.speed).sum();
+            Ok(json!({
+                "downloadSpeed": (speed as u64).to_string(),
+                "uploadSpeed": "0",
+                "numActive": count(|s| *s == QueueState::Active),
+                "numWaiting": count(|s| matches!(s, QueueState::Queued | QueueState::Paused)),
+                "numStopped": count(QueueState::is_finished),
+                "numStoppedTotal": count(QueueState::is_finished),
+            }))
+        }
+        "aria2.getVersion" => Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "enabledFeatures": ["HTTPS"],
+        })),
+        _ => Err(RpcError {
+            code: -32601,
+            message: format!("Method not found: {}", method),
+        }),
+    }
+}
+
+fn respond(request: &Value, queue: &DownloadQueue, opts: &RpcOptions) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32600, "message": "Invalid Request" },
+        });
+    };
+    let params = request
+        .get("params")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+
+    match call(method, params, queue, opts) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    }
+}
+
+/// Answers a JSON-RPC request (or batch) against `queue`.
+pub fn handle(request: &Value, queue: &DownloadQueue, opts: &RpcOptions) -> Value {
+    match request {
+        Value::Array(batch) => Value::Array(
+            batch
+                .iter()
+                .map(|request| respond(request, queue, opts))
+                .collect(),
+        ),
+        request => respond(request, queue, opts),
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    queue: DownloadQueue,
+    opts: &RpcOptions,
+) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    let header_end = loop {
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buffer.len() > MAX_HEADER_SIZE {
+            return write_response(
+                &mut stream,
+                "431 Request Header Fields Too Large",
+                "text/plain",
+                b"",
+            )
+            .await;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    };
+    let content_length = header("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    // Browsers cannot send JSON cross-site without a CORS preflight, which
+    // is never granted, so web pages cannot drive the daemon
+    let is_json = header("content-type").is_some_and(|v| {
+        let mime = v.split(';').next().unwrap_or("").trim();
+        mime.eq_ignore_ascii_case("application/json")
+            || mime.eq_ignore_ascii_case("application/json-rpc")
+    });
+
+    match (method, path.split('?').next().unwrap_or("")) {
+        #[cfg(feature = "webui")]
+        ("GET", "/") => {
+            write_response(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                crate::webui::INDEX_HTML.as_bytes(),
+            )
+            .await
+        }
+        #[cfg(feature = "metrics")]
+        ("GET", "/metrics") => {
+            let body = queue.metrics().render(&queue.snapshot());
+            write_response(
+                &mut stream,
+                "200 OK",
+                "text/plain; version=0.0.4",
+                body.as_bytes(),
+            )
+            .await
+        }
+        ("POST", "/jsonrpc") if !is_json => {
+            write_response(
+                &mut stream,
+                "415 Unsupported Media Type",
+                "text/plain",
+                b"Content-Type must be application/json",
+            )
+            .await
+        }
+        ("POST", "/jsonrpc") => {
+            if content_length > MAX_BODY_SIZE {
+                return write_response(&mut stream, "413 Payload Too Large", "text/plain", b"")
+                    .await;
+            }
+            let mut body = buffer[header_end..].to_vec();
+            while body.len() < content_length {
+                let mut chunk = vec![0u8; content_length - body.len()];
+                let n = stream.read(&mut chunk).await?;
+                if n == 0 {
+                    break;
+                }
+                body.extend_from_slice(&chunk[..n]);
+            }
+
+            let response = match serde_json::from_slice::<Value>(&body) {
+                Ok(request) => handle(&request, &queue, opts),
+                Err(e) => json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+                }),
+            };
+            write_response(
+                &mut stream,
+                "200 OK",
+                "application/json-rpc",
+                response.to_string().as_bytes(),
+            )
+            .await
+        }
+        _ => write_response(&mut stream, "404 Not Found", "text/plain", b"").await,
+    }
+}
+
+/// Serves the aria2-compatible JSON-RPC API over HTTP at `/jsonrpc`.
+///
+/// With the `webui` feature the same server also serves a dashboard at `/`,
+/// and with the `metrics` feature Prometheus metrics at `/metrics`.
+///
+/// Added downloads are pushed to `queue`, which should be drained by
+/// [`Downloader::run_queue`](crate::Downloader::run_queue) running alongside.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dwrs::Downloader;
+/// use dwrs::queue::DownloadQueue;
+/// use dwrs::rpc::{self, RpcOptions};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let downloader = Downloader::new_default();
+/// let queue = DownloadQueue::new(4);
+/// tokio::spawn(rpc::serve(queue.clone(), RpcOptions::default()));
+/// downloader.run_queue(&queue).await;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn serve(queue: DownloadQueue, opts: RpcOptions) -> std::io::Result<()> {
+    if opts.secret.is_none() && !opts.addr.ip().is_loopback() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("a secret is required to listen on {}", opts.addr),
+        ));
+    }
+    let listener = TcpListener::bind(opts.addr).await?;
+    serve_on(listener, queue, opts).await
+}
+
+/// Like [`serve`], but on an already bound listener such as one passed by
+/// systemd socket activation. [`RpcOptions::addr`] is ignored.
+///
+/// Fails right away if the listener is reachable from other machines and
+/// no [`RpcOptions::secret`] is set.
+pub async fn serve_on(
+    listener: TcpListener,
+    queue: DownloadQueue,
+    opts: RpcOptions,
+) -> std::io::Result<()> {
+    let addr = listener.local_addr()?;
+    if opts.secret.is_none() && !addr.ip().is_loopback() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("a secret is required to listen on {}", addr),
+        ));
+    }
+    log::info!("JSON-RPC listening on http://{}/jsonrpc", addr);
+    let opts = std::sync::Arc::new(opts);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        log::debug!("RPC connection from {}", peer);
+        let queue = queue.clone();
+        let opts = opts.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, queue, &opts).await {
+                log::warn!("RPC connection {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aria2_methods() {
+        let queue = DownloadQueue::new(1);
+        let opts = RpcOptions {
+            secret: Some("s3cret".to_string()),
+            dir: PathBuf::from("/tmp/dl"),
+            ..Default::default()
+        };
+
+        let denied = handle(
+            &json!({"id": 1, "method": "aria2.addUri", "params": [["https://example.com/a.iso"]]}),
+            &queue,
+            &opts,
+        );
+        assert_eq!(denied["error"]["message"], "Unauthorized");
+
+        let added = handle(
+            &json!({"id": 2, "method": "aria2.addUri", "params": [
+                "token:s3cret", ["https://example.com/a.iso"], {"out": "b.iso"}
+            ]}),
+            &queue,
+            &opts,
+        );
+        let gid = added["result"].as_str().unwrap().to_string();
+        assert_eq!(gid.len(), 16);
+
+        let batch = handle(
+            &json!([
+                {"id": 3, "method": "aria2.pause", "params": ["token:s3cret", gid]},
+                {"id": 4, "method": "aria2.tellStatus", "params": ["token:s3cret", gid, ["status", "files"]]},
+                {"id": 5, "method": "aria2.remove", "params": ["token:s3cret", gid]},
+                {"id": 6, "method": "aria2.tellStopped", "params": ["token:s3cret", 0, 10, ["status"]]},
+            ]),
+            &queue,
+            &opts,
+        );
+        assert_eq!(batch[0]["result"], gid);
+        assert_eq!(batch[1]["result"]["status"], "paused");
+        assert_eq!(batch[1]["result"]["files"][0]["path"], "/tmp/dl/b.iso");
+        assert!(batch[1]["result"].get("gid").is_none());
+        assert_eq!(batch[3]["result"][0]["status"], "removed");
+
+        let scheduled = handle(
+            &json!({"id": 7, "method": "aria2.addUri", "params": [
+                "token:s3cret", ["https://example.com/c.iso"], {"start-in": "1h"}
+            ]}),
+            &queue,
+            &opts,
+        );
+        let id = DownloadId::from_str_radix(scheduled["result"].as_str().unwrap(), 16).unwrap();
+        assert!(queue.status(id).unwrap().is_scheduled());
+
+        for options in [
+            json!({"out": "../escape.iso"}),
+            json!({"out": "/etc/cron.d/x"}),
+            json!({"dir": "/tmp"}),
+            json!({"dir": "iso/../.."}),
+        ] {
+            let escaped = handle(
+                &json!({"id": 8, "method": "aria2.addUri", "params": [
+                    "token:s3cret", ["https://example.com/d.iso"], options
+                ]}),
+                &queue,
+                &opts,
+            );
+            assert_eq!(escaped["error"]["code"], -32602, "{}", options);
+        }
+        let nested = handle(
+            &json!({"id": 9, "method": "aria2.addUri", "params": [
+                "token:s3cret", ["https://example.com/e.iso"], {"dir": "iso"}
+            ]}),
+            &queue,
+            &opts,
+        );
+        let id = DownloadId::from_str_radix(nested["result"].as_str().unwrap(), 16).unwrap();
+        assert_eq!(
+            queue.status(id).unwrap().output,
+            Path::new("/tmp/dl/iso/e.iso")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_checks() {
+        let public = RpcOptions {
+            addr: SocketAddr::from(([0, 0, 0, 0], 0)),
+            ..Default::default()
+        };
+        let e = serve(DownloadQueue::new(1), public).await.unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_on(
+            listener,
+            DownloadQueue::new(1),
+            RpcOptions::default(),
+        ));
+        let url = format!("http://{}/jsonrpc", addr);
+        let request = json!({"id": 1, "method": "aria2.getVersion"});
+        let client = reqwest::Client::new();
+
+        let form = client
+            .post(&url)
+            .header("Content-Type", "text/plain")
+            .body(request.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(form.status(), 415);
+        assert!(form.headers().get("access-control-allow-origin").is_none());
+
+        let json = client.post(&url).json(&request).send().await.unwrap();
+        assert_eq!(json.status(), 200);
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Longest file name most file systems accept, in bytes
+pub const MAX_NAME_LEN: usize = 255;
+/// Extensions longer than this are not kept when a name is shortened
+const MAX_EXT_LEN: usize = 16;
+/// Longest path Windows opens without the `\\?\` prefix
+pub const MAX_PATH: usize = 260;
+/// Device names Windows reserves, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// How file names taken from URLs and server responses are cleaned up,
+/// set with `filename_mode` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilenameMode {
+    /// Replace path separators and control characters and defuse `.`/`..`
+    #[default]
+    Basic,
+    /// Also replace everything but letters, digits, `.`, `-` and `_`, and
+    /// never start a name with `.` or `-`
+    Strict,
+}
+
+impl FilenameMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilenameMode::Basic => "basic",
+            FilenameMode::Strict => "strict",
+        }
+    }
+
+    /// Makes this the mode used by [`sanitize`].
+    pub fn apply(self) {
+        STRICT.store(self == FilenameMode::Strict, Ordering::Relaxed);
+    }
+
+    /// Mode last set with [`FilenameMode::apply`].
+    pub fn current() -> Self {
+        if STRICT.load(Ordering::Relaxed) {
+            FilenameMode::Strict
+        } else {
+            FilenameMode::Basic
+        }
+    }
+}
+
+impl std::str::FromStr for FilenameMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "basic" => Ok(FilenameMode::Basic),
+            "strict" => Ok(FilenameMode::Strict),
+            other => Err(format!(
+                "unknown filename mode '{}' (expected basic or strict)",
+                other
+            )),
+        }
+    }
+}
+
+/// Cuts `name` down to [`MAX_NAME_LEN`] bytes, keeping a short extension.
+fn truncate(name: String) -> String {
+    if name.len() <= MAX_NAME_LEN {
+        return name;
+    }
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty() && ext.len() <= MAX_EXT_LEN)
+        .map(|ext| format!(".{}", ext))
+        .unwrap_or_default();
+    let mut end = MAX_NAME_LEN - ext.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &name[..end], ext)
+}
+
+/// Makes `name` valid on Windows: characters it forbids are replaced,
+/// trailing dots and spaces dropped and reserved device names such as
+/// `CON` or `nul.txt` get a `_` after the stem.
+pub fn windows_safe(name: &str) -> String {
+    let mut safe: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    safe.truncate(safe.trim_end_matches(['.', ' ']).len());
+    let stem = safe.split('.').next().unwrap_or("").trim_end();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        safe.insert(stem.len(), '_');
+    }
+    safe
+}
+
+/// Turns `name` into a single path component in the given mode.
+///
+/// The result never contains a path separator, is never empty, `.` or `..`
+/// and is at most [`MAX_NAME_LEN`] bytes long, so joining it to an output
+/// directory cannot leave that directory. Strict names, and all names on
+/// Windows, are also made [safe for Windows](windows_safe).
+pub fn sanitize_with(name: &str, mode: FilenameMode) -> String {
+    let strict = mode == FilenameMode::Strict;
+    let mut clean: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c if strict && !(c.is_alphanumeric() || matches!(c, '.' | '-' | '_')) => '_',
+            c => c,
+        })
+        .collect();
+    clean = truncate(clean);
+    if clean.chars().all(|c| c == '.') {
+        clean = "_".repeat(clean.len().max(1));
+    }
+    if strict || cfg!(windows) {
+        clean = windows_safe(&clean);
+        if clean.is_empty() {
+            clean.push('_');
+        }
+    }
+    if strict && clean.starts_with(['.', '-']) {
+        clean.replace_range(..1, "_");
+    }
+    clean
+}
+
+/// Sanitizes a name a server controls, such as the last segment of a URL
+/// path, in the mode set with [`FilenameMode::apply`].
+pub fn sanitize(name: &str) -> String {
+    sanitize_with(name, FilenameMode::current())
+}
+
+/// `path` with the `\\?\` prefix if it is an absolute Windows path longer
+/// than [`MAX_PATH`], e.g. `C:\dl\...` or `\\server\share\...`.
+fn with_long_prefix(path: &str) -> Option<String> {
+    if path.len() < MAX_PATH || path.starts_with(r"\\?\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", unc));
+    }
+    let bytes = path.as_bytes();
+    let drive = bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    drive.then(|| format!(r"\\?\{}", path))
+}
+
+/// Joins `relative`, a path chosen by someone else such as an RPC client
+/// or a manifest, to `base`. Absolute paths and `..` components are
+/// refused, so the result cannot leave `base`.
+pub fn confine(base: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let mut path = base.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("{} leaves {}", relative.display(), base.display()));
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Path to open `path` with, so output paths longer than [`MAX_PATH`] work
+/// on Windows. Other platforms and short paths get `path` back unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) || path.as_os_str().len() < MAX_PATH {
+        return path.to_path_buf();
+    }
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    with_long_prefix(&absolute.to_string_lossy()).map_or(absolute, PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        let basic = |name| sanitize_with(name, FilenameMode::Basic);
+        assert_eq!(basic("report 1.pdf"), "report 1.pdf");
+        assert_eq!(basic(".."), "__");
+        assert_eq!(basic(""), "_");
+        assert_eq!(basic("..\\..\\etc/passwd"), ".._.._etc_passwd");
+        assert_eq!(basic("a\u{0}b\nc"), "a_b_c");
+        assert_eq!(basic(".bashrc"), ".bashrc");
+
+        let strict = |name| sanitize_with(name, FilenameMode::Strict);
+        assert_eq!(strict("my file?.iso"), "my_file_.iso");
+        assert_eq!(strict(".bashrc"), "_bashrc");
+        assert_eq!(strict("-rf"), "_rf");
+        assert_eq!(strict("résumé.pdf"), "résumé.pdf");
+
+        let long = format!("{}.tar.gz", "é".repeat(200));
+        let short = basic(&long);
+        assert!(short.len() <= MAX_NAME_LEN);
+        assert!(short.ends_with("é.gz"));
+
+        assert_eq!(windows_safe("CON"), "CON_");
+        assert_eq!(windows_safe("nul.tar.gz"), "nul_.tar.gz");
+        assert_eq!(windows_safe("Com1 .txt"), "Com1_ .txt");
+        assert_eq!(windows_safe("console.log"), "console.log");
+        assert_eq!(windows_safe("a:b?.txt. . "), "a_b_.txt");
+        assert_eq!(strict("..."), "___");
+        assert_eq!(strict("aux"), "aux_");
+
+        let long = format!("C:/dl/{}", "a".repeat(MAX_PATH));
+        assert_eq!(
+            with_long_prefix(&long),
+            Some(format!(r"\\?\C:\dl\{}", "a".repeat(MAX_PATH)))
+        );
+        let unc = format!(r"\\server\share\{}", "a".repeat(MAX_PATH));
+        assert!(
+            with_long_prefix(&unc)
+                .unwrap()
+                .starts_with(r"\\?\UNC\server\share\")
+        );
+        assert_eq!(with_long_prefix(r"C:\dl\a.iso"), None);
+    }
+
+    #[test]
+    fn test_confine() {
+        let base = Path::new("dl");
+        assert_eq!(
+            confine(base, Path::new("./iso/a.iso")),
+            Ok(base.join("iso/a.iso"))
+        );
+        assert!(confine(base, Path::new("../a.iso")).is_err());
+        assert!(confine(base, Path::new("iso/../../a.iso")).is_err());
+        assert!(confine(base, Path::new("/etc/passwd")).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Client;
+use std::collections::HashSet;
+
+use crate::utils::unescape_xml;
+
+static LOC_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</loc>").expect("valid loc regex")
+});
+
+/// Nested sitemap indexes deeper than this are ignored
+const MAX_INDEX_DEPTH: usize = 5;
+
+/// Include/exclude filters applied to sitemap entries
+#[derive(Debug, Clone, Default)]
+pub struct SitemapOptions {
+    /// Only keep URLs matching this pattern
+    pub include: Option<Regex>,
+    /// Drop URLs matching this pattern
+    pub exclude: Option<Regex>,
+}
+
+impl SitemapOptions {
+    pub fn matches(&self, url: &str) -> bool {
+        self.include.as_ref().is_none_or(|re| re.is_match(url))
+            && !self.exclude.as_ref().is_some_and(|re| re.is_match(url))
+    }
+}
+
+/// Parsed sitemap document
+#[derive(Debug, PartialEq)]
+pub enum Sitemap {
+    /// `<urlset>` with page URLs
+    UrlSet(Vec<String>),
+    /// `<sitemapindex>` pointing at further sitemaps
+    Index(Vec<String>),
+}
+
+/// Parses a sitemap or sitemap index document.
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    let locs = LOC_RE
+        .captures_iter(xml)
+        .map(|c| unescape_xml(c[1].trim()))
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if xml.contains("<sitemapindex") {
+        Sitemap::Index(locs)
+    } else {
+        Sitemap::UrlSet(locs)
+    }
+}
+
+/// Fetches a sitemap, following sitemap indexes, and returns the filtered page URLs.
+pub async fn fetch_sitemap(
+    client: &Client,
+    url: &str,
+    opts: &SitemapOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut pending = vec![(url.to_string(), 0usize)];
+    let mut seen = HashSet::new();
+    let mut urls = Vec::new();
+
+    while let Some((sitemap_url, depth)) = pending.pop() {
+        if !seen.insert(sitemap_url.clone()) {
+            continue;
+        }
+
+        log::info!("Fetching sitemap: {}", sitemap_url);
+        let body = client
+            .get(&sitemap_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        match parse_sitemap(&body) {
+            Sitemap::Index(children) => {
+                if depth >= MAX_INDEX_DEPTH {
+                    log::warn!("Sitemap index nesting too deep, skipping {}", sitemap_url);
+                    continue;
+                }
+                log::debug!(
+                    "{} is an index with {} sitemaps",
+                    sitemap_url,
+                    children.len()
+                );
+                pending.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+            }
+            Sitemap::UrlSet(locs) => {
+                log::debug!("{} lists {} URLs", sitemap_url, locs.len());
+                urls.extend(locs.into_iter().filter(|u| opts.matches(u)));
+            }
+        }
+    }
+
+    let mut unique = HashSet::new();
+    urls.retain(|u| unique.insert(u.clone()));
+
+    if urls.is_empty() {
+        return Err(format!("No URLs found in sitemap {}", url).into());
+    }
+
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_urlset_and_index() {
+        let urlset = r#"<?xml version="1.0"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://example.com/a?x=1&amp;y=2</loc></url>
+              <url><loc><![CDATA[https://example.com/b]]></loc></url>
+            </urlset>"#;
+        assert_eq!(
+            parse_sitemap(urlset),
+            Sitemap::UrlSet(vec![
+                "https://example.com/a?x=1&y=2".to_string(),
+                "https://example.com/b".to_string(),
+            ])
+        );
+
+        let index = r#"<sitemapindex><sitemap><loc>https://example.com/s1.xml</loc></sitemap></sitemapindex>"#;
+        assert_eq!(
+            parse_sitemap(index),
+            Sitemap::Index(vec!["https://example.com/s1.xml".to_string()])
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE};
+use reqwest::{Method, Response, StatusCode};
+
+use crate::transport::{Transport, TransportError, request, with_range};
+
+/// What a server reported about a URL without its body being downloaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlStatus {
+    pub url: String,
+    /// URL after following redirects
+    pub final_url: String,
+    pub status: u16,
+    /// Size of the full resource in bytes, if announced
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    /// Whether the server accepts byte range requests, i.e. downloads can resume
+    pub resumable: bool,
+    /// Response headers in the order the server sent them
+    pub headers: Vec<(String, String)>,
+}
+
+impl UrlStatus {
+    /// A URL is alive when it answered with a 2xx status after redirects.
+    pub fn is_alive(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    fn from_response(url: &str, response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+        // A 206 reply carries the chunk length; the full size is in Content-Range.
+        let size = if partial {
+            header(CONTENT_RANGE).and_then(|range| range.rsplit('/').next()?.parse().ok())
+        } else {
+            header(CONTENT_LENGTH).and_then(|len| len.parse().ok())
+        };
+        Self {
+            url: url.to_string(),
+            final_url: response.url().to_string(),
+            status: response.status().as_u16(),
+            size,
+            content_type: header(CONTENT_TYPE),
+            resumable: partial || header(ACCEPT_RANGES).is_some_and(|v| v == "bytes"),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Checks `url` with a HEAD request.
+///
+/// Servers that reject HEAD (or fail it) get a one-byte range GET instead,
+/// whose body is dropped unread.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let client = reqwest::Client::new();
+/// let status = dwrs::spider::check(&client, "https://example.com/file.iso", &[]).await?;
+/// println!("{} {:?} resumable={}", status.status, status.size, status.resumable);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn check(
+    transport: &dyn Transport,
+    url: &str,
+    headers: &[(String, String)],
+) -> Result<UrlStatus, TransportError> {
+    match transport
+        .execute(request(Method::HEAD, url, headers)?)
+        .await
+    {
+        Ok(response)
+            if !matches!(
+                response.status(),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            ) =>
+        {
+            return Ok(UrlStatus::from_response(url, &response));
+        }
+        Ok(response) => log::debug!(
+            "HEAD {} returned {}, retrying with GET",
+            url,
+            response.status()
+        ),
+        Err(e) => log::debug!("HEAD {} failed ({}), retrying with GET", url, e),
+    }
+
+    let range = with_range(request(Method::GET, url, headers)?, 0, Some(0));
+    let response = transport.execute(range).await?;
+    Ok(UrlStatus::from_response(url, &response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[tokio::test]
+    async fn test_check_falls_back_to_range_get() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method("HEAD").path("/ok.iso");
+            then.status(200)
+                .header("Content-Length", "1024")
+                .header("Content-Type", "application/octet-stream")
+                .header("Accept-Ranges", "bytes");
+        });
+        server.mock(|when, then| {
+            when.method("HEAD").path("/nohead");
+            then.status(405);
+        });
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/nohead")
+                .header("Range", "bytes=0-0");
+            then.status(206)
+                .header("Content-Range", "bytes 0-0/5000")
+                .body("x");
+        });
+        server.mock(|when, then| {
+            when.method("HEAD").path("/gone");
+            then.status(404);
+        });
+
+        let client = reqwest::Client::new();
+        let ok = check(&client, &server.url("/ok.iso"), &[]).await.unwrap();
+        assert!(ok.is_alive() && ok.resumable);
+        assert_eq!(ok.size, Some(1024));
+        assert_eq!(ok.content_type.as_deref(), Some("application/octet-stream"));
+        assert!(
+            ok.headers
+                .contains(&("accept-ranges".to_string(), "bytes".to_string()))
+        );
+
+        let fallback = check(&client, &server.url("/nohead"), &[]).await.unwrap();
+        assert_eq!((fallback.status, fallback.size), (206, Some(5000)));
+        assert!(fallback.is_alive() && fallback.resumable);
+
+        let gone = check(&client, &server.url("/gone"), &[]).await.unwrap();
+        assert!(!gone.is_alive());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::units::{format_bytes, format_speed};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::queue::{DownloadQueue, QueueItemStatus, QueueState};
+
+/// Set for the child started by `--background`, which then keeps a state file
+pub const BACKGROUND_ENV: &str = "DWRS_BACKGROUND";
+/// How often a running process rewrites its state file
+pub const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+/// State files not updated for this long belong to a process that died
+const STALE_AFTER: u64 = 30;
+
+/// Progress of one `dwrs` process as written to its state file and
+/// printed by `dwrs status --json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub pid: u32,
+    /// Unix time the process started writing its state
+    pub started_at: u64,
+    /// Unix time of the last update
+    pub updated_at: u64,
+    pub items: Vec<ItemState>,
+}
+
+/// One download of a [`ProcessState`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemState {
+    pub id: u64,
+    pub url: String,
+    pub output: PathBuf,
+    /// `queued`, `scheduled`, `paused`, `active`, `completed`, `failed` or `removed`
+    pub state: String,
+    pub downloaded: u64,
+    /// Total size in bytes, `0` if unknown
+    pub total: u64,
+    /// Bytes per second
+    pub speed: u64,
+    pub error: Option<String>,
+    /// Unix time a scheduled download starts
+    #[serde(default)]
+    pub start_at: Option<u64>,
+}
+
+impl From<&QueueItemStatus> for ItemState {
+    fn from(item: &QueueItemStatus) -> Self {
+        let (state, error) = match &item.state {
+            QueueState::Queued if item.is_scheduled() => ("scheduled", None),
+            QueueState::Queued => ("queued", None),
+            QueueState::Paused => ("paused", None),
+            QueueState::Active => ("active", None),
+            QueueState::Completed => ("completed", None),
+            QueueState::Failed(e) => ("failed", Some(e.clone())),
+            QueueState::Removed => ("removed", None),
+        };
+        Self {
+            id: item.id,
+            url: item.url.clone(),
+            output: item.output.clone(),
+            state: state.to_string(),
+            downloaded: item.downloaded,
+            total: item.total,
+            speed: item.speed as u64,
+            error,
+            start_at: item
+                .start_at
+                .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Directory holding one `<pid>.json` state file per running process.
+pub fn state_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("dwrs")
+        .join("status")
+}
+
+/// State file of the current process inside [`state_dir`].
+pub fn default_path() -> PathBuf {
+    state_dir().join(format!("{}.json", std::process::id()))
+}
+
+/// Keeps a state file in sync with a queue until dropped.
+///
+/// The file is rewritten every [`UPDATE_INTERVAL`] and removed on drop.
+pub struct StateWriter {
+    path: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl StateWriter {
+    pub fn spawn(path: &Path, queue: DownloadQueue) -> Self {
+        let path = path.to_path_buf();
+        let task = tokio::spawn({
+            let path = path.clone();
+            async move {
+                let started_at = now();
+                loop {
+                    let state = ProcessState {
+                        pid: std::process::id(),
+                        started_at,
+                        updated_at: now(),
+                        items: queue.snapshot().iter().map(ItemState::from).collect(),
+                    };
+                    if let Err(e) = write(&path, &state).await {
+                        log::debug!("Failed to write {}: {}", path.display(), e);
+                    }
+                    tokio::time::sleep(UPDATE_INTERVAL).await;
+                }
+            }
+        });
+        Self { path, task }
+    }
+}
+
+impl Drop for StateWriter {
+    fn drop(&mut self) {
+        self.task.abort();
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Writes `state` to `path` atomically, so readers never see half a file.
+async fn write(path: &Path, state: &ProcessState) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let content = serde_json::to_vec(state).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+/// Reads the state of every running process from `dir`, oldest first.
+///
+/// Files left behind by processes that stopped updating them are deleted.
+pub fn read_all(dir: &Path) -> Vec<ProcessState> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut states: Vec<ProcessState> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let state = std::fs::read(&path)
+                .ok()
+                .and_then(|content| serde_json::from_slice::<ProcessState>(&content).ok());
+            match state {
+                Some(state) if now().saturating_sub(state.updated_at) <= STALE_AFTER => Some(state),
+                _ => {
+                    log::debug!("Removing stale state file {}", path.display());
+                    std::fs::remove_file(&path).ok();
+                    None
+                }
+            }
+        })
+        .collect();
+    states.sort_by_key(|state| (state.started_at, state.pid));
+    states
+}
+
+/// Renders `states` as text, one block per process with a bar per download.
+pub fn render(states: &[ProcessState]) -> String {
+    const WIDTH: usize = 20;
+    let mut out = String::new();
+    for state in states {
+        let running = Duration::from_secs(now().saturating_sub(state.started_at));
+        out.push_str(&format!(
+            "PID {}, running for {}\n",
+            state.pid,
+            crate::summary::format_duration(running)
+        ));
+        for item in &state.items {
+            let fraction = if item.total > 0 {
+                (item.downloaded as f64 / item.total as f64).min(1.0)
+            } else if item.state == "completed" {
+                1.0
+            } else {
+                0.0
+            };
+            let filled = (fraction * WIDTH as f64).round() as usize;
+            let size = if item.total > 0 {
+                format!(
+                    "{}/{}",
+                    format_bytes(item.downloaded),
+                    format_bytes(item.total)
+                )
+            } else {
+                format_bytes(item.downloaded)
+            };
+            let speed = match (item.state.as_str(), item.start_at) {
+                ("active", _) => format_speed(item.speed as f64),
+                ("scheduled", Some(at)) => format!(
+                    "at {}",
+                    crate::deferred::format_local(UNIX_EPOCH + Duration::from_secs(at))
+                ),
+                _ => String::new(),
+            };
+            out.push_str(&format!(
+                "  {:<9} [{}{}] {:>3}%  {:<21} {:<12} {}\n",
+                item.state,
+                "#".repeat(filled),
+                "-".repeat(WIDTH - filled),
+                (fraction * 100.0) as u32,
+                size,
+                speed,
+                item.output.display()
+            ));
+            if let Some(error) = &item.error {
+                out.push_str(&format!("            {}\n", error));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DownloadEntry;
+
+    #[tokio::test]
+    async fn test_state_file_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("dwrs-status-{}", std::process::id()));
+        let queue = DownloadQueue::new(1);
+        queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+
+        let writer = StateWriter::spawn(&dir.join("1.json"), queue);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::write(dir.join("2.json"), "{}").unwrap();
+
+        let states = read_all(&dir);
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].pid, std::process::id());
+        assert_eq!(states[0].items[0].url, "https://example.com/a");
+        assert_eq!(states[0].items[0].state, "queued");
+        assert!(!dir.join("2.json").exists(), "stale file kept");
+        assert!(render(&states).contains("  queued    [--------------------]   0%  0 B"));
+
+        drop(writer);
+        assert!(read_all(&dir).is_empty());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,203 @@
+//! Where [`Downloader::download_to_storage`](crate::Downloader::download_to_storage)
+//! puts files.
+//!
+//! A [`Storage`] hands out a writer per file name, so downloads keep their
+//! retries, rate limits and progress without a local filesystem: [`Memory`]
+//! keeps files in memory, [`Directory`] writes them below a directory, and
+//! other backends (an object store, a browser's file system) implement the
+//! trait themselves.
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
+
+/// Writer returned by [`Storage::create`]
+pub type StorageWriter<'a> = Box<dyn AsyncWrite + Unpin + Send + 'a>;
+
+/// Stores downloaded files by name.
+pub trait Storage: Send + Sync {
+    /// Opens `name` for writing, replacing what is stored under it. The
+    /// file is complete once the writer is shut down.
+    fn create<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<StorageWriter<'a>>>;
+
+    /// Removes `name`, e.g. what a failed download left behind.
+    fn remove<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<()>>;
+}
+
+/// Files kept in memory; clones share the same files.
+///
+/// ```
+/// use dwrs::storage::{Memory, Storage};
+/// use tokio::io::AsyncWriteExt;
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let storage = Memory::default();
+/// let mut writer = storage.create("notes.txt").await?;
+/// writer.write_all(b"hello").await?;
+/// writer.shutdown().await?;
+/// assert_eq!(storage.get("notes.txt").unwrap(), "hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    files: Arc<Mutex<BTreeMap<String, Bytes>>>,
+}
+
+impl Memory {
+    /// Content of `name`, once its writer has been shut down.
+    pub fn get(&self, name: &str) -> Option<Bytes> {
+        self.files.lock().unwrap().get(name).cloned()
+    }
+
+    /// Names of the stored files, sorted.
+    pub fn names(&self) -> Vec<String> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl Storage for Memory {
+    fn create<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<StorageWriter<'a>>> {
+        let writer = MemoryWriter {
+            files: self.files.clone(),
+            name: name.to_string(),
+            buf: Vec::new(),
+        };
+        Box::pin(async move { Ok(Box::new(writer) as StorageWriter<'a>) })
+    }
+
+    fn remove<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<()>> {
+        self.files.lock().unwrap().remove(name);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Collects a file of [`Memory`] and stores it on shutdown.
+struct MemoryWriter {
+    files: Arc<Mutex<BTreeMap<String, Bytes>>>,
+    name: String,
+    buf: Vec<u8>,
+}
+
+impl AsyncWrite for MemoryWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().buf.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let content = Bytes::from(std::mem::take(&mut this.buf));
+        this.files
+            .lock()
+            .unwrap()
+            .insert(this.name.clone(), content);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Files below a directory; names that would leave it are refused.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, name: &str) -> std::io::Result<PathBuf> {
+        crate::sanitize::confine(&self.root, Path::new(name))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+    }
+}
+
+impl Storage for Directory {
+    fn create<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<StorageWriter<'a>>> {
+        Box::pin(async move {
+            let path = self.path(name)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let file = tokio::fs::File::create(&path).await?;
+            Ok(Box::new(file) as StorageWriter<'a>)
+        })
+    }
+
+    fn remove<'a>(&'a self, name: &'a str) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(name)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_directory() {
+        let root = std::env::temp_dir().join("dwrs_test_storage");
+        let storage = Directory::new(&root);
+        let mut writer = storage.create("sub/a.txt").await.unwrap();
+        writer.write_all(b"hello").await.unwrap();
+        writer.shutdown().await.unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read(root.join("sub/a.txt")).unwrap(), b"hello");
+
+        storage.remove("sub/a.txt").await.unwrap();
+        storage.remove("sub/a.txt").await.unwrap();
+        assert!(!root.join("sub/a.txt").exists());
+        assert!(storage.create("../escape.txt").await.is_err());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_download_to_storage() {
+        use httpmock::MockServer;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method("GET").path("/a.txt");
+            then.status(200).body("hello");
+        });
+        server.mock(|when, then| {
+            when.method("GET").path("/missing.txt");
+            then.status(404);
+        });
+
+        let downloader = crate::Downloader::builder().retries(1).build().unwrap();
+        let storage = Memory::default();
+        let size = downloader
+            .download_to_storage(&server.url("/a.txt"), &storage, "a.txt")
+            .await
+            .unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(storage.get("a.txt").unwrap(), "hello");
+
+        assert!(
+            downloader
+                .download_to_storage(&server.url("/missing.txt"), &storage, "b.txt")
+                .await
+                .is_err()
+        );
+        assert_eq!(storage.names(), ["a.txt"]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::units::{format_bytes, format_decimal, format_speed};
+use rust_i18n::t;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Outcome of one file, as shown in the end-of-batch summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    pub url: String,
+    pub output: PathBuf,
+    /// Bytes on disk when the download ended
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Download attempts made, the first one included
+    pub attempts: usize,
+    /// Checksum from the manifest or the server, verified on success
+    pub checksum: Option<String>,
+    /// `None` on success
+    pub error: Option<String>,
+}
+
+impl FileSummary {
+    /// Average speed in bytes per second
+    pub fn speed(&self) -> f64 {
+        speed(self.bytes, self.duration)
+    }
+}
+
+fn speed(bytes: u64, duration: Duration) -> f64 {
+    let secs = duration.as_secs_f64();
+    if secs > 0.0 { bytes as f64 / secs } else { 0.0 }
+}
+
+/// Formats a duration as `4.2s`, `3m05s` or `1h02m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", format_decimal(duration.as_secs_f64(), 1)),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Renders the per-file table plus totals in the current locale.
+///
+/// `elapsed` is the wall time of the whole batch, which is shorter than the
+/// sum of the per-file times when files ran in parallel.
+pub fn render(files: &[FileSummary], elapsed: Duration) -> String {
+    let header = [
+        t!("summary.file"),
+        t!("summary.size"),
+        t!("summary.time"),
+        t!("summary.speed"),
+        t!("summary.status"),
+    ]
+    .map(|s| s.to_string());
+
+    let rows: Vec<[String; 5]> = files
+        .iter()
+        .map(|file| {
+            let name = file
+                .output
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.url.clone());
+            let status = match &file.error {
+                None => t!("summary.ok").to_string(),
+                Some(e) => format!("{}: {}", t!("summary.failed"), e),
+            };
+            [
+                name,
+                format_bytes(file.bytes),
+                format_duration(file.duration),
+                format_speed(file.speed()),
+                status,
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // The status column is last and may be long; don't pad it.
+    widths[4] = 0;
+
+    let line = |cells: &[String; 5]| {
+        let mut out = String::new();
+        for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            let pad = width.saturating_sub(cell.chars().count());
+            // Numbers are right-aligned, text left-aligned.
+            if (1..=3).contains(&i) {
+                out.push_str(&" ".repeat(pad));
+                out.push_str(cell);
+            } else {
+                out.push_str(cell);
+                out.push_str(&" ".repeat(pad));
+            }
+            out.push_str("  ");
+        }
+        out.trim_end().to_string()
+    };
+
+    let bytes: u64 = files.iter().map(|f| f.bytes).sum();
+    let failed = files.iter().filter(|f| f.error.is_some()).count();
+    let mut out = format!("{}\n{}\n", t!("summary.title"), line(&header));
+    for row in &rows {
+        out.push_str(&line(row));
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "{}  {}  {}  {}\n",
+        t!("summary.total", ok = files.len() - failed, failed = failed),
+        format_bytes(bytes),
+        format_duration(elapsed),
+        format_speed(speed(bytes, elapsed))
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let files = vec![
+            FileSummary {
+                url: "https://example.com/a.iso".to_string(),
+                output: PathBuf::from("out/a.iso"),
+                bytes: 2048,
+                duration: Duration::from_secs(2),
+                attempts: 1,
+                checksum: None,
+                error: None,
+            },
+            FileSummary {
+                url: "https://example.com/b".to_string(),
+                output: PathBuf::from("b"),
+                bytes: 0,
+                duration: Duration::from_millis(300),
+                attempts: 3,
+                checksum: None,
+                error: Some("404".to_string()),
+            },
+        ];
+        rust_i18n::set_locale("en");
+        let table = render(&files, Duration::from_secs(2));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[1], "File       Size  Time       Speed  Status");
+        assert_eq!(lines[2], "a.iso  2.00 KiB  2.0s  1.00 KiB/s  ok");
+        assert_eq!(lines[3], "b           0 B  0.3s       0 B/s  failed: 404");
+        assert_eq!(
+            lines[4],
+            "Total: 1 ok, 1 failed  2.00 KiB  2.0s  1.00 KiB/s"
+        );
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m05s");
+    }
+}
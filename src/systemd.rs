@@ -0,0 +1,190 @@
+use crate::units::format_bytes;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+
+use crate::queue::{QueueItemStatus, QueueState};
+
+/// First file descriptor passed by socket activation (`SD_LISTEN_FDS_START`)
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Sends a state such as `READY=1` or `STATUS=...` to the service manager.
+///
+/// Does nothing and returns `false` unless running under systemd with
+/// `Type=notify` (i.e. `NOTIFY_SOCKET` is set).
+pub fn notify(state: &str) -> bool {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return false;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return false;
+    };
+    let path = path.to_string_lossy();
+    let sent = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+                .and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return false,
+        None => socket.send_to(state.as_bytes(), path.as_ref()),
+    };
+    if let Err(e) = &sent {
+        log::debug!("sd_notify failed: {}", e);
+    }
+    sent.is_ok()
+}
+
+/// Takes the first listening socket passed by systemd socket activation.
+///
+/// Returns `None` when the process was not socket-activated or the
+/// descriptor is not a listening TCP socket. The `LISTEN_*` variables are
+/// removed and the socket is closed on exec, so hooks inherit neither.
+/// Must be called at most once, since it takes ownership of the descriptor.
+pub fn take_listener() -> Option<std::net::TcpListener> {
+    let pid = std::env::var("LISTEN_PID").ok()?.parse::<u32>().ok()?;
+    let fds = std::env::var("LISTEN_FDS").ok()?.parse::<usize>().ok()?;
+    if pid != std::process::id() || fds == 0 {
+        return None;
+    }
+    // SAFETY: called once while the daemon starts, before any download,
+    // hook or other thread that reads the environment runs.
+    unsafe {
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+    }
+    if fds > 1 {
+        log::warn!("Socket activation passed {} sockets, using the first", fds);
+    }
+    if let Err(e) = check_listener(LISTEN_FDS_START) {
+        log::warn!("Ignoring the socket passed by systemd: {}", e);
+        return None;
+    }
+    // SAFETY: systemd hands over LISTEN_FDS open descriptors starting at 3,
+    // and nothing else in the process owns them.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    if let Err(e) = listener.local_addr() {
+        log::warn!("Ignoring the socket passed by systemd: {}", e);
+        return None;
+    }
+    listener.set_nonblocking(true).ok()?;
+    Some(listener)
+}
+
+/// Fails unless `fd` is a listening stream socket, and sets `FD_CLOEXEC`
+/// on it.
+fn check_listener(fd: RawFd) -> std::io::Result<()> {
+    let option = |name| {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: value and len describe a valid c_int buffer
+        let res = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                name,
+                (&raw mut value).cast(),
+                &mut len,
+            )
+        };
+        match res {
+            -1 => Err(std::io::Error::last_os_error()),
+            _ => Ok(value),
+        }
+    };
+    if option(libc::SO_TYPE)? != libc::SOCK_STREAM {
+        return Err(std::io::Error::other("not a stream socket"));
+    }
+    if option(libc::SO_ACCEPTCONN)? == 0 {
+        return Err(std::io::Error::other("not listening"));
+    }
+    // SAFETY: fcntl only reads and sets the flags of the descriptor
+    let set = unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        flags != -1 && libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) != -1
+    };
+    if !set {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Status line shown by `systemctl status`, e.g.
+/// `3 downloads active, 2 queued, 1.20 GiB remaining`.
+pub fn status_line(items: &[QueueItemStatus]) -> String {
+    let active = items
+        .iter()
+        .filter(|i| i.state == QueueState::Active)
+        .count();
+    let queued = items
+        .iter()
+        .filter(|i| i.state == QueueState::Queued)
+        .count();
+    let remaining: u64 = items
+        .iter()
+        .filter(|i| matches!(i.state, QueueState::Active | QueueState::Queued))
+        .map(|i| i.total.saturating_sub(i.downloaded))
+        .sum();
+
+    let mut status = format!(
+        "{} download{} active",
+        active,
+        if active == 1 { "" } else { "s" }
+    );
+    if queued > 0 {
+        status.push_str(&format!(", {} queued", queued));
+    }
+    if remaining > 0 {
+        status.push_str(&format!(", {} remaining", format_bytes(remaining)));
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn test_check_listener() {
+        use std::os::fd::AsRawFd;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(check_listener(listener.as_raw_fd()).is_ok());
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(check_listener(udp.as_raw_fd()).is_err());
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        assert!(check_listener(stream.as_raw_fd()).is_err());
+    }
+
+    #[test]
+    fn test_status_line() {
+        let item = |state, downloaded, total| QueueItemStatus {
+            id: 1,
+            url: "https://example.com/a".to_string(),
+            output: PathBuf::from("a"),
+            priority: 0,
+            state,
+            downloaded,
+            total,
+            speed: 0.0,
+            elapsed: Duration::ZERO,
+            attempts: 1,
+            start_at: None,
+            report: None,
+            connections: Vec::new(),
+        };
+        assert_eq!(status_line(&[]), "0 downloads active");
+        let items = [
+            item(QueueState::Active, 512, 2048),
+            item(QueueState::Queued, 0, 0),
+            item(QueueState::Completed, 4096, 4096),
+        ];
+        assert_eq!(
+            status_line(&items),
+            "1 download active, 1 queued, 1.50 KiB remaining"
+        );
+    }
+}
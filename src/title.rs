@@ -0,0 +1,99 @@
+//! Overall progress in the title of the terminal window, for `--title`,
+//! e.g. `dwrs 63% · 3 files · 42.00 MiB/s`.
+//!
+//! The title is set with the xterm escape sequence most terminals and tmux
+//! understand, so progress stays visible while the window is in the
+//! background. The old title is pushed on the terminal's title stack and
+//! restored when the downloads are done, where the terminal supports it.
+
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::localization::plural;
+use crate::queue::{DownloadQueue, QueueItemStatus};
+use crate::units::format_speed;
+
+/// How often the title is brought up to date
+const TICK: Duration = Duration::from_secs(1);
+
+/// Title for the items of a queue; `expected` is the size of the whole
+/// batch if it is known up front.
+pub fn render(items: &[QueueItemStatus], expected: Option<u64>) -> String {
+    let downloaded: u64 = items.iter().map(|item| item.downloaded).sum();
+    let total = expected.unwrap_or_else(|| items.iter().map(|item| item.total).sum());
+    let left = items
+        .iter()
+        .filter(|item| !item.state.is_finished())
+        .count();
+    let speed: f64 = items.iter().map(|item| item.speed).sum();
+
+    let mut title = "dwrs".to_string();
+    if let Some(percent) = (downloaded.min(total) * 100).checked_div(total) {
+        title.push_str(&format!(" {}%", percent));
+    }
+    title.push_str(&format!(
+        " · {} · {}",
+        plural("title.files", left, &[]),
+        format_speed(speed)
+    ));
+    title
+}
+
+fn set_title(title: &str) {
+    let mut stderr = std::io::stderr();
+    write!(stderr, "\x1b]2;{}\x07", title).ok();
+    stderr.flush().ok();
+}
+
+/// Keeps the title at the progress of a queue until dropped.
+pub(crate) struct TitleUpdater {
+    ticker: JoinHandle<()>,
+}
+
+impl TitleUpdater {
+    /// Starts updating the title, unless stderr is not a terminal.
+    pub(crate) fn spawn(queue: DownloadQueue) -> Option<Self> {
+        if !std::io::stderr().is_terminal() {
+            return None;
+        }
+        // Save the current title on the xterm title stack
+        eprint!("\x1b[22;2t");
+        let ticker = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            loop {
+                interval.tick().await;
+                set_title(&render(&queue.snapshot(), queue.expected_size()));
+            }
+        });
+        Some(Self { ticker })
+    }
+}
+
+impl Drop for TitleUpdater {
+    fn drop(&mut self) {
+        self.ticker.abort();
+        // Terminals without a title stack keep the empty title
+        set_title("");
+        eprint!("\x1b[23;2t");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DownloadEntry;
+
+    #[test]
+    fn test_render() {
+        let queue = DownloadQueue::new(1);
+        queue.push(DownloadEntry::new("https://example.com/a", "a"), 0);
+        queue.push(DownloadEntry::new("https://example.com/b", "b"), 0);
+        assert_eq!(render(&queue.snapshot(), None), "dwrs · 2 files · 0 B/s");
+
+        let mut items = queue.snapshot();
+        items[0].downloaded = 50;
+        items[0].speed = 2048.0;
+        assert_eq!(render(&items, Some(200)), "dwrs 25% · 2 files · 2.00 KiB/s");
+    }
+}
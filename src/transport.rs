@@ -0,0 +1,215 @@
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue, RANGE};
+use reqwest::{Method, Request, Response, Url};
+
+/// Error of a [`Transport`]
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Sends the HTTP requests of downloads and URL checks.
+///
+/// dwrs only needs `HEAD` requests, `GET` requests with an optional
+/// `Range` header, the requests of a [`Payload`] and the streamed body of
+/// their responses, all built with [`request`]. The transport is
+/// [`reqwest::Client`] unless one is set with
+/// [`Downloader::set_transport`](crate::Downloader::set_transport), e.g. to
+/// answer from canned responses in tests, record traffic or send through
+/// another HTTP stack. Responses are built from an `http::Response`:
+///
+/// ```
+/// use dwrs::transport::{Transport, TransportError};
+/// use futures::future::BoxFuture;
+///
+/// struct Canned(&'static str);
+///
+/// impl Transport for Canned {
+///     fn execute(
+///         &self,
+///         request: reqwest::Request,
+///     ) -> BoxFuture<'_, Result<reqwest::Response, TransportError>> {
+///         let body = if request.method() == reqwest::Method::HEAD { "" } else { self.0 };
+///         let response = http::Response::builder()
+///             .header("Content-Length", self.0.len())
+///             .body(body)
+///             .unwrap();
+///         Box::pin(async move { Ok(response.into()) })
+///     }
+/// }
+/// ```
+pub trait Transport: Send + Sync {
+    /// Sends `request`, returning the response as soon as its headers are in.
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>>;
+}
+
+impl Transport for reqwest::Client {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+        Box::pin(async move { Ok(reqwest::Client::execute(self, request).await?) })
+    }
+}
+
+/// Builds a request for `url` with the extra `headers`.
+pub fn request(
+    method: Method,
+    url: &str,
+    headers: &[(String, String)],
+) -> Result<Request, TransportError> {
+    let mut request = Request::new(method, Url::parse(url)?);
+    for (name, value) in headers {
+        request.headers_mut().append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+    Ok(request)
+}
+
+/// Method and body of downloads that are not a plain `GET`, for
+/// `--method POST --data @payload.json`.
+///
+/// Such a request is sent once per attempt, without a `HEAD` request
+/// first, and its response is saved in one stream: it is never split into
+/// chunks or resumed, as ranges do not apply to it.
+///
+/// ```
+/// use dwrs::transport::Payload;
+///
+/// let payload = Payload::new(None, Some(r#"{"format":"csv"}"#)).unwrap().unwrap();
+/// assert_eq!(payload.method, reqwest::Method::POST);
+/// let request = payload.request("https://example.com/export", &[]).unwrap();
+/// assert_eq!(request.headers()["content-type"], "application/json");
+///
+/// assert!(Payload::new(None, None).unwrap().is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payload {
+    pub method: Method,
+    pub body: Bytes,
+}
+
+impl Payload {
+    /// Payload of `method` and `data`, which is sent as is or read from
+    /// the file after `@` (`@-` for stdin). Data without a method is
+    /// posted, like curl does; `None` means a plain `GET`.
+    pub fn new(method: Option<Method>, data: Option<&str>) -> std::io::Result<Option<Self>> {
+        let body = match data {
+            Some("@-") => {
+                let mut body = Vec::new();
+                std::io::Read::read_to_end(&mut std::io::stdin(), &mut body)?;
+                Bytes::from(body)
+            }
+            Some(data) => match data.strip_prefix('@') {
+                Some(path) => Bytes::from(std::fs::read(path).map_err(|e| {
+                    std::io::Error::new(e.kind(), format!("cannot read {}: {}", path, e))
+                })?),
+                None => Bytes::copy_from_slice(data.as_bytes()),
+            },
+            None => Bytes::new(),
+        };
+        let method = match method {
+            Some(method) => method,
+            None if data.is_some() => Method::POST,
+            None => return Ok(None),
+        };
+        if method == Method::GET && body.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self { method, body }))
+    }
+
+    /// Builds the request for `url` with the extra `headers`. A body
+    /// without a `Content-Type` header is sent as JSON if it parses as
+    /// such and as form data otherwise.
+    pub fn request(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<Request, TransportError> {
+        let mut request = request(self.method.clone(), url, headers)?;
+        if !self.body.is_empty() && !request.headers().contains_key(CONTENT_TYPE) {
+            let content_type = if serde_json::from_slice::<serde_json::Value>(&self.body).is_ok() {
+                "application/json"
+            } else {
+                "application/x-www-form-urlencoded"
+            };
+            request
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        }
+        *request.body_mut() = Some(self.body.clone().into());
+        Ok(request)
+    }
+}
+
+/// Asks for the bytes from `start` to `end`, both included, or to the end
+/// of the file without `end`.
+pub fn with_range(mut request: Request, start: u64, end: Option<u64>) -> Request {
+    let range = match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    };
+    request
+        .headers_mut()
+        .insert(RANGE, HeaderValue::from_str(&range).expect("valid range"));
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request with the same body, without any network
+    struct Canned;
+
+    impl Transport for Canned {
+        fn execute(&self, _request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+            let response = http::Response::builder()
+                .header("Content-Length", "11")
+                .body("hello world")
+                .unwrap();
+            Box::pin(async move { Ok(response.into()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let request = request(
+            Method::GET,
+            "https://example.com/a.bin",
+            &[("X-Token".to_string(), "secret".to_string())],
+        )
+        .unwrap();
+        assert_eq!(request.headers()["x-token"], "secret");
+        assert_eq!(
+            with_range(request, 5, Some(9)).headers()[RANGE],
+            "bytes=5-9"
+        );
+        assert!(super::request(Method::GET, "not a url", &[]).is_err());
+
+        let form = Payload::new(Some(Method::PUT), Some("a=1&b=2"))
+            .unwrap()
+            .unwrap();
+        let request = form.request("https://example.com/", &[]).unwrap();
+        assert_eq!(request.method(), Method::PUT);
+        assert_eq!(
+            request.headers()[CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(request.body().unwrap().as_bytes(), Some(&b"a=1&b=2"[..]));
+        let typed = [("Content-Type".to_string(), "text/csv".to_string())];
+        let request = form.request("https://example.com/", &typed).unwrap();
+        assert_eq!(request.headers()[CONTENT_TYPE], "text/csv");
+        assert!(Payload::new(Some(Method::GET), None).unwrap().is_none());
+        assert!(Payload::new(None, Some("@/nonexistent/payload.json")).is_err());
+
+        let downloader = crate::Downloader::builder()
+            .progress(false)
+            .transport(Canned)
+            .build()
+            .unwrap();
+        let body = downloader
+            .download_bytes("https://example.invalid/a.bin", 100)
+            .await
+            .unwrap();
+        assert_eq!(body, "hello world");
+    }
+}
@@ -0,0 +1,435 @@
+use crate::units::{format_bytes, format_speed};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::control;
+use crate::download::ConnectionStatus;
+use crate::file_parser::{DownloadEntry, filename_from_url, resolve_url};
+use crate::queue::{DownloadId, DownloadQueue, QueueItemStatus, QueueState};
+use crate::status::ItemState;
+
+/// How often the screen is redrawn when no key is pressed
+const TICK: Duration = Duration::from_millis(250);
+/// Height of the log pane, borders excluded
+const LOG_LINES: u16 = 8;
+const BAR_WIDTH: usize = 20;
+/// Connections of the selected download shown at most, borders excluded
+const CONNECTION_LINES: usize = 8;
+const HELP: &str = "↑↓ select  p pause/resume  c cancel  +/- priority  a add URL  q quit";
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+enum Mode {
+    Normal,
+    /// Typing the URL to add
+    AddUrl(String),
+}
+
+struct App {
+    queue: DownloadQueue,
+    selected: Option<DownloadId>,
+    mode: Mode,
+    /// Result of the last action, shown in place of the help line
+    message: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    fn new(queue: DownloadQueue) -> Self {
+        Self {
+            queue,
+            selected: None,
+            mode: Mode::Normal,
+            message: None,
+            quit: false,
+        }
+    }
+
+    fn position(&self, items: &[QueueItemStatus]) -> Option<usize> {
+        let position = self
+            .selected
+            .and_then(|id| items.iter().position(|item| item.id == id));
+        position.or((!items.is_empty()).then_some(0))
+    }
+
+    fn select(&mut self, items: &[QueueItemStatus], offset: isize) {
+        let Some(position) = self.position(items) else {
+            return;
+        };
+        let position = position.saturating_add_signed(offset).min(items.len() - 1);
+        self.selected = Some(items[position].id);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, items: &[QueueItemStatus]) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit();
+            return;
+        }
+        if let Mode::AddUrl(input) = &mut self.mode {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    self.mode = Mode::Normal;
+                    self.add(input.trim());
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let selected = self.position(items).map(|position| &items[position]);
+        self.message = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit(),
+            KeyCode::Up | KeyCode::Char('k') => self.select(items, -1),
+            KeyCode::Down | KeyCode::Char('j') => self.select(items, 1),
+            KeyCode::Char('a') => self.mode = Mode::AddUrl(String::new()),
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                if let Some(item) = selected {
+                    let done = match item.state {
+                        QueueState::Paused => self.queue.resume(item.id),
+                        _ => self.queue.pause(item.id),
+                    };
+                    if !done {
+                        self.message = Some(format!("#{} cannot be paused", item.id));
+                    }
+                }
+            }
+            KeyCode::Char('c') | KeyCode::Delete => {
+                if let Some(item) = selected
+                    && !self.queue.cancel(item.id)
+                {
+                    self.message = Some(format!("#{} already finished", item.id));
+                }
+            }
+            KeyCode::Char(c @ ('+' | '-')) => {
+                if let Some(item) = selected {
+                    let priority = item.priority + if c == '+' { 1 } else { -1 };
+                    if !self.queue.set_priority(item.id, priority) {
+                        self.message = Some(format!("#{} already started", item.id));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, input: &str) {
+        let Some(url) = resolve_url(input, None) else {
+            self.message = Some(format!("Not an http(s) URL: {}", input));
+            return;
+        };
+        let output = filename_from_url(&url);
+        let id = self.queue.push(DownloadEntry::new(url, output), 0);
+        self.selected = Some(id);
+    }
+
+    /// Closes the queue; transfers still running are stopped with their
+    /// partial files kept for `--continue`.
+    fn quit(&mut self) {
+        self.quit = true;
+        self.queue.close();
+        if self
+            .queue
+            .snapshot()
+            .iter()
+            .any(|item| !item.state.is_finished())
+        {
+            control::stop();
+        }
+    }
+}
+
+fn progress_bar(item: &QueueItemStatus) -> String {
+    let fraction = if item.total > 0 {
+        (item.downloaded as f64 / item.total as f64).min(1.0)
+    } else if item.state == QueueState::Completed {
+        1.0
+    } else {
+        0.0
+    };
+    bar(fraction)
+}
+
+fn bar(fraction: f64) -> String {
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "{}{} {:>3}%",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        (fraction * 100.0) as u32
+    )
+}
+
+/// One connection of a parallel download; stalled ones stand out.
+fn connection_line(connection: &ConnectionStatus) -> Line<'static> {
+    let line = Line::from(format!(
+        "#{:<3} {}  {}/{}  {}",
+        connection.index,
+        bar((connection.downloaded as f64 / connection.size() as f64).min(1.0)),
+        format_bytes(connection.downloaded),
+        format_bytes(connection.size()),
+        format_speed(connection.speed)
+    ));
+    if connection.is_complete() {
+        line.green()
+    } else if connection.speed == 0.0 {
+        line.red()
+    } else {
+        line
+    }
+}
+
+fn row(item: &QueueItemStatus) -> Row<'static> {
+    let size = if item.total > 0 {
+        format!(
+            "{}/{}",
+            format_bytes(item.downloaded),
+            format_bytes(item.total)
+        )
+    } else {
+        format_bytes(item.downloaded)
+    };
+    let active = item.state == QueueState::Active;
+    let speed = if active {
+        format_speed(item.speed)
+    } else {
+        String::new()
+    };
+    let eta = if active && item.speed > 0.0 && item.total > item.downloaded {
+        let left = (item.total - item.downloaded) as f64 / item.speed;
+        crate::summary::format_duration(Duration::from_secs_f64(left))
+    } else {
+        String::new()
+    };
+    let style = match item.state {
+        QueueState::Completed => Style::new().green(),
+        QueueState::Failed(_) => Style::new().red(),
+        QueueState::Paused | QueueState::Removed => Style::new().dark_gray(),
+        _ => Style::new(),
+    };
+    Row::new([
+        item.id.to_string(),
+        ItemState::from(item).state,
+        progress_bar(item),
+        size,
+        speed,
+        eta,
+        item.priority.to_string(),
+        item.output.display().to_string(),
+    ])
+    .style(style)
+}
+
+fn draw(frame: &mut Frame, app: &App, items: &[QueueItemStatus], logs: &[String]) {
+    let connections = app
+        .position(items)
+        .map_or(&[][..], |position| &items[position].connections);
+    let shown = connections.len().min(CONNECTION_LINES);
+    let [table_area, connections_area, log_area, footer_area] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(if shown > 0 { shown as u16 + 2 } else { 0 }),
+        Constraint::Length(LOG_LINES + 2),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let count = |f: fn(&QueueState) -> bool| items.iter().filter(|i| f(&i.state)).count();
+    let speed: f64 = items.iter().map(|item| item.speed).sum();
+    let title = format!(
+        " dwrs: {} active, {} waiting, {} done, {} ",
+        count(|s| *s == QueueState::Active),
+        count(|s| matches!(s, QueueState::Queued | QueueState::Paused)),
+        count(QueueState::is_finished),
+        format_speed(speed)
+    );
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(9),
+        Constraint::Length(BAR_WIDTH as u16 + 5),
+        Constraint::Length(21),
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Length(4),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(items.iter().map(row), widths)
+        .header(
+            Row::new([
+                "#", "State", "Progress", "Size", "Speed", "ETA", "Prio", "Output",
+            ])
+            .bold(),
+        )
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().reversed());
+    let mut state = TableState::default().with_selected(app.position(items));
+    frame.render_stateful_widget(table, table_area, &mut state);
+
+    if shown > 0 {
+        let lines: Vec<_> = connections[..shown].iter().map(connection_line).collect();
+        let title = format!(" Connections ({}) ", connections.len());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            connections_area,
+        );
+    }
+
+    let skip = logs.len().saturating_sub(LOG_LINES as usize);
+    let log = Paragraph::new(
+        logs[skip..]
+            .iter()
+            .map(|l| Line::from(l.as_str()))
+            .collect::<Vec<_>>(),
+    )
+    .block(Block::bordered().title(" Log "));
+    frame.render_widget(log, log_area);
+
+    let footer = match (&app.mode, &app.message) {
+        (Mode::AddUrl(input), _) => Line::from(format!("Add URL: {}█", input)),
+        (Mode::Normal, Some(message)) => Line::from(message.as_str()).yellow(),
+        (Mode::Normal, None)
+            if !items.is_empty() && items.iter().all(|i| i.state.is_finished()) =>
+        {
+            Line::from(format!("All downloads finished.  {}", HELP)).green()
+        }
+        (Mode::Normal, None) => Line::from(HELP).dark_gray(),
+    };
+    frame.render_widget(footer, footer_area);
+}
+
+fn run_app(terminal: &mut DefaultTerminal, queue: DownloadQueue) -> std::io::Result<()> {
+    let mut app = App::new(queue);
+    while !app.quit && !control::is_stopping() {
+        let items = app.queue.snapshot();
+        terminal.draw(|frame| draw(frame, &app, &items, &crate::logging::captured()))?;
+        if event::poll(TICK)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key, &items);
+        }
+    }
+    Ok(())
+}
+
+/// Shows `queue` as a full-screen table until the user quits.
+///
+/// Log records are shown in a pane instead of being printed. Downloads can be
+/// paused, cancelled, reprioritized and added from the keyboard. Quitting
+/// closes the queue, so whatever drains it returns once running transfers
+/// have stopped.
+pub async fn run(queue: DownloadQueue) -> std::io::Result<()> {
+    with_terminal(move |terminal| run_app(terminal, queue)).await
+}
+
+/// Runs `f` on a blocking thread with the terminal switched to full-screen
+/// mode and log records captured, restoring both afterwards.
+pub(crate) async fn with_terminal<T, F>(f: F) -> std::io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut DefaultTerminal) -> std::io::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        crate::logging::capture_terminal();
+        let result = ratatui::try_init().and_then(|mut terminal| {
+            ACTIVE.store(true, Ordering::SeqCst);
+            f(&mut terminal)
+        });
+        restore();
+        crate::logging::release_terminal();
+        result
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Gives the terminal back if the interface is shown, e.g. before exiting
+/// on a signal.
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        ratatui::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn test_dashboard() {
+        let queue = DownloadQueue::new(1);
+        let a = queue.push(DownloadEntry::new("https://example.com/a.iso", "a.iso"), 0);
+        queue.push(DownloadEntry::new("https://example.com/b.iso", "b.iso"), 0);
+
+        let mut app = App::new(queue.clone());
+        let items = queue.snapshot();
+        app.handle_key(KeyEvent::from(KeyCode::Down), &items);
+        app.handle_key(KeyEvent::from(KeyCode::Char('+')), &items);
+        app.handle_key(KeyEvent::from(KeyCode::Char('a')), &items);
+        for c in "https://example.com/c.iso".chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)), &items);
+        }
+        app.handle_key(KeyEvent::from(KeyCode::Enter), &items);
+
+        let items = queue.snapshot();
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            (items[0].output.to_str(), items[0].priority),
+            (Some("b.iso"), 1)
+        );
+        assert_eq!(queue.status(a).unwrap().priority, 0);
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        let logs = vec!["WARN  Retrying".to_string()];
+        terminal
+            .draw(|frame| draw(frame, &app, &items, &logs))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("0 active, 3 waiting, 0 done"));
+        assert!(screen.contains("c.iso"));
+        assert!(screen.contains("WARN  Retrying"));
+
+        let mut items = items;
+        let selected = app.position(&items).unwrap();
+        items[selected].connections = (0..2)
+            .map(|index| ConnectionStatus {
+                index,
+                start: index as u64 * 100,
+                end: index as u64 * 100 + 99,
+                downloaded: 50,
+                speed: 0.0,
+            })
+            .collect();
+        terminal
+            .draw(|frame| draw(frame, &app, &items, &logs))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Connections (2)"));
+        assert!(screen.contains("#1"));
+    }
+}
@@ -0,0 +1,117 @@
+//! Sizes and speeds as shown to people, in summaries, progress lines,
+//! notifications and the `{size}`/`{speed}` keys of bar templates.
+//!
+//! Whether sizes count in powers of 1024 (`MiB`) or 1000 (`MB`) is set
+//! with `units` in the config; the decimal separator follows the UI locale.
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DECIMAL: AtomicBool = AtomicBool::new(false);
+
+/// Prefixes sizes are shown with, set with `units` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Powers of 1024: KiB, MiB, GiB...
+    #[default]
+    Binary,
+    /// Powers of 1000: kB, MB, GB...
+    Decimal,
+}
+
+impl Units {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Units::Binary => "binary",
+            Units::Decimal => "decimal",
+        }
+    }
+
+    /// Makes these the units used by [`format_bytes`] and [`format_speed`].
+    pub fn apply(self) {
+        DECIMAL.store(self == Units::Decimal, Ordering::Relaxed);
+    }
+
+    /// Units last set with [`Units::apply`].
+    pub fn current() -> Self {
+        if DECIMAL.load(Ordering::Relaxed) {
+            Units::Decimal
+        } else {
+            Units::Binary
+        }
+    }
+
+    fn base_and_prefixes(self) -> (f64, [&'static str; 6]) {
+        match self {
+            Units::Binary => (1024.0, ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+            Units::Decimal => (1000.0, ["kB", "MB", "GB", "TB", "PB", "EB"]),
+        }
+    }
+}
+
+impl std::str::FromStr for Units {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "binary" => Ok(Units::Binary),
+            "decimal" => Ok(Units::Decimal),
+            other => Err(format!(
+                "unknown units '{}' (expected binary or decimal)",
+                other
+            )),
+        }
+    }
+}
+
+/// Formats `value` with `precision` decimals and the separator of the UI
+/// locale, e.g. `1.50` or `1,50`.
+pub fn format_decimal(value: f64, precision: usize) -> String {
+    let formatted = format!("{:.*}", precision, value);
+    let separator = t!("format.decimal_separator");
+    if separator == "." {
+        formatted
+    } else {
+        formatted.replace('.', &separator)
+    }
+}
+
+/// Formats a byte count in the current [`Units`], e.g. `512 B` or `1.50 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    let (base, prefixes) = Units::current().base_and_prefixes();
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / base;
+    let mut prefix = 0;
+    while value >= base && prefix < prefixes.len() - 1 {
+        value /= base;
+        prefix += 1;
+    }
+    format!("{} {}", format_decimal(value, 2), prefixes[prefix])
+}
+
+/// Formats a speed in bytes per second, e.g. `2.00 MiB/s`.
+pub fn format_speed(bytes_per_sec: f64) -> String {
+    format!("{}/s", format_bytes(bytes_per_sec.max(0.0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.50 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.00 MiB");
+        assert_eq!(format_speed(2048.0), "2.00 KiB/s");
+        assert_eq!("decimal".parse(), Ok(Units::Decimal));
+        assert!("metric".parse::<Units>().is_err());
+
+        let (base, prefixes) = Units::Decimal.base_and_prefixes();
+        assert_eq!((base, prefixes[1]), (1000.0, "MB"));
+    }
+}
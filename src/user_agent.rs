@@ -0,0 +1,97 @@
+//! The `User-Agent` of every request, for `--user-agent` and the
+//! `user_agent` config key.
+//!
+//! Some CDNs refuse agents they do not know, so besides a literal string a
+//! preset name can be given, e.g. `--ua browser`.
+
+/// What dwrs sends unless told otherwise
+pub const DEFAULT: &str = concat!("dwrs/", env!("CARGO_PKG_VERSION"));
+
+/// Preset names and the agents they stand for
+pub const PRESETS: &[(&str, &str)] = &[
+    ("dwrs", DEFAULT),
+    (
+        "browser",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:128.0) Gecko/20100101 Firefox/128.0",
+    ),
+    (
+        "chrome",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+         Chrome/126.0.0.0 Safari/537.36",
+    ),
+    ("curl", "curl/8.5.0"),
+    ("wget", "Wget/1.21.4"),
+];
+
+/// The agent of a preset name, or `value` itself if it names none.
+pub fn resolve(value: &str) -> String {
+    PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value.trim()))
+        .map_or_else(|| value.to_string(), |(_, agent)| agent.to_string())
+}
+
+/// Checks that `value` can be sent as a header.
+pub fn check(value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err("empty user agent".to_string());
+    }
+    reqwest::header::HeaderValue::from_str(&resolve(value))
+        .map(drop)
+        .map_err(|_| format!("invalid user agent '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("curl"), "curl/8.5.0");
+        assert_eq!(resolve("Browser"), PRESETS[1].1);
+        assert_eq!(resolve("dwrs"), DEFAULT);
+        assert_eq!(resolve("MyBot/1.0"), "MyBot/1.0");
+        assert!(check("MyBot/1.0").is_ok());
+        assert!(check("bad\nagent").is_err());
+        assert!(check(" ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sent_with_every_request() {
+        use httpmock::MockServer;
+
+        // Two chunks of the smallest size worth a connection of its own
+        let chunk = 2 * 1024 * 1024;
+        let server = MockServer::start();
+        let head = server.mock(|when, then| {
+            when.method("HEAD")
+                .path("/file.bin")
+                .header("User-Agent", "curl/8.5.0");
+            then.status(200)
+                .header("Content-Length", (2 * chunk).to_string())
+                .header("Accept-Ranges", "bytes");
+        });
+        let chunks = server.mock(|when, then| {
+            when.method("GET")
+                .path("/file.bin")
+                .header_exists("Range")
+                .header("User-Agent", "curl/8.5.0");
+            then.status(206).body(vec![b'x'; chunk]);
+        });
+        let downloader = crate::Downloader::builder()
+            .progress(false)
+            .workers(2)
+            .min_parallel_size(0)
+            .user_agent(resolve("curl"))
+            .build()
+            .unwrap();
+        let output = std::env::temp_dir().join(format!("dwrs-ua-{}.bin", std::process::id()));
+        downloader
+            .download_file(&server.url("/file.bin"), output.clone())
+            .await
+            .unwrap();
+        head.assert();
+        chunks.assert_calls(2);
+        std::fs::remove_file(&output).ok();
+    }
+}
@@ -1,4 +1,4 @@
-use colored::Colorize;
+use std::path::{Path, PathBuf};
 use std::{borrow::Cow, collections::HashMap};
 
 #[derive(Debug, Clone)]
@@ -65,7 +65,11 @@ pub fn render(tokens: &[Token], vars: &HashMap<&str, Cow<'_, str>>) -> String {
     out
 }
 
-fn apply_color(value: &str, style: Option<&str>) -> String {
+/// Styles `value` with a comma-separated list such as `red,bold`.
+#[cfg(feature = "terminal")]
+pub(crate) fn apply_color(value: &str, style: Option<&str>) -> String {
+    use colored::Colorize;
+
     let style = match style {
         Some(s) => s,
         None => return value.to_string(),
@@ -94,6 +98,73 @@ fn apply_color(value: &str, style: Option<&str>) -> String {
     styled.to_string()
 }
 
+/// Without the `terminal` feature values are left unstyled.
+#[cfg(not(feature = "terminal"))]
+pub(crate) fn apply_color(value: &str, _style: Option<&str>) -> String {
+    value.to_string()
+}
+
+/// Decodes the predefined XML entities.
+pub fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parses a byte size such as `512`, `500K`, `2M` or `1.5G` (binary units).
+pub fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit
+        .trim()
+        .to_ascii_uppercase()
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+    {
+        "" => 1u64,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Deepest directory holding all of `paths`, `.` if they share none.
+pub fn common_dir<'a>(paths: impl IntoIterator<Item = &'a Path>) -> PathBuf {
+    let mut common: Option<PathBuf> = None;
+    for path in paths {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        common = Some(match common {
+            None => dir.to_path_buf(),
+            Some(common) => common
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_unix_time(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0).map_or_else(
+        || secs.to_string(),
+        |t| t.format("%Y-%m-%d %H:%M").to_string(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,4 +196,29 @@ mod tests {
         let result = render(&tokens, &vars);
         assert_eq!(result, "Hello World!");
     }
+
+    #[test]
+    fn test_common_dir() {
+        let paths = [Path::new("dl/a/x.zip"), Path::new("dl/a/b/y.zip")];
+        assert_eq!(common_dir(paths), Path::new("dl/a"));
+        assert_eq!(
+            common_dir([Path::new("x.zip"), Path::new("dl/y.zip")]),
+            Path::new(".")
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("500K"), Some(500 * 1024));
+        assert_eq!(parse_size("1.5M"), Some(3 * 512 * 1024));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("fast"), None);
+    }
+
+    #[test]
+    fn test_format_unix_time() {
+        assert_eq!(format_unix_time(0), "1970-01-01 00:00");
+        assert_eq!(format_unix_time(1_709_210_096), "2024-02-29 12:34");
+    }
 }
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::file_parser::{DownloadEntry, ManifestOptions, parse_manifest};
+use crate::queue::{DownloadId, DownloadQueue, QueueState};
+
+/// Subdirectory manifests are moved to once all their downloads succeeded
+pub const DONE_DIR: &str = "done";
+/// Subdirectory manifests are moved to when they could not be read or a download failed
+pub const FAILED_DIR: &str = "failed";
+/// Extensions of the files picked up as manifests
+const MANIFEST_EXTENSIONS: &[&str] = &["txt", "json", "csv"];
+
+/// Options for [`watch`]
+#[derive(Debug, Clone)]
+pub struct WatchDirOptions {
+    /// Directory manifests are dropped into
+    pub dir: PathBuf,
+    /// Directory the outputs of the manifests are saved in; entries whose
+    /// output is absolute or leaves it with `..` are skipped
+    pub output_dir: PathBuf,
+    /// How often the directory is scanned
+    pub interval: Duration,
+}
+
+/// A manifest taken from the watched directory and the downloads it queued
+struct Job {
+    path: PathBuf,
+    ids: Vec<DownloadId>,
+}
+
+/// Manifest files directly inside `dir`, hidden files excluded.
+fn manifests(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let hidden = name.to_string_lossy().starts_with('.');
+        let manifest = path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+            MANIFEST_EXTENSIONS
+                .iter()
+                .any(|m| e.eq_ignore_ascii_case(m))
+        });
+        if !hidden && manifest && entry.file_type()?.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Entries of `manifest` with their outputs under `output_dir`, leaving
+/// out those whose output is absolute or climbs out with `..`.
+fn confined(entries: Vec<DownloadEntry>, output_dir: &Path, manifest: &Path) -> Vec<DownloadEntry> {
+    entries
+        .into_iter()
+        .filter_map(
+            |mut entry| match crate::sanitize::confine(output_dir, &entry.output) {
+                Ok(output) => {
+                    entry.output = output;
+                    Some(entry)
+                }
+                Err(e) => {
+                    log::error!("Skipping {} in {}: {}", entry.url, manifest.display(), e);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Moves a manifest into `subdir` next to it, numbering the name if taken.
+pub fn archive(path: &Path, subdir: &str) -> std::io::Result<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new(".")).join(subdir);
+    std::fs::create_dir_all(&dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut target = dir.join(name.as_ref());
+    let mut n = 1;
+    while target.exists() {
+        target = dir.join(format!("{}.{}", name, n));
+        n += 1;
+    }
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Watches `opts.dir` for dropped manifests and pushes their entries to `queue`.
+///
+/// A manifest is read once its size and modification time stayed the same
+/// for one scan, so files still being written are left alone. When all its
+/// downloads finished it is moved to [`DONE_DIR`], or to [`FAILED_DIR`] if
+/// one of them failed or it could not be parsed. Runs until downloads are
+/// [stopped](crate::control::stop).
+pub async fn watch(queue: DownloadQueue, opts: WatchDirOptions) -> std::io::Result<()> {
+    let mut seen: HashMap<PathBuf, (u64, Option<SystemTime>)> = HashMap::new();
+    let mut jobs: Vec<Job> = Vec::new();
+
+    while !crate::control::is_stopping() {
+        let paths = manifests(&opts.dir)?;
+        seen.retain(|path, _| paths.contains(path));
+        for path in paths {
+            if jobs.iter().any(|job| job.path == path) {
+                continue;
+            }
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let stamp = (metadata.len(), metadata.modified().ok());
+            if seen.insert(path.clone(), stamp) != Some(stamp) {
+                continue;
+            }
+            seen.remove(&path);
+
+            let parsed = parse_manifest(&path, &ManifestOptions::default())
+                .await
+                .map(|entries| confined(entries, &opts.output_dir, &path));
+            match parsed {
+                Ok(entries) if !entries.is_empty() => {
+                    log::info!(
+                        "Picked up {} with {} download(s)",
+                        path.display(),
+                        entries.len()
+                    );
+                    let ids = entries
+                        .into_iter()
+                        .map(|entry| queue.push(entry, 0))
+                        .collect();
+                    jobs.push(Job { path, ids });
+                }
+                result => {
+                    match result {
+                        Err(e) => log::error!("Cannot read manifest {}: {}", path.display(), e),
+                        Ok(_) => log::warn!("Manifest {} has no entries", path.display()),
+                    }
+                    if let Err(e) = archive(&path, FAILED_DIR) {
+                        log::error!("Cannot move {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+
+        jobs.retain(|job| {
+            let states: Vec<_> = job
+                .ids
+                .iter()
+                .filter_map(|id| queue.status(*id))
+                .map(|status| status.state)
+                .collect();
+            if !states.iter().all(QueueState::is_finished) {
+                return true;
+            }
+            let failed = states
+                .iter()
+                .filter(|state| **state != QueueState::Completed)
+                .count();
+            let subdir = if failed == 0 { DONE_DIR } else { FAILED_DIR };
+            log::info!(
+                "Finished {}: {} ok, {} failed",
+                job.path.display(),
+                states.len() - failed,
+                failed
+            );
+            if let Err(e) = archive(&job.path, subdir) {
+                log::error!("Cannot move {}: {}", job.path.display(), e);
+            }
+            false
+        });
+
+        tokio::time::sleep(opts.interval).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_manifests_are_picked_up_and_archived() {
+        let dir = std::env::temp_dir().join(format!("dwrs-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("list.txt"), "https://example.com/a.iso\n").unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(
+            dir.join("escape.txt"),
+            "https://example.com/b.iso ../b.iso\nhttps://example.com/c.iso /etc/c.iso\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join(".partial.txt"), "").unwrap();
+        std::fs::write(dir.join("notes.md"), "").unwrap();
+
+        let queue = DownloadQueue::new(1);
+        let opts = WatchDirOptions {
+            dir: dir.clone(),
+            output_dir: PathBuf::from("/tmp/dl"),
+            interval: Duration::from_millis(20),
+        };
+        let watcher = tokio::spawn(watch(queue.clone(), opts));
+        let (id, entry) = tokio::time::timeout(Duration::from_secs(5), queue.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.output, PathBuf::from("/tmp/dl/a.iso"));
+        queue.finish(id, Ok(()));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        watcher.abort();
+
+        assert!(dir.join(DONE_DIR).join("list.txt").exists());
+        assert!(dir.join(FAILED_DIR).join("broken.json").exists());
+        // Every entry wrote outside the output directory, so none was queued
+        assert!(dir.join(FAILED_DIR).join("escape.txt").exists());
+        assert!(queue.snapshot().iter().all(|item| item.id == id));
+        assert!(dir.join(".partial.txt").exists());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,6 @@
+/// Dashboard page served by the daemon at `/`.
+///
+/// The page only talks to `/jsonrpc`, so everything it shows is also
+/// available to other aria2 frontends. Open it with `?token=<secret>`
+/// when the daemon runs with `--rpc-secret`.
+pub const INDEX_HTML: &str = include_str!("webui.html");